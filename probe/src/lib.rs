@@ -0,0 +1,31 @@
+//! No-op definitions of DATIR's probe syntax, so that crates placing probes also build with
+//! plain cargo.
+//!
+//! DATIR defines `datir_probe!` itself while compiling a crate, and replaces every probe it finds
+//! with an update of the probe's site. Outside of DATIR, depend on this crate and import the
+//! macro at the crate root:
+//! ```rust,ignore
+//! #![feature(register_tool)]
+//! #![register_tool(datir)]
+//!
+//! #[macro_use]
+//! extern crate datir_probe;
+//! ```
+//! Registering the `datir` tool makes `#[datir::probe("name")]`, and DATIR's other attributes
+//! (`#[datir::skip]`, `#[datir::only]`, `#[datir::keep_abi]`, `#[datir::unit]`), inert. DATIR
+//! drops the `extern crate` item, and only registers the tool if the crate doesn't already.
+
+#![no_std]
+
+/// Probe point binding the listed variables to the site `<base>:::PROBE_<name>` when the crate is
+/// instrumented by DATIR, where `<base>` is the base ppt name of the enclosing function.
+///
+/// Without DATIR, this only borrows each variable, exactly like the definition DATIR declares
+/// for the probes it doesn't observe.
+#[macro_export]
+macro_rules! datir_probe {
+    ($name:literal $(, $var:expr)* $(,)?) => {{
+        let _ = $name;
+        $( let _ = &$var; )*
+    }};
+}
@@ -13,10 +13,10 @@
 //! [SiteBind] implementations for every array and slice
 //! shape.
 
-use crate::ati::ati::{ATI_ANALYSIS, Site};
-use crate::ati::refs::{TaggedRef, TaggedRefMut};
-use crate::ati::site_binds::SiteBind;
-use crate::ati::tagged::{TagTuple, Tagged};
+use crate::{ATI_ANALYSIS, Site};
+use crate::refs::{TaggedRef, TaggedRefMut};
+use crate::site_binds::SiteBind;
+use crate::tagged::{TagTuple, Tagged};
 
 // =================== TYPE ALIAS ===================
 
@@ -36,7 +36,7 @@ impl<T, const N: usize> TaggedArray<T, N> {
 impl<'a, T> TaggedRef<'a, [T]> {
     /// Length of the borrowed slice as a tagged `usize`, carrying the slice's id.
     pub fn len(&self) -> Tagged<usize> {
-        Tagged(*self.0, self.1.len())
+        Tagged(self.0, self.1.len())
     }
 }
 impl<'a, T> TaggedRefMut<'a, [T]> {
@@ -60,7 +60,7 @@ impl<T, const N: usize> SiteBind for TaggedArray<T, N> {
 }
 impl<'a, T, const N: usize> SiteBind for TaggedRef<'a, [T; N]> {
     fn bind(&self, site: &mut Site, var_name: &str) {
-        site.bind(&format!("{var_name}.length"), *self.0);
+        site.bind(&format!("{var_name}.length"), self.0);
         for i in 0..N {
             self.1[i].bind(site, &format!("{var_name}[{i}]"));
         }
@@ -135,7 +135,7 @@ where
         ATI_ANALYSIS
             .lock()
             .unwrap()
-            .union_and_get_id(self.0, &index.0);
+            .union_and_get_id(&self.0, &index.0);
         &self.1[index.1]
     }
 }
@@ -201,7 +201,7 @@ impl<T, const N: usize> TaggedArray<T, N> {
             .lock()
             .unwrap()
             .union_and_get_id(&self.0, &range_id);
-        TaggedRef(&self.0, &self.1[range.into_raw()])
+        TaggedRef(self.0, &self.1[range.into_raw()])
     }
 
     /// Mutable variant of [TaggedArray::subslice]. Builds a [TaggedRefMut] over a sub-slice
@@ -231,7 +231,7 @@ impl<'a, S: ?Sized> TaggedRef<'a, S> {
         ATI_ANALYSIS
             .lock()
             .unwrap()
-            .union_and_get_id(self.0, &range_id);
+            .union_and_get_id(&self.0, &range_id);
         self.map(|s| &s[range.into_raw()])
     }
 }
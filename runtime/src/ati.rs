@@ -1,7 +1,7 @@
 //! Analysis state for dynamic abstract type inference.
 //!
-//! Every type in this file is injected into the instrumented crate by
-//! `crate::callbacks::codegen::define_types`.
+//! Every type in this file is reached by instrumented crates through the `datir_runtime` crate
+//! root.
 //!
 //! Key points are summarized below.
 //!
@@ -9,7 +9,11 @@
 //! inside an `Arc<Mutex<..>>` so every instrumented method call can acquire it. It contains
 //! the value union-find (which tracks every interaction between tracked values across the
 //! whole program) and the collection of sites (which produce the per-site abstract type
-//! partition).
+//! partition). It further hands out the ids of `const` / `static` items, which are assigned
+//! lazily, the first time an item is read or bound. If the binary was instrumented with
+//! `--contract`, it also checks every union against the expected partition, see
+//! [crate::contract], and it checks every union against the units of `#[datir::unit]`
+//! annotations, see [crate::units].
 //!
 //! [Site] is a program point created by the shims emitted by
 //! `crate::callbacks::codegen`. Each site records which tagged values were bound
@@ -22,7 +26,12 @@
 
 // FIXME: this file definitely has some dead code somewhere, and can probably be
 // refactored to remove some functions.
-use crate::ati::tagged::{Id, Tagged, Tagger};
+use crate::{
+    FnSpan, ati_file,
+    contract::{Contract, ContractMode},
+    tagged::{Id, Tagged, Tagger},
+    units::{Unit, Units},
+};
 
 /// Top-level global that owns all information about all value interactions
 /// and ATI site states.
@@ -47,6 +56,15 @@ pub struct Site {
     observed_var_tags: std::collections::HashMap<String, Id>,
     /// Human-readable ppt name, used for debug output and `.decls`-format emission.
     name: String,
+    /// Number of times [`Site::update`] was called, i.e. the number of times the program point
+    /// was reached.
+    updates: u64,
+    /// Unit annotations of the variables bound since the last [`Site::update`] call, applied to
+    /// their values by [`ATI::update_site`]. Cleared on update.
+    observed_units: Vec<(String, Unit)>,
+    /// Names of the units carried by the values each variable was ever bound to, see
+    /// [crate::units].
+    var_units: std::collections::BTreeMap<String, std::collections::BTreeSet<&'static str>>,
 }
 
 impl Site {
@@ -57,6 +75,9 @@ impl Site {
             var_tags: std::collections::BTreeMap::new(),
             observed_var_tags: std::collections::HashMap::new(),
             name: name.to_owned(),
+            updates: 0,
+            observed_units: Vec::new(),
+            var_units: std::collections::BTreeMap::new(),
         }
     }
 
@@ -66,6 +87,14 @@ impl Site {
         self.observed_var_tags.insert(var_name.into(), id);
     }
 
+    /// Records that the variable named `var_name`, bound at this site, is annotated with the unit
+    /// `unit` at `at` (`file:line:col`). Its value, and the values of its components (e.g. the
+    /// elements of an array), are marked with the unit once the site is updated.
+    pub fn mark_unit(&mut self, var_name: &str, unit: &'static str, at: &'static str) {
+        self.observed_units
+            .push((var_name.into(), Unit { name: unit, at }));
+    }
+
     /// Folds the variables observed since the last update into the per-site partition.
     ///
     /// For each observed variable, looks up the current leader of its tag in `value_uf` and
@@ -73,6 +102,7 @@ impl Site {
     /// the algorithm from the paper. A variable's abstract type is the union-find class of
     /// every `value_uf` leader it has ever been observed to hold.
     pub fn update(&mut self, value_uf: &mut UnionFind) {
+        self.updates += 1;
         // for each variable
         for (var, new_tag) in self.observed_var_tags.iter_mut() {
             match self.var_tags.get_mut(var) {
@@ -121,35 +151,82 @@ impl Site {
     }
 
     /// Produces ATI output for this site to stdout. Called at the end of main.
+    ///
+    /// Abstract types holding values of some unit are suffixed with the units, e.g. `x -> 0 [ms]`.
     pub fn report(&mut self) {
         println!("{}", self.name);
-        for (var, tag) in self.var_tags.iter() {
-            let leader = self.type_uf.find(tag).unwrap();
-            println!("{var} -> {leader:?}");
+        let partition = self.partition();
+        let mut units: std::collections::HashMap<usize, std::collections::BTreeSet<&'static str>> =
+            std::collections::HashMap::new();
+        for (var, ty) in partition.iter() {
+            let var_units = self.var_units.get(var).into_iter().flatten();
+            units.entry(*ty).or_default().extend(var_units);
+        }
+        for (var, ty) in partition {
+            match units[&ty].iter().copied().collect::<Vec<_>>().as_slice() {
+                [] => println!("{var} -> {ty}"),
+                names => println!("{var} -> {ty} [{}]", names.join(", ")),
+            }
         }
         println!("---");
     }
 
+    /// Lists the abstract type of every variable of this site, ordered by variable name.
+    ///
+    /// The leaders of `type_uf` depend on the order values were tagged in, so they differ
+    /// between runs (and thread interleavings) which produce the same partition. Types are
+    /// instead numbered from 0, in order of their lexicographically smallest variable, so that
+    /// equal partitions are always numbered the same.
+    pub fn partition(&mut self) -> Vec<(String, usize)> {
+        let Site {
+            type_uf, var_tags, ..
+        } = self;
+        let mut numbers: std::collections::HashMap<Id, usize> = std::collections::HashMap::new();
+        var_tags
+            .iter()
+            .map(|(var, tag)| {
+                let leader = type_uf.find(tag).unwrap();
+                let next = numbers.len();
+                (var.clone(), *numbers.entry(leader).or_insert(next))
+            })
+            .collect()
+    }
+
     /// Emits the variable blocks for this site in `.ati` format.
     pub fn produce_ati(&mut self, output: &mut std::fs::File) {
         use std::io::Write;
 
-        for (var, tag) in self.var_tags.iter() {
+        for (var, ty) in self.partition() {
             // Do this in the merger. .ati files include all information for all
             // vars. even nested arrays. We will then reconstruct [..] comp information
             // by unioning the ATs of contained values.
             // let Some(var) = collapse_array_indices(var) else {
             //     continue;
             // };
-            let var = var.replace('\\', "\\\\").replace(' ', "\\_");
+            writeln!(output, "var {} {}", ati_file::escape(&var), ty).unwrap();
+        }
+    }
 
-            let leader = self.type_uf.find(tag).unwrap();
-            writeln!(output, "var {} {}", var, leader).unwrap();
+    /// Unions, within `global_uf`, the current `value_uf` leaders of every abstract type of this
+    /// site. A site's abstract type may hold values which never interacted (e.g. a variable
+    /// bound to unrelated values over several calls), which are of the same program-wide type.
+    fn project_types(&mut self, value_uf: &mut UnionFind, global_uf: &mut UnionFind) {
+        for index in 0..self.type_uf.index_to_set.len() {
+            let tag = self.type_uf.index_to_set[index];
+            let leader = self.type_uf.find(&tag).unwrap();
+            let tag = global_uf.introduce_tag(value_uf.find(&tag).unwrap());
+            let leader = global_uf.introduce_tag(value_uf.find(&leader).unwrap());
+            global_uf.union_tags(&tag, &leader);
         }
     }
 }
 
+/// The partition of one site as `(ppt name, number of updates, partition)`, see
+/// [`Sites::partitions`].
+pub type SitePartition = (String, u64, Vec<(String, usize)>);
+
 /// Owns the collection of every analyzed site, keyed by ppt name.
+#[derive(Default)]
 pub struct Sites {
     /// Sites currently parked in the collection. A site is removed from the map while a shim
     /// is registering variables to it (via [`Sites::extract`]) and reinserted via
@@ -194,13 +271,42 @@ impl Sites {
         }
     }
 
+    /// Lists the program-wide abstract type of every variable of every site, as
+    /// `(ppt name, variable name, type leader)`, ordered by ppt then variable name.
+    ///
+    /// Program-wide types are the `value_uf` sets, unioned whenever some site considers two of
+    /// them to be the same abstract type.
+    pub fn global_types(&mut self, value_uf: &mut UnionFind) -> Vec<(String, String, Id)> {
+        let mut global_uf = UnionFind::new();
+        for site in self.locs.values_mut() {
+            site.project_types(value_uf, &mut global_uf);
+        }
+
+        let mut members = Vec::new();
+        for (name, site) in self.locs.iter() {
+            for (var, tag) in site.var_tags.iter() {
+                let leader = value_uf.find(tag).unwrap();
+                let leader = global_uf.find(&leader).unwrap();
+                members.push((name.clone(), var.clone(), leader));
+            }
+        }
+        members
+    }
+
+    /// Lists the partition of every site, ordered by ppt name, see [`Site::partition`].
+    pub fn partitions(&mut self) -> Vec<SitePartition> {
+        self.locs
+            .iter_mut()
+            .map(|(name, site)| (name.clone(), site.updates, site.partition()))
+            .collect()
+    }
+
     /// Emits an `.ati` file covering all sites.
     pub fn produce_ati(&mut self, mut output: std::fs::File) {
         use std::io::Write;
 
         for (name, site) in self.locs.iter_mut() {
-            let pt_name = name.replace('\\', "\\\\").replace(' ', "\\_");
-            writeln!(output, "ppt {}", pt_name).unwrap();
+            writeln!(output, "ppt {}", ati_file::escape(name)).unwrap();
             site.produce_ati(&mut output);
             writeln!(output).unwrap();
         }
@@ -212,7 +318,7 @@ impl Sites {
 /// Keys are [`Id`]s. Internally the structure stores parents and ranks in `Vec`s indexed by
 /// dense `usize`s, with `id_to_index` / `index_to_set` translating between an [`Id`] and its
 /// slot. A bundled [`Tagger`] hands out fresh [`Id`]s for [`UnionFind::make_set`].
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct UnionFind {
     /// Reverse lookup from an externally meaningful [`Id`] to its dense index.
     id_to_index: std::collections::HashMap<Id, usize>,
@@ -327,12 +433,20 @@ impl UnionFind {
 ///
 /// Every instrumented operation acquires the surrounding `Mutex<ATI>`, mutates either the
 /// global value union-find or the relevant [Site], and releases the lock.
+#[derive(Default)]
 pub struct ATI {
     /// Global value union-find. Every interaction between two tracked values (e.g. a
     /// comparison or an arithmetic op) merges the two operand ids here.
     value_uf: UnionFind,
     /// Collection of program points, keyed by ppt name.
     sites: Sites,
+    /// Ids of tracked `const` / `static` items, keyed by the item's path. Every read of such an
+    /// item carries the same id, so that all of its uses interact.
+    globals: std::collections::HashMap<&'static str, Id>,
+    /// Expected partition every union is checked against, if instrumented with `--contract`.
+    contract: Option<Contract>,
+    /// Units of the classes of `value_uf`, marked by `#[datir::unit]` annotations.
+    units: Units,
 }
 
 impl ATI {
@@ -341,6 +455,9 @@ impl ATI {
         Self {
             value_uf: UnionFind::new(),
             sites: Sites::new(),
+            globals: std::collections::HashMap::new(),
+            contract: None,
+            units: Units::default(),
         }
     }
 
@@ -351,6 +468,40 @@ impl ATI {
         Tagged(id, value)
     }
 
+    /// Tags a value read from the `const` / `static` item identified by `key` with the item's id,
+    /// assigning it on first use.
+    pub fn track_global<T>(key: &'static str, value: T) -> Tagged<T> {
+        let id = ATI_ANALYSIS.lock().unwrap().global_id(key);
+        Tagged(id, value)
+    }
+
+    /// Binds the `const` / `static` item identified by `key` as the variable `var_name` at
+    /// `site`, assigning the item its id if it was never read.
+    pub fn bind_global(&mut self, site: &mut Site, key: &'static str, var_name: &str) {
+        let id = self.global_id(key);
+        site.bind(var_name, id);
+    }
+
+    /// Returns the id of the `const` / `static` item identified by `key`, assigning a fresh one
+    /// on first use.
+    fn global_id(&mut self, key: &'static str) -> Id {
+        let ATI {
+            value_uf, globals, ..
+        } = self;
+        *globals.entry(key).or_insert_with(|| value_uf.make_set())
+    }
+
+    /// Loads the contract at `path`, which every later union is checked against, see
+    /// [Contract::load]. Called by the main shim, before anything else.
+    pub fn load_contract(
+        &mut self,
+        path: &str,
+        mode: ContractMode,
+        spans: &[(&'static str, FnSpan)],
+    ) {
+        self.contract = Some(Contract::load(path, mode, spans));
+    }
+
     /// Fetches a site, or creates it, with the given name.
     pub fn get_site(&mut self, name: &str) -> Site {
         self.sites.extract(name)
@@ -359,13 +510,62 @@ impl ATI {
     /// Update abstract types at this site, then store it back
     /// into the map. Call whenever you are done registering variables to a site.
     pub fn update_site(&mut self, mut site: Site) {
+        self.apply_units(&mut site);
+        if let Some(contract) = self.contract.as_mut() {
+            let value_uf = &mut self.value_uf;
+            let vars = site
+                .observed_var_tags
+                .iter()
+                .map(|(var, tag)| (var, value_uf.find(tag).unwrap()));
+            contract.observe(&site.name, vars);
+        }
         site.update(&mut self.value_uf);
         self.sites.stash(site);
     }
 
-    /// Unions two ids in the global value union-find and returns the new leader.
+    /// Marks the values of the variables `site` annotates with a unit, then records the units
+    /// of every variable bound at `site`, see [crate::units].
+    fn apply_units(&mut self, site: &mut Site) {
+        for (var, unit) in std::mem::take(&mut site.observed_units) {
+            for (bound, tag) in site.observed_var_tags.iter() {
+                let component = bound
+                    .strip_prefix(var.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with(['.', '[', ':']));
+                if component {
+                    let leader = self.value_uf.find(tag).unwrap();
+                    self.units.mark(leader, unit);
+                }
+            }
+        }
+        if self.units.is_empty() {
+            return;
+        }
+        for (var, tag) in site.observed_var_tags.iter() {
+            let leader = self.value_uf.find(tag).unwrap();
+            let units: Vec<&'static str> = self.units.of(&leader).collect();
+            if !units.is_empty() {
+                site.var_units.entry(var.clone()).or_default().extend(units);
+            }
+        }
+    }
+
+    /// Unions two ids in the global value union-find and returns the new leader. The union is
+    /// checked against the units of both classes, see [crate::units], and with a contract
+    /// loaded, against the contract, see [crate::contract].
     pub fn union_and_get_id(&mut self, id1: &Id, id2: &Id) -> Id {
-        self.value_uf.union_tags(id1, id2).unwrap()
+        if self.contract.is_none() && self.units.is_empty() {
+            return self.value_uf.union_tags(id1, id2).unwrap();
+        }
+        let leader1 = self.value_uf.find(id1).unwrap();
+        let leader2 = self.value_uf.find(id2).unwrap();
+        let leader = self.value_uf.union_tags(id1, id2).unwrap();
+        if leader1 != leader2 {
+            self.units.union(leader1, leader2, leader);
+            if let Some(contract) = self.contract.as_mut() {
+                contract.union(leader1, leader2, leader);
+            }
+        }
+        leader
     }
 
     /// Allocates a fresh id in the value union-find. Used by operators that produce a result
@@ -376,7 +576,7 @@ impl ATI {
 
     /// Observes two tagged values interacting and merges their ids in the value union-find.
     pub fn union_tags<T>(&mut self, tv1: &Tagged<T>, tv2: &Tagged<T>) {
-        self.value_uf.union_tags(&tv1.0, &tv2.0);
+        self.union_and_get_id(&tv1.0, &tv2.0);
     }
 
     /// Produces the output partition that defines abstract types, written to stdout.
@@ -384,6 +584,17 @@ impl ATI {
         self.sites.report();
     }
 
+    /// Lists the program-wide abstract type of every variable of every site, see
+    /// [`Sites::global_types`].
+    pub fn global_types(&mut self) -> Vec<(String, String, Id)> {
+        self.sites.global_types(&mut self.value_uf)
+    }
+
+    /// Lists the partition of every site, see [`Sites::partitions`].
+    pub fn partitions(&mut self) -> Vec<SitePartition> {
+        self.sites.partitions()
+    }
+
     /// Writes the analysis result to `output_file` in `.ati` format.
     // FIXME: would be nice to conditionally include either this or what is required for
     // report() to function, no reason to include both in executable every time
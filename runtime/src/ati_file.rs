@@ -0,0 +1,173 @@
+//! Reading and writing of the `.ati` files written by binaries instrumented in `--release` mode.
+//!
+//! Lives in the runtime, which writes them (see [Sites::produce_ati](crate::Sites::produce_ati))
+//! and reads contracts out of them (see [crate::contract]), and is used by DATIR's subcommands as
+//! well.
+//!
+//! An `.ati` file lists the abstract type of every variable, at every program point reached by
+//! one execution of the binary:
+//! ```text
+//! ppt <ppt name>
+//! var <var name> <abstract type id>
+//! ...
+//!
+//! ppt <ppt name>
+//! ...
+//! ```
+//! Spaces within names are written as `\_`, and backslashes as `\\`, see [escape]. Names are kept
+//! in this escaped form, and [unescape] recovers the names used by the `.decls` file.
+//!
+//! Ids are only meaningful within a single ppt: two variables of a ppt share an abstract type iff
+//! they are listed with the same id.
+//!
+//! The report an instrumented binary prints to stdout outside of `--release` mode holds the same
+//! partition, see [AtiFile::parse_report]. Its names are escaped when parsed, so that both forms
+//! can be compared.
+
+use std::collections::BTreeMap;
+
+/// Marker preceding the per-site report within the stdout of an instrumented binary.
+const REPORT_START: &str = "===ATI-ANALYSIS-START===";
+
+/// Contents of an `.ati` file, the partition of the variables of every program point.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct AtiFile {
+    /// Abstract type id of each variable, keyed by (escaped) ppt name, then by variable name.
+    pub ppts: BTreeMap<String, BTreeMap<String, u64>>,
+}
+
+impl AtiFile {
+    /// Reads and parses the `.ati` file at `path`.
+    pub fn read(path: &std::path::Path) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        Self::parse(&contents)
+    }
+
+    /// Reads the partition at `path`, either an `.ati` file or the saved stdout of an
+    /// instrumented binary, which is told apart by its `===ATI-ANALYSIS-START===` line.
+    pub fn read_any(path: &std::path::Path) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        if contents.lines().any(|line| line == REPORT_START) {
+            Self::parse_report(&contents)
+        } else {
+            Self::parse(&contents)
+        }
+    }
+
+    /// Parses the contents of an `.ati` file. Errors name the offending line.
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        let mut ati = AtiFile::default();
+        let mut current: Option<&mut BTreeMap<String, u64>> = None;
+
+        for (line_idx, line) in contents.lines().enumerate() {
+            let malformed = || format!("malformed line {}: {line:?}", line_idx + 1);
+            let mut words = line.split_whitespace();
+            match words.next() {
+                None => current = None,
+                Some("ppt") => {
+                    let name = words.next().ok_or_else(malformed)?;
+                    if words.next().is_some() {
+                        return Err(malformed());
+                    }
+                    current = Some(ati.ppts.entry(name.to_string()).or_default());
+                }
+                Some("var") => {
+                    let (Some(var), Some(id), None) = (words.next(), words.next(), words.next())
+                    else {
+                        return Err(malformed());
+                    };
+                    let id = id.parse::<u64>().map_err(|_| malformed())?;
+                    let Some(vars) = current.as_mut() else {
+                        return Err(format!(
+                            "variable outside of any ppt on line {}: {line:?}",
+                            line_idx + 1
+                        ));
+                    };
+                    vars.insert(var.to_string(), id);
+                }
+                Some(_) => return Err(malformed()),
+            }
+        }
+
+        Ok(ati)
+    }
+
+    /// Parses the per-site report following the `===ATI-ANALYSIS-START===` line of the stdout of
+    /// an instrumented binary:
+    /// ```text
+    /// <ppt name>
+    /// <var name> -> <abstract type id>
+    /// ...
+    /// ---
+    /// ```
+    /// The units an id may be suffixed with (e.g. `x -> 0 [ms]`) are ignored, and so is anything
+    /// following the report (e.g. program-wide types). Errors name the offending line.
+    pub fn parse_report(contents: &str) -> Result<Self, String> {
+        let mut lines = contents.lines().enumerate();
+        if !lines.any(|(_, line)| line == REPORT_START) {
+            return Err(format!("no `{REPORT_START}` line"));
+        }
+
+        let mut ati = AtiFile::default();
+        let mut current: Option<&mut BTreeMap<String, u64>> = None;
+        for (line_idx, line) in lines {
+            let malformed = || format!("malformed line {}: {line:?}", line_idx + 1);
+            if line.starts_with("===") {
+                break;
+            }
+            match current.as_mut() {
+                None if line.is_empty() => {}
+                None => current = Some(ati.ppts.entry(escape(line)).or_default()),
+                Some(_) if line == "---" => current = None,
+                Some(vars) => {
+                    let (var, id) = line.rsplit_once(" -> ").ok_or_else(malformed)?;
+                    let id = id.split_once(" [").map_or(id, |(id, _)| id);
+                    let id = id.parse::<u64>().map_err(|_| malformed())?;
+                    vars.insert(escape(var), id);
+                }
+            }
+        }
+        match current {
+            Some(_) => Err("last ppt of the report is missing its closing `---`".to_string()),
+            None => Ok(ati),
+        }
+    }
+
+    /// Writes this partition out in `.ati` format, ppts and variables in lexicographic order.
+    pub fn write(&self, output: &mut impl std::io::Write) -> std::io::Result<()> {
+        for (ppt, vars) in &self.ppts {
+            writeln!(output, "ppt {ppt}")?;
+            for (var, id) in vars {
+                writeln!(output, "var {var} {id}")?;
+            }
+            writeln!(output)?;
+        }
+        Ok(())
+    }
+}
+
+/// Escapes a ppt or variable name for an `.ati` file.
+pub fn escape(name: &str) -> String {
+    name.replace('\\', "\\\\").replace(' ', "\\_")
+}
+
+/// Reverses the escaping [escape] applies to ppt and variable names, giving back the name as the
+/// `.decls` file spells it.
+pub fn unescape(name: &str) -> String {
+    let mut unescaped = String::with_capacity(name.len());
+    let mut chars = name.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('\\')) => {
+                chars.next();
+                unescaped.push('\\');
+            }
+            ('\\', Some('_')) => {
+                chars.next();
+                unescaped.push(' ');
+            }
+            _ => unescaped.push(c),
+        }
+    }
+    unescaped
+}
@@ -0,0 +1,202 @@
+//! Abstract-type contracts, enforced when DATIR instruments with `--contract PATH`.
+//!
+//! A contract is a reviewed partition, either an `.ati` file or the saved stdout of a run, which
+//! the instrumented binary loads when it starts (see
+//! [ATI::load_contract](crate::ATI::load_contract)). Variables listed with different ids at a ppt
+//! are expected to stay of distinct abstract types. Whenever a union of the value union-find
+//! ([ATI::union_and_get_id](crate::ATI::union_and_get_id)) would merge two classes of values,
+//! which variables expected to be distinct at some ppt were bound to, a violation is reported as
+//! configured by [ContractMode].
+//!
+//! To do so, the contract records which contracted variables were bound to a value of each class
+//! of the value union-find, and merges those records alongside the classes. Only unions are
+//! checked: variables which come to share an abstract type without any of their values
+//! interacting (e.g. a variable bound to values of both classes over several calls) aren't
+//! reported. `datir diff` catches those once the run is over.
+//!
+//! A violation is located at the innermost instrumented function the current thread is running,
+//! using the source spans the main shim passes along. Each violation is reported once per run.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    FnSpan,
+    ati_file::{self, AtiFile},
+    tagged::Id,
+};
+
+thread_local! {
+    /// Base ppt names of the instrumented functions the current thread is running, innermost
+    /// last. Only maintained while a contract is loaded.
+    static CALL_STACK: std::cell::RefCell<Vec<String>> =
+        const { std::cell::RefCell::new(Vec::new()) };
+}
+
+/// How violations of a contract are reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContractMode {
+    /// Print a warning to stderr, and carry on.
+    Warn,
+    /// Append a line to `<contract path>.violations.log`, and carry on.
+    Log,
+    /// Panic, stopping the analysis.
+    Panic,
+}
+
+/// A contracted variable, bound to some value of a class of the value union-find.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Holder {
+    ppt: String,
+    var: String,
+    /// Abstract type the contract expects the variable to be of at `ppt`.
+    expected: u64,
+}
+
+/// An expected partition, alongside what is needed to check it, see the module documentation.
+#[derive(Debug)]
+pub struct Contract {
+    mode: ContractMode,
+    /// Where violations are appended to in [ContractMode::Log].
+    log_path: String,
+    /// Expected abstract type of every contracted variable, keyed by ppt name, then variable
+    /// name.
+    expected: HashMap<String, HashMap<String, u64>>,
+    /// Contracted variables bound to a value of each class, keyed by the leader of the class.
+    holders: HashMap<Id, Vec<Holder>>,
+    /// Source location of the function owning each ppt, keyed by base ppt name.
+    spans: HashMap<&'static str, FnSpan>,
+    /// Violations already reported, as `(ppt, var, var)`.
+    reported: HashSet<(String, String, String)>,
+}
+
+impl Contract {
+    /// Records the variables bound at the site `ppt` right before it is updated, alongside the
+    /// leader of their value, and tracks which function the current thread is running.
+    ///
+    /// A call returns through the aggregate `:::EXIT` site, whether or not it also updates an
+    /// `:::EXITnn` site, so only the former pops the call.
+    pub fn observe<'a>(&mut self, ppt: &str, vars: impl Iterator<Item = (&'a String, Id)>) {
+        if let Some((base, kind)) = ppt.rsplit_once(":::") {
+            CALL_STACK.with_borrow_mut(|stack| {
+                if kind == "ENTER" {
+                    stack.push(base.to_string());
+                } else if kind == "EXIT" && stack.last().is_some_and(|top| top == base) {
+                    stack.pop();
+                }
+            });
+        }
+
+        let Some(expected) = self.expected.get(ppt) else {
+            return;
+        };
+        for (var, leader) in vars {
+            let Some(&ty) = expected.get(var) else {
+                continue;
+            };
+            let holder = Holder {
+                ppt: ppt.to_string(),
+                var: var.clone(),
+                expected: ty,
+            };
+            let holders = self.holders.entry(leader).or_default();
+            if !holders.contains(&holder) {
+                holders.push(holder);
+            }
+        }
+    }
+
+    /// Checks the union of the classes led by `leader1` and `leader2` into the class led by
+    /// `leader`, then merges their records.
+    pub fn union(&mut self, leader1: Id, leader2: Id, leader: Id) {
+        let holders1 = self.holders.remove(&leader1).unwrap_or_default();
+        let holders2 = self.holders.remove(&leader2).unwrap_or_default();
+        for a in holders1.iter() {
+            for b in holders2.iter() {
+                if a.ppt == b.ppt && a.expected != b.expected {
+                    self.violated(a, b);
+                }
+            }
+        }
+
+        let mut merged = holders1;
+        for holder in holders2 {
+            if !merged.contains(&holder) {
+                merged.push(holder);
+            }
+        }
+        if !merged.is_empty() {
+            self.holders.insert(leader, merged);
+        }
+    }
+
+    /// Reports that `a` and `b`, of the same ppt, were merged.
+    fn violated(&mut self, a: &Holder, b: &Holder) {
+        let (first, second) = if a.var < b.var { (a, b) } else { (b, a) };
+        let key = (first.ppt.clone(), first.var.clone(), second.var.clone());
+        if !self.reported.insert(key) {
+            return;
+        }
+
+        let location = CALL_STACK.with_borrow(|stack| match stack.last() {
+            Some(base) => match self.spans.get(base.as_str()) {
+                Some(span) => format!(
+                    "in `{base}` ({}:{}:{})",
+                    span.file, span.line_start, span.col_start
+                ),
+                None => format!("in `{base}`"),
+            },
+            None => "outside of any instrumented function".to_string(),
+        });
+        let message = format!(
+            "abstract type contract violated at {}: `{}` and `{}` are expected to be of distinct \
+             abstract types, but values they were bound to interacted {location}",
+            first.ppt, first.var, second.var
+        );
+
+        match self.mode {
+            ContractMode::Warn => eprintln!("warning: {message}"),
+            ContractMode::Log => {
+                use std::io::Write;
+
+                let mut log = std::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&self.log_path)
+                    .unwrap_or_else(|e| {
+                        panic!("Unable to open contract log {:?}: {e}", self.log_path)
+                    });
+                writeln!(log, "{} {message}", std::process::id()).unwrap();
+            }
+            ContractMode::Panic => panic!("{message}"),
+        }
+    }
+}
+
+impl Contract {
+    /// Loads the contract at `path` (an `.ati` file, or the stdout of a run). `spans` maps base
+    /// ppt names to the location of their function.
+    pub fn load(path: &str, mode: ContractMode, spans: &[(&'static str, FnSpan)]) -> Self {
+        let partition = AtiFile::read_any(std::path::Path::new(path))
+            .unwrap_or_else(|e| panic!("Unable to load abstract type contract {path:?}: {e}"));
+        // Sites are named as the `.decls` file spells them.
+        let expected = partition
+            .ppts
+            .into_iter()
+            .map(|(ppt, vars)| {
+                let vars = vars
+                    .into_iter()
+                    .map(|(var, id)| (ati_file::unescape(&var), id))
+                    .collect();
+                (ati_file::unescape(&ppt), vars)
+            })
+            .collect();
+        Contract {
+            mode,
+            log_path: format!("{path}.violations.log"),
+            expected,
+            holders: HashMap::new(),
+            spans: spans.iter().copied().collect(),
+            reported: HashSet::new(),
+        }
+    }
+}
@@ -0,0 +1,130 @@
+//! Program-wide abstract types, reported when DATIR instruments with `--global-types`.
+//!
+//! Each [Site](crate::ati::Site) partitions its own variables, so the per-site report never says
+//! that `foo`'s `len` and `bar`'s `size` hold the same abstract type. Projecting the classes of
+//! every site back onto the global value union-find (see
+//! [Sites::global_types](crate::ati::Sites::global_types)) does: each resulting program-wide type
+//! lists every `(ppt, variable)` belonging to it, across all program points.
+//!
+//! Types are numbered in order of their smallest `(ppt, variable)` member, and named after the
+//! variable name most of their members share, which makes for a starting point when turning a
+//! type into a newtype. [ATI::report_global_types] prints them to stdout, alongside the per-site
+//! report, while [ATI::produce_global_types] writes them as JSON next to the `.ati` file.
+
+use crate::{ATI, json_output::json_str};
+
+/// A program-wide abstract type.
+#[derive(Debug)]
+pub struct GlobalType {
+    /// Number of the type, unique within one execution.
+    pub id: usize,
+    /// Suggested name of the type, derived from the names of its members.
+    pub name: String,
+    /// Every `(ppt name, variable name)` of this type, ordered by ppt then variable name.
+    pub members: Vec<(String, String)>,
+}
+
+impl ATI {
+    /// Groups the variables of every site by program-wide abstract type.
+    pub fn collect_global_types(&mut self) -> Vec<GlobalType> {
+        let mut types: Vec<GlobalType> = Vec::new();
+        let mut type_of_leader = std::collections::HashMap::new();
+        for (ppt, var, leader) in self.global_types() {
+            let id = *type_of_leader.entry(leader).or_insert_with(|| {
+                types.push(GlobalType {
+                    id: types.len(),
+                    name: String::new(),
+                    members: Vec::new(),
+                });
+                types.len() - 1
+            });
+            types[id].members.push((ppt, var));
+        }
+
+        for ty in types.iter_mut() {
+            ty.name = type_name(&ty.members);
+        }
+        types
+    }
+
+    /// Prints every program-wide abstract type to stdout, following the per-site report.
+    pub fn report_global_types(&mut self) {
+        println!("===ATI-GLOBAL-TYPES-START===");
+        for ty in self.collect_global_types() {
+            println!("type {} ({})", ty.id, ty.name);
+            for (ppt, var) in ty.members.iter() {
+                println!("{ppt} {var}");
+            }
+            println!("---");
+        }
+    }
+
+    /// Writes every program-wide abstract type to `output_file`, as a JSON object holding a
+    /// `types` array of `{"id", "name", "members": [{"ppt", "var"}, ...]}` objects.
+    pub fn produce_global_types(&mut self, output_file: &str) {
+        use std::io::Write;
+
+        let cwd = std::env::current_dir().expect("Unable to determine current working directory.");
+        let mut output = std::fs::File::create(cwd.join(output_file)).unwrap();
+
+        let types: Vec<String> = self
+            .collect_global_types()
+            .into_iter()
+            .map(|ty| {
+                let members: Vec<String> = ty
+                    .members
+                    .iter()
+                    .map(|(ppt, var)| {
+                        format!(r#"{{"ppt":{},"var":{}}}"#, json_str(ppt), json_str(var))
+                    })
+                    .collect();
+                format!(
+                    r#"{{"id":{},"name":{},"members":[{}]}}"#,
+                    ty.id,
+                    json_str(&ty.name),
+                    members.join(",")
+                )
+            })
+            .collect();
+        writeln!(output, "{{\"types\":[\n{}\n]}}", types.join(",\n")).unwrap();
+    }
+}
+
+/// Names a type after the variable name shared by most of its members (ties broken
+/// lexicographically), with array indices collapsed (`arr[3]` counts as `arr[..]`). `return` is
+/// only used when every member is a return value.
+fn type_name(members: &[(String, String)]) -> String {
+    let mut counts: std::collections::BTreeMap<String, usize> = std::collections::BTreeMap::new();
+    for (_, var) in members {
+        *counts.entry(collapse_indices(var)).or_default() += 1;
+    }
+    if counts.len() > 1 {
+        counts.remove("return");
+    }
+    // `max_by_key` keeps the last maximum, iterate in reverse to keep the smallest name.
+    counts
+        .into_iter()
+        .rev()
+        .max_by_key(|(_, count)| *count)
+        .map(|(name, _)| name)
+        .unwrap_or_default()
+}
+
+/// Replaces every array index of a variable name with `..` (`arr[3].x` becomes `arr[..].x`).
+fn collapse_indices(var: &str) -> String {
+    let mut collapsed = String::with_capacity(var.len());
+    let mut rest = var;
+    while let Some(open) = rest.find('[') {
+        let (before, after) = rest.split_at(open + 1);
+        collapsed.push_str(before);
+        let digits = after.len() - after.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        if digits > 0 && after[digits..].starts_with(']') {
+            collapsed.push_str("..");
+            rest = &after[digits..];
+        } else {
+            rest = after;
+        }
+    }
+    collapsed.push_str(rest);
+    collapsed
+}
@@ -18,8 +18,8 @@
 //! - enumerate-after-combinators need a mechanism to thread the length id through `.filter()`,
 //!   `.map()`, and friends.
 
-use crate::ati::refs::{TaggedRef, TaggedRefMut};
-use crate::ati::tagged::{Id, Tagged};
+use crate::refs::{TaggedRef, TaggedRefMut};
+use crate::tagged::{Id, Tagged};
 
 // =================== SHIM ITERATORS ===================
 
@@ -34,7 +34,7 @@ pub struct TaggedSliceIter<'a, T> {
 impl<'a, T> Iterator for TaggedSliceIter<'a, T> {
     type Item = TaggedRef<'a, T>;
     fn next(&mut self) -> Option<Self::Item> {
-        self.inner.next().map(|t| TaggedRef(&t.0, &t.1))
+        self.inner.next().map(|t| TaggedRef(t.0, &t.1))
     }
     fn size_hint(&self) -> (usize, Option<usize>) {
         self.inner.size_hint()
@@ -43,7 +43,7 @@ impl<'a, T> Iterator for TaggedSliceIter<'a, T> {
 
 impl<'a, T> DoubleEndedIterator for TaggedSliceIter<'a, T> {
     fn next_back(&mut self) -> Option<Self::Item> {
-        self.inner.next_back().map(|t| TaggedRef(&t.0, &t.1))
+        self.inner.next_back().map(|t| TaggedRef(t.0, &t.1))
     }
 }
 
@@ -158,14 +158,14 @@ impl<I: std::iter::FusedIterator> std::iter::FusedIterator for TaggedEnumerate<I
 impl<'a, T> TaggedRef<'a, [Tagged<T>]> {
     /// Returns a [TaggedSliceIter] over this borrowed slice.
     pub fn iter(&self) -> TaggedSliceIter<'a, T> {
-        TaggedSliceIter { inner: self.1.iter(), length_id: *self.0 }
+        TaggedSliceIter { inner: self.1.iter(), length_id: self.0 }
     }
 }
 
 impl<'a, T, const N: usize> TaggedRef<'a, [Tagged<T>; N]> {
     /// Returns a [TaggedSliceIter] over this borrowed array.
     pub fn iter(&self) -> TaggedSliceIter<'a, T> {
-        TaggedSliceIter { inner: self.1.iter(), length_id: *self.0 }
+        TaggedSliceIter { inner: self.1.iter(), length_id: self.0 }
     }
 }
 
@@ -202,7 +202,7 @@ impl<'a, T> IntoIterator for TaggedRef<'a, [Tagged<T>]> {
     type Item = TaggedRef<'a, T>;
     type IntoIter = TaggedSliceIter<'a, T>;
     fn into_iter(self) -> Self::IntoIter {
-        TaggedSliceIter { inner: self.1.iter(), length_id: *self.0 }
+        TaggedSliceIter { inner: self.1.iter(), length_id: self.0 }
     }
 }
 
@@ -210,7 +210,7 @@ impl<'a, T, const N: usize> IntoIterator for TaggedRef<'a, [Tagged<T>; N]> {
     type Item = TaggedRef<'a, T>;
     type IntoIter = TaggedSliceIter<'a, T>;
     fn into_iter(self) -> Self::IntoIter {
-        TaggedSliceIter { inner: self.1.iter(), length_id: *self.0 }
+        TaggedSliceIter { inner: self.1.iter(), length_id: self.0 }
     }
 }
 
@@ -0,0 +1,162 @@
+//! Machine-readable output of the analysis, selected when DATIR instruments with `--format json`.
+//!
+//! The output is a stream of JSON Lines, one JSON object per line, each tagged with its kind as
+//! `"record"`. The first record describes the run:
+//! ```json
+//! {"record":"run","format":"datir-ati","version":2,"run_id":"…","program":"…","args":[…],"pid":1234,"finished_at":1700000000}
+//! ```
+//! `program` is the path of the instrumented binary, and `args` the arguments it was run with,
+//! without `program`. It is followed by one `ppt` record per site, ordered by ppt, each directly
+//! followed by one record per variable of the site, ordered by variable name:
+//! ```json
+//! {"record":"ppt","run_id":"…","ppt":"main.rs::sum:::ENTER","span":{"file":"main.rs","line_start":12,"col_start":1,"line_end":14,"col_end":2},"invocations":5}
+//! {"record":"var","run_id":"…","ppt":"main.rs::sum:::ENTER","var":"a","type":3,"span":{…},"invocations":5}
+//! ```
+//! Sites without variables (e.g. `main:::ENTER`) only have their `ppt` record. `type` is the
+//! abstract type id of the variable, only meaningful within its ppt, as in the text report. Both
+//! records hold the `span` and `invocations` of the site: `span` is the source location of the
+//! function (or, for `:::OBJECT` ppts, of the struct) owning the ppt, or `null` if unknown (e.g.
+//! for functions of instrumented dependencies), and `invocations` is the number of times the ppt
+//! was reached. With `--global-types`, one `global_type` record per program-wide abstract type
+//! follows, see [crate::global_types]:
+//! ```json
+//! {"record":"global_type","run_id":"…","id":0,"name":"len","members":[{"ppt":"…","var":"…"}]}
+//! ```
+//! The schema is versioned through [JSON_FORMAT_VERSION], which is bumped whenever a record
+//! changes in a way existing consumers can't ignore. Adding fields doesn't bump it.
+
+use crate::ATI;
+
+/// Version of the JSON Lines schema, written into the `run` record.
+pub const JSON_FORMAT_VERSION: u32 = 2;
+
+/// Source location of the function or struct owning a ppt, as passed by the instrumented binary.
+/// Lines and columns are 1-based.
+#[derive(Debug, Clone, Copy)]
+pub struct FnSpan {
+    pub file: &'static str,
+    pub line_start: u32,
+    pub col_start: u32,
+    pub line_end: u32,
+    pub col_end: u32,
+}
+
+impl ATI {
+    /// Prints the analysis to stdout as JSON Lines, following the `===ATI-ANALYSIS-START===`
+    /// marker. `spans` maps base ppt names to the location of their function or struct.
+    pub fn report_json(&mut self, spans: &[(&str, FnSpan)], global_types: bool) {
+        let run_id = format!("{:016x}", {
+            use std::hash::{BuildHasher, Hasher};
+            std::collections::hash_map::RandomState::new()
+                .build_hasher()
+                .finish()
+        });
+        println!("===ATI-ANALYSIS-START===");
+        for record in self.json_records(&run_id, spans, global_types) {
+            println!("{record}");
+        }
+    }
+
+    /// Writes the analysis to `output_file` as JSON Lines, identifying the run as `run_id`, see
+    /// [ATI::report_json].
+    pub fn produce_json(
+        &mut self,
+        output_file: &str,
+        run_id: &str,
+        spans: &[(&str, FnSpan)],
+        global_types: bool,
+    ) {
+        use std::io::Write;
+
+        let cwd = std::env::current_dir().expect("Unable to determine current working directory.");
+        let mut output = std::fs::File::create(cwd.join(output_file)).unwrap();
+        for record in self.json_records(run_id, spans, global_types) {
+            writeln!(output, "{record}").unwrap();
+        }
+    }
+
+    /// Renders every record of the analysis, see the module documentation.
+    fn json_records(
+        &mut self,
+        run_id: &str,
+        spans: &[(&str, FnSpan)],
+        global_types: bool,
+    ) -> Vec<String> {
+        let run_id = json_str(run_id);
+        let program = std::env::args()
+            .next()
+            .map_or("null".to_string(), |arg| json_str(&arg));
+        let args: Vec<String> = std::env::args().skip(1).map(|arg| json_str(&arg)).collect();
+        let finished_at = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        let mut records = vec![format!(
+            r#"{{"record":"run","format":"datir-ati","version":{JSON_FORMAT_VERSION},"run_id":{run_id},"program":{program},"args":[{}],"pid":{},"finished_at":{finished_at}}}"#,
+            args.join(","),
+            std::process::id(),
+        )];
+
+        let spans: std::collections::HashMap<&str, FnSpan> = spans.iter().copied().collect();
+        for (ppt, invocations, partition) in self.partitions() {
+            let base = ppt
+                .rsplit_once(":::")
+                .map_or(ppt.as_str(), |(base, _)| base);
+            let span = spans.get(base).map_or("null".to_string(), |span| {
+                format!(
+                    r#"{{"file":{},"line_start":{},"col_start":{},"line_end":{},"col_end":{}}}"#,
+                    json_str(span.file),
+                    span.line_start,
+                    span.col_start,
+                    span.line_end,
+                    span.col_end
+                )
+            });
+            let ppt = json_str(&ppt);
+            records.push(format!(
+                r#"{{"record":"ppt","run_id":{run_id},"ppt":{ppt},"span":{span},"invocations":{invocations}}}"#
+            ));
+            for (var, ty) in partition {
+                records.push(format!(
+                    r#"{{"record":"var","run_id":{run_id},"ppt":{ppt},"var":{},"type":{ty},"span":{span},"invocations":{invocations}}}"#,
+                    json_str(&var)
+                ));
+            }
+        }
+
+        if global_types {
+            for ty in self.collect_global_types() {
+                let members: Vec<String> = ty
+                    .members
+                    .iter()
+                    .map(|(ppt, var)| {
+                        format!(r#"{{"ppt":{},"var":{}}}"#, json_str(ppt), json_str(var))
+                    })
+                    .collect();
+                records.push(format!(
+                    r#"{{"record":"global_type","run_id":{run_id},"id":{},"name":{},"members":[{}]}}"#,
+                    ty.id,
+                    json_str(&ty.name),
+                    members.join(",")
+                ));
+            }
+        }
+        records
+    }
+}
+
+/// Renders `s` as a JSON string literal.
+pub fn json_str(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
@@ -0,0 +1,113 @@
+//! Runtime library linked into every instrumented crate.
+//!
+//! DATIR compiles this crate into a `datir_runtime` rlib before the second compilation pass, and
+//! passes it to every instrumented crate through `--extern`. The crate provides the types and
+//! globals that instrumentation calls into at runtime. Generated code refers to them through
+//! fully qualified `::datir_runtime::...` paths, so nothing is added to the user's namespace.
+//!
+//! At a high-level:
+//!
+//! [ati] defines [ATI](ati::ATI), [Site](ati::Site), [Sites](ati::Sites), and
+//! [UnionFind](ati::UnionFind), the data structures that record value interactions and produce
+//! the abstract type partition. The [ATI_ANALYSIS](ati::ATI_ANALYSIS) global owns the single
+//! live instance of this state.
+//!
+//! [global_types] groups the variables of every site by program-wide abstract type, projecting
+//! each site's classes back onto the global value union-find. [json_output] writes the analysis
+//! as versioned JSON Lines instead of the text report, when instrumenting with `--format json`.
+//! [contract] checks the unions of the value union-find against a reviewed partition, when
+//! instrumenting with `--contract`. [ati_file] reads and writes partitions in the `.ati` format,
+//! for the runtime and DATIR's subcommands alike. [units] reports interactions between values
+//! annotated with different units of measure.
+//!
+//! [tagged] defines the [Tagged](tagged::Tagged) wrapper that pairs a tracked value with
+//! a unique [Id](tagged::Id). [refs] defines [TaggedRef](refs::TaggedRef) and
+//! [TaggedRefMut](refs::TaggedRefMut), the shared and unique borrow forms emitted whenever
+//! pass 2 takes a reference to a tracked value.
+//!
+//! [arrays] holds every array and slice helper, including the
+//! [TaggedArray](arrays::TaggedArray) type alias, length and indexing operators, slice-index
+//! sugar, and the [SiteBind](site_binds::SiteBind) implementations for array and slice
+//! shapes. [ranges] holds every range helper, including the six tagged range type aliases,
+//! the constructors on [ATI](ati::ATI), iterator and [RangeBounds](std::ops::RangeBounds)
+//! implementations, the [TaggedSliceIndex](arrays::TaggedSliceIndex) implementations, and
+//! the [SiteBind](site_binds::SiteBind) implementations for ranges. [strings] treats a
+//! `String` / `&str` like an array whose id is its length id, with the
+//! [track_str](ati::ATI::track_str) constructor for literals, the length, position and
+//! character iteration methods returning tagged values, and the
+//! [TaggedStrIndex](strings::TaggedStrIndex) implementations behind `&s[range]`.
+//!
+//! [tagged_ops] implements the standard arithmetic, comparison, and shift operator traits
+//! for the tagged wrappers. Each operator unions the operand ids in the value union-find
+//! before delegating to the underlying primitive. [iterators] provides shim iterators that
+//! yield [TaggedRef](refs::TaggedRef) and [TaggedRefMut](refs::TaggedRefMut) elements, plus
+//! an enumerate variant that emits tagged indices.
+//!
+//! [site_binds] defines the [SiteBind](site_binds::SiteBind) trait and its blanket and
+//! per-shape implementations. Pass 2's generated shims call `.bind()` on every variable to
+//! register its tag with the enclosing [Site](ati::Site).
+//!
+//! Every instrumented crate links the same rlib, so the [ATI_ANALYSIS](ati::ATI_ANALYSIS)
+//! global is defined exactly once, even when several crates are instrumented. Method calls on
+//! runtime traits (`.bind()`, `.share()`, `.reborrow()`, ...) are resolved through [prelude],
+//! which pass 2 glob-imports into every instrumented module.
+
+// allows performing trait specialization, which the runtime makes heavy use of
+// to dispatch the appropriate function during monomorphization.
+// In other words, if we define some trait MyTrait, and then implement:
+// > impl<T>    MyTrait for T          { fn foo() ... }
+// > impl<T>    MyTrait for &T         { fn foo() ... }
+// > impl<T, N> MyTrait for [T; N]     { fn foo() ... }
+// > impl<T>    MyTrait for Wrapper<T> { fn foo() ... }
+// we can always call T.foo(), regardless of what T is, as there is a
+// default implementation for all generic Ts. However, if T.foo() is invoked,
+// and T is actually a Wrapper<T>, then it will dispatch the foo() defined
+// for Wrapper<T> rather than for T. Without min_specialization, those trait
+// implementations would overlap.
+// Note: full_specialization is unsound, and also unnecessary here.
+#![feature(min_specialization)]
+// allows iterating over tagged ranges, see [ranges].
+#![feature(step_trait)]
+// allows defining a Dynamically Sized Type (used for representing Tagged References
+// to Unsized types like [T]) while allowing automatic coercion from a Sized type.
+#![feature(unsize)]
+#![feature(coerce_unsized)]
+// allows the string helpers to be generic over the same patterns as `str::find`, see [strings].
+#![feature(pattern)]
+
+pub mod arrays;
+pub mod ati;
+pub mod ati_file;
+pub mod contract;
+pub mod global_types;
+pub mod iterators;
+pub mod json_output;
+pub mod ranges;
+pub mod refs;
+pub mod site_binds;
+pub mod strings;
+pub mod tagged;
+pub mod tagged_ops;
+pub mod units;
+
+pub use arrays::*;
+pub use ati::*;
+pub use contract::*;
+pub use global_types::*;
+pub use iterators::*;
+pub use json_output::*;
+pub use ranges::*;
+pub use refs::*;
+pub use site_binds::*;
+pub use strings::*;
+pub use tagged::*;
+pub use units::*;
+
+/// Brings every runtime trait into scope anonymously, so that method calls emitted by pass 2
+/// resolve without making the trait names visible to the user's code.
+pub mod prelude {
+    pub use crate::arrays::TaggedSliceIndex as _;
+    pub use crate::refs::{Reborrow as _, Share as _};
+    pub use crate::site_binds::SiteBind as _;
+    pub use crate::tagged::TagTuple as _;
+}
@@ -1,6 +1,6 @@
 //! Range support for the runtime library.
 //!
-//! Ranges in instrumented code carry a wrapper [Id](crate::ati::tagged::Id) on top of the
+//! Ranges in instrumented code carry a wrapper [Id](crate::tagged::Id) on top of the
 //! standard library range structure. The wrapper id is unioned with the endpoint ids at
 //! construction, so any iteration or indexing through the range interacts with both endpoints.
 //! Pass 2 lowers each `a..b` form to one of the `track_range_*` constructors below.
@@ -8,14 +8,15 @@
 //! This file contains every range-shaped helper, including the six type aliases, the
 //! constructors on [ATI], the [Iterator] and [RangeBounds](std::ops::RangeBounds)
 //! implementations,
-//! [TaggedSliceIndex] implementations for
-//! `arr[range]`-style indexing, and the [SiteBind]
+//! [TaggedSliceIndex] and [TaggedStrIndex] implementations for
+//! `arr[range]` and `s[range]`-style indexing, and the [SiteBind]
 //! implementations used to register a range to a site.
 
-use crate::ati::arrays::TaggedSliceIndex;
-use crate::ati::ati::{ATI, ATI_ANALYSIS, Site};
-use crate::ati::site_binds::SiteBind;
-use crate::ati::tagged::Tagged;
+use crate::arrays::TaggedSliceIndex;
+use crate::{ATI, ATI_ANALYSIS, Site};
+use crate::site_binds::SiteBind;
+use crate::strings::TaggedStrIndex;
+use crate::tagged::Tagged;
 
 // =================== TYPE ALIASES ===================
 
@@ -389,6 +390,46 @@ impl<T> TaggedSliceIndex<T> for TaggedRangeFull {
     }
 }
 
+// =================== TAGGED STR INDEX ===================
+// Strings are only indexed by byte offsets, so these are limited to ranges of `usize`.
+
+impl TaggedStrIndex for TaggedRange<usize> {
+    type Raw = std::ops::Range<usize>;
+    fn into_raw(self) -> Self::Raw {
+        self.1.start.1..self.1.end.1
+    }
+}
+impl TaggedStrIndex for TaggedRangeInclusive<usize> {
+    type Raw = std::ops::RangeInclusive<usize>;
+    fn into_raw(self) -> Self::Raw {
+        self.1.start().1..=self.1.end().1
+    }
+}
+impl TaggedStrIndex for TaggedRangeFrom<usize> {
+    type Raw = std::ops::RangeFrom<usize>;
+    fn into_raw(self) -> Self::Raw {
+        self.1.start.1..
+    }
+}
+impl TaggedStrIndex for TaggedRangeTo<usize> {
+    type Raw = std::ops::RangeTo<usize>;
+    fn into_raw(self) -> Self::Raw {
+        ..self.1.end.1
+    }
+}
+impl TaggedStrIndex for TaggedRangeToInclusive<usize> {
+    type Raw = std::ops::RangeToInclusive<usize>;
+    fn into_raw(self) -> Self::Raw {
+        ..=self.1.end.1
+    }
+}
+impl TaggedStrIndex for TaggedRangeFull {
+    type Raw = std::ops::RangeFull;
+    fn into_raw(self) -> Self::Raw {
+        ..
+    }
+}
+
 // =================== SITE BIND ===================
 
 // FIXME: Im not convinced that all ranges need a start/end bind, which is separate from 
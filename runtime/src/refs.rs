@@ -1,11 +1,16 @@
 //! Defines the borrow-form tagged wrappers, [TaggedRef] and [TaggedRefMut].
 //!
 //! Pass 2 rewrites every reference to a tracked value into one of these wrappers, instead of
-//! using a regular `&Tagged<T>` or `&mut Tagged<T>`. The wrappers split the borrow into one
-//! reference to the [Id] and one reference to the inner value, which
+//! using a regular `&Tagged<T>` or `&mut Tagged<T>`. The wrappers split the borrow into the
+//! [Id] and one reference to the inner value, which
 //! lets a slice-shaped reference live as `TaggedRef<'_, [T]>` even when the storage of the
 //! id and the values is not contiguous in memory.
 //!
+//! A [TaggedRef] holds a copy of the id: the referent can't be assigned while it is borrowed,
+//! so its id can't change either. Values with no id stored anywhere, like string literals, can
+//! then be borrowed with a fresh one. A [TaggedRefMut] borrows the id, so that assignments
+//! through it reach the referent's id.
+//!
 //! [TaggedRef] is shared and `Copy`. [TaggedRefMut] is unique and must not be `Copy` or
 //! `Clone`, so pass 2 emits explicit [Reborrow::reborrow] calls anywhere the source code
 //! would have relied on the compiler's implicit `&mut` reborrow.
@@ -14,10 +19,9 @@
 //! this is always a semantically valid operation, and is used heavily by pass 2 to normalize
 //! reference shapes.
 
-use crate::ati::tagged::{Id, TagTuple, Tagged};
+use crate::tagged::{Id, TagTuple, Tagged};
 
-/// A shared "view" over a tagged value, consisting of one borrow of the id and one borrow of
-/// the inner value.
+/// A shared "view" over a tagged value, consisting of the id and one borrow of the inner value.
 ///
 /// Produced by instrumenting `&x` when `x` is a [Tagged]. The `T: ?Sized` bound lets
 /// `TaggedRef<'a, [T]>` serve as the slice representation, with unsized coercion from
@@ -27,8 +31,8 @@ use crate::ati::tagged::{Id, TagTuple, Tagged};
 /// Methods that would otherwise be hung off [Tagged] (e.g. the slice `.len()`) are declared
 /// directly on [TaggedRef] and [TaggedRefMut], since there is no single [Tagged] value in
 /// memory to deref through.
-pub struct TaggedRef<'a, T: ?Sized>(pub &'a Id, pub &'a T);
-impl<'a, T: std::hash::Hash> std::hash::Hash for TaggedRef<'a, T> {
+pub struct TaggedRef<'a, T: ?Sized>(pub Id, pub &'a T);
+impl<'a, T: ?Sized + std::hash::Hash> std::hash::Hash for TaggedRef<'a, T> {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         self.1.hash(state);
     }
@@ -40,7 +44,7 @@ impl<'a, T: std::hash::Hash> std::hash::Hash for TaggedRef<'a, T> {
 /// Move-only by construction. Pass 2 inserts an explicit [Reborrow::reborrow] call
 /// anywhere the source code would have relied on Rust's implicit `&mut` reborrow.
 pub struct TaggedRefMut<'a, T: ?Sized>(pub &'a mut Id, pub &'a mut T);
-impl<'a, T: std::hash::Hash> std::hash::Hash for TaggedRefMut<'a, T> {
+impl<'a, T: ?Sized + std::hash::Hash> std::hash::Hash for TaggedRefMut<'a, T> {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         self.1.hash(state);
     }
@@ -49,7 +53,7 @@ impl<'a, T: std::hash::Hash> std::hash::Hash for TaggedRefMut<'a, T> {
 impl<'a, T: ?Sized> TagTuple for TaggedRef<'a, T> {
     type Inner = T;
     fn id(&self) -> Id {
-        *self.0
+        self.0
     }
     fn value(&self) -> &T {
         self.1
@@ -113,7 +117,7 @@ pub trait Share<'a, T: ?Sized> {
 
 impl<T> Share<'_, T> for Tagged<T> {
     fn share(&self) -> TaggedRef<'_, T> {
-        TaggedRef(&self.0, &self.1)
+        TaggedRef(self.0, &self.1)
     }
 }
 
@@ -129,7 +133,7 @@ impl<'a, T: ?Sized> Share<'a, T> for TaggedRefMut<'a, T> {
     /// Downgrades the unique borrows held by `TaggedRefMut` to shared borrows for the
     /// lifetime of `&self`.
     fn share(&self) -> TaggedRef<'_, T> {
-        TaggedRef(&*self.0, &*self.1)
+        TaggedRef(*self.0, &*self.1)
     }
 }
 
@@ -190,7 +194,7 @@ impl<'a, T: ?Sized> std::ops::DerefMut for TaggedRefMut<'a, T> {
 // Copy/Clone for TaggedRef only. TaggedRefMut holds unique borrows and must not be
 // Copy/Clone, to avoid aliasing the mutable refs.
 impl<'a, T: ?Sized> Clone for TaggedRef<'a, T> {
-    /// `Clone` for [TaggedRef] is a `Copy`, both contained borrows are shared.
+    /// `Clone` for [TaggedRef] is a `Copy`, the id is `Copy` and the borrow is shared.
     fn clone(&self) -> Self {
         *self
     }
@@ -201,7 +205,7 @@ impl<'a, T: ?Sized> Copy for TaggedRef<'a, T> {}
 impl<'a, T: ?Sized + std::fmt::Debug> std::fmt::Debug for TaggedRef<'a, T> {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         f.debug_tuple("TaggedRef")
-            .field(self.0)
+            .field(&self.0)
             .field(&self.1)
             .finish()
     }
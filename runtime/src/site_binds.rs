@@ -1,17 +1,18 @@
 //! Defines [SiteBind], the trait pass 2 invokes from generated shims to register every
 //! in-scope tracked variable with a [Site].
 //!
-//! The blanket and atomic implementations live here. Array, slice, and range shapes are
-//! covered in [crate::ati::arrays] and [crate::ati::ranges], next to the rest of those
-//! shapes' helpers.
+//! The blanket, atomic, and std enum (`Option`, `Result`) implementations live here. Array,
+//! slice, and range shapes are covered in [crate::arrays] and [crate::ranges], next to the rest
+//! of those shapes' helpers.
 //!
 //! For user-defined compound types (structs, enums), pass 2's codegen step in
 //! `crate::callbacks::codegen::data_types` generates a per-type [SiteBind] implementation
-//! that recursively delegates to each field's [SiteBind].
+//! that recursively delegates to each field's [SiteBind]. With `--object-ppts`, those of
+//! structs hold a [StructBind] guard.
 
-use crate::ati::ati::Site;
-use crate::ati::refs::{TaggedRef, TaggedRefMut};
-use crate::ati::tagged::Tagged;
+use crate::ati::Site;
+use crate::refs::{TaggedRef, TaggedRefMut};
+use crate::tagged::Tagged;
 
 /// Recursively binds the receiver as a variable named `var_name` at `site`. Every
 /// `Tagged<T>` should implement this trait. Compound types have a per-type implementation
@@ -33,17 +34,17 @@ impl<T> SiteBind for T {
 }
 
 /// Blanket implementation used by all atomic tagged types (like `Tagged<u32>`). The
-/// per-shape impls in [crate::ati::arrays] and [crate::ati::ranges] override this for
+/// per-shape impls in [crate::arrays] and [crate::ranges] override this for
 /// arrays, slices, and ranges via specialization.
 impl<T> SiteBind for Tagged<T> {
     default fn bind(&self, site: &mut Site, var_name: &str) {
         site.bind(var_name, self.0);
     }
 }
-/// Blanket implementation for shared tagged borrows, binds via the borrowed id.
+/// Blanket implementation for shared tagged borrows, binds via the id they hold.
 impl<'a, T: ?Sized> SiteBind for TaggedRef<'a, T> {
     default fn bind(&self, site: &mut Site, var_name: &str) {
-        site.bind(var_name, *self.0);
+        site.bind(var_name, self.0);
     }
 }
 /// Blanket implementation for unique tagged borrows, binds via the borrowed id.
@@ -68,6 +69,88 @@ impl<T> SiteBind for &mut T {
         (**self).bind(site, var_name);
     }
 }
+/// A box carries no id of its own either, so that recursive structs (`Option<Box<Node>>`) bind
+/// the whole chain of values they own.
+impl<T> SiteBind for Box<T> {
+    default fn bind(&self, site: &mut Site, var_name: &str) {
+        (**self).bind(site, var_name);
+    }
+}
+
+// ==========================    STRUCT BINDS   ===============================
+
+thread_local! {
+    /// `:::OBJECT` sites of the generated struct binds in progress on this thread, see
+    /// [StructBind].
+    static STRUCT_BINDS_IN_PROGRESS: std::cell::RefCell<Vec<&'static str>> =
+        const { std::cell::RefCell::new(Vec::new()) };
+}
+
+/// Guard held by the generated [SiteBind] implementation of a struct for the duration of its
+/// bind, when instrumenting with `--object-ppts`.
+///
+/// Only the outermost bind of each struct type folds its struct into the type's `:::OBJECT` (and
+/// `:::CLASS`) site, once its fields are bound. Structs of the same type nested within it (e.g.
+/// the rest of a linked list) are bound as its fields instead, so that they neither open the
+/// `:::OBJECT` site while it is already taken out of the analysis, nor bind their own fields once
+/// more per level. Structs of other types nested within it are folded into their own site.
+pub struct StructBind {
+    object_ppt: Option<&'static str>,
+}
+
+impl StructBind {
+    /// Enters a bind of a struct whose `:::OBJECT` site is named `object_ppt`.
+    pub fn enter(object_ppt: &'static str) -> Self {
+        let outermost = STRUCT_BINDS_IN_PROGRESS.with_borrow_mut(|in_progress| {
+            let outermost = !in_progress.contains(&object_ppt);
+            if outermost {
+                in_progress.push(object_ppt);
+            }
+            outermost
+        });
+        StructBind {
+            object_ppt: outermost.then_some(object_ppt),
+        }
+    }
+
+    /// Whether no other bind of a struct of the same type was in progress when this one was
+    /// entered.
+    pub fn is_outermost(&self) -> bool {
+        self.object_ppt.is_some()
+    }
+}
+
+impl Drop for StructBind {
+    fn drop(&mut self) {
+        if let Some(object_ppt) = self.object_ppt {
+            STRUCT_BINDS_IN_PROGRESS.with_borrow_mut(|in_progress| {
+                in_progress.retain(|ppt| *ppt != object_ppt);
+            });
+        }
+    }
+}
+
+// ==========================    STD ENUMS   =================================
+// `Option` and `Result` bind their payload like a user-defined enum would (see
+// `generate_enum_impls`), under a `::{Variant}.0` suffix of the parent name. Only the payload
+// of the variant currently held is bound, so a `None` binds nothing.
+
+impl<T> SiteBind for Option<T> {
+    fn bind(&self, site: &mut Site, var_name: &str) {
+        if let Some(value) = self {
+            value.bind(site, &format!("{var_name}::Some.0"));
+        }
+    }
+}
+
+impl<T, E> SiteBind for Result<T, E> {
+    fn bind(&self, site: &mut Site, var_name: &str) {
+        match self {
+            Ok(value) => value.bind(site, &format!("{var_name}::Ok.0")),
+            Err(error) => error.bind(site, &format!("{var_name}::Err.0")),
+        }
+    }
+}
 
 // ==========================    TUPLE TYPES   ===============================
 /// Implements [SiteBind] for tuples, with each entry bound under a `.0` / `.1` / etc. suffix
@@ -84,7 +167,7 @@ macro_rules! tuple_impl_site_bind {
         }
         impl<'a, $($T),+> SiteBind for TaggedRef<'a, ($($T,)+)> {
             fn bind(&self, site: &mut Site, var_name: &str) {
-                site.bind(var_name, *self.0);
+                site.bind(var_name, self.0);
                 $(
                     self.1.$idx.bind(site, &format!("{}.{}", var_name, stringify!($idx)));
                 )+
@@ -0,0 +1,245 @@
+//! String support for the runtime library.
+//!
+//! Pass 2 rewrites every owned `String` to a `Tagged<String>`, every `&str` / `&String` to a
+//! [TaggedRef] (mutable, if necessary), and every string literal to a `TaggedRef<'static, str>`
+//! built by [ATI::track_str], which holds a fresh id. Like the wrapper id of a tagged array, the
+//! id stored alongside a string is its "length id": lengths, byte offsets and char indices drawn
+//! from the string all carry it, and slicing a string with a tagged range unions the range id
+//! with it.
+//!
+//! The contents of a string are not tracked, so the chars yielded by [TaggedRef::chars] and
+//! [TaggedRef::char_indices] are tagged afresh, like values returned by an untracked call.
+//!
+//! This file collects the [ATI::track_str] constructor, the inherent length, position and
+//! iteration methods shadowing those of `str`, the [TaggedStrIndex] trait used to lower
+//! `&s[range]`, and the concatenation operators on tagged strings. Tagged strings
+//! are bound to a site as a single variable, through the blanket [SiteBind] implementations.
+//!
+//! [SiteBind]: crate::site_binds::SiteBind
+
+use std::str::pattern::Pattern;
+
+use crate::refs::{TaggedRef, TaggedRefMut};
+use crate::tagged::{Id, TagTuple, Tagged};
+use crate::{ATI, ATI_ANALYSIS};
+
+// =================== CONSTRUCTOR ===================
+
+impl ATI {
+    /// Tags a string literal with a fresh id, producing the `TaggedRef<str>` every `&str` is
+    /// represented as.
+    pub fn track_str(value: &str) -> TaggedRef<'_, str> {
+        let id = ATI_ANALYSIS.lock().unwrap().make_id();
+        TaggedRef(id, value)
+    }
+}
+
+// =================== SLICE INDEXING ===================
+
+/// Counterpart of [TaggedSliceIndex](crate::arrays::TaggedSliceIndex) for strings, implemented
+/// by every tagged range of `usize` byte offsets. Pass 2 lowers `&s[range]` to
+/// [TaggedRef::substr], which unions the range id with the string id.
+pub trait TaggedStrIndex: TagTuple {
+    /// The raw, untagged range type that this tagged range converts into.
+    type Raw: std::slice::SliceIndex<str, Output = str>;
+    /// Consumes the tagged range and returns the equivalent untagged range.
+    fn into_raw(self) -> Self::Raw;
+}
+
+// =================== INHERENT METHODS ===================
+// Shadow the `str` methods reachable through `Deref` which return lengths or byte offsets,
+// so that those come back tagged with the string's id.
+
+impl<'a> TaggedRef<'a, str> {
+    /// Length of the string in bytes, carrying the string's id.
+    pub fn len(self) -> Tagged<usize> {
+        Tagged(self.0, self.1.len())
+    }
+
+    /// Byte offset of the first match of `pat`, carrying the string's id.
+    pub fn find<P: Pattern>(self, pat: P) -> Option<Tagged<usize>> {
+        self.1.find(pat).map(|i| Tagged(self.0, i))
+    }
+
+    /// Byte offset of the last match of `pat`, carrying the string's id.
+    pub fn rfind<P: Pattern>(self, pat: P) -> Option<Tagged<usize>>
+    where
+        for<'h> P::Searcher<'h>: std::str::pattern::ReverseSearcher<'h>,
+    {
+        self.1.rfind(pat).map(|i| Tagged(self.0, i))
+    }
+
+    /// Returns a [TaggedChars] over the chars of this string.
+    pub fn chars(self) -> TaggedChars<'a> {
+        TaggedChars {
+            inner: self.1.chars(),
+        }
+    }
+
+    /// Returns a [TaggedCharIndices] over the chars of this string, alongside their byte
+    /// offsets.
+    pub fn char_indices(self) -> TaggedCharIndices<'a> {
+        TaggedCharIndices {
+            inner: self.1.char_indices(),
+            length_id: self.0,
+        }
+    }
+
+    /// Builds a [TaggedRef] over a substring, given a tagged range of byte offsets. Unions the
+    /// string id with the range id, and the substring keeps the string's id.
+    pub fn substr<R: TaggedStrIndex>(self, range: R) -> TaggedRef<'a, str> {
+        let range_id = range.id();
+        ATI_ANALYSIS
+            .lock()
+            .unwrap()
+            .union_and_get_id(&self.0, &range_id);
+        TaggedRef(self.0, &self.1[range.into_raw()])
+    }
+}
+
+/// Forwards the inherent string methods of a tagged `String` shape to its `as_str()` view.
+/// `$lt` is the lifetime of the view, tied to the receiver `$slf: $recv`.
+macro_rules! forward_tagged_str_methods {
+    ($lt:lifetime, $slf:ident: $recv:ty) => {
+        /// Length of the string in bytes, carrying the string's id.
+        pub fn len($slf: $recv) -> Tagged<usize> {
+            $slf.as_str().len()
+        }
+
+        /// Byte offset of the first match of `pat`, carrying the string's id.
+        pub fn find<P: Pattern>($slf: $recv, pat: P) -> Option<Tagged<usize>> {
+            $slf.as_str().find(pat)
+        }
+
+        /// Byte offset of the last match of `pat`, carrying the string's id.
+        pub fn rfind<P: Pattern>($slf: $recv, pat: P) -> Option<Tagged<usize>>
+        where
+            for<'h> P::Searcher<'h>: std::str::pattern::ReverseSearcher<'h>,
+        {
+            $slf.as_str().rfind(pat)
+        }
+
+        /// Returns a [TaggedChars] over the chars of this string.
+        pub fn chars($slf: $recv) -> TaggedChars<$lt> {
+            $slf.as_str().chars()
+        }
+
+        /// Returns a [TaggedCharIndices] over the chars of this string, alongside their byte
+        /// offsets.
+        pub fn char_indices($slf: $recv) -> TaggedCharIndices<$lt> {
+            $slf.as_str().char_indices()
+        }
+
+        /// Builds a [TaggedRef] over a substring, see [TaggedRef::substr].
+        pub fn substr<R: TaggedStrIndex>($slf: $recv, range: R) -> TaggedRef<$lt, str> {
+            $slf.as_str().substr(range)
+        }
+    };
+}
+
+impl Tagged<String> {
+    /// Borrows the string as a `TaggedRef<str>`, carrying the string's id.
+    pub fn as_str(&self) -> TaggedRef<'_, str> {
+        TaggedRef(self.0, self.1.as_str())
+    }
+
+    forward_tagged_str_methods!('_, self: &Self);
+}
+
+impl<'a> TaggedRef<'a, String> {
+    /// Borrows the string as a `TaggedRef<str>`, carrying the string's id.
+    pub fn as_str(self) -> TaggedRef<'a, str> {
+        TaggedRef(self.0, self.1.as_str())
+    }
+
+    forward_tagged_str_methods!('a, self: Self);
+}
+
+impl<'a> TaggedRefMut<'a, String> {
+    /// Borrows the string as a `TaggedRef<str>`, carrying the string's id.
+    pub fn as_str(&self) -> TaggedRef<'_, str> {
+        TaggedRef(*self.0, self.1.as_str())
+    }
+
+    forward_tagged_str_methods!('_, self: &Self);
+}
+
+// =================== ITERATORS ===================
+
+/// Iterator over the chars of a tagged string, yielding each as a `Tagged<char>` with a fresh
+/// id.
+pub struct TaggedChars<'a> {
+    inner: std::str::Chars<'a>,
+}
+
+impl<'a> Iterator for TaggedChars<'a> {
+    type Item = Tagged<char>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(ATI::track)
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a> DoubleEndedIterator for TaggedChars<'a> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(ATI::track)
+    }
+}
+
+impl<'a> std::iter::FusedIterator for TaggedChars<'a> {}
+
+/// Iterator over the chars of a tagged string, yielding `(Tagged<usize>, Tagged<char>)`. Every
+/// byte offset reuses the string's length id, while each char receives a fresh id.
+pub struct TaggedCharIndices<'a> {
+    inner: std::str::CharIndices<'a>,
+    length_id: Id,
+}
+
+impl<'a> Iterator for TaggedCharIndices<'a> {
+    type Item = (Tagged<usize>, Tagged<char>);
+    fn next(&mut self) -> Option<Self::Item> {
+        let (i, c) = self.inner.next()?;
+        Some((Tagged(self.length_id, i), ATI::track(c)))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a> DoubleEndedIterator for TaggedCharIndices<'a> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let (i, c) = self.inner.next_back()?;
+        Some((Tagged(self.length_id, i), ATI::track(c)))
+    }
+}
+
+impl<'a> std::iter::FusedIterator for TaggedCharIndices<'a> {}
+
+// =================== OPERATORS ===================
+// Comparisons between tagged strings are covered by the generic impls in [crate::tagged_ops].
+
+/// `s + t` appends a borrowed string to an owned one. The length of the result is the sum of
+/// both lengths, so like arithmetic, the result id is the union of the two operand ids.
+impl<'a> std::ops::Add<TaggedRef<'a, str>> for Tagged<String> {
+    type Output = Tagged<String>;
+    fn add(self, rhs: TaggedRef<'a, str>) -> Self::Output {
+        let merged = ATI_ANALYSIS
+            .lock()
+            .unwrap()
+            .union_and_get_id(&self.0, &rhs.0);
+        Tagged(merged, self.1 + rhs.1)
+    }
+}
+
+/// Assigning variant of the above `Add` impl, `s += t`.
+impl<'a> std::ops::AddAssign<TaggedRef<'a, str>> for Tagged<String> {
+    fn add_assign(&mut self, rhs: TaggedRef<'a, str>) {
+        self.0 = ATI_ANALYSIS
+            .lock()
+            .unwrap()
+            .union_and_get_id(&self.0, &rhs.0);
+        self.1 += rhs.1;
+    }
+}
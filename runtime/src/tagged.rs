@@ -3,20 +3,20 @@
 //!
 //! Every tracked value emitted by pass 2 is rewritten from `T` into `Tagged<T>`, which pairs the
 //! original value with a unique [Id]. The id is what the union-find structures in
-//! [crate::ati::ati] use to record interactions between values and to form abstract-type sets.
+//! [crate::ati] use to record interactions between values and to form abstract-type sets.
 //!
 //! Borrows of a tagged value (`&Tagged<T>`, `&mut Tagged<T>`) are not used directly. Pass 2
-//! converts those into [TaggedRef](crate::ati::refs::TaggedRef) and
-//! [TaggedRefMut](crate::ati::refs::TaggedRefMut), defined in [crate::ati::refs].
+//! converts those into [TaggedRef](crate::refs::TaggedRef) and
+//! [TaggedRefMut](crate::refs::TaggedRefMut), defined in [crate::refs].
 
-use crate::ati::ati::ATI_ANALYSIS;
+use crate::ati::ATI_ANALYSIS;
 
 /// Type alias for ids, kept short and easy to swap if the underlying integer width ever needs
 /// to change.
 pub type Id = u64;
 
 /// Hands out fresh [Id]s, one per call to [Tagger::tag].
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct Tagger {
     /// Next id that will be returned.
     next_id: Id,
@@ -39,8 +39,8 @@ impl Tagger {
 
 /// A value of type `T` paired with a unique [Id].
 ///
-/// Not intended to be constructed directly. Use [crate::ati::ati::ATI::track] or one of the
-/// `track_range_*` constructors in [crate::ati::ranges] to obtain one.
+/// Not intended to be constructed directly. Use [crate::ati::ATI::track] or one of the
+/// `track_range_*` constructors in [crate::ranges] to obtain one.
 #[derive(Debug, Clone, Copy)]
 pub struct Tagged<T: ?Sized>(pub Id, pub T);
 
@@ -53,8 +53,8 @@ impl<T: std::hash::Hash> std::hash::Hash for Tagged<T> {
 /// Common abstraction over every tagged wrapper, exposing the wrapper's id and a borrow of
 /// its inner value.
 ///
-/// Implemented for [Tagged], [TaggedRef](crate::ati::refs::TaggedRef), and
-/// [TaggedRefMut](crate::ati::refs::TaggedRefMut). Lets generic code (operator impls, slice
+/// Implemented for [Tagged], [TaggedRef](crate::refs::TaggedRef), and
+/// [TaggedRefMut](crate::refs::TaggedRefMut). Lets generic code (operator impls, slice
 /// indexing) reach into any tagged shape without matching on the concrete wrapper type.
 pub trait TagTuple {
     /// The inner value type stored in this tagged wrapper.
@@ -11,9 +11,10 @@
 //! (`+`, `-`, `*`, `/`, `%`, `&`, `|`, `^`) merge the lhs, rhs, and result ids. Shift operators
 //! (`<<`, `>>`) merge only the lhs and the result, since the rhs is treated as a count rather
 //! than a value-level interaction. Unary `Neg` and `Not` push down to the underlying value
-//! while keeping the id intact, and `Deref` on [Tagged] enables auto-deref to any `T` method.
+//! while keeping the id intact, and `Deref` / `DerefMut` on [Tagged] enable auto-deref to any `T`
+//! method.
 
-use crate::ati::{
+use crate::{
     ati::ATI_ANALYSIS,
     refs::{TaggedRef, TaggedRefMut},
     tagged::{TagTuple, Tagged},
@@ -22,7 +23,7 @@ use crate::ati::{
 // =====================    COMPARISON OPS    ===================
 // Comparison goes through the [TagTuple] trait, which exposes `.id()` and `.value()` on every
 // tagged wrapper. The macros below take advantage of that to avoid matching on each shape
-// separately.
+// separately. `T` may be unsized, so that borrowed strings (`TaggedRef<str>`) compare too.
 
 /// `PartialEq` and `PartialOrd` between an `lhs` and `rhs` (both of which must impl
 /// [TagTuple]). Unions the tags on every call before delegating comparison to the underlying
@@ -31,7 +32,7 @@ macro_rules! impl_tagged_partial_cmp {
     ($($gens:tt),+ ; $lhs:ty, $rhs:ty) => {
         impl<$($gens),+> std::cmp::PartialEq<$rhs> for $lhs
         where
-            T: std::cmp::PartialEq,
+            T: ?Sized + std::cmp::PartialEq,
         {
             fn eq(&self, other: &$rhs) -> bool {
                 ATI_ANALYSIS
@@ -44,7 +45,7 @@ macro_rules! impl_tagged_partial_cmp {
 
         impl<$($gens),+> std::cmp::PartialOrd<$rhs> for $lhs
         where
-            T: std::cmp::PartialOrd,
+            T: ?Sized + std::cmp::PartialOrd,
         {
             fn partial_cmp(&self, other: &$rhs) -> Option<std::cmp::Ordering> {
                 ATI_ANALYSIS
@@ -61,11 +62,11 @@ macro_rules! impl_tagged_partial_cmp {
 /// sides, so only the three self-self cases are valid.
 macro_rules! impl_tagged_total_cmp {
     ($($gens:tt),+ ; $ty:ty) => {
-        impl<$($gens),+> std::cmp::Eq for $ty where T: std::cmp::Eq {}
+        impl<$($gens),+> std::cmp::Eq for $ty where T: ?Sized + std::cmp::Eq {}
 
         impl<$($gens),+> std::cmp::Ord for $ty
         where
-            T: std::cmp::Ord,
+            T: ?Sized + std::cmp::Ord,
         {
             fn cmp(&self, other: &Self) -> std::cmp::Ordering {
                 ATI_ANALYSIS
@@ -173,7 +174,7 @@ macro_rules! impl_tagged_arithmetic_op {
                 let merged = ATI_ANALYSIS
                     .lock()
                     .unwrap()
-                    .union_and_get_id(&self.0, rhs.0);
+                    .union_and_get_id(&self.0, &rhs.0);
                 Tagged(merged, self.1 $op *rhs.1)
             }
         }
@@ -187,7 +188,7 @@ macro_rules! impl_tagged_arithmetic_op {
                 let merged = ATI_ANALYSIS
                     .lock()
                     .unwrap()
-                    .union_and_get_id(self.0, rhs.0);
+                    .union_and_get_id(&self.0, &rhs.0);
                 Tagged(merged, *self.1 $op *rhs.1)
             }
         }
@@ -201,7 +202,7 @@ macro_rules! impl_tagged_arithmetic_op {
                 let merged = ATI_ANALYSIS
                     .lock()
                     .unwrap()
-                    .union_and_get_id(self.0, &rhs.0);
+                    .union_and_get_id(&self.0, &rhs.0);
                 Tagged(merged, *self.1 $op rhs.1)
             }
         }
@@ -227,7 +228,7 @@ macro_rules! impl_tagged_arithmetic_op {
                 let merged = ATI_ANALYSIS
                     .lock()
                     .unwrap()
-                    .union_and_get_id(&self.0, rhs.0);
+                    .union_and_get_id(&self.0, &rhs.0);
                 *self = Tagged(merged, self.1 $op *rhs.1);
             }
         }
@@ -287,7 +288,7 @@ macro_rules! impl_tagged_shift_op {
             type Output = Tagged<T>;
             fn $method(self, rhs: Self) -> Self::Output {
                 let new_id = ATI_ANALYSIS.lock().unwrap().make_id();
-                ATI_ANALYSIS.lock().unwrap().union_and_get_id(&new_id, self.0);
+                ATI_ANALYSIS.lock().unwrap().union_and_get_id(&new_id, &self.0);
                 Tagged(new_id, *self.1 $op *rhs.1)
             }
         }
@@ -299,7 +300,7 @@ macro_rules! impl_tagged_shift_op {
             type Output = Tagged<T>;
             fn $method(self, rhs: Tagged<T>) -> Self::Output {
                 let new_id = ATI_ANALYSIS.lock().unwrap().make_id();
-                ATI_ANALYSIS.lock().unwrap().union_and_get_id(&new_id, self.0);
+                ATI_ANALYSIS.lock().unwrap().union_and_get_id(&new_id, &self.0);
                 Tagged(new_id, *self.1 $op rhs.1)
             }
         }
@@ -368,3 +369,11 @@ impl<T> std::ops::Deref for Tagged<T> {
         &self.1
     }
 }
+
+/// Mutable counterpart of the `Deref` impl above, allowing `&mut self` methods on `T` (e.g.
+/// `String::push_str`) to be dispatched on a `Tagged<T>` receiver.
+impl<T> std::ops::DerefMut for Tagged<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.1
+    }
+}
@@ -0,0 +1,101 @@
+//! Unit-of-measure annotations, checked against the interactions the value union-find records.
+//!
+//! Parameters and fields annotated with `#[datir::unit("ms")]` are marked with their unit when a
+//! shim binds them (see [Site::mark_unit](crate::Site::mark_unit)). Units are held by the classes
+//! of the value union-find, and merged alongside them: whenever a union merges classes carrying
+//! different units, e.g. a duration in `ms` compared to one in `s`, a dimensional error is printed
+//! to stderr, naming the annotation of either unit. ATI unions the operands of every arithmetic
+//! operator, so multiplying values of different units is reported all the same.
+//!
+//! The units of each abstract type are listed alongside its variables in the report, see
+//! [Site::report](crate::Site::report).
+
+use std::collections::{HashMap, HashSet};
+
+use crate::tagged::Id;
+
+/// A unit annotation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Unit {
+    /// Name of the unit, e.g. `ms`.
+    pub name: &'static str,
+    /// Source location of the annotation, as `file:line:col`.
+    pub at: &'static str,
+}
+
+/// Units carried by the classes of the value union-find.
+#[derive(Debug, Default)]
+pub struct Units {
+    /// Distinct units (by name) of each class, keyed by the leader of the class.
+    by_leader: HashMap<Id, Vec<Unit>>,
+    /// Dimensional errors already reported, as the locations of both annotations.
+    reported: HashSet<(&'static str, &'static str)>,
+}
+
+impl Units {
+    /// Whether no value was ever marked with a unit, in which case unions needn't be checked.
+    pub fn is_empty(&self) -> bool {
+        self.by_leader.is_empty()
+    }
+
+    /// Names of the units carried by the class led by `leader`.
+    pub fn of(&self, leader: &Id) -> impl Iterator<Item = &'static str> + '_ {
+        self.by_leader
+            .get(leader)
+            .into_iter()
+            .flatten()
+            .map(|unit| unit.name)
+    }
+
+    /// Marks the class led by `leader` with `unit`, which is checked like a union.
+    pub fn mark(&mut self, leader: Id, unit: Unit) {
+        let units = self.by_leader.remove(&leader).unwrap_or_default();
+        self.merge(units, vec![unit], leader);
+    }
+
+    /// Checks the union of the classes led by `leader1` and `leader2` into the class led by
+    /// `leader`, then merges their units.
+    pub fn union(&mut self, leader1: Id, leader2: Id, leader: Id) {
+        let units1 = self.by_leader.remove(&leader1).unwrap_or_default();
+        let units2 = self.by_leader.remove(&leader2).unwrap_or_default();
+        self.merge(units1, units2, leader);
+    }
+
+    /// Reports every pair of different units of `units1` and `units2`, then stores their union
+    /// as the units of `leader`.
+    fn merge(&mut self, units1: Vec<Unit>, units2: Vec<Unit>, leader: Id) {
+        for a in units1.iter() {
+            for b in units2.iter() {
+                if a.name != b.name {
+                    self.mismatched(*a, *b);
+                }
+            }
+        }
+
+        let mut merged = units1;
+        for unit in units2 {
+            if !merged.iter().any(|held| held.name == unit.name) {
+                merged.push(unit);
+            }
+        }
+        if !merged.is_empty() {
+            self.by_leader.insert(leader, merged);
+        }
+    }
+
+    /// Reports that values of units `a` and `b` interacted.
+    fn mismatched(&mut self, a: Unit, b: Unit) {
+        let (first, second) = if (a.name, a.at) < (b.name, b.at) {
+            (a, b)
+        } else {
+            (b, a)
+        };
+        if !self.reported.insert((first.at, second.at)) {
+            return;
+        }
+        eprintln!(
+            "dimensional error: a value in `{}` ({}) interacted with a value in `{}` ({})",
+            first.name, first.at, second.name, second.at
+        );
+    }
+}
@@ -22,7 +22,17 @@ pub fn datir_arg_init(program_name: &str) -> ArgParser {
 
     ArgParser::new(
         program_name,
-        "DATIR: dynamic abstract type inference for Rust",
+        "DATIR: dynamic abstract type inference for Rust\n\n\
+         Subcommands:\n  \
+         merge <ATI_OUT_DIR_PATH>    Merge the .ati files of many --release runs (see merge --help)\n  \
+         comparability <ATI_PATH>    Write the abstract types of an .ati file into a .decls file \
+         (see comparability --help)\n  \
+         suggest <FILE>              Propose newtypes for the program-wide abstract types of a \
+         --global-types run (see suggest --help)\n  \
+         diff <OLD> <NEW>            List the variables whose abstract type differs between two \
+         runs (see diff --help)\n  \
+         static <FILE>               Infer abstract types without running the crate, by unification \
+         over its MIR (see static --help)",
     )
     .arg(ArgSpec::positional(
         "file",
@@ -73,6 +83,241 @@ pub fn datir_arg_init(program_name: &str) -> ArgParser {
         "--test",
         "Run in test mode, skipping debug logging and printing ATI output to stdout",
     ))
+    .arg(
+        ArgSpec::keyword(
+            "instrument-crates",
+            "Comma separated allowlist of dependencies to instrument alongside FILE, each given \
+             as the crate name and the path to its root source file. Dependencies are \
+             instrumented in the listed order, so list a dependency before its dependants.",
+        )
+        .long("--instrument-crates")
+        .value_name("NAME=PATH[,NAME=PATH...]"),
+    )
+    .arg(ArgSpec::flag(
+        "exit-per-return",
+        "--exit-per-return",
+        "Also observe every return site of a function as its own EXITnn program point, \
+         where nn is the line of the return within the original source",
+    ))
+    .arg(ArgSpec::flag(
+        "object-ppts",
+        "--object-ppts",
+        "Also observe the fields of every struct value bound at a program point at the \
+         struct's OBJECT program point, reporting field abstract types once per struct",
+    ))
+    .arg(
+        ArgSpec::keyword(
+            "skip",
+            "Comma separated glob patterns (`*`, `?`) over base program point names. Matching \
+             functions are compiled uninstrumented, like those marked #[datir::skip].",
+        )
+        .long("--skip")
+        .value_name("GLOB[,GLOB...]"),
+    )
+    .arg(
+        ArgSpec::keyword(
+            "only",
+            "Comma separated glob patterns (`*`, `?`) over base program point names. Only \
+             matching functions, and those marked #[datir::only], are instrumented.",
+        )
+        .long("--only")
+        .value_name("GLOB[,GLOB...]"),
+    )
+    .arg(ArgSpec::flag(
+        "preserve-public-abi",
+        "--preserve-public-abi",
+        "Have functions exported from the crate keep their original signatures, tagging \
+         arguments on entry and untagging results on return, so that uninstrumented crates \
+         can still call them. Functions marked #[datir::keep_abi] or #[no_mangle] always do",
+    ))
+    .arg(ArgSpec::flag(
+        "global-types",
+        "--global-types",
+        "Also report program-wide abstract types, listing the variables of every program point \
+         which share each of them. Printed after the report, or written next to each .ati file \
+         as JSON with --release",
+    ))
+    .arg(
+        ArgSpec::keyword(
+            "format",
+            "Format of the analysis written by the instrumented binary: `text` for the report \
+             (or .ati files with --release), `json` for versioned JSON Lines (or .jsonl files \
+             with --release)",
+        )
+        .long("--format")
+        .value_name("FORMAT")
+        .default_value("text"),
+    )
+    .arg(
+        ArgSpec::keyword(
+            "contract",
+            "Reviewed partition (an .ati file or a saved report) the instrumented binary loads at \
+             startup, reporting every interaction merging variables it lists as distinct",
+        )
+        .long("--contract")
+        .value_name("PATH"),
+    )
+    .arg(
+        ArgSpec::keyword(
+            "contract-mode",
+            "How violations of --contract are reported: `warn` on stderr, `log` to \
+             PATH.violations.log, or `panic`",
+        )
+        .long("--contract-mode")
+        .value_name("MODE")
+        .default_value("warn"),
+    )
+}
+
+/// Specifies the command line arguments of the `merge` subcommand, see [crate::merge].
+pub fn merge_arg_init(program_name: &str) -> ArgParser {
+    ArgParser::new(
+        format!("{program_name} merge"),
+        "Merges the .ati files written by every run of a binary instrumented with --release",
+    )
+    .arg(ArgSpec::positional(
+        "dir",
+        "ATI_OUT_DIR_PATH",
+        "Directory containing the .ati files to merge",
+    ))
+    .arg(
+        ArgSpec::keyword(
+            "output",
+            "Location of the merged .ati file. Defaults to ATI_OUT_DIR_PATH with an .ati extension",
+        )
+        .short("-o")
+        .long("--output")
+        .value_name("PATH"),
+    )
+}
+
+/// Specifies the command line arguments of the `comparability` subcommand, see
+/// [crate::comparability].
+pub fn comparability_arg_init(program_name: &str) -> ArgParser {
+    ArgParser::new(
+        format!("{program_name} comparability"),
+        "Writes the abstract types of an .ati file into the comparability of every variable \
+         of a .decls file",
+    )
+    .arg(ArgSpec::positional(
+        "ati",
+        "ATI_PATH",
+        "Path to the .ati file (e.g. merged with `merge`) holding the abstract types",
+    ))
+    .arg(
+        ArgSpec::keyword("decls-path", "Path to the .decls file describing the instrumented crate")
+            .short("-d")
+            .long("--decls-path")
+            .value_name("PATH")
+            .required(),
+    )
+    .arg(
+        ArgSpec::keyword(
+            "output",
+            "Location of the written .decls file. Defaults to DECLS_PATH with a \
+             .comparable.decls extension",
+        )
+        .short("-o")
+        .long("--output")
+        .value_name("PATH"),
+    )
+}
+
+/// Specifies the command line arguments of the `suggest` subcommand, see [crate::suggest].
+pub fn suggest_arg_init(program_name: &str) -> ArgParser {
+    ArgParser::new(
+        format!("{program_name} suggest"),
+        "Proposes newtypes for the program-wide abstract types of a crate, listing every \
+         signature, field and local they change, and writing them as rustfix suggestions",
+    )
+    .arg(ArgSpec::positional(
+        "file",
+        "FILE",
+        "Path to root source file of the crate the types were inferred for",
+    ))
+    .arg(
+        ArgSpec::keyword(
+            "types",
+            "Program-wide abstract types, either the stdout of a --global-types run, or the \
+             .types.json file of a --release --global-types run",
+        )
+        .short("-t")
+        .long("--types")
+        .value_name("PATH")
+        .required(),
+    )
+    .arg(
+        ArgSpec::keyword(
+            "output",
+            "Location of the written rustfix suggestions. Defaults to FILE with a \
+             .suggestions.json extension",
+        )
+        .short("-o")
+        .long("--output")
+        .value_name("PATH"),
+    )
+}
+
+/// Specifies the command line arguments of the `diff` subcommand, see [crate::diff].
+pub fn diff_arg_init(program_name: &str) -> ArgParser {
+    ArgParser::new(
+        format!("{program_name} diff"),
+        "Lists, per program point, the variables whose abstract type differs between two runs. \
+         Exits with a non-zero status if they differ",
+    )
+    .arg(ArgSpec::positional(
+        "old",
+        "OLD",
+        "Partition to compare against, either an .ati file or the stdout of a run",
+    ))
+    .arg(ArgSpec::positional(
+        "new",
+        "NEW",
+        "Partition to compare, either an .ati file or the stdout of a run",
+    ))
+}
+
+/// Specifies the command line arguments of the `static` subcommand, see [crate::static_ati].
+pub fn static_arg_init(program_name: &str) -> ArgParser {
+    ArgParser::new(
+        format!("{program_name} static"),
+        "Infers the abstract types of a crate without running it, by a flow-insensitive \
+         unification over its MIR, and prints them as the report of a run",
+    )
+    .arg(ArgSpec::positional(
+        "file",
+        "FILE",
+        "Path to root source file of the crate to analyze",
+    ))
+    .arg(
+        ArgSpec::keyword(
+            "output",
+            "Write the inferred abstract types as an .ati file at PATH, rather than printing them",
+        )
+        .short("-o")
+        .long("--output")
+        .value_name("PATH"),
+    )
+    .arg(
+        ArgSpec::keyword(
+            "compare",
+            "Partition observed by a run of the crate, either an .ati file or the stdout of a \
+             run. Lists the variables whose abstract type differs from the inferred one",
+        )
+        .short("-c")
+        .long("--compare")
+        .value_name("PATH"),
+    )
+    .arg(
+        ArgSpec::keyword(
+            "instrument-crates",
+            "Comma separated allowlist of dependencies the crate was instrumented alongside, each \
+             given as the crate name and the path to its root source file. Each dependency is \
+             analyzed on its own, in the listed order, so list a dependency before its dependants.",
+        )
+        .long("--instrument-crates")
+        .value_name("NAME=PATH[,NAME=PATH...]"),
+    )
 }
 
 /// Represents the different kinds of command line arguments
@@ -317,18 +562,18 @@ impl ArgParser {
                     }
                 }
             } else {
-                let spec = self
-                    .specs
-                    .iter()
-                    .find(|s| s.kind == ArgKind::Positional)
+                // Positional arguments are filled in the order they were specified.
+                let mut positionals = self.specs.iter().filter(|s| s.kind == ArgKind::Positional);
+                let last = positionals
+                    .clone()
+                    .last()
                     .ok_or_else(|| ArgError::UnexpectedPositional(arg.clone()))?;
-
-                if let Some(first) = parsed.values.get(spec.name) {
+                let Some(spec) = positionals.find(|s| !parsed.values.contains_key(s.name)) else {
                     return Err(ArgError::DuplicatePositional {
-                        first: first.clone(),
+                        first: parsed.values[last.name].clone(),
                         second: arg,
                     });
-                }
+                };
 
                 parsed.values.insert(spec.name, arg);
             }
@@ -12,6 +12,10 @@
 //! A few other helpers are defined within this file as well, view individual function doc
 //! comments to see what they do.
 
+use crate::callbacks::{
+    codegen::runtime, gather::first_pass_info::GlobalItem, types::CanBeTupled,
+};
+
 /// Creates an inner name that does not clash with any other function/method
 /// defined in the same `(mod_path, namespace)` slot.
 /// 
@@ -180,6 +184,47 @@ pub fn create_param_binds<'a>(
     params: impl Iterator<Item = &'a rustc_ast::Param>,
     ppt: &decls_gen::ProgramPoint,
 ) -> Vec<String> {
+    bound_params(params, ppt)
+        .map(|param| {
+            let var_name = get_param_name(param);
+            format!(r#"{var_name}.bind(&mut {site_name}, "{var_name}");"#)
+        })
+        .collect()
+}
+
+/// Generates bind statements for the `const` / `static` items in `globals` against a site
+/// variable.
+///
+/// Globals are bound through their runtime key, so that items which aren't visible from the
+/// function (e.g. private to another module) are bound all the same. Only the globals `ppt`
+/// declares a variable for are bound. decls-gen declares none, so these only come from a
+/// hand-edited `.decls` file.
+pub fn create_global_binds(
+    site_name: &str,
+    globals: &[GlobalItem],
+    ppt: &decls_gen::ProgramPoint,
+) -> Vec<String> {
+    globals
+        .iter()
+        .filter(|global| ppt.var_decl(global.var_name.clone()).is_some())
+        .map(|global| create_global_bind(site_name, global))
+        .collect()
+}
+
+/// Generates a bind statement for the `const` / `static` item `global` against a site variable,
+/// see [create_global_binds].
+pub fn create_global_bind(site_name: &str, global: &GlobalItem) -> String {
+    let analysis = runtime::runtime_path("ATI_ANALYSIS");
+    let GlobalItem { key, var_name } = global;
+    format!(r#"{analysis}.lock().unwrap().bind_global(&mut {site_name}, r"{key}", "{var_name}");"#)
+}
+
+/// Filters `params` down to the formals which get bound at a site of `ppt`, see
+/// [create_param_binds].
+pub fn bound_params<'a>(
+    params: impl Iterator<Item = &'a rustc_ast::Param>,
+    ppt: &decls_gen::ProgramPoint,
+) -> impl Iterator<Item = &'a rustc_ast::Param> {
     params
         .filter(|param| {
             matches!(
@@ -191,16 +236,7 @@ pub fn create_param_binds<'a>(
                     | rustc_ast::TyKind::Path(_, _)
             )
         })
-        .filter_map(|param| {
-            let var_name = get_param_name(param);
-            if is_dead(ppt, &var_name) {
-                return None;
-            }
-            Some(format!(
-                r#"{var_name}.bind(&mut {site_name}, "{var_name}");"#
-            ))
-        })
-        .collect()
+        .filter(|param| !is_dead(ppt, &get_param_name(param)))
 }
 
 /// Returns true iff `ppt`'s `VariableDecl` for some `formal` is tagged
@@ -223,7 +259,7 @@ pub fn is_dead(ppt: &decls_gen::ProgramPoint, formal: &str) -> bool {
 /// Used by the wrapper to decide whether the formal needs a `.reborrow()` when forwarded to the
 /// inner fn. `TaggedRefMut` is move-only, but the binding still has to live
 /// for the EXIT-site binds.
-fn is_tagged_ref_mut(ty: &rustc_ast::Ty) -> bool {
+pub fn is_tagged_ref_mut(ty: &rustc_ast::Ty) -> bool {
     let rustc_ast::TyKind::Path(_, path) = &ty.kind else {
         return false;
     };
@@ -232,3 +268,123 @@ fn is_tagged_ref_mut(ty: &rustc_ast::Ty) -> bool {
         .map(|seg| seg.ident.name.as_str() == "TaggedRefMut")
         .unwrap_or(false)
 }
+
+/// Returns true if the tupled type `ty` is known to be `Copy` from its syntax alone: tagged
+/// primitives (other than `String`), shared references, and tuples and arrays of those.
+///
+/// Anything else (`TaggedRefMut`, collections, user-defined types, ...) may not be `Copy`.
+pub fn is_known_copy(ty: &rustc_ast::Ty) -> bool {
+    match &ty.kind {
+        rustc_ast::TyKind::Ref(_, mut_ty) => !mut_ty.mutbl.is_mut(),
+        rustc_ast::TyKind::Tup(tys) => tys.iter().all(|ty| is_known_copy(ty)),
+        rustc_ast::TyKind::Array(elem, _) => is_known_copy(elem),
+        rustc_ast::TyKind::Path(None, path) => {
+            let Some(seg) = path.segments.last() else {
+                return false;
+            };
+            match seg.ident.name.as_str() {
+                "TaggedRef" => true,
+                // `Tagged<T>` is `Copy` whenever `T` is, e.g. `Tagged<u32>` or `Tagged<[..; N]>`,
+                // but not `Tagged<String>` or a tagged range.
+                "Tagged" => {
+                    let Some(rustc_ast::GenericArgs::AngleBracketed(args)) = seg.args.as_deref()
+                    else {
+                        return false;
+                    };
+                    let Some(rustc_ast::AngleBracketedArg::Arg(rustc_ast::GenericArg::Type(inner))) =
+                        args.args.first()
+                    else {
+                        return false;
+                    };
+                    is_known_copy(inner)
+                        || (inner.can_be_tupled()
+                            && inner.kind.is_simple_path() != Some(rustc_span::sym::String))
+                }
+                _ => false,
+            }
+        }
+        _ => false,
+    }
+}
+
+/// Converts the qualifiers of a fn header to a string like `const unsafe extern "C" `, in the
+/// order rustc expects them.
+///
+/// Returns an empty string for a plain `fn`.
+pub fn fn_header_to_string(header: &rustc_ast::FnHeader) -> String {
+    let mut s = String::new();
+    if let rustc_ast::Const::Yes(_) = header.constness {
+        s.push_str("const ");
+    }
+    match header.coroutine_kind {
+        Some(rustc_ast::CoroutineKind::Async { .. }) => s.push_str("async "),
+        Some(rustc_ast::CoroutineKind::Gen { .. }) => s.push_str("gen "),
+        Some(rustc_ast::CoroutineKind::AsyncGen { .. }) => s.push_str("async gen "),
+        None => {}
+    }
+    match header.safety {
+        rustc_ast::Safety::Unsafe(_) => s.push_str("unsafe "),
+        rustc_ast::Safety::Safe(_) => s.push_str("safe "),
+        rustc_ast::Safety::Default => {}
+    }
+    match &header.ext {
+        rustc_ast::Extern::None => {}
+        rustc_ast::Extern::Implicit(_) => s.push_str("extern "),
+        rustc_ast::Extern::Explicit(abi, _) => {
+            s.push_str(&format!("extern \"{}\" ", abi.symbol_unescaped.as_str()))
+        }
+    }
+
+    s
+}
+
+/// Converts the generic params to a turbofish like `::<T, N>`, naming every type and const
+/// parameter. Lifetimes are left to inference.
+///
+/// Returns an empty string if there are no type or const params.
+pub fn generic_params_to_turbofish(generics: &rustc_ast::Generics) -> String {
+    let args: Vec<&str> = generics
+        .params
+        .iter()
+        .filter(|param| !matches!(param.kind, rustc_ast::GenericParamKind::Lifetime))
+        .map(|param| param.ident.as_str())
+        .collect();
+    if args.is_empty() {
+        return String::new();
+    }
+
+    format!("::<{}>", args.join(", "))
+}
+
+/// Wraps the call of an inner fn/method declared with `header`, so that it completes before the
+/// EXIT site is opened: async calls are awaited, and unsafe calls are placed in an unsafe block.
+pub fn complete_inner_call(header: &rustc_ast::FnHeader, call: String) -> String {
+    let call = match header.coroutine_kind {
+        Some(rustc_ast::CoroutineKind::Async { .. }) => format!("{call}.await"),
+        _ => call,
+    };
+    match header.safety {
+        rustc_ast::Safety::Unsafe(_) => format!("unsafe {{ {call} }}"),
+        _ => call,
+    }
+}
+
+/// Converts the type of a parameter (or return value) of a fn keeping its untagged signature
+/// into the type its inner fn works with.
+///
+/// Only tupleable primitives passed by value are tagged by the shim, see
+/// [crate::callbacks::codegen::function]. Every other type is passed through unchanged.
+pub fn untagged_boundary_ty_to_tagged_string(ty: &rustc_ast::Ty) -> String {
+    let ty_str = rustc_ast_pretty::pprust::ty_to_string(ty);
+    if is_tagged_at_boundary(ty) {
+        format!("{}<{ty_str}>", runtime::runtime_path("Tagged"))
+    } else {
+        ty_str
+    }
+}
+
+/// Returns true if a value of type `ty`, passed to or returned from a fn keeping its untagged
+/// signature, is tagged (or untagged) by that fn's shim.
+pub fn is_tagged_at_boundary(ty: &rustc_ast::Ty) -> bool {
+    !matches!(ty.kind, rustc_ast::TyKind::Ref(..)) && ty.can_be_tupled()
+}
@@ -1,20 +1,30 @@
-//! Implements the runtime library's [SiteBind](crate::ati::site_binds::SiteBind) trait for
+//! Implements the runtime library's `datir_runtime::SiteBind` trait for
 //! user-defined compound types.
 //!
 //! All values (of both atomic and compound types), must be able to be "bound" to sites. This
 //! ultimately just means recording the existing Id associated with some value that is stored
-//! within some variable, at a particular site. The [SiteBind](crate::ati::site_binds::SiteBind)
+//! within some variable, at a particular site. The `datir_runtime::SiteBind`
 //! trait governs this behavior. Following instrumentation, DATIR generates
-//! [SiteBind](crate::ati::site_binds::SiteBind) implementations for all user-defined compound
+//! `datir_runtime::SiteBind` implementations for all user-defined compound
 //! types, so that they recursively bind any tagged fields stored within them.
 //!
 //! To see an example of how this is actually used by shim functions, look at
 //! [crate::callbacks::codegen::function].
+//!
+//! Following Daikon's `:::OBJECT` program points, the generated struct implementations can also
+//! bind the struct's fields to a per-type site (see [`DatirConfig::object_ppts`]), which then
+//! reports the abstract types of those fields regardless of where the struct was observed. The
+//! `:::CLASS` site next to it reports those of the `const` / `static` items of the struct's module.
+//!
+//! [`DatirConfig::object_ppts`]: crate::config::DatirConfig::object_ppts
 
 use crate::{
     callbacks::codegen::common::{
-        generic_args_to_string, generic_params_to_string, where_clause_to_string,
+        create_global_bind, generic_args_to_string, generic_params_to_string,
+        where_clause_to_string,
     },
+    callbacks::codegen::{runtime, units},
+    callbacks::gather::first_pass_info::ObjectPpt,
     callbacks::parsing,
 };
 
@@ -26,39 +36,88 @@ use crate::{
 ///
 /// Both Struct and Tuple struct flavors are accepted (Unit structs have no
 /// fields and produce an empty impl body but are still valid).
+///
+/// If `object_ppt` is given, every bind additionally binds the struct's fields, as `this.<field>`,
+/// to the struct's `:::OBJECT` site, and updates it. Each struct value observed at any site
+/// (including the `self` of every method shim) is thus folded into a single, per-type partition
+/// of its fields. The globals of the struct's module are bound to its `:::CLASS` site alongside.
+/// Only the outermost bind of each struct type does so (see `datir_runtime::StructBind`), the
+/// structs of the same type nested within it are folded into its `:::OBJECT` site as its fields.
+///
+/// Fields annotated with `#[datir::unit]` are marked with their unit at every site they are bound
+/// to, see [units].
 pub fn generate_struct_impls(
     psess: &rustc_session::parse::ParseSess,
     struct_name: &str,
     fields: &[rustc_ast::FieldDef],
     generics: &rustc_ast::Generics,
+    object_ppt: Option<&ObjectPpt>,
     new_items: &mut Vec<Box<rustc_ast::Item>>,
 ) {
-    let bind_calls = fields
-        .iter()
-        .enumerate()
-        .map(|(i, field)| match &field.ident {
-            Some(field_name) => {
-                let field_name = field_name.as_str();
-                format!(r#"self.{field_name}.bind(site, &format!("{{var_name}}.{field_name}"));"#)
-            }
-            None => format!(r#"self.{i}.bind(site, &format!("{{var_name}}.{i}"));"#),
-        })
-        .collect::<Vec<_>>()
-        .join("\n");
+    let bind_calls = build_field_binds(fields, "site", "var_name");
+    let unit_marks = units::build_field_unit_marks(psess, fields, "site", "var_name");
+    let (struct_bind, object_update) = match object_ppt {
+        Some(ObjectPpt {
+            base_ppt_name,
+            class_globals,
+        }) => {
+            let analysis = runtime::runtime_path("ATI_ANALYSIS");
+            let struct_bind_enter = runtime::runtime_path("StructBind::enter");
+            let object_binds = build_field_binds(fields, "&mut site_object", r#""this""#);
+            let object_unit_marks =
+                units::build_field_unit_marks(psess, fields, "&mut site_object", r#""this""#);
+            let class_update = if class_globals.is_empty() {
+                String::new()
+            } else {
+                let class_binds = class_globals
+                    .iter()
+                    .map(|global| create_global_bind("site_class", global))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                format!(
+                    r#"
+                    let mut site_class = {analysis}.lock().unwrap().get_site(r"{base_ppt_name}:::CLASS");
+                    {class_binds}
+                    {analysis}.lock().unwrap().update_site(site_class);
+                    "#
+                )
+            };
+            (
+                format!(r#"let struct_bind = {struct_bind_enter}(r"{base_ppt_name}:::OBJECT");"#),
+                format!(
+                    r#"
+                    if struct_bind.is_outermost() {{
+                        let mut site_object = {analysis}.lock().unwrap().get_site(r"{base_ppt_name}:::OBJECT");
+                        {object_binds}
+                        {object_unit_marks}
+                        {analysis}.lock().unwrap().update_site(site_object);
+                        {class_update}
+                    }}
+                    "#
+                ),
+            )
+        }
+        None => (String::new(), String::new()),
+    };
 
     let generic_params = generic_params_to_string(generics);
     let generic_args = generic_args_to_string(generics);
     let where_clause = where_clause_to_string(generics);
 
+    let site_bind = runtime::runtime_path("SiteBind");
+    let site = runtime::runtime_path("Site");
     let code = format!(
         r#"
-        impl{generic_params} SiteBind for {struct_name}{generic_args}{where_clause} {{
-            fn bind(&self, site: &mut Site, var_name: &str) {{
+        impl{generic_params} {site_bind} for {struct_name}{generic_args}{where_clause} {{
+            fn bind(&self, site: &mut {site}, var_name: &str) {{
+                {struct_bind}
                 {bind_calls}
+                {unit_marks}
+                {object_update}
             }}
         }}
-        impl{generic_params} SiteBind for &{struct_name}{generic_args}{where_clause} {{
-            fn bind(&self, site: &mut Site, var_name: &str) {{
+        impl{generic_params} {site_bind} for &{struct_name}{generic_args}{where_clause} {{
+            fn bind(&self, site: &mut {site}, var_name: &str) {{
                 (**self).bind(site, var_name);
             }}
         }}
@@ -70,6 +129,23 @@ pub fn generate_struct_impls(
     }
 }
 
+/// Generates bind statements for each of the struct's `fields` against `site`, naming each field
+/// after the `parent` variable name (both given as source expressions).
+fn build_field_binds(fields: &[rustc_ast::FieldDef], site: &str, parent: &str) -> String {
+    fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| match &field.ident {
+            Some(field_name) => {
+                let field_name = field_name.as_str();
+                format!(r#"self.{field_name}.bind({site}, &format!("{{}}.{field_name}", {parent}));"#)
+            }
+            None => format!(r#"self.{i}.bind({site}, &format!("{{}}.{i}", {parent}));"#),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
 /// Implements the BindToSite trait on a user-defined enum.
 /// 
 /// The generated impl matches on the enum and recursively binds each variant's payload
@@ -129,17 +205,19 @@ pub fn generate_enum_impls(
     let generic_args = generic_args_to_string(generics);
     let where_clause = where_clause_to_string(generics);
 
+    let site_bind = runtime::runtime_path("SiteBind");
+    let site = runtime::runtime_path("Site");
     let code = format!(
         r#"
-        impl{generic_params} SiteBind for {enum_name}{generic_args}{where_clause} {{
-            fn bind(&self, site: &mut Site, var_name: &str) {{
+        impl{generic_params} {site_bind} for {enum_name}{generic_args}{where_clause} {{
+            fn bind(&self, site: &mut {site}, var_name: &str) {{
                 match self {{
                     {arms_str}
                 }}
             }}
         }}
-        impl{generic_params} SiteBind for &{enum_name}{generic_args}{where_clause} {{
-            fn bind(&self, site: &mut Site, var_name: &str) {{
+        impl{generic_params} {site_bind} for &{enum_name}{generic_args}{where_clause} {{
+            fn bind(&self, site: &mut {site}, var_name: &str) {{
                 (**self).bind(site, var_name);
             }}
         }}
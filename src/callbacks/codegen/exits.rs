@@ -0,0 +1,213 @@
+//! Instruments every exit of a function body as its own `:::EXITnn` program point.
+//!
+//! By default, the shim of a function (see [crate::callbacks::codegen::function]) only updates
+//! a single, aggregate `:::EXIT` site once the inner function returns. Following Daikon's
+//! convention, each exit of a function can also be observed separately, as an `EXITnn` program
+//! point, where `nn` is the line number of the exit within the original source file. This is what
+//! `decls-gen` uses to name return sites as well.
+//!
+//! When [`DatirConfig::exit_ppt_per_return`] is set, every `return` expression within the body of
+//! the inner function is rewritten from `return <expr>` to:
+//! ```rust
+//! return {
+//!     let __ati_ret = <expr>;
+//!     let mut site_exit = ATI_ANALYSIS.get_site("foo:::EXIT12");
+//!     // ... bind live formals ...
+//!     SiteBind::bind(&__ati_ret, &mut site_exit, "return");
+//!     ATI_ANALYSIS.update_site(site_exit);
+//!     __ati_ret
+//! }
+//! ```
+//! Globals declared at the aggregate exit program point are bound to every exit site as well.
+//!
+//! The tail expression of the body is rewritten in the same way, as the exit at the line the
+//! tail expression starts on. Functions returning unit, which fall off the end of their body,
+//! instead get the site update appended to the body, as the exit at the line of its closing brace.
+//!
+//! `return`s within closures, async blocks and nested items exit those, rather than the
+//! function, and are left untouched. Early returns through `?` are not observed.
+//!
+//! [`DatirConfig::exit_ppt_per_return`]: crate::config::DatirConfig::exit_ppt_per_return
+
+use rustc_ast::mut_visit::MutVisitor;
+
+use crate::callbacks::{
+    codegen::{
+        common::{bound_params, get_param_name, is_known_copy, is_tagged_ref_mut},
+        runtime,
+    },
+    parsing,
+};
+
+/// Name of the binding holding the returned value, while the exit site is updated.
+const RETURNED_BINDING: &str = "__ati_ret";
+
+/// Prefix of the bindings holding the entry values of formals bound at each exit.
+const SNAPSHOT_PREFIX: &str = "__ati_exit_";
+
+/// Rewrites every exit of `body` to update its own `{base_ppt_name}:::EXITnn` site.
+///
+/// `params` are the formals of the function owning `body` (without any receiver), bound at each
+/// exit if live at `exit_ppt`, like at the aggregate exit site. `extra_binds` bind anything else
+/// (the receiver, globals) to a site named `site_exit`, and `output` is the return type of the
+/// function.
+pub fn instrument_exit_sites(
+    psess: &rustc_session::parse::ParseSess,
+    base_ppt_name: &str,
+    params: &[&rustc_ast::Param],
+    exit_ppt: &decls_gen::ProgramPoint,
+    extra_binds: &[String],
+    output: &rustc_ast::FnRetTy,
+    body: &mut rustc_ast::Block,
+) {
+    // the body may shadow or mutate its formals, while the aggregate exit site binds the values
+    // they were called with. Formals whose (already tupled) type is known to be Copy are thus
+    // snapshotted on entry, and those snapshots are bound instead. Mutable references and other
+    // formals which may not be Copy would be moved by a snapshot, and are bound as they are.
+    let mut snapshots = Vec::new();
+    let mut exit_binds = extra_binds.to_vec();
+    for param in bound_params(params.iter().copied(), exit_ppt) {
+        let name = get_param_name(param);
+        let bound = if is_tagged_ref_mut(&param.ty) || !is_known_copy(&param.ty) {
+            name.clone()
+        } else {
+            let snapshot = format!("{SNAPSHOT_PREFIX}{name}");
+            snapshots.push(format!(
+                "#[allow(unused_variables)] let {snapshot} = {name};"
+            ));
+            snapshot
+        };
+        exit_binds.push(format!(r#"{bound}.bind(&mut site_exit, "{name}");"#));
+    }
+
+    let exit_binds = exit_binds.join("\n");
+    let mut instrumenter = ExitSiteInstrumenter {
+        psess,
+        base_ppt_name,
+        exit_binds: &exit_binds,
+        binds_return: matches!(output, rustc_ast::FnRetTy::Ty(_)),
+    };
+    instrumenter.visit_block(body);
+
+    // the tail expression (or the end of a unit body) is the last exit.
+    let end_line = instrumenter.line_of(body.span.shrink_to_hi());
+    let ends_in_return = matches!(
+        body.stmts.last().map(|stmt| &stmt.kind),
+        Some(rustc_ast::StmtKind::Semi(expr)) if matches!(expr.kind, rustc_ast::ExprKind::Ret(_))
+    );
+    match body.stmts.last_mut().map(|stmt| &mut stmt.kind) {
+        Some(rustc_ast::StmtKind::Expr(tail)) => {
+            let line = instrumenter.line_of(tail.span);
+            instrumenter.wrap_returned(tail, line);
+        }
+        _ if !instrumenter.binds_return && !ends_in_return => {
+            let update = instrumenter.site_update_src(end_line, None);
+            body.stmts
+                .push(parsing::parse_stmt(psess, format!("{{ {update} }}")));
+        }
+        _ => {}
+    }
+
+    for (i, snapshot) in snapshots.into_iter().enumerate() {
+        body.stmts.insert(i, parsing::parse_stmt(psess, snapshot));
+    }
+}
+
+/// Visitor rewriting every `return` expression of a function body.
+struct ExitSiteInstrumenter<'a> {
+    psess: &'a rustc_session::parse::ParseSess,
+    base_ppt_name: &'a str,
+    exit_binds: &'a str,
+    /// Whether the function returns a value other than unit, which is bound to each exit site.
+    binds_return: bool,
+}
+
+impl ExitSiteInstrumenter<'_> {
+    /// Line of `span` within the original source file.
+    fn line_of(&self, span: rustc_span::Span) -> usize {
+        self.psess.source_map().lookup_char_pos(span.lo()).line
+    }
+
+    /// Source which opens the exit site at `line`, binds all live formals to it, and stores it.
+    /// If the function returns a value, `returned` names the binding holding it.
+    fn site_update_src(&self, line: usize, returned: Option<&str>) -> String {
+        let analysis = runtime::runtime_path("ATI_ANALYSIS");
+        let base_ppt_name = self.base_ppt_name;
+        let exit_binds = self.exit_binds;
+        // the type of the returned value may only be inferred from the function's signature
+        // (e.g. `.collect()`, `todo!()`), which method call syntax can't wait for.
+        let return_bind = match returned {
+            Some(value) if self.binds_return => format!(
+                r#"{}(&{value}, &mut site_exit, "{}");"#,
+                runtime::runtime_path("SiteBind::bind"),
+                decls_gen::decls::RETURN_VAR_NAME
+            ),
+            _ => String::new(),
+        };
+
+        format!(
+            r#"let mut site_exit = {analysis}.lock().unwrap().get_site(r"{base_ppt_name}:::EXIT{line}");
+            {exit_binds}
+            {return_bind}
+            {analysis}.lock().unwrap().update_site(site_exit);"#
+        )
+    }
+
+    /// Replaces `returned` with a block which updates the exit site at `line`, before evaluating
+    /// to the original value.
+    fn wrap_returned(&self, returned: &mut rustc_ast::Expr, line: usize) {
+        let update = self.site_update_src(line, Some(RETURNED_BINDING));
+        let mut block = parsing::parse_expr(
+            self.psess,
+            format!("{{ let {RETURNED_BINDING} = (); {update} {RETURNED_BINDING} }}"),
+        );
+
+        // swap the placeholder initializer for the returned expression.
+        let rustc_ast::ExprKind::Block(body, _) = &mut block.kind else {
+            panic!("Parsed exit site update is not a block expression");
+        };
+        let rustc_ast::StmtKind::Let(local) = &mut body.stmts[0].kind else {
+            panic!("Parsed exit site update does not start with a let statement");
+        };
+        let rustc_ast::LocalKind::Init(init) = &mut local.kind else {
+            panic!("Parsed exit site update has an uninitialized let statement");
+        };
+        **init = std::mem::replace(returned, rustc_ast::Expr::dummy());
+        *returned = block;
+    }
+}
+
+impl MutVisitor for ExitSiteInstrumenter<'_> {
+    fn visit_expr(&mut self, expr: &mut rustc_ast::Expr) {
+        match &mut expr.kind {
+            // these exit something other than the function.
+            rustc_ast::ExprKind::Closure(..) | rustc_ast::ExprKind::Gen(..) => {}
+
+            // `return <expr>` and `return` (as `return { <update> }`).
+            rustc_ast::ExprKind::Ret(returned) => {
+                let line = self.line_of(expr.span);
+                match returned {
+                    Some(returned) => {
+                        self.visit_expr(returned);
+                        self.wrap_returned(returned, line);
+                    }
+                    None => {
+                        let update = self.site_update_src(line, None);
+                        *returned = Some(Box::new(parsing::parse_expr(
+                            self.psess,
+                            format!("{{ {update} }}"),
+                        )));
+                    }
+                }
+            }
+
+            _ => rustc_ast::mut_visit::walk_expr(self, expr),
+        }
+    }
+
+    /// Nested items have their own exits.
+    fn visit_item(&mut self, _item: &mut rustc_ast::Item) {}
+
+    /// Array lengths and const generic arguments cannot return.
+    fn visit_anon_const(&mut self, _node: &mut rustc_ast::AnonConst) {}
+}
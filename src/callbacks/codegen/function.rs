@@ -50,11 +50,18 @@
 //! therefore it is not bound to the exit site. Further note the return value, which is bound to
 //! the exit site.
 //!
-//! `.bind()` functionality is dependent on the [SiteBind](crate::ati::site_binds::SiteBind)
+//! Module-level `const` / `static` items which `decls-gen` declared at a program point (named
+//! `::MAX`, `::module::LIMIT`, ...) are bound to its sites as well, through the ids the runtime
+//! associates with them, see [crate::callbacks::instrument::expr].
+//!
+//! If [`DatirConfig::exit_ppt_per_return`] is set, each exit of `foo0` additionally updates its
+//! own `foo:::EXITnn` site, see [crate::callbacks::codegen::exits].
+//!
+//! `.bind()` functionality is dependent on the `datir_runtime::SiteBind`
 //! trait, defined within the runtime library. Implementation of this trait on compound types
 //! will result in each recursive field being bound to the site, as a *separate variable*. It's
 //! for this reason, that all user-defined compound types have a
-//! [SiteBind](crate::ati::site_binds::SiteBind) implementation dynamically generated. Calling
+//! `datir_runtime::SiteBind` implementation dynamically generated. Calling
 //! `.bind()` on an untracked type will cause a no-op, as there is no known information about
 //! that value. Calling `.bind()` on a simple `Tagged<T>` (or reference variant), will only
 //! associate the single Id.
@@ -62,16 +69,65 @@
 //! If the original function does not return anything (or returns unit), then the return value is
 //! also ignored. If the original function is main, corresponding ENTER and EXIT sites are still
 //! created, but at the end of the function, `.produce_ati()` is invoked (if DATIR is running in
-//!  --release mode), or `.report()` otherwise, to actually write comparability output.
+//!  --release mode), or `.report()` otherwise, to actually write comparability output. With
+//! `--global-types`, program-wide abstract types are then reported (or produced) as well. With
+//! `--format json`, `.produce_json()` / `.report_json()` are invoked instead, and are passed the
+//! source location of every instrumented function and struct of the crate. With `--contract`, the
+//! shim of main loads the expected partition before anything else, see the runtime's `contract`
+//! module.
+//!
+//! The shim keeps the header (`async`, `unsafe`, ...) of the original function, and so does the
+//! inner function. The shim of an `async fn` awaits the inner function, so that the EXIT site is
+//! updated once the returned future completes, rather than when it is created.
+//!
+//! Functions skipped by the user (see [crate::callbacks::gather::scope]) get no shim at all.
+//!
+//! `const fn`s and `extern "C" fn`s keep their original, untagged signature (see
+//! [crate::callbacks::fn_headers]). Their shim tags the tupleable primitives it is passed, calls
+//! the (Rust ABI, non-const) inner function, and returns the untagged result:
+//! ```rust
+//! extern "C" fn foo(x: u32) -> u32 {
+//!     let mut x = ATI::track(x);
+//!     // ... ENTER site management ...
+//!     let res = foo0(x);
+//!     // ... EXIT site management ...
+//!     return res.1;
+//! }
+//!
+//! fn foo0(mut x: Tagged<u32>) -> Tagged<u32> {
+//!     // foo's original logic, instrumented...
+//! }
+//! ```
+//! Functions on the public API boundary (see [`fn_headers::preserves_abi`]) keep their signature
+//! the same way, so that uninstrumented code can still call them, and get the same shim.
+//!
+//! As the runtime is unavailable in const contexts, a `const fn`'s shim is instead moved to a
+//! separate, non-const function, and the `const fn` selects between it and an uninstrumented copy
+//! of its original body, depending on where it is evaluated:
+//! ```rust
+//! const fn foo(x: u32) -> u32 {
+//!     ::std::intrinsics::const_eval_select((x,), foo2, foo1)
+//! }
+//!
+//! fn foo1(x: u32) -> u32 { /* the shim, as above */ }
+//!
+//! const fn foo2(x: u32) -> u32 {
+//!     // foo's original logic...
+//! }
+//! ```
 
 use crate::{
     callbacks::codegen::common::{
-        build_inner_call_args, create_param_binds, generic_params_to_string, get_param_name,
-        get_unique_inner_name, where_clause_to_string,
+        build_inner_call_args, complete_inner_call, create_global_binds, create_param_binds,
+        fn_header_to_string, generic_params_to_string, generic_params_to_turbofish, get_param_name,
+        get_unique_inner_name, is_tagged_at_boundary, untagged_boundary_ty_to_tagged_string,
+        where_clause_to_string,
     },
-    callbacks::gather::first_pass_info::{FirstPassInfo, FnNamespace},
+    callbacks::codegen::{exits, runtime, units},
+    callbacks::fn_headers,
+    callbacks::gather::first_pass_info::{FirstPassInfo, FnNamespace, GlobalItem, PptSpan},
     callbacks::parsing,
-    config::DatirConfig,
+    config::{ContractMode, DatirConfig, OutputFormat},
 };
 
 use decls_gen::decls::RETURN_VAR_NAME;
@@ -80,27 +136,43 @@ use decls_gen::decls::RETURN_VAR_NAME;
 /// 
 /// Shims perform site management, take a look at the header comment on this file to see
 /// what that involves.
+///
+/// `const_eval_body` is the uninstrumented body of a `const fn`, evaluated in const contexts.
 pub fn generate_function_shim(
     datir_config: &DatirConfig,
     first_pass: &FirstPassInfo,
     psess: &rustc_session::parse::ParseSess,
     mod_path: &str,
     func: &mut Box<rustc_ast::Fn>,
+    const_eval_body: Option<&rustc_ast::Block>,
     new_items: &mut Vec<Box<rustc_ast::Item>>,
 ) {
     let box rustc_ast::Fn {
         ident,
         generics,
-        sig: rustc_ast::FnSig { decl, .. },
+        sig: rustc_ast::FnSig { header, decl, .. },
         body,
         ..
     } = func;
 
+    // functions skipped by the user are compiled as they were written.
+    if first_pass
+        .fns
+        .is_skipped(mod_path, FnNamespace::Free, ident.as_str())
+    {
+        return;
+    }
+
     // find a name for the function which does not conflict with
     // any other name in the current module namespace.
     let orig_name = ident.as_str().to_string();
-    let known_names = first_pass.fns.names_in(mod_path, FnNamespace::Free);
+    let mut known_names = first_pass.fns.names_in(mod_path, FnNamespace::Free);
     let inner_name = get_unique_inner_name(&orig_name, &known_names);
+    known_names.insert(inner_name.clone());
+    let keeps_signature = fn_headers::ast_keeps_untagged_signature(header)
+        || first_pass
+            .fns
+            .preserves_abi(mod_path, FnNamespace::Free, ident.as_str());
     if datir_config.print_function_signatures {
         datir_config.log(
             "FunctionStubs",
@@ -123,12 +195,54 @@ pub fn generate_function_shim(
         });
 
     // rip the original body out of the free function.
-    let orig_body = body
+    let mut orig_body = body
         .take()
         .unwrap_or_else(|| panic!("free fn `{orig_name}` in module `{mod_path}` has no body."));
 
-    // construct fn item that will at some point contain the original body
-    let inner_template = build_inner_fn_template(&inner_name, generics, &decl.inputs, &decl.output);
+    // EXIT-ppt liveness will determine which formals get bound at the
+    // exit site. Pass 1 already validated existence of both ppts.
+    let enter_ppt = datir_config
+        .decls_file
+        .enter_ppt(entry)
+        .expect("ENTER ppt missing.");
+    let exit_ppt = datir_config
+        .decls_file
+        .exit_ppt(entry)
+        .expect("EXIT ppt missing");
+
+    // observe each exit of the original body separately, if requested.
+    if datir_config.exit_ppt_per_return && orig_name != "main" {
+        let params = decl.inputs.iter().collect::<Vec<_>>();
+        exits::instrument_exit_sites(
+            psess,
+            entry,
+            &params,
+            exit_ppt,
+            &create_global_binds("site_exit", &first_pass.globals, exit_ppt),
+            &decl.output,
+            &mut orig_body,
+        );
+    }
+
+    // construct fn item that will at some point contain the original body. It keeps the
+    // header of the original function, except for qualifiers only the shim must keep.
+    let inner_header = if keeps_signature {
+        rustc_ast::FnHeader {
+            constness: rustc_ast::Const::No,
+            ext: rustc_ast::Extern::None,
+            ..*header
+        }
+    } else {
+        *header
+    };
+    let inner_template = build_inner_fn_template(
+        &inner_name,
+        &inner_header,
+        generics,
+        &decl.inputs,
+        &decl.output,
+        keeps_signature,
+    );
     let mut parsed_items = parsing::parse_items(psess, inner_template, None);
     let mut inner_item = parsed_items
         .pop()
@@ -149,25 +263,19 @@ pub fn generate_function_shim(
     // module
     new_items.push(inner_item);
 
-    // EXIT-ppt liveness will determine which formals get bound at the
-    // exit site. Pass 1 already validated existence of both ppts.
-    let enter_ppt = datir_config
-        .decls_file
-        .enter_ppt(entry)
-        .expect("ENTER ppt missing.");
-    let exit_ppt = datir_config
-        .decls_file
-        .exit_ppt(entry)
-        .expect("EXIT ppt missing");
-
     // construct the "shim code", and insert it where the original body was.
     let wrapper_src = build_fn_wrapper_block(
+        psess,
         datir_config,
         entry,
         &orig_name,
         &inner_name,
+        header,
+        keeps_signature,
         &decl.inputs,
         &decl.output,
+        &first_pass.globals,
+        &first_pass.ppt_spans,
         enter_ppt,
         exit_ppt,
     );
@@ -176,19 +284,171 @@ pub fn generate_function_shim(
     let rustc_ast::ExprKind::Block(new_block, _) = parsed_wrapper.kind else {
         panic!("wrapper-block source for free fn `{orig_name}` did not parse as a block");
     };
+
+    // the shim of a const fn cannot run in const contexts, so move it to a separate runtime
+    // function, and have the const fn select between it and the original body.
+    if let rustc_ast::Const::Yes(_) = header.constness {
+        let const_eval_body = const_eval_body
+            .unwrap_or_else(|| panic!("const fn `{orig_name}` has no const-evaluated body."));
+        let runtime_name = get_unique_inner_name(&orig_name, &known_names);
+        known_names.insert(runtime_name.clone());
+        let const_name = get_unique_inner_name(&orig_name, &known_names);
+
+        new_items.push(build_helper_fn(
+            psess,
+            &runtime_name,
+            header,
+            generics,
+            &decl.inputs,
+            &decl.output,
+            new_block,
+            false,
+        ));
+        new_items.push(build_helper_fn(
+            psess,
+            &const_name,
+            header,
+            generics,
+            &decl.inputs,
+            &decl.output,
+            Box::new(const_eval_body.clone()),
+            true,
+        ));
+
+        let selector_src = build_const_eval_select_block(
+            &const_name,
+            &runtime_name,
+            generics,
+            &decl.inputs,
+        );
+        let parsed_selector = parsing::parse_expr(psess, selector_src);
+        let rustc_ast::ExprKind::Block(selector_block, _) = parsed_selector.kind else {
+            panic!("const-eval-select source for const fn `{orig_name}` did not parse as a block");
+        };
+        *body = Some(selector_block);
+        return;
+    }
+
     *body = Some(new_block);
 }
 
+/// Builds a helper of a `const fn`, with the untagged signature of the original function and
+/// `body` as its body.
+///
+/// The helper is never `unsafe`, as `const_eval_select` only accepts safe functions. If the
+/// original function was `unsafe`, then the body is placed in an unsafe block instead. Only the
+/// helper evaluated in const contexts (`is_const`) is a `const fn`.
+fn build_helper_fn(
+    psess: &rustc_session::parse::ParseSess,
+    name: &str,
+    header: &rustc_ast::FnHeader,
+    generics: &rustc_ast::Generics,
+    inputs: &[rustc_ast::Param],
+    output: &rustc_ast::FnRetTy,
+    body: Box<rustc_ast::Block>,
+    is_const: bool,
+) -> Box<rustc_ast::Item> {
+    let generic_params = generic_params_to_string(generics);
+    let where_clause = where_clause_to_string(generics);
+    let constness = if is_const { "const " } else { "" };
+    let declared = inputs
+        .iter()
+        .map(|p| {
+            format!(
+                "mut {}: {}",
+                get_param_name(p),
+                rustc_ast_pretty::pprust::ty_to_string(&p.ty)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    let ret = match output {
+        rustc_ast::FnRetTy::Ty(t) => format!(" -> {}", rustc_ast_pretty::pprust::ty_to_string(t)),
+        rustc_ast::FnRetTy::Default(_) => String::new(),
+    };
+    let is_unsafe = matches!(header.safety, rustc_ast::Safety::Unsafe(_));
+    let block = if is_const && is_unsafe {
+        "{ unsafe { } }"
+    } else {
+        "{ }"
+    };
+
+    let template = format!(
+        "#[allow(unused_mut, unused_unsafe)]\n\
+         {constness}fn {name}{generic_params}({declared}){ret}{where_clause} {block}"
+    );
+    let mut helper = parsing::parse_items(psess, template, None)
+        .pop()
+        .expect("helper-fn template did not parse into an item");
+
+    let rustc_ast::ItemKind::Fn(box rustc_ast::Fn {
+        body: Some(ref mut helper_body),
+        ..
+    }) = helper.kind
+    else {
+        panic!("helper-fn template did not yield ItemKind::Fn");
+    };
+    if is_const && is_unsafe {
+        let rustc_ast::StmtKind::Expr(unsafe_block) = &mut helper_body.stmts[0].kind else {
+            panic!("helper-fn template does not hold an unsafe block");
+        };
+        let rustc_ast::ExprKind::Block(unsafe_block, _) = &mut unsafe_block.kind else {
+            panic!("helper-fn template does not hold an unsafe block");
+        };
+        unsafe_block.stmts = body.stmts;
+    } else {
+        *helper_body = body;
+    }
+
+    helper
+}
+
+/// Creates the body of a `const fn`, which evaluates `const_name` in const contexts, and
+/// `runtime_name` otherwise, passing along every parameter.
+fn build_const_eval_select_block(
+    const_name: &str,
+    runtime_name: &str,
+    generics: &rustc_ast::Generics,
+    inputs: &[rustc_ast::Param],
+) -> String {
+    let turbofish = generic_params_to_turbofish(generics);
+    let args = match inputs {
+        [] => "()".to_string(),
+        _ => format!(
+            "({},)",
+            inputs.iter().map(get_param_name).collect::<Vec<_>>().join(", ")
+        ),
+    };
+
+    format!(
+        "{{ ::std::intrinsics::const_eval_select({args}, {const_name}{turbofish}, \
+         {runtime_name}{turbofish}) }}"
+    )
+}
+
 /// Source for an inner free function signature with an empty placeholder body. 
 /// 
 /// The caller parses this template, then transplants the user's original body into this, to 
 /// create an "inner" function which holds the original function's logic.
+///
+/// If the original function keeps its untagged signature (`keeps_signature`), the types of its
+/// parameters and return value are tagged here instead.
 fn build_inner_fn_template(
     inner_name: &str,
+    header: &rustc_ast::FnHeader,
     generics: &rustc_ast::Generics,
     inputs: &[rustc_ast::Param],
     output: &rustc_ast::FnRetTy,
+    keeps_signature: bool,
 ) -> String {
+    let ty_to_string = |ty: &rustc_ast::Ty| {
+        if keeps_signature {
+            untagged_boundary_ty_to_tagged_string(ty)
+        } else {
+            rustc_ast_pretty::pprust::ty_to_string(ty)
+        }
+    };
+    let qualifiers = fn_header_to_string(header);
     let generic_params = generic_params_to_string(generics);
     let where_clause = where_clause_to_string(generics);
     let declared = inputs
@@ -200,44 +460,81 @@ fn build_inner_fn_template(
             // also needs to allow mutable access!! just make everything mutable?
             // FIXME: i honestly think this system sucks. not sure how to avoid it
             // without changing the ref (specifically mut ref) implementation again...
-            format!(
-                "mut {}: {}",
-                get_param_name(p),
-                rustc_ast_pretty::pprust::ty_to_string(&p.ty)
-            )
+            format!("mut {}: {}", get_param_name(p), ty_to_string(&p.ty))
         })
         .collect::<Vec<_>>()
         .join(", ");
     let ret = match output {
-        rustc_ast::FnRetTy::Ty(t) => format!(" -> {}", rustc_ast_pretty::pprust::ty_to_string(t)),
+        rustc_ast::FnRetTy::Ty(t) => format!(" -> {}", ty_to_string(t)),
         rustc_ast::FnRetTy::Default(_) => String::new(),
     };
-    format!("fn {inner_name}{generic_params}({declared}){ret}{where_clause} {{ }}")
+    format!("{qualifiers}fn {inner_name}{generic_params}({declared}){ret}{where_clause} {{ }}")
 }
 
 /// Creates the body for a free fn's shim. 
 /// 
 /// Each shim will:
-/// 1. open ENTER, bind every formal, update;
+/// 1. open ENTER, bind every formal, mark the formals annotated with `#[datir::unit]` with their
+///    unit (see [units]), update;
 /// 2. call `inner_name(args)`;
 /// 3. open EXIT, bind only formals still live at exit (per the EXIT ppt's
 ///    `is_uninit()` tags) and the return value when non-unit, update.
 ///
 /// Special-cased for fn_name == "main": no param binds, no return value to
-/// bind, and the analysis report is produced after the EXIT site update.
+/// bind, the contract (if any) is loaded before the ENTER site, and the analysis report is
+/// produced after the EXIT site update. `ppt_spans` is only used by the JSON output of the report
+/// and by the contract.
+///
+/// The inner call is awaited and/or placed in an unsafe block, according to `header`. If the
+/// function keeps its untagged signature (`keeps_signature`), tupleable parameters are tagged on
+/// entry, and a tagged return value is untagged.
 fn build_fn_wrapper_block(
+    psess: &rustc_session::parse::ParseSess,
     config: &DatirConfig,
     base_ppt_name: &str,
     fn_name: &str,
     inner_name: &str,
+    header: &rustc_ast::FnHeader,
+    keeps_signature: bool,
     inputs: &[rustc_ast::Param],
     output: &rustc_ast::FnRetTy,
+    globals: &[GlobalItem],
+    ppt_spans: &std::collections::BTreeMap<String, PptSpan>,
     enter_ppt: &decls_gen::ProgramPoint,
     exit_ppt: &decls_gen::ProgramPoint,
 ) -> String {
     let passed = build_inner_call_args(inputs.iter());
-    let enter_binds = create_param_binds("site_enter", inputs.iter(), enter_ppt).join("\n");
+    let enter_globals = create_global_binds("site_enter", globals, enter_ppt).join("\n");
+    let exit_globals = create_global_binds("site_exit", globals, exit_ppt).join("\n");
+    let enter_binds = create_param_binds("site_enter", inputs.iter(), enter_ppt)
+        .into_iter()
+        .chain(units::create_param_unit_marks(
+            psess,
+            "site_enter",
+            inputs.iter(),
+            enter_ppt,
+        ))
+        .collect::<Vec<_>>()
+        .join("\n");
     let exit_binds = create_param_binds("site_exit", inputs.iter(), exit_ppt).join("\n");
+    let call = complete_inner_call(header, format!("{inner_name}({passed})"));
+
+    let analysis = runtime::runtime_path("ATI_ANALYSIS");
+
+    let track = runtime::runtime_path("ATI::track");
+    let tag_params = inputs
+        .iter()
+        .filter(|p| keeps_signature && is_tagged_at_boundary(&p.ty))
+        .map(|p| {
+            let name = get_param_name(p);
+            format!("let mut {name} = {track}({name});")
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let returned = match output {
+        rustc_ast::FnRetTy::Ty(t) if keeps_signature && is_tagged_at_boundary(t) => "res.1",
+        _ => "res",
+    };
 
     if fn_name == "main" {
         // In --release mode each execution produces a fresh .ati file at
@@ -245,27 +542,77 @@ fn build_fn_wrapper_block(
         // don't clobber each other. The dir was wiped + canonicalized in
         // datir's main.rs, so the path here is absolute. Outside release
         // mode just dump the report to stdout via the existing API.
-        let post = match &config.ati_output_dir {
-            Some(dir) => format!(
-                r#"{{
-                    let __ati_rand: u64 = std::random::random(..);
-                    let __ati_path = format!(r"{}/{{:016x}}.ati", __ati_rand);
-                    ATI_ANALYSIS.lock().unwrap().produce_ati(&__ati_path);
-                }}"#,
-                dir.to_str().expect("ati_output_dir is not valid UTF-8"),
+        // With --global-types, program-wide types follow the report, or are written next to
+        // the .ati file, sharing its name.
+        let global_types = config.global_types;
+        let post = match (&config.ati_output_dir, config.output_format) {
+            (Some(dir), OutputFormat::Text) => {
+                let dir = dir.to_str().expect("ati_output_dir is not valid UTF-8");
+                let global_types = if global_types {
+                    format!(
+                        r#"{analysis}.lock().unwrap().produce_global_types(&format!(r"{dir}/{{:016x}}.types.json", __ati_rand));"#
+                    )
+                } else {
+                    String::new()
+                };
+                format!(
+                    r#"{{
+                        let __ati_rand: u64 = std::random::random(..);
+                        let __ati_path = format!(r"{dir}/{{:016x}}.ati", __ati_rand);
+                        {analysis}.lock().unwrap().produce_ati(&__ati_path);
+                        {global_types}
+                    }}"#
+                )
+            }
+            // JSON Lines output holds the program-wide types as records of their own.
+            (Some(dir), OutputFormat::Json) => {
+                let dir = dir.to_str().expect("ati_output_dir is not valid UTF-8");
+                let spans = build_ppt_spans(ppt_spans);
+                format!(
+                    r#"{{
+                        let __ati_rand: u64 = std::random::random(..);
+                        let __ati_path = format!(r"{dir}/{{:016x}}.jsonl", __ati_rand);
+                        {analysis}.lock().unwrap().produce_json(&__ati_path, &format!("{{:016x}}", __ati_rand), {spans}, {global_types});
+                    }}"#
+                )
+            }
+            (None, OutputFormat::Json) => {
+                let spans = build_ppt_spans(ppt_spans);
+                format!("{analysis}.lock().unwrap().report_json({spans}, {global_types});")
+            }
+            (None, OutputFormat::Text) if global_types => format!(
+                "{analysis}.lock().unwrap().report(); {analysis}.lock().unwrap().report_global_types();"
             ),
-            None => "ATI_ANALYSIS.lock().unwrap().report();".to_string(),
+            (None, OutputFormat::Text) => format!("{analysis}.lock().unwrap().report();"),
+        };
+
+        // With --contract, the expected partition is loaded before the first site is entered.
+        let contract = match &config.contract {
+            Some(path) => {
+                let path = path.to_str().expect("contract path is not valid UTF-8");
+                let mode = runtime::runtime_path(match config.contract_mode {
+                    ContractMode::Warn => "ContractMode::Warn",
+                    ContractMode::Log => "ContractMode::Log",
+                    ContractMode::Panic => "ContractMode::Panic",
+                });
+                let spans = build_ppt_spans(ppt_spans);
+                format!(r#"{analysis}.lock().unwrap().load_contract(r"{path}", {mode}, {spans});"#)
+            }
+            None => String::new(),
         };
 
         return format!(
             r#"{{
-                let mut site_enter = ATI_ANALYSIS.lock().unwrap().get_site(r"{base_ppt_name}:::ENTER");
-                ATI_ANALYSIS.lock().unwrap().update_site(site_enter);
+                {contract}
+                let mut site_enter = {analysis}.lock().unwrap().get_site(r"{base_ppt_name}:::ENTER");
+                {enter_globals}
+                {analysis}.lock().unwrap().update_site(site_enter);
 
-                {inner_name}();
+                {call};
 
-                let mut site_exit = ATI_ANALYSIS.lock().unwrap().get_site(r"{base_ppt_name}:::EXIT");
-                ATI_ANALYSIS.lock().unwrap().update_site(site_exit);
+                let mut site_exit = {analysis}.lock().unwrap().get_site(r"{base_ppt_name}:::EXIT");
+                {exit_globals}
+                {analysis}.lock().unwrap().update_site(site_exit);
 
                 {post}
             }}"#
@@ -275,32 +622,54 @@ fn build_fn_wrapper_block(
     match output {
         rustc_ast::FnRetTy::Ty(_) => format!(
             r#"{{
-                let mut site_enter = ATI_ANALYSIS.lock().unwrap().get_site(r"{base_ppt_name}:::ENTER");
+                {tag_params}
+                let mut site_enter = {analysis}.lock().unwrap().get_site(r"{base_ppt_name}:::ENTER");
                 {enter_binds}
-                ATI_ANALYSIS.lock().unwrap().update_site(site_enter);
+                {enter_globals}
+                {analysis}.lock().unwrap().update_site(site_enter);
 
-                let res = {inner_name}({passed});
+                let res = {call};
 
-                let mut site_exit = ATI_ANALYSIS.lock().unwrap().get_site(r"{base_ppt_name}:::EXIT");
+                let mut site_exit = {analysis}.lock().unwrap().get_site(r"{base_ppt_name}:::EXIT");
                 {exit_binds}
+                {exit_globals}
                 res.bind(&mut site_exit, "{RETURN_VAR_NAME}");
-                ATI_ANALYSIS.lock().unwrap().update_site(site_exit);
+                {analysis}.lock().unwrap().update_site(site_exit);
 
-                return res;
+                return {returned};
             }}"#
         ),
         rustc_ast::FnRetTy::Default(_) => format!(
             r#"{{
-                let mut site_enter = ATI_ANALYSIS.lock().unwrap().get_site(r"{base_ppt_name}:::ENTER");
+                {tag_params}
+                let mut site_enter = {analysis}.lock().unwrap().get_site(r"{base_ppt_name}:::ENTER");
                 {enter_binds}
-                ATI_ANALYSIS.lock().unwrap().update_site(site_enter);
+                {enter_globals}
+                {analysis}.lock().unwrap().update_site(site_enter);
 
-                {inner_name}({passed});
+                {call};
 
-                let mut site_exit = ATI_ANALYSIS.lock().unwrap().get_site(r"{base_ppt_name}:::EXIT");
+                let mut site_exit = {analysis}.lock().unwrap().get_site(r"{base_ppt_name}:::EXIT");
                 {exit_binds}
-                ATI_ANALYSIS.lock().unwrap().update_site(site_exit);
+                {exit_globals}
+                {analysis}.lock().unwrap().update_site(site_exit);
             }}"#
         ),
     }
 }
+
+/// Builds the `&[(base ppt name, FnSpan)]` slice expression the JSON output and the contract of
+/// the main shim are passed, locating the function or struct owning each ppt.
+fn build_ppt_spans(ppt_spans: &std::collections::BTreeMap<String, PptSpan>) -> String {
+    let fn_span = runtime::runtime_path("FnSpan");
+    let entries: Vec<String> = ppt_spans
+        .iter()
+        .map(|(base, span)| {
+            format!(
+                "({base:?}, {fn_span} {{ file: {:?}, line_start: {}, col_start: {}, line_end: {}, col_end: {} }})",
+                span.file, span.line_start, span.col_start, span.line_end, span.col_end
+            )
+        })
+        .collect();
+    format!("&[{}]", entries.join(", "))
+}
@@ -5,14 +5,22 @@
 //! This file is analogous to [crate::callbacks::codegen::function], but handles the method-specific edge
 //! cases and caveats described in [crate::callbacks::codegen::methods], namely Self-qualification, and
 //! managing the `self` parameter.
+//!
+//! Methods on the public API boundary (see [`fn_headers::preserves_abi`]) keep their untagged
+//! signature, the same way free functions do: the shim tags the tupleable primitives it is passed,
+//! and untags the returned value.
 
 use crate::{
     callbacks::codegen::common::{
-        build_inner_call_args, create_param_binds, generic_params_to_string, get_param_name,
-        get_unique_inner_name, is_dead, where_clause_to_string,
+        build_inner_call_args, complete_inner_call, create_global_binds, create_param_binds,
+        fn_header_to_string, generic_params_to_string, get_param_name, get_unique_inner_name,
+        is_dead, is_tagged_at_boundary, untagged_boundary_ty_to_tagged_string,
+        where_clause_to_string,
     },
     callbacks::codegen::methods::self_qualifier::SelfPathQualifier,
-    callbacks::gather::first_pass_info::{FirstPassInfo, FnNamespace},
+    callbacks::codegen::{exits, runtime, units},
+    callbacks::fn_headers,
+    callbacks::gather::first_pass_info::{FirstPassInfo, FnNamespace, GlobalItem},
     callbacks::gather::type_key::TypeKey,
     callbacks::parsing,
     config::DatirConfig,
@@ -45,7 +53,7 @@ pub fn generate_method_shim(
     let rustc_ast::AssocItemKind::Fn(box rustc_ast::Fn {
         ident,
         generics: method_generics,
-        sig: rustc_ast::FnSig { decl, .. },
+        sig: rustc_ast::FnSig { header, decl, .. },
         body,
         ..
     }) = &mut assoc_item.kind
@@ -53,7 +61,21 @@ pub fn generate_method_shim(
         return;
     };
 
+    // `const fn` and `extern "C" fn` methods are left uninstrumented by pass 1, and so are
+    // methods skipped by the user.
+    if fn_headers::ast_keeps_untagged_signature(header)
+        || first_pass
+            .fns
+            .is_skipped(mod_path, FnNamespace::Method(type_key), ident.as_str())
+    {
+        return;
+    }
+
     // same strategy here as free functions.
+    let keeps_signature =
+        first_pass
+            .fns
+            .preserves_abi(mod_path, FnNamespace::Method(type_key), ident.as_str());
     let orig_name = ident.as_str().to_string();
     let inner_name = get_unique_inner_name(&orig_name, known_names);
     if datir_config.print_function_signatures {
@@ -100,14 +122,6 @@ pub fn generate_method_shim(
         rustc_ast::mut_visit::MutVisitor::visit_block(&mut qualifier, &mut orig_body);
     }
 
-    inner_templates.push(build_inner_method_template(
-        &inner_name,
-        method_generics,
-        &decl.inputs,
-        &decl.output,
-    ));
-    taken_bodies.push(orig_body);
-
     // EXIT-ppt liveness will determine which formals get bound at
     // the exit site (e.g. owned `self` is dead at exit unless Copy).
     let enter_ppt = datir_config
@@ -119,12 +133,42 @@ pub fn generate_method_shim(
         .exit_ppt(entry)
         .expect("EXIT ppt missing, should have been validated in pass 1");
 
+    // observe each exit of the original body separately, if requested.
+    if datir_config.exit_ppt_per_return {
+        let (receiver, non_self) = split_receiver(&decl.inputs);
+        exits::instrument_exit_sites(
+            psess,
+            entry,
+            &non_self,
+            exit_ppt,
+            &std::iter::once(build_self_bind(&receiver, "site_exit", exit_ppt))
+                .chain(create_global_binds("site_exit", &first_pass.globals, exit_ppt))
+                .collect::<Vec<_>>(),
+            &decl.output,
+            &mut orig_body,
+        );
+    }
+
+    inner_templates.push(build_inner_method_template(
+        &inner_name,
+        header,
+        method_generics,
+        &decl.inputs,
+        &decl.output,
+        keeps_signature,
+    ));
+    taken_bodies.push(orig_body);
+
     // replace the existing method body with the stub code.
     let wrapper_src = build_method_wrapper_block(
+        psess,
         entry,
         &inner_name,
+        header,
+        keeps_signature,
         &decl.inputs,
         &decl.output,
+        &first_pass.globals,
         enter_ppt,
         exit_ppt,
     );
@@ -140,13 +184,25 @@ pub fn generate_method_shim(
 
 /// Source for an inner method signature with an empty placeholder body.
 /// 
-/// The caller parses this template, then transplants the user's original body.
+/// The caller parses this template, then transplants the user's original body. The inner method
+/// keeps the header (`async`, `unsafe`) of the original method. If the original method keeps its
+/// untagged signature (`keeps_signature`), the types of its non-self parameters and return value
+/// are tagged here instead.
 fn build_inner_method_template(
     inner_name: &str,
+    header: &rustc_ast::FnHeader,
     method_generics: &rustc_ast::Generics,
     inputs: &[rustc_ast::Param],
     output: &rustc_ast::FnRetTy,
+    keeps_signature: bool,
 ) -> String {
+    let ty_to_string = |ty: &rustc_ast::Ty| {
+        if keeps_signature {
+            untagged_boundary_ty_to_tagged_string(ty)
+        } else {
+            rustc_ast_pretty::pprust::ty_to_string(ty)
+        }
+    };
     let generic_params = generic_params_to_string(method_generics);
     let where_clause = where_clause_to_string(method_generics);
 
@@ -168,13 +224,7 @@ fn build_inner_method_template(
         }
     };
     let other = iter
-        .map(|p| {
-            format!(
-                "mut {}: {}",
-                get_param_name(p),
-                rustc_ast_pretty::pprust::ty_to_string(&p.ty)
-            )
-        })
+        .map(|p| format!("mut {}: {}", get_param_name(p), ty_to_string(&p.ty)))
         .collect::<Vec<_>>()
         .join(", ");
     let declared = match (receiver_str.is_empty(), other.is_empty()) {
@@ -183,10 +233,11 @@ fn build_inner_method_template(
         (false, false) => format!("{receiver_str}, {other}"),
     };
     let ret = match output {
-        rustc_ast::FnRetTy::Ty(t) => format!(" -> {}", rustc_ast_pretty::pprust::ty_to_string(t)),
+        rustc_ast::FnRetTy::Ty(t) => format!(" -> {}", ty_to_string(t)),
         rustc_ast::FnRetTy::Default(_) => String::new(),
     };
-    format!("fn {inner_name}{generic_params}({declared}){ret}{where_clause} {{ }}")
+    let qualifiers = fn_header_to_string(header);
+    format!("{qualifiers}fn {inner_name}{generic_params}({declared}){ret}{where_clause} {{ }}")
 }
 
 /// Constructs the body for a method's shim. 
@@ -195,22 +246,24 @@ fn build_inner_method_template(
 /// be correctly forwarded when invoking the inner method. The receiver is
 /// bound under the name `"self"`, and is filtered by liveness in the same way
 /// as other formals (an owned non-Copy `self` is dead at exit and gets
-/// skipped).
+/// skipped). Like free fns, globals declared at the ppts are bound, the inner call of an `async`
+/// method is awaited, and the inner call of an `unsafe` method is placed in an unsafe block. If the
+/// method keeps its untagged signature (`keeps_signature`), tupleable non-self parameters are
+/// tagged on entry, and a tagged return value is untagged. Formals annotated with
+/// `#[datir::unit]` are marked with their unit at the ENTER site, see [units].
 fn build_method_wrapper_block(
+    psess: &rustc_session::parse::ParseSess,
     base_ppt_name: &str,
     inner_name: &str,
+    header: &rustc_ast::FnHeader,
+    keeps_signature: bool,
     inputs: &[rustc_ast::Param],
     output: &rustc_ast::FnRetTy,
+    globals: &[GlobalItem],
     enter_ppt: &decls_gen::ProgramPoint,
     exit_ppt: &decls_gen::ProgramPoint,
 ) -> String {
-    let receiver = determine_receiver_kind(inputs);
-
-    let mut inputs_iter = inputs.iter();
-    if !matches!(receiver, ReceiverKind::None) {
-        inputs_iter.next();
-    }
-    let non_self: Vec<&rustc_ast::Param> = inputs_iter.collect();
+    let (receiver, non_self) = split_receiver(inputs);
 
     let passed = build_inner_call_args(non_self.iter().copied());
 
@@ -218,70 +271,84 @@ fn build_method_wrapper_block(
         ReceiverKind::None => format!("Self::{inner_name}({passed})"),
         _ => format!("self.{inner_name}({passed})"),
     };
+    let call_expr = complete_inner_call(header, call_expr);
 
-    let self_bind = |site_name: &str, ppt: &decls_gen::ProgramPoint| -> String {
-        if matches!(receiver, ReceiverKind::None) || is_dead(ppt, "self") {
-            return String::new();
-        }
-        match receiver {
-            ReceiverKind::None => unreachable!(),
-            ReceiverKind::Value => format!(r#"self.bind(&mut {site_name}, "self");"#),
-            ReceiverKind::Ref | ReceiverKind::RefMut => {
-                format!(r#"(*self).bind(&mut {site_name}, "self");"#)
-            }
-        }
-    };
-
-    let enter_binds = std::iter::once(self_bind("site_enter", enter_ppt))
+    let enter_binds = std::iter::once(build_self_bind(&receiver, "site_enter", enter_ppt))
         .chain(create_param_binds(
             "site_enter",
             non_self.iter().copied(),
             enter_ppt,
         ))
+        .chain(units::create_param_unit_marks(
+            psess,
+            "site_enter",
+            non_self.iter().copied(),
+            enter_ppt,
+        ))
+        .chain(create_global_binds("site_enter", globals, enter_ppt))
         .filter(|s| !s.is_empty())
         .collect::<Vec<_>>()
         .join("\n");
 
-    let exit_binds = std::iter::once(self_bind("site_exit", exit_ppt))
+    let exit_binds = std::iter::once(build_self_bind(&receiver, "site_exit", exit_ppt))
         .chain(create_param_binds(
             "site_exit",
             non_self.iter().copied(),
             exit_ppt,
         ))
+        .chain(create_global_binds("site_exit", globals, exit_ppt))
         .filter(|s| !s.is_empty())
         .collect::<Vec<_>>()
         .join("\n");
 
+    let analysis = runtime::runtime_path("ATI_ANALYSIS");
     let return_var_name = decls_gen::decls::RETURN_VAR_NAME;
 
+    let track = runtime::runtime_path("ATI::track");
+    let tag_params = non_self
+        .iter()
+        .filter(|p| keeps_signature && is_tagged_at_boundary(&p.ty))
+        .map(|p| {
+            let name = get_param_name(p);
+            format!("let mut {name} = {track}({name});")
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let returned = match output {
+        rustc_ast::FnRetTy::Ty(t) if keeps_signature && is_tagged_at_boundary(t) => "res.1",
+        _ => "res",
+    };
+
     match output {
         rustc_ast::FnRetTy::Ty(_) => format!(
             r#"{{
-                let mut site_enter = ATI_ANALYSIS.lock().unwrap().get_site(r"{base_ppt_name}:::ENTER");
+                {tag_params}
+                let mut site_enter = {analysis}.lock().unwrap().get_site(r"{base_ppt_name}:::ENTER");
                 {enter_binds}
-                ATI_ANALYSIS.lock().unwrap().update_site(site_enter);
+                {analysis}.lock().unwrap().update_site(site_enter);
 
                 let res = {call_expr};
 
-                let mut site_exit = ATI_ANALYSIS.lock().unwrap().get_site(r"{base_ppt_name}:::EXIT");
+                let mut site_exit = {analysis}.lock().unwrap().get_site(r"{base_ppt_name}:::EXIT");
                 {exit_binds}
                 res.bind(&mut site_exit, "{return_var_name}");
-                ATI_ANALYSIS.lock().unwrap().update_site(site_exit);
+                {analysis}.lock().unwrap().update_site(site_exit);
 
-                return res;
+                return {returned};
             }}"#
         ),
         rustc_ast::FnRetTy::Default(_) => format!(
             r#"{{
-                let mut site_enter = ATI_ANALYSIS.lock().unwrap().get_site(r"{base_ppt_name}:::ENTER");
+                {tag_params}
+                let mut site_enter = {analysis}.lock().unwrap().get_site(r"{base_ppt_name}:::ENTER");
                 {enter_binds}
-                ATI_ANALYSIS.lock().unwrap().update_site(site_enter);
+                {analysis}.lock().unwrap().update_site(site_enter);
 
                 {call_expr};
 
-                let mut site_exit = ATI_ANALYSIS.lock().unwrap().get_site(r"{base_ppt_name}:::EXIT");
+                let mut site_exit = {analysis}.lock().unwrap().get_site(r"{base_ppt_name}:::EXIT");
                 {exit_binds}
-                ATI_ANALYSIS.lock().unwrap().update_site(site_exit);
+                {analysis}.lock().unwrap().update_site(site_exit);
             }}"#
         ),
     }
@@ -321,3 +388,33 @@ fn determine_receiver_kind(params: &[rustc_ast::Param]) -> ReceiverKind {
         _ => ReceiverKind::None,
     }
 }
+
+/// Splits `params` into the [`ReceiverKind`] of a method, and its non-self parameters.
+fn split_receiver(params: &[rustc_ast::Param]) -> (ReceiverKind, Vec<&rustc_ast::Param>) {
+    let receiver = determine_receiver_kind(params);
+
+    let mut params_iter = params.iter();
+    if !matches!(receiver, ReceiverKind::None) {
+        params_iter.next();
+    }
+    (receiver, params_iter.collect())
+}
+
+/// Generates the bind statement of `self` against a site variable, or an empty string if the
+/// method has no receiver, or `self` is dead at `ppt`.
+fn build_self_bind(
+    receiver: &ReceiverKind,
+    site_name: &str,
+    ppt: &decls_gen::ProgramPoint,
+) -> String {
+    if matches!(receiver, ReceiverKind::None) || is_dead(ppt, "self") {
+        return String::new();
+    }
+    match receiver {
+        ReceiverKind::None => unreachable!(),
+        ReceiverKind::Value => format!(r#"self.bind(&mut {site_name}, "self");"#),
+        ReceiverKind::Ref | ReceiverKind::RefMut => {
+            format!(r#"(*self).bind(&mut {site_name}, "self");"#)
+        }
+    }
+}
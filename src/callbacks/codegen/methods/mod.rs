@@ -17,6 +17,9 @@
 //!   trait `MyTrait` that returned `Self::SomeType`, then the inner function must rewrite the
 //!   return value to be `<Self as MyTrait>::SomeType`. This rewrite is done via the visitor
 //!   within [self_qualifier].
+//! - The receiver is bound under the name `self`. If [`DatirConfig::object_ppts`] is set, binding
+//!   a struct receiver also folds its fields into the struct's `:::OBJECT` site, see
+//!   [crate::callbacks::codegen::data_types].
 
 use crate::{
     callbacks::codegen::common::{generic_params_to_string, where_clause_to_string},
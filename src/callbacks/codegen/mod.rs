@@ -1,12 +1,12 @@
-//! Injects the runtime library, and creates shim functions and compound type trait implementations.
+//! Links the runtime library, and creates shim functions and compound type trait implementations.
 //! 
 //! Following transforming of the existing AST, there is some additional code that needs to be
 //! generated and injected into the compiled crate. This inserted code is namely used
 //! to perform ENTER/EXIT site management and provide user-defined compound types with
 //! implementation of special runtime-library traits.
 //!
-//! Further, the runtime libary itself must be injected, alongside feature flags for a few unstable
-//! rust features.
+//! Further, the runtime libary itself must be linked (see [runtime]), alongside feature flags for
+//! a few unstable rust features.
 //!
 //! This module contains the code to perform that, done at the end of the Instrument
 //! compilation callback in [crate::callbacks::instrument].
@@ -15,9 +15,15 @@ use crate::{callbacks::gather::first_pass_info::FirstPassInfo, config::DatirConf
 
 mod common;
 mod data_types;
-pub mod define_types;
+mod exits;
 mod function;
 mod methods;
+pub mod probes;
+pub mod runtime;
+mod units;
+
+/// Uninstrumented bodies of `const fn`s, keyed by the span of the fn item.
+pub type ConstEvalBodies = std::collections::HashMap<rustc_span::Span, Box<rustc_ast::Block>>;
 
 /// For each fn/method in the crate, modifies the body of the function to create 
 /// to manage ENTER and EXIT sites. 
@@ -31,9 +37,13 @@ mod methods;
 ///
 /// Recurses into submodules. `module_path`` is the file-derived Rust module path ("" for the crate
 /// root, "dep" for a non-root file).
+///
+/// `const_eval_bodies` holds the uninstrumented bodies of every `const fn`, keyed by the span of
+/// the fn item, which are evaluated in const contexts instead of the shim.
 pub fn generate_shims(
     datir_config: &DatirConfig,
     first_pass: &FirstPassInfo,
+    const_eval_bodies: &ConstEvalBodies,
     krate: &mut rustc_ast::Crate,
     module_path: &str,
     psess: &rustc_session::parse::ParseSess,
@@ -41,6 +51,7 @@ pub fn generate_shims(
     generate_shims_in_mod(
         datir_config,
         first_pass,
+        const_eval_bodies,
         &mut krate.items,
         module_path,
         psess,
@@ -53,6 +64,7 @@ pub fn generate_shims(
 fn generate_shims_in_mod(
     datir_config: &DatirConfig,
     first_pass: &FirstPassInfo,
+    const_eval_bodies: &ConstEvalBodies,
     items: &mut thin_vec::ThinVec<Box<rustc_ast::Item>>,
     mod_path: &str,
     psess: &rustc_session::parse::ParseSess,
@@ -62,6 +74,7 @@ fn generate_shims_in_mod(
     let mut new_items: Vec<Box<rustc_ast::Item>> = Vec::new();
 
     for item in items.iter_mut() {
+        let span = item.span;
         match &mut item.kind {
             // Free functions
             rustc_ast::ItemKind::Fn(func) => {
@@ -71,6 +84,7 @@ fn generate_shims_in_mod(
                     psess,
                     mod_path,
                     func,
+                    const_eval_bodies.get(&span).map(|body| &**body),
                     &mut new_items,
                 );
             }
@@ -86,11 +100,16 @@ fn generate_shims_in_mod(
                 generics,
                 rustc_ast::VariantData::Tuple(fields, ..),
             ) => {
+                let object_ppt = first_pass
+                    .object_ppts
+                    .get(ident.span, psess.source_map())
+                    .filter(|_| datir_config.object_ppts);
                 data_types::generate_struct_impls(
                     psess,
                     ident.as_str(),
                     fields,
                     generics,
+                    object_ppt,
                     &mut new_items,
                 );
             }
@@ -126,7 +145,14 @@ fn generate_shims_in_mod(
                 } else {
                     format!("{mod_path}::{}", mod_ident.as_str())
                 };
-                generate_shims_in_mod(datir_config, first_pass, sub_items, &sub_mod_path, psess);
+                generate_shims_in_mod(
+                    datir_config,
+                    first_pass,
+                    const_eval_bodies,
+                    sub_items,
+                    &sub_mod_path,
+                    psess,
+                );
             }
 
             _ => {}
@@ -138,12 +164,6 @@ fn generate_shims_in_mod(
         items.push(new_item);
     }
 
-    if !mod_path.is_empty() {
-        // if we are in a submodule / non-root file, then import root
-        // to make types available.
-        let imports = crate::callbacks::parsing::parse_items(psess, "use crate::*;".into(), None);
-        for import in imports {
-            items.insert(0, import);
-        }
-    }
+    // make the runtime traits' methods available to generated code in this module.
+    runtime::import_runtime_prelude(psess, items);
 }
@@ -0,0 +1,161 @@
+//! Defines the syntax of user-placed probe points, and generates the code updating their sites.
+//!
+//! Function boundaries are not always where abstract types are most interesting, e.g. at a loop
+//! head, or right after a complex computation. Users can place probe points anywhere within a
+//! function body, either as a macro statement binding the listed variables:
+//! ```rust
+//! datir_probe!("loop_head", sum, i, limit);
+//! ```
+//! or as an attribute on a `let` statement, binding the variables that statement introduces:
+//! ```rust
+//! #[datir::probe("after_sum")]
+//! let (sum, carry) = add(a, b);
+//! ```
+//!
+//! Each probe updates the site `{base}:::PROBE_{name}`, where `{base}` is the base ppt name of
+//! the enclosing function, the same as its `:::ENTER` / `:::EXIT` sites. Probes sharing a name
+//! within a function share their site.
+//!
+//! Neither form is part of the Rust language, so both compilations declare them before the crate
+//! is expanded, see [`declare_probe_syntax`]: the `datir` tool is registered, making the
+//! attribute inert, and a `datir_probe!` macro which only borrows its arguments is defined at
+//! the crate root. The Instrument compilation replaces every probe it finds with a site update,
+//! see [crate::callbacks::instrument::probes]. Outside of DATIR, crates get the same no-op
+//! definitions from the `datir-probe` crate (see `probe/`), which DATIR's compilations drop.
+//!
+//! decls-gen declares no probe ppts, so their sites are only named here (see [`PROBE_PPT_KIND`]).
+//! Each probe ppt is declared as a Daikon `point` ppt, appended to the `.decls` file decls-gen
+//! wrote, see [`append_probe_decls`]. Only the variables of tupleable type a probe binds are
+//! declared.
+
+use rustc_ast as ast;
+use rustc_session::parse::ParseSess;
+
+use crate::callbacks::{codegen::runtime, gather::first_pass_info::ProbeVar, parsing};
+
+/// Name of the probe macro.
+pub const PROBE_MACRO: &str = "datir_probe";
+
+/// Tool namespace of DATIR's attributes (`#[datir::probe("name")]`, `#[datir::skip]`, ...).
+pub const DATIR_TOOL: &str = "datir";
+
+/// Name of the probe attribute within [`DATIR_TOOL`].
+pub const PROBE_ATTR: &str = "probe";
+
+/// Crate name of the no-op probe definitions, which crates building without DATIR depend on.
+pub const PROBE_CRATE: &str = "datir_probe";
+
+/// Prefix of the ppt kind (the part after `:::`) of every probe site.
+pub const PROBE_PPT_KIND: &str = "PROBE_";
+
+/// Registers the `datir` tool and defines the `datir_probe!` macro at the root of `krate`.
+///
+/// Registering the tool also makes the scope attributes (`#[datir::skip]`, `#[datir::only]`)
+/// inert, see [crate::callbacks::gather::scope], and so do `#[datir::keep_abi]`, see
+/// [crate::callbacks::fn_headers], and `#[datir::unit]`, see [crate::callbacks::codegen::units].
+///
+/// The macro is defined before any other item, so that its textual scope spans every module of
+/// the crate. Probes the Instrument compilation can't resolve (e.g. within the arguments of
+/// another macro) expand to this definition, and are not observed.
+///
+/// Crates which also build without DATIR register the tool themselves, and import the macro
+/// from [`PROBE_CRATE`]. The tool is then left as is, and the `extern crate` item is removed,
+/// since the crate isn't passed to DATIR's compilations and its macro would be ambiguous.
+pub fn declare_probe_syntax(psess: &ParseSess, krate: &mut ast::Crate) {
+    if !has_crate_attribute(krate, "feature", "register_tool") {
+        runtime::add_crate_attribute("#![feature(register_tool)]", psess, krate);
+    }
+    if !has_crate_attribute(krate, "register_tool", DATIR_TOOL) {
+        runtime::add_crate_attribute(&format!("#![register_tool({DATIR_TOOL})]"), psess, krate);
+    }
+    krate.items.retain(|item| {
+        !matches!(
+            &item.kind,
+            ast::ItemKind::ExternCrate(None, ident) if ident.as_str() == PROBE_CRATE
+        )
+    });
+
+    let items = parsing::parse_items(
+        psess,
+        format!(
+            r#"#[allow(unused_macros)]
+            macro_rules! {PROBE_MACRO} {{
+                ($name:literal $(, $var:expr)* $(,)?) => {{{{
+                    let _ = $name;
+                    $( let _ = &$var; )*
+                }}}};
+            }}"#
+        ),
+        None,
+    );
+    for (i, item) in items.into_iter().enumerate() {
+        krate.items.insert(i, item);
+    }
+}
+
+/// Whether `krate` has a `#![{name}(...)]` attribute listing `arg`.
+fn has_crate_attribute(krate: &ast::Crate, name: &str, arg: &str) -> bool {
+    krate.attrs.iter().any(|attr| {
+        attr.has_name(rustc_span::Symbol::intern(name))
+            && attr.meta_item_list().is_some_and(|args| {
+                args.iter()
+                    .any(|inner| inner.has_name(rustc_span::Symbol::intern(arg)))
+            })
+    })
+}
+
+/// Returns the name of the site updated by the probe `name`, within the function whose base ppt
+/// name is `base_ppt_name`.
+pub fn probe_ppt_name(base_ppt_name: &str, name: &str) -> String {
+    format!("{base_ppt_name}:::{PROBE_PPT_KIND}{name}")
+}
+
+/// Appends a declaration of each probe ppt of `probe_ppts` (keyed by ppt name, holding the
+/// variables it binds) to the `.decls` file at `decls_path`.
+pub fn append_probe_decls(
+    decls_path: &std::path::Path,
+    probe_ppts: &std::collections::BTreeMap<String, Vec<ProbeVar>>,
+) -> std::io::Result<()> {
+    use std::io::Write;
+
+    let mut file = std::fs::OpenOptions::new().append(true).open(decls_path)?;
+    for (ppt, vars) in probe_ppts {
+        writeln!(file, "\nppt {ppt}\nppt-type point")?;
+        for var in vars {
+            writeln!(file, "variable {}", var.name)?;
+            writeln!(file, "  var-kind variable")?;
+            writeln!(file, "  dec-type {}", var.dec_type)?;
+            writeln!(file, "  rep-type {}", var.rep_type)?;
+            writeln!(file, "  comparability -1")?;
+        }
+    }
+    Ok(())
+}
+
+/// Builds a block statement, which binds each of `vars` (source of in-scope expressions) to the
+/// site of the probe `name`, and updates it.
+pub fn build_probe_stmt(
+    psess: &ParseSess,
+    base_ppt_name: &str,
+    name: &str,
+    vars: &[String],
+) -> ast::Stmt {
+    let analysis = runtime::runtime_path("ATI_ANALYSIS");
+    let ppt_name = probe_ppt_name(base_ppt_name, name);
+    let binds = vars
+        .iter()
+        .map(|var| format!("({var}).bind(&mut site_probe, {var:?});"))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    parsing::parse_stmt(
+        psess,
+        format!(
+            r#"{{
+                let mut site_probe = {analysis}.lock().unwrap().get_site({ppt_name:?});
+                {binds}
+                {analysis}.lock().unwrap().update_site(site_probe);
+            }}"#
+        ),
+    )
+}
@@ -0,0 +1,76 @@
+//! Defines functions which link the runtime library (the `datir_runtime` crate, see `runtime/`)
+//! into the crate being instrumented, and build paths to the items it defines.
+//!
+//! The runtime is not pasted into the user's crate. Instead, it is compiled once into its own rlib
+//! and passed to rustc via `--extern` (see [crate::dependencies]), so the `ATI_ANALYSIS` global,
+//! which holds value-interaction state, is defined exactly once no matter how many crates are
+//! instrumented.
+//!
+//! Generated code refers to runtime items through fully qualified `::datir_runtime::...` paths,
+//! so user items named `Site`, `ATI` or `Tagged` do not collide with them. Method calls on runtime
+//! traits are resolved through `datir_runtime::prelude`, which only brings anonymous (`as _`)
+//! trait imports into scope.
+
+use rustc_ast as ast;
+use rustc_session::parse::ParseSess;
+
+use crate::{callbacks::parsing, dependencies::RUNTIME_CRATE_NAME};
+
+/// Returns the fully qualified path to `item` (e.g. `ATI::track`) within the runtime crate.
+pub fn runtime_path(item: &str) -> String {
+    format!("::{RUNTIME_CRATE_NAME}::{item}")
+}
+
+/// Builds the fully qualified AST path to the runtime item made of `segments`, with `last_args`
+/// being the generic arguments of the last segment.
+pub fn runtime_ast_path(segments: &[&str], last_args: Option<Box<ast::GenericArgs>>) -> ast::Path {
+    let mut path_segments = vec![
+        ast::PathSegment::path_root(rustc_span::DUMMY_SP),
+        ast::PathSegment::from_ident(rustc_span::Ident::from_str(RUNTIME_CRATE_NAME)),
+    ];
+    path_segments.extend(
+        segments
+            .iter()
+            .map(|seg| ast::PathSegment::from_ident(rustc_span::Ident::from_str(seg))),
+    );
+    path_segments.last_mut().unwrap().args = last_args;
+
+    ast::Path {
+        span: rustc_span::DUMMY_SP,
+        segments: path_segments.into(),
+        tokens: None,
+    }
+}
+
+/// Declares the runtime crate at the root of `krate`.
+///
+/// Editions 2018+ resolve `::datir_runtime` through the extern prelude regardless, but crates on
+/// the 2015 edition resolve it relative to the crate root, where this declaration puts it.
+pub fn link_runtime_crate(psess: &ParseSess, krate: &mut ast::Crate) {
+    let items = parsing::parse_items(psess, format!("extern crate {RUNTIME_CRATE_NAME};"), None);
+    for (i, item) in items.into_iter().enumerate() {
+        krate.items.insert(i, item);
+    }
+}
+
+/// Imports the runtime prelude at the start of `items`, so that methods of runtime traits can be
+/// called by generated code within that module.
+pub fn import_runtime_prelude(psess: &ParseSess, items: &mut thin_vec::ThinVec<Box<ast::Item>>) {
+    let imports = parsing::parse_items(
+        psess,
+        format!(
+            "#[allow(unused_imports)]\nuse {}::*;",
+            runtime_path("prelude")
+        ),
+        None,
+    );
+    for (i, import) in imports.into_iter().enumerate() {
+        items.insert(i, import);
+    }
+}
+
+/// Adds a crate attribute tag (`#![feature(...)]`) to the crate.
+pub fn add_crate_attribute(attr: &str, psess: &ParseSess, krate: &mut ast::Crate) {
+    let attr = parsing::parse_single_unstable_compiler_attribute(psess, attr.into(), None);
+    krate.attrs.push(attr);
+}
@@ -0,0 +1,109 @@
+//! Defines the syntax of unit-of-measure annotations, and generates the code marking annotated
+//! values with their unit.
+//!
+//! Parameters and struct fields can be annotated with the unit they hold:
+//! ```rust
+//! fn wait(#[datir::unit("ms")] timeout: u64, #[datir::unit("s")] grace: u64) { ... }
+//!
+//! struct Config {
+//!     #[datir::unit("ms")]
+//!     timeout: u64,
+//! }
+//! ```
+//!
+//! The annotation is inert, as the `datir` tool is registered by [`declare_probe_syntax`]. When
+//! the ENTER site of a function is bound, each annotated parameter is marked with its unit, and
+//! so is each annotated field whenever a struct is bound to any site, see
+//! [`generate_struct_impls`]. The runtime then reports every interaction between values of
+//! different units, see `datir_runtime::units`.
+//!
+//! [`declare_probe_syntax`]: crate::callbacks::codegen::probes::declare_probe_syntax
+//! [`generate_struct_impls`]: crate::callbacks::codegen::data_types::generate_struct_impls
+
+use rustc_session::parse::ParseSess;
+
+use crate::callbacks::codegen::{
+    common::{bound_params, get_param_name},
+    probes::DATIR_TOOL,
+};
+
+/// Name of the unit attribute within [`DATIR_TOOL`].
+pub const UNIT_ATTR: &str = "unit";
+
+/// Returns the unit `attrs` annotate their node with, alongside the source location of the
+/// annotation (as `file:line:col`), if any.
+fn unit_annotation(psess: &ParseSess, attrs: &[rustc_ast::Attribute]) -> Option<(String, String)> {
+    use rustc_ast::attr::AttributeExt;
+
+    let attr = attrs.iter().find(|attr| {
+        attr.path_matches(&[
+            rustc_span::Symbol::intern(DATIR_TOOL),
+            rustc_span::Symbol::intern(UNIT_ATTR),
+        ])
+    })?;
+    let unit = match attr.meta_item_list().as_deref() {
+        Some(
+            [
+                rustc_ast::MetaItemInner::Lit(rustc_ast::MetaItemLit {
+                    kind: rustc_ast::LitKind::Str(unit, _),
+                    ..
+                }),
+            ],
+        ) => unit.to_string(),
+        _ => panic!("`#[{DATIR_TOOL}::{UNIT_ATTR}]` expects a single string literal unit."),
+    };
+
+    let loc = psess.source_map().lookup_char_pos(attr.span.lo());
+    let file = match &loc.file.name {
+        rustc_span::FileName::Real(rfn) => rfn
+            .path(rustc_span::RemapPathScopeComponents::MACRO)
+            .display()
+            .to_string(),
+        name => format!("{name:?}"),
+    };
+    Some((unit, format!("{file}:{}:{}", loc.line, loc.col.0 + 1)))
+}
+
+/// Generates a statement marking each annotated parameter of `params` bound at `site_name` (a
+/// site of `ppt`) with its unit.
+pub fn create_param_unit_marks<'a>(
+    psess: &ParseSess,
+    site_name: &str,
+    params: impl Iterator<Item = &'a rustc_ast::Param>,
+    ppt: &decls_gen::ProgramPoint,
+) -> Vec<String> {
+    bound_params(params, ppt)
+        .filter_map(|param| {
+            let (unit, at) = unit_annotation(psess, &param.attrs)?;
+            let var_name = get_param_name(param);
+            Some(format!(
+                r#"{site_name}.mark_unit("{var_name}", {unit:?}, {at:?});"#
+            ))
+        })
+        .collect()
+}
+
+/// Generates a statement marking each annotated field of `fields`, bound at `site` and named
+/// after the `parent` variable name (both given as source expressions), with its unit.
+pub fn build_field_unit_marks(
+    psess: &ParseSess,
+    fields: &[rustc_ast::FieldDef],
+    site: &str,
+    parent: &str,
+) -> String {
+    fields
+        .iter()
+        .enumerate()
+        .filter_map(|(i, field)| {
+            let (unit, at) = unit_annotation(psess, &field.attrs)?;
+            let field_name = match &field.ident {
+                Some(ident) => ident.to_string(),
+                None => i.to_string(),
+            };
+            Some(format!(
+                r#"({site}).mark_unit(&format!("{{}}.{field_name}", {parent}), {unit:?}, {at:?});"#
+            ))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
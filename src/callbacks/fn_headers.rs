@@ -0,0 +1,77 @@
+//! Provides helper functions which determine whether a function keeps its original, untagged
+//! signature once instrumented.
+//!
+//! Most instrumented functions have their parameter and return types tupled, and are called with
+//! tagged values. Two kinds of functions can't be treated that way:
+//! - `const fn`s can be evaluated in const contexts, where the runtime library (and its
+//!   `ATI_ANALYSIS` global) is unavailable. These must accept and return the same values as before.
+//! - Functions with a non-Rust ABI (`extern "C" fn`) can be called from foreign code, which knows
+//!   nothing about tagged values.
+//!
+//! These functions keep their signature, and only tag values inside their body, see
+//! [crate::callbacks::codegen::function]. Calls to them are treated like calls to any other
+//! untracked function.
+//!
+//! Functions on the public API boundary of the crate can be asked to do the same, see
+//! [`preserves_abi`], so that an instrumented library remains callable by uninstrumented
+//! dependants, and `#[no_mangle]` exports keep the signature foreign code expects. Unlike the
+//! above, these are decided by the Gather compilation, and recorded within the
+//! `FnIndex` (see [crate::callbacks::gather::first_pass_info]).
+//!
+//! IMPORTANT NOTE: The header-based implementations below need to remain in sync for consistency.
+
+use crate::{callbacks::codegen::probes::DATIR_TOOL, config::DatirConfig};
+
+/// Name of the attribute having a function, or every function of an impl block or module, keep
+/// its untagged signature (`#[datir::keep_abi]`).
+pub const KEEP_ABI_ATTR: &str = "keep_abi";
+
+/// Returns true if a function declared with `header` keeps its untagged signature.
+pub fn ast_keeps_untagged_signature(header: &rustc_ast::FnHeader) -> bool {
+    let is_const = matches!(header.constness, rustc_ast::Const::Yes(_));
+    let has_foreign_abi = match &header.ext {
+        rustc_ast::Extern::None => false,
+        rustc_ast::Extern::Implicit(_) => true,
+        rustc_ast::Extern::Explicit(abi, _) => abi.symbol_unescaped.as_str() != "Rust",
+    };
+
+    is_const || has_foreign_abi
+}
+
+/// Returns true if the function identified by `def_id` keeps its untagged signature.
+pub fn keeps_untagged_signature(
+    tcx: rustc_middle::ty::TyCtxt<'_>,
+    def_id: rustc_span::def_id::DefId,
+) -> bool {
+    tcx.is_const_fn(def_id) || !tcx.fn_sig(def_id).skip_binder().abi().is_rustic_abi()
+}
+
+/// Returns true if the function identified by `def_id` lies on the public API boundary, and keeps
+/// its untagged signature so that uninstrumented code can call it.
+///
+/// These are the functions exported from their crate if [`DatirConfig::preserve_public_abi`] is
+/// set, alongside functions marked `#[no_mangle]`, or `#[datir::keep_abi]` (on the function, or on
+/// an impl block or module it is defined within).
+pub fn preserves_abi(
+    tcx: rustc_middle::ty::TyCtxt<'_>,
+    def_id: rustc_span::def_id::DefId,
+    config: &DatirConfig,
+) -> bool {
+    let exported = match def_id.as_local() {
+        Some(ldid) => tcx.effective_visibilities(()).is_exported(ldid),
+        // only exported functions of a dependency can be called from another crate.
+        None => true,
+    };
+    let no_mangle = tcx
+        .codegen_fn_attrs(def_id)
+        .flags
+        .contains(rustc_middle::middle::codegen_fn_attrs::CodegenFnAttrFlags::NO_MANGLE);
+    let keep_abi = [
+        rustc_span::Symbol::intern(DATIR_TOOL),
+        rustc_span::Symbol::intern(KEEP_ABI_ATTR),
+    ];
+    let marked = std::iter::successors(Some(def_id), |&def_id| tcx.opt_parent(def_id))
+        .any(|def_id| tcx.get_attrs_by_path(def_id, &keep_abi).next().is_some());
+
+    (config.preserve_public_abi && exported) || no_mangle || marked
+}
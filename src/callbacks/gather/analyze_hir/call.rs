@@ -2,40 +2,77 @@
 //!
 //! See the top-level comment in [crate::callbacks::gather::analyze_hir] for more information as to
 //! why this is necessary.
+//!
+//! Calls crossing the boundary of a skipped function (see [crate::callbacks::gather::scope]) are
+//! recorded here as well: calls to a skipped function are recorded like any other untracked call,
+//! and calls made by a skipped function to an instrumented one as a [`CallFromSkipped`]. Calls to
+//! functions on the public API boundary (see [`fn_headers::preserves_abi`]) are untracked calls as
+//! well, as these functions keep their untagged signature.
+//!
+//! Strings are tagged as a whole, yet are still operated on through the methods of `str` /
+//! `String`. Calls to those methods are recorded as untracked calls made on the untupled receiver.
+//! The methods which the runtime shadows with tagged equivalents (see [`RUNTIME_STR_METHODS`])
+//! are called on the tagged receiver, and only have their arguments untupled.
+
+use rustc_middle::ty::TypeVisitableExt;
 
 use crate::{
-    callbacks::gather::{analyze_hir::AnalyzeHirVisitor, first_pass_info::UntrackedCall},
-    callbacks::types::CanBeTupled,
+    callbacks::gather::{
+        analyze_hir::AnalyzeHirVisitor,
+        first_pass_info::{CallFromSkipped, RetShape, UntrackedCall},
+    },
+    callbacks::{
+        fn_headers,
+        types::{self, CanBeTupled},
+    },
 };
 
+/// Bounds how deep [`ret_shape`] descends into nested return types. Anything deeper is left
+/// untagged, which also stops the recursion on recursive local structs.
+const MAX_RET_SHAPE_DEPTH: usize = 8;
+
+/// Methods of `str` / `String` which the runtime library defines on the tagged strings, so that
+/// the lengths and byte offsets they return carry the string's id.
+const RUNTIME_STR_METHODS: &[&str] = &["len", "find", "rfind", "chars", "char_indices", "as_str"];
+
 impl<'tcx, 'a> AnalyzeHirVisitor<'tcx, 'a> {
     /// If the call expression is to a non-instrumented function, mark this
-    /// call as requiring argument untupling, and potentially return value tupling.
+    /// call as requiring argument untupling, and potentially return value tagging.
     pub fn observe_call(&mut self, expr: &rustc_hir::Expr) {
-        let rustc_hir::ExprKind::Call(func, _args) = expr.kind else {
+        let rustc_hir::ExprKind::Call(func, args) = expr.kind else {
             panic!("Called observe_call with non-call expression.");
         };
 
+        // `expr?` desugars into calls to `Try::branch` and `FromResidual::from_residual`, which
+        // pass the tagged payload of an `Option` / `Result` through as is.
+        if func.span.is_desugaring(rustc_span::DesugaringKind::QuestionMark) {
+            return;
+        }
+
         if let rustc_hir::ExprKind::Path(ref qpath) = func.kind {
             let ldid = expr.hir_id.owner.def_id;
             let typeck = self.tcx.typeck(ldid);
             if let rustc_hir::def::Res::Def(kind, def_id) = typeck.qpath_res(qpath, func.hir_id) {
                 // Tuple struct constructors are parsed as calls. Skip them.
                 let is_constructor = matches!(kind, rustc_hir::def::DefKind::Ctor(_, _));
-                if !is_constructor && !self.first_pass.fns.contains(&def_id) {
+                if !is_constructor && !self.is_instrumented_fn(def_id) {
                     // We found a function that is untracked, as self.first_pass never had
-                    // the appropriate defid registered for it.
+                    // the appropriate defid registered for it, nor is it defined within an
+                    // instrumented dependency.
 
                     // this function call might need to have it's inputs
-                    // untupled, and it's output tupled, depending on the type signature.
+                    // untupled, and it's output tagged, depending on the type signature.
                     // store all this information in FirstPassInfo.
                     let span = func.span;
                     let ret_ty = typeck.expr_ty(expr);
+                    let call_module = self.tcx.parent_module(expr.hir_id).to_def_id();
                     self.first_pass.untracked_fn_calls.record(
                         span,
                         self.tcx.sess.source_map(),
                         UntrackedCall {
-                            ret_is_tupleable: ret_ty.can_be_tupled(),
+                            ret_shape: ret_shape(self.tcx, ret_ty, call_module, 0),
+                            untupled_args: untupled_args(typeck, args),
+                            untupled_receiver: false,
                         },
                     );
                 }
@@ -45,4 +82,311 @@ impl<'tcx, 'a> AnalyzeHirVisitor<'tcx, 'a> {
             // yes? closures? ignoring for now...
         }
     }
+
+    /// If the method call expression is to a skipped method, or to a method keeping its untagged
+    /// signature on the public API boundary, mark this call as requiring argument untupling, and
+    /// potentially return value tagging.
+    ///
+    /// Calls to the methods of `str` / `String` are marked as well, additionally requiring the
+    /// receiver to be untupled. Method calls to other untracked methods are left alone, as the
+    /// runtime library implements the methods of tupleable primitives on their tagged
+    /// equivalents.
+    pub fn observe_method_call(&mut self, expr: &rustc_hir::Expr) {
+        let rustc_hir::ExprKind::MethodCall(segment, receiver, args, _) = expr.kind else {
+            panic!("Called observe_method_call with non-method-call expression.");
+        };
+
+        let typeck = self.tcx.typeck(expr.hir_id.owner.def_id);
+        let Some(def_id) = typeck.type_dependent_def_id(expr.hir_id) else {
+            return;
+        };
+
+        let ret_ty = typeck.expr_ty(expr);
+        let call_module = self.tcx.parent_module(expr.hir_id).to_def_id();
+        let call = if self.is_skipped(def_id) || self.keeps_abi(def_id) {
+            UntrackedCall {
+                ret_shape: ret_shape(self.tcx, ret_ty, call_module, 0),
+                untupled_args: untupled_args(typeck, args),
+                untupled_receiver: false,
+            }
+        } else if types::is_string(typeck.expr_ty_adjusted(receiver).peel_refs())
+            && !self.is_instrumented_fn(def_id)
+        {
+            let untupled_args = args
+                .iter()
+                .map(|arg| typeck.expr_ty_adjusted(arg).peel_refs().can_be_tupled())
+                .collect();
+            if RUNTIME_STR_METHODS.contains(&segment.ident.as_str()) {
+                // the runtime's method returns tagged values, only the pattern is untupled.
+                UntrackedCall {
+                    ret_shape: RetShape::Opaque,
+                    untupled_args,
+                    untupled_receiver: false,
+                }
+            } else {
+                UntrackedCall {
+                    ret_shape: ret_shape(self.tcx, ret_ty, call_module, 0),
+                    untupled_args,
+                    // an explicitly dereferenced string (`(*s).trim()`) is already untagged.
+                    untupled_receiver: !matches!(
+                        receiver.kind,
+                        rustc_hir::ExprKind::Unary(rustc_hir::UnOp::Deref, _)
+                    ),
+                }
+            }
+        } else {
+            return;
+        };
+        self.first_pass.untracked_method_calls.record(
+            segment.ident.span,
+            self.tcx.sess.source_map(),
+            call,
+        );
+    }
+
+    /// If the (method) call expression, found within the body of a skipped function, is to an
+    /// instrumented function, mark this call as requiring argument tagging, and potentially
+    /// return value untupling.
+    pub fn observe_call_from_skipped(&mut self, expr: &rustc_hir::Expr) {
+        let typeck = self.tcx.typeck(expr.hir_id.owner.def_id);
+        let (span, def_id, args) = match expr.kind {
+            rustc_hir::ExprKind::Call(func, args) => {
+                let rustc_hir::ExprKind::Path(ref qpath) = func.kind else {
+                    return;
+                };
+                let rustc_hir::def::Res::Def(
+                    rustc_hir::def::DefKind::Fn | rustc_hir::def::DefKind::AssocFn,
+                    def_id,
+                ) = typeck.qpath_res(qpath, func.hir_id)
+                else {
+                    return;
+                };
+                (func.span, def_id, args)
+            }
+            rustc_hir::ExprKind::MethodCall(segment, _receiver, args, _) => {
+                let Some(def_id) = typeck.type_dependent_def_id(expr.hir_id) else {
+                    return;
+                };
+                (segment.ident.span, def_id, args)
+            }
+            _ => return,
+        };
+        if !self.is_instrumented_fn(def_id) {
+            return;
+        }
+
+        let ret_ty = typeck.expr_ty(expr);
+        let call_module = self.tcx.parent_module(expr.hir_id).to_def_id();
+        self.first_pass.calls_from_skipped.record(
+            span,
+            self.tcx.sess.source_map(),
+            CallFromSkipped {
+                tagged_args: args
+                    .iter()
+                    .map(|arg| typeck.expr_ty_adjusted(arg).can_be_tupled())
+                    .collect(),
+                ret_shape: ret_shape(self.tcx, ret_ty, call_module, 0),
+            },
+        );
+    }
+}
+
+impl<'tcx, 'a> AnalyzeHirVisitor<'tcx, 'a> {
+    /// Whether calls to `def_id` reach an instrumented function, and therefore take and return
+    /// tagged values.
+    ///
+    /// Functions of the current crate must have been registered in the `FnIndex`, and must not
+    /// keep their untagged signature (see [crate::callbacks::fn_headers]). Functions of
+    /// an allowlisted dependency were registered by that dependency's own Gather compilation, so
+    /// every free function and impl method defined there is assumed to be instrumented, unless
+    /// it was left out of the dependency's instrumentation scope.
+    fn is_instrumented_fn(&self, def_id: rustc_span::def_id::DefId) -> bool {
+        // `const fn`s and `extern "C" fn`s keep their signature, so they are called with
+        // untagged values, just like untracked functions.
+        if matches!(
+            self.tcx.def_kind(def_id),
+            rustc_hir::def::DefKind::Fn | rustc_hir::def::DefKind::AssocFn
+        ) && (fn_headers::keeps_untagged_signature(self.tcx, def_id) || self.keeps_abi(def_id))
+        {
+            return false;
+        }
+
+        if def_id.is_local() {
+            return self.first_pass.fns.contains(&def_id);
+        }
+        if !self
+            .config
+            .instruments_crate(self.tcx.crate_name(def_id.krate).as_str())
+            || self.is_skipped(def_id)
+        {
+            return false;
+        }
+        match self.tcx.def_kind(def_id) {
+            rustc_hir::def::DefKind::Fn => true,
+            rustc_hir::def::DefKind::AssocFn => matches!(
+                self.tcx.def_kind(self.tcx.parent(def_id)),
+                rustc_hir::def::DefKind::Impl { .. }
+            ),
+            _ => false,
+        }
+    }
+
+    /// Whether `def_id` is a function the user left out of instrumentation, see
+    /// [crate::callbacks::gather::scope].
+    ///
+    /// Functions of an allowlisted dependency were recorded as such by that dependency's own
+    /// Gather compilation, see [`DatirConfig::skipped_dependency_fns`].
+    ///
+    /// [`DatirConfig::skipped_dependency_fns`]: crate::config::DatirConfig::skipped_dependency_fns
+    fn is_skipped(&self, def_id: rustc_span::def_id::DefId) -> bool {
+        if def_id.is_local() {
+            return self.first_pass.fns.contains_skipped(&def_id);
+        }
+        self.config
+            .skipped_dependency_fns
+            .contains(&self.tcx.def_path_hash(def_id))
+    }
+
+    /// Whether `def_id` is an instrumented function which keeps its untagged signature, as it
+    /// lies on the public API boundary of its crate.
+    ///
+    /// Functions of the current crate were registered as such by [`GatherAtiInfo`]. Functions of
+    /// an allowlisted dependency were registered by that dependency's own Gather compilation,
+    /// which ran with the same configuration, and are recomputed here.
+    ///
+    /// [`GatherAtiInfo`]: crate::callbacks::gather::GatherAtiInfo
+    fn keeps_abi(&self, def_id: rustc_span::def_id::DefId) -> bool {
+        if def_id.is_local() {
+            return self.first_pass.fns.contains_preserved_abi(&def_id);
+        }
+        self.config
+            .instruments_crate(self.tcx.crate_name(def_id.krate).as_str())
+            && fn_headers::preserves_abi(self.tcx, def_id, self.config)
+    }
+}
+
+/// Computes which of `args`, passed to an untracked function, are untupled. Only tupleable
+/// scalars and arrays / slices (or references to them) are wrapped in `Tagged` / `TaggedRef` once
+/// instrumented; any other value (`Waker::noop()`, `&mut future`, a local struct) is passed as
+/// it is.
+fn untupled_args<'tcx>(
+    typeck: &rustc_middle::ty::TypeckResults<'tcx>,
+    args: &[rustc_hir::Expr<'tcx>],
+) -> Vec<bool> {
+    args.iter()
+        .map(|arg| {
+            let ty = typeck.expr_ty_adjusted(arg).peel_refs();
+            ty.can_be_tupled()
+                || matches!(
+                    ty.kind(),
+                    rustc_middle::ty::Array(..) | rustc_middle::ty::Slice(..)
+                )
+        })
+        .collect()
+}
+
+/// Computes which parts of a value of type `ty`, returned by an untracked call made from within
+/// `call_module`, need to be tagged.
+///
+/// Local structs are only rebuilt through the generic parameters of their fields' declared types,
+/// see [`field_shape`]. Structs with fields that aren't visible from `call_module`
+/// can't be rebuilt with a struct expression, and are left untagged.
+fn ret_shape<'tcx>(
+    tcx: rustc_middle::ty::TyCtxt<'tcx>,
+    ty: rustc_middle::ty::Ty<'tcx>,
+    call_module: rustc_span::def_id::DefId,
+    depth: usize,
+) -> RetShape {
+    if ty.can_be_tupled() {
+        return RetShape::Leaf;
+    }
+    if let rustc_middle::ty::Ref(_, referent, rustc_ast::Mutability::Not) = ty.kind()
+        && referent.is_str()
+    {
+        return RetShape::Str;
+    }
+    if depth >= MAX_RET_SHAPE_DEPTH {
+        return RetShape::Opaque;
+    }
+
+    let nested = |ty: rustc_middle::ty::Ty<'tcx>| ret_shape(tcx, ty, call_module, depth + 1);
+    match ty.kind() {
+        rustc_middle::ty::Tuple(elems) => RetShape::tuple(elems.iter().map(nested).collect()),
+        rustc_middle::ty::Array(elem, _) => RetShape::Array(Box::new(nested(*elem))),
+        rustc_middle::ty::Adt(adt, args)
+            if tcx.is_diagnostic_item(rustc_span::sym::Option, adt.did()) =>
+        {
+            RetShape::option(nested(args.type_at(0)))
+        }
+        rustc_middle::ty::Adt(adt, args)
+            if tcx.is_diagnostic_item(rustc_span::sym::Result, adt.did()) =>
+        {
+            RetShape::result(nested(args.type_at(0)), nested(args.type_at(1)))
+        }
+        rustc_middle::ty::Adt(adt, args) if adt.is_struct() && adt.did().is_local() => {
+            let mut fields = Vec::new();
+            for field in adt.non_enum_variant().fields.iter() {
+                if !field.vis.is_accessible_from(call_module, tcx) {
+                    return RetShape::Opaque;
+                }
+                let declared_ty = tcx.type_of(field.did).instantiate_identity();
+                let shape = field_shape(tcx, declared_ty, field.ty(tcx, args), call_module, depth);
+                fields.push((field.name.to_string(), shape));
+            }
+            let path = rustc_middle::ty::print::with_no_trimmed_paths!(tcx.def_path_str(adt.did()));
+            RetShape::structure(format!("crate::{path}"), fields)
+        }
+        _ => RetShape::Opaque,
+    }
+}
+
+/// Computes which parts of a struct field declared with type `declared`, and instantiated as
+/// `ty`, need to be tagged.
+///
+/// Every part of `declared` that isn't a generic parameter was already rewritten to hold
+/// `Tagged<T>`s when the struct definition was instrumented, so only the parts of `ty` which
+/// substitute a type parameter are tagged: a field `pair: (T, u32)` is rebuilt through `pair.0`
+/// alone, and `data: [u32; N]` isn't rebuilt at all.
+fn field_shape<'tcx>(
+    tcx: rustc_middle::ty::TyCtxt<'tcx>,
+    declared: rustc_middle::ty::Ty<'tcx>,
+    ty: rustc_middle::ty::Ty<'tcx>,
+    call_module: rustc_span::def_id::DefId,
+    depth: usize,
+) -> RetShape {
+    if !declared.has_param() {
+        return RetShape::Opaque;
+    }
+    if depth >= MAX_RET_SHAPE_DEPTH {
+        return RetShape::Opaque;
+    }
+
+    let nested = |declared, ty| field_shape(tcx, declared, ty, call_module, depth + 1);
+    match (declared.kind(), ty.kind()) {
+        (rustc_middle::ty::Tuple(declared), rustc_middle::ty::Tuple(elems)) => RetShape::tuple(
+            declared
+                .iter()
+                .zip(elems.iter())
+                .map(|(declared, elem)| nested(declared, elem))
+                .collect(),
+        ),
+        (rustc_middle::ty::Array(declared, _), rustc_middle::ty::Array(elem, _)) => {
+            RetShape::tagged_array(nested(*declared, *elem))
+        }
+        (rustc_middle::ty::Adt(adt, declared_args), rustc_middle::ty::Adt(_, args))
+            if tcx.is_diagnostic_item(rustc_span::sym::Option, adt.did()) =>
+        {
+            RetShape::option(nested(declared_args.type_at(0), args.type_at(0)))
+        }
+        (rustc_middle::ty::Adt(adt, declared_args), rustc_middle::ty::Adt(_, args))
+            if tcx.is_diagnostic_item(rustc_span::sym::Result, adt.did()) =>
+        {
+            RetShape::result(
+                nested(declared_args.type_at(0), args.type_at(0)),
+                nested(declared_args.type_at(1), args.type_at(1)),
+            )
+        }
+        // A type parameter, or a local generic struct, which is rebuilt through its own fields.
+        _ => ret_shape(tcx, ty, call_module, depth + 1),
+    }
 }
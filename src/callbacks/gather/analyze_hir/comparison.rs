@@ -0,0 +1,35 @@
+//! Defines how the [`AnalyzeHirVisitor`] records information about comparison expressions.
+//!
+//! See the top-level comment in [crate::callbacks::gather::analyze_hir] for more information as to
+//! why this is necessary.
+
+use crate::callbacks::{
+    gather::analyze_hir::{AnalyzeHirVisitor, index::is_range_lang_item},
+    types::CanBeTupled,
+};
+
+impl<'tcx, 'a> AnalyzeHirVisitor<'tcx, 'a> {
+    /// If the comparison expression compares values of a compound type (like `Option<u32>`, or a
+    /// user-defined struct), mark it as comparing through the type's own `PartialEq` /
+    /// `PartialOrd` impl. Ranges and `String`s are excluded, as they are tagged as a whole.
+    pub fn observe_comparison(&mut self, expr: &rustc_hir::Expr) {
+        let rustc_hir::ExprKind::Binary(op, lhs, _) = expr.kind else {
+            panic!("Called observe_comparison with non-binary expression: {:?}", expr);
+        };
+        if !op.node.is_comparison() {
+            return;
+        }
+
+        let ldid = expr.hir_id.owner.def_id;
+        let typeck = self.tcx.typeck(ldid);
+        let lhs_ty = typeck.expr_ty(lhs).peel_refs();
+        if let Some(adt) = lhs_ty.ty_adt_def()
+            && !lhs_ty.can_be_tupled()
+            && !is_range_lang_item(self.tcx, adt.did())
+        {
+            self.first_pass
+                .compound_comparisons
+                .mark(expr.span, self.tcx.sess.source_map());
+        }
+    }
+}
@@ -3,10 +3,13 @@
 //! See the top-level comment in [crate::callbacks::gather::analyze_hir] for more information as to
 //! why this is necessary.
 
-use crate::{callbacks::gather::analyze_hir::AnalyzeHirVisitor, callbacks::types::CanBeTupled};
+use crate::{
+    callbacks::gather::analyze_hir::AnalyzeHirVisitor,
+    callbacks::types::{self, CanBeTupled},
+};
 
-// A TaggedRef(Mut?)<T> == (&(mut?) Id, &(mut?) T). Nested references will only
-// turn the innermost reference into a TaggedRef. In other words, for some tuplable T,
+// A TaggedRef<T> == (Id, &T), and a TaggedRefMut<T> == (&mut Id, &mut T). Nested references
+// will only turn the innermost reference into a TaggedRef. In other words, for some tuplable T,
 // &&&T becomes &&&Tagged<T> which then becomes &&TaggedRef<T>, to avoid dragging around an
 // owned Id. If T is a not a tuplable type (i.e. a user-defined compound type), then
 // &T remains &T.
@@ -30,8 +33,11 @@ impl<'tcx, 'a> AnalyzeHirVisitor<'tcx, 'a> {
         let ldid = expr.hir_id.owner.def_id;
         let typeck = self.tcx.typeck(ldid);
         let inner_ty = typeck.expr_ty(inner);
+        // a string can't be moved out of the reference to rebuild a `Tagged<String>`. The deref
+        // is left to `TaggedRef::deref`, producing the untagged place.
         if let rustc_middle::ty::Ref(_, referent, _) = *inner_ty.kind()
-            && referent.can_be_tupled() {
+            && referent.can_be_tupled()
+            && !types::is_string(referent) {
                 self.first_pass
                     .tag_stripping_deref
                     .mark(expr.span, self.tcx.sess.source_map());
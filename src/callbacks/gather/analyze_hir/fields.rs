@@ -0,0 +1,54 @@
+//! Defines how the [`AnalyzeHirVisitor`] records the field accesses made by skipped functions.
+//!
+//! Skipped bodies are compiled as they were written, but the structs and enums they use are
+//! always instrumented, so a field declared as `u32` holds a `Tagged<u32>`. Accesses to such
+//! fields are untupled, which works for both reads (`self.len + 1`) and writes
+//! (`self.len += 1`), as `.1` is a place expression.
+
+use crate::callbacks::{gather::analyze_hir::AnalyzeHirVisitor, types::CanBeTupled};
+
+impl<'tcx, 'a> AnalyzeHirVisitor<'tcx, 'a> {
+    /// If the field expression, found within the body of a skipped function, accesses a field of
+    /// a crate-local type declared with a tupleable type, mark it as requiring untupling.
+    ///
+    /// Fields whose declared type holds tagged values in any other way (`(u32, u32)`, `[u32; N]`,
+    /// `Vec<u32>`, ...) can't be untupled in place, and are rejected.
+    pub fn observe_field_from_skipped(&mut self, expr: &rustc_hir::Expr) {
+        let rustc_hir::ExprKind::Field(base, ident) = expr.kind else {
+            return;
+        };
+
+        let typeck = self.tcx.typeck(expr.hir_id.owner.def_id);
+        let rustc_middle::ty::Adt(adt, _) = typeck.expr_ty_adjusted(base).peel_refs().kind() else {
+            return;
+        };
+        if !adt.did().is_local() {
+            return;
+        }
+        let Some(field_idx) = typeck.opt_field_index(expr.hir_id) else {
+            return;
+        };
+
+        // generic fields hold whatever the skipped body instantiated them with, untagged.
+        let field = &adt.non_enum_variant().fields[field_idx];
+        let declared = self.tcx.type_of(field.did).instantiate_identity();
+        if declared.can_be_tupled() {
+            self.first_pass
+                .skipped_field_accesses
+                .mark(expr.span, self.tcx.sess.source_map());
+        } else if declared.walk().any(|arg| {
+            arg.as_type().is_some_and(|ty| {
+                ty.can_be_tupled() || matches!(ty.kind(), rustc_middle::ty::Array(..))
+            })
+        }) {
+            self.tcx.dcx().span_fatal(
+                expr.span,
+                format!(
+                    "DATIR: field `{ident}` of type `{declared}` holds tagged values once \
+                     instrumented, and can't be accessed by a skipped function. Only fields of \
+                     primitive type are untupled for skipped code."
+                ),
+            );
+        }
+    }
+}
@@ -0,0 +1,64 @@
+//! Defines how the [`AnalyzeHirVisitor`] records information about reads of `const` and `static`
+//! items.
+//!
+//! See the top-level comment in [crate::callbacks::gather::analyze_hir] for more information as to
+//! why this is necessary.
+
+use crate::{
+    callbacks::gather::{analyze_hir::AnalyzeHirVisitor, first_pass_info::GlobalRead},
+    callbacks::types::CanBeTupled,
+};
+
+impl<'tcx, 'a> AnalyzeHirVisitor<'tcx, 'a> {
+    /// If the path expression reads a `const` / `static` item of tupleable type, mark it as
+    /// requiring tagging, with the key identifying the item at runtime.
+    pub fn observe_global_read(&mut self, expr: &rustc_hir::Expr) {
+        let rustc_hir::ExprKind::Path(ref qpath) = expr.kind else {
+            panic!("Called observe_global_read with non-path expression.");
+        };
+
+        let ldid = expr.hir_id.owner.def_id;
+        let typeck = self.tcx.typeck(ldid);
+        let rustc_hir::def::Res::Def(kind, def_id) = typeck.qpath_res(qpath, expr.hir_id) else {
+            return;
+        };
+        if !typeck.expr_ty(expr).can_be_tupled() {
+            return;
+        }
+
+        let key = match kind {
+            // reads of a `static mut` may be places which are written to, which a tagged copy
+            // of the value can't stand in for.
+            rustc_hir::def::DefKind::Const
+            | rustc_hir::def::DefKind::Static {
+                mutability: rustc_ast::Mutability::Not,
+                ..
+            } => Some(global_key(self.tcx, def_id)),
+            // `T::ZERO` resolves to the trait's item, regardless of `T`.
+            rustc_hir::def::DefKind::AssocConst => {
+                let in_trait = matches!(
+                    self.tcx.def_kind(self.tcx.parent(def_id)),
+                    rustc_hir::def::DefKind::Trait
+                );
+                (!in_trait).then(|| global_key(self.tcx, def_id))
+            }
+            _ => return,
+        };
+
+        self.first_pass.global_reads.record(
+            expr.span,
+            self.tcx.sess.source_map(),
+            GlobalRead { key },
+        );
+    }
+}
+
+/// Returns the key identifying the `const` / `static` item `def_id` at runtime, which is the
+/// same no matter which (instrumented) crate reads the item.
+pub fn global_key(tcx: rustc_middle::ty::TyCtxt<'_>, def_id: rustc_span::def_id::DefId) -> String {
+    format!(
+        "{}{}",
+        tcx.crate_name(def_id.krate),
+        tcx.def_path(def_id).to_string_no_crate_verbose()
+    )
+}
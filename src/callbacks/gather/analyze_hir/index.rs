@@ -3,7 +3,7 @@
 //! See the top-level comment in [crate::callbacks::gather::analyze_hir] for more information as to
 //! why this is necessary.
 
-use crate::callbacks::gather::analyze_hir::AnalyzeHirVisitor;
+use crate::callbacks::{gather::analyze_hir::AnalyzeHirVisitor, types};
 
 /// Lang items for every range struct that is valid as an indexing argument and
 /// produces a slice result (Range, RangeFrom, RangeFull, RangeInclusive,
@@ -22,7 +22,7 @@ const RANGE_LANG_ITEMS: &[rustc_hir::LangItem] = &[
 ];
 
 /// Returns true if this `did` refers to some Range variant.
-fn is_range_lang_item<'tcx>(
+pub fn is_range_lang_item<'tcx>(
     tcx: rustc_middle::ty::TyCtxt<'tcx>,
     did: rustc_span::def_id::DefId,
 ) -> bool {
@@ -36,8 +36,10 @@ impl<'tcx, 'a> AnalyzeHirVisitor<'tcx, 'a> {
     /// this function records the input expression as requiring a special runtime library 
     /// `.subslice()` call  post-transformation which will construct an equivalent non-instrumented
     /// range object before applying the index operation to the collection.
+    ///
+    /// Strings are recorded apart, as they are sliced through a `.substr()` call instead.
     pub fn observe_range(&mut self, expr: &rustc_hir::Expr) {
-        let rustc_hir::ExprKind::Index(_, idx, _) = expr.kind else {
+        let rustc_hir::ExprKind::Index(base, idx, _) = expr.kind else {
             panic!("Invoked observe_range with non-range expr: {:?}", expr);
         };
 
@@ -49,9 +51,12 @@ impl<'tcx, 'a> AnalyzeHirVisitor<'tcx, 'a> {
             .map(|adt| is_range_lang_item(self.tcx, adt.did()))
             .unwrap_or(false)
         {
-            self.first_pass
-                .index_by_range
-                .mark(expr.span, self.tcx.sess.source_map());
+            let facts = if types::is_string(typeck.expr_ty(base).peel_refs()) {
+                &mut self.first_pass.index_str_by_range
+            } else {
+                &mut self.first_pass.index_by_range
+            };
+            facts.mark(expr.span, self.tcx.sess.source_map());
         }
     }
 }
@@ -0,0 +1,69 @@
+//! Defines how the [`AnalyzeHirVisitor`] records the arguments of std macro invocations.
+//!
+//! Pass 2 instruments the arguments of well-known std macros (`vec!`, `assert!`, `format!`, ...)
+//! before expansion, see [crate::callbacks::instrument::macros]. Once instrumented, an argument
+//! of tupleable type is tagged, which the macro may not expect: formatting a `Tagged<T>` prints
+//! its id alongside the value. Record these arguments, so that pass 2 can untuple them.
+//!
+//! Variables captured by a format string (`println!("{x}")`) are not arguments pass 2 can see.
+//! Record their names against the invocation instead, so that pass 2 can pass them explicitly.
+
+use crate::callbacks::{gather::analyze_hir::AnalyzeHirVisitor, types::CanBeTupled};
+
+impl<'tcx, 'a> AnalyzeHirVisitor<'tcx, 'a> {
+    /// If `expr` was passed as an argument to a std macro, and its type is tupleable (or a
+    /// reference to a tupleable type), records its span, or the name it was captured by.
+    ///
+    /// An argument keeps the syntax context of the invocation, while the expression the macro
+    /// expanded it into belongs to the expansion.
+    pub fn observe_macro_arg(&mut self, expr: &rustc_hir::Expr) {
+        let parent_span = self.tcx.hir_span(self.tcx.parent_hir_id(expr.hir_id));
+        if parent_span.ctxt() == expr.span.ctxt() {
+            return;
+        }
+        let expn = parent_span.ctxt().outer_expn_data();
+        let rustc_span::ExpnKind::Macro(rustc_span::MacroKind::Bang, _) = expn.kind else {
+            return;
+        };
+        if expn.macro_def_id.is_none_or(|def_id| def_id.is_local()) {
+            return;
+        }
+
+        let typeck = self.tcx.typeck(expr.hir_id.owner.def_id);
+        if !typeck.expr_ty(expr).peel_refs().can_be_tupled() {
+            return;
+        }
+
+        let sm = self.tcx.sess.source_map();
+        match captured_name(expr, sm) {
+            // the format string is within the invocation, whose span the parent maps to.
+            Some(name) => {
+                let mut names = self
+                    .first_pass
+                    .format_captures
+                    .get(parent_span, sm)
+                    .cloned()
+                    .unwrap_or_default();
+                if !names.contains(&name) {
+                    names.push(name);
+                    self.first_pass
+                        .format_captures
+                        .record(parent_span, sm, names);
+                }
+            }
+            None => self.first_pass.tagged_macro_args.mark(expr.span, sm),
+        }
+    }
+}
+
+/// If `expr` is a variable captured within a format string (`{x}`, `{x:?}`), returns its name.
+fn captured_name(expr: &rustc_hir::Expr, sm: &rustc_span::source_map::SourceMap) -> Option<String> {
+    let rustc_hir::ExprKind::Path(rustc_hir::QPath::Resolved(None, path)) = expr.kind else {
+        return None;
+    };
+    let [segment] = path.segments else {
+        return None;
+    };
+    let preceding = sm.span_to_prev_source(expr.span).ok()?;
+    preceding.ends_with('{').then(|| segment.ident.to_string())
+}
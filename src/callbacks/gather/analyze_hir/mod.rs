@@ -4,7 +4,13 @@
 //! More specifically, this visitor collects (and adds to [`FirstPassInfo`]) code locations where:
 //! - A call to an uninstrumented function is made. These are places where any tupled
 //!   values passed in as input to this function need to be untupled, and a return value
-//!   potentially needs to be tupled.
+//!   potentially needs to be tupled. Method calls are only recorded when made to skipped
+//!   methods (see [crate::callbacks::gather::scope]).
+//!
+//! - A skipped function calls an instrumented one, or accesses a field of a crate-local struct.
+//!   Skipped bodies are otherwise compiled as they were written, so only these are recorded
+//!   within them: calls, as places where arguments need to be tagged and the return value
+//!   untupled, and field accesses, as places where the tagged field needs to be untupled.
 //!
 //! - A reference is created to some tuplable value (e.g. `let x = &mut 10`). These are
 //!   places where a `TaggedRef<T>` or `TaggedRefMut<T>` needs to be constructed
@@ -31,6 +37,12 @@
 //!   `TaggedRef<[T]>` or `Tagged<[T; N]>` it acts on. See
 //!   `crate::callbacks::instrument::expr::addr_of` for more information.
 //!   Whenever the standard library is instrumented, it's possible this could be removed.
+//!   Strings indexed by a range are recorded apart, as they are sliced through `.substr()`.
+//!
+//! - A method of `str` / `String` is called (e.g. `s.trim()`). Strings are tagged as a whole, so
+//!   these are untracked calls made on the untupled receiver, unless the runtime library shadows
+//!   the method to return a tagged length or byte offset (e.g. `s.len()`). Similarly, a `&String`
+//!   which is deref-coerced into a `&str` is recorded, as `TaggedRef<String>` doesn't coerce.
 //!
 //! - A match statement, matches on a tagged type (meaning it originally was either a tuplable
 //!   primitive, or a reference to one). Because transformation will change the type of this target
@@ -46,24 +58,51 @@
 //!   pattern semantics. Note that we rely on the tagged types deref semantics, to access the
 //!   underlying value, ignoring the tag entirely for the purpose of the match.
 //!
+//! - A `const` or `static` item of tuplable type is read (e.g. `x < MAX`). These items are
+//!   left untagged, as thier initializers run in const contexts where `ATI::track` can't be
+//!   called. Each read is instead tagged with an id shared by every read of that item.
+//!
+//! - A user-placed probe point (`datir_probe!`, `#[datir::probe]`) is found. Pass 2 replaces
+//!   these before expansion, and needs the base ppt name of the function enclosing each of them.
+//!   The variables each probe binds are recorded too, to declare the probe's ppt.
+//!
+//! - An argument of tupleable type is passed to a std macro (e.g. `println!("{}", x)`), or
+//!   captured by a format string (`println!("{x}")`). Pass 2 instruments these arguments before
+//!   expansion, and untuples them where the macro needs the untagged value.
+//!
+//! - Two values of a compound type are compared (e.g. `Some(x) == Some(y)`). Pass 2 otherwise
+//!   compares tagged operands field by field, while these compare through the type's own
+//!   `PartialEq` / `PartialOrd` impl, which compares (and so interacts) the tagged values within.
+//!
 //! As of 3/29/26, we are choosing to ignore uninstrumented libraries, meaning that
 //! the first bullet is really an unnecessary step. The code is still left, as a proof-of-concept.
+//! Dependencies allowlisted in [`DatirConfig::instrumented_crates`] are instrumented, so calls
+//! into them are treated like calls to functions within the crate.
 
-use crate::callbacks::gather::first_pass_info::FirstPassInfo;
+use crate::{callbacks::gather::first_pass_info::FirstPassInfo, config::DatirConfig};
 
 mod assignment;
 mod call;
+mod comparison;
 mod deref;
+mod fields;
+mod globals;
 mod index;
+mod macros;
 mod match_expr;
+mod probes;
 mod references;
 
+pub use globals::global_key;
+
 /// Visitor that finds code spans of interest (listed at the top of this file).
 /// Updates `self.first_pass` to include this information.
 pub struct AnalyzeHirVisitor<'tcx, 'a> {
     pub tcx: rustc_middle::ty::TyCtxt<'tcx>,
     /// Collection of all gathered information.
     pub first_pass: &'a mut FirstPassInfo,
+    /// DATIR configuration, used to determine which dependencies are instrumented.
+    pub config: &'a DatirConfig,
 }
 
 impl<'tcx, 'a> rustc_hir::intravisit::Visitor<'tcx> for AnalyzeHirVisitor<'tcx, 'a> {
@@ -96,10 +135,23 @@ impl<'tcx, 'a> rustc_hir::intravisit::Visitor<'tcx> for AnalyzeHirVisitor<'tcx,
             return;
         }
 
+        // Skipped bodies stay untagged, only their calls into instrumented functions, and their
+        // accesses to the (instrumented) fields of the crate's structs change.
+        if self.first_pass.fns.contains_skipped(&ldid.to_def_id()) {
+            self.observe_call_from_skipped(expr);
+            self.observe_field_from_skipped(expr);
+            rustc_hir::intravisit::walk_expr(self, expr);
+            return;
+        }
+
         // Regardless of expr kind, normalize any `&T` / `&mut T`-typed expression with a
         // tupleable / array / slice referent. See [`Self::observe_ref_normalization`].
         self.observe_ref_normalization(expr);
 
+        // Arguments of std macros are instrumented by pass 2 before expansion, which can't tell
+        // their types. See [`Self::observe_macro_arg`].
+        self.observe_macro_arg(expr);
+
         match expr.kind {
             // A call to a function might require us to untuple the arguments,
             // and then tuple back the return value, if it is a call to a function
@@ -108,6 +160,17 @@ impl<'tcx, 'a> rustc_hir::intravisit::Visitor<'tcx> for AnalyzeHirVisitor<'tcx,
                 self.observe_call(expr);
             }
 
+            // Skipped methods take and return untagged values, just like untracked functions.
+            rustc_hir::ExprKind::MethodCall(..) => {
+                self.observe_method_call(expr);
+            }
+
+            // Comparisons between compound values (`Option<u32>`, user structs, ...) rely on the
+            // type's own `PartialEq` / `PartialOrd` impl, as there is no single tag to compare.
+            rustc_hir::ExprKind::Binary(..) => {
+                self.observe_comparison(expr);
+            }
+
             // Unary * on an instrumented &T / &mut T with tupleable T
             // strips the tag post-instrumentation (TaggedRef::deref -> T). Record
             // the span so pass 2 can rebuild a Tagged<T> from the borrowed fields,
@@ -147,9 +210,34 @@ impl<'tcx, 'a> rustc_hir::intravisit::Visitor<'tcx> for AnalyzeHirVisitor<'tcx,
                 self.observe_match(expr);
             }
 
+            // Reads of `const` / `static` items hold untagged values, as their initializers are
+            // evaluated in const contexts. Record them, so that pass 2 tags every read of an item
+            // with the same id.
+            rustc_hir::ExprKind::Path(..) => {
+                self.observe_global_read(expr);
+            }
+
+            // Probes are replaced before expansion by pass 2, which can't tell which function
+            // encloses them. Record the program point each `datir_probe!` belongs to.
+            rustc_hir::ExprKind::Block(..) => {
+                self.observe_probe_macro(expr);
+            }
+
             _ => {}
         }
 
         rustc_hir::intravisit::walk_expr(self, expr);
     }
+
+    /// Called on each `let` statement, recording the program point of `#[datir::probe]`s.
+    fn visit_local(&mut self, local: &'tcx rustc_hir::LetStmt<'tcx>) {
+        let owner = local.hir_id.owner.def_id;
+        if self.tcx.has_typeck_results(owner)
+            && !self.first_pass.fns.contains_skipped(&owner.to_def_id())
+        {
+            self.observe_probe_attr(local);
+        }
+
+        rustc_hir::intravisit::walk_local(self, local);
+    }
 }
@@ -0,0 +1,163 @@
+//! Defines how the [`AnalyzeHirVisitor`] records user-placed probe points.
+//!
+//! Probes are replaced by the instrument compilation before expansion, where the enclosing
+//! function's base ppt name is unknown. Record it against the span of each probe.
+//!
+//! decls-gen declares no probe ppts, so the variables of tupleable type each probe binds are
+//! recorded as well, to declare the probe's ppt alongside the ones decls-gen wrote.
+//!
+//! See [crate::callbacks::codegen::probes] for the probe syntax.
+
+use decls_gen::DeclsFile;
+
+use crate::callbacks::{
+    codegen::probes::{self, DATIR_TOOL, PROBE_ATTR, PROBE_MACRO},
+    gather::{
+        analyze_hir::AnalyzeHirVisitor,
+        first_pass_info::{ProbeSite, ProbeVar},
+    },
+    types::{self, CanBeTupled},
+};
+
+impl<'tcx, 'a> AnalyzeHirVisitor<'tcx, 'a> {
+    /// If the block was expanded from a `datir_probe!` invocation, records the base ppt name of
+    /// the enclosing function against the span of the invocation.
+    ///
+    /// The block starts with a `let _ = "name";` statement, followed by a `let _ = &var;`
+    /// statement per variable, see [`probes::declare_probe_syntax`].
+    pub fn observe_probe_macro(&mut self, expr: &rustc_hir::Expr<'tcx>) {
+        let rustc_hir::ExprKind::Block(block, _) = expr.kind else {
+            panic!("Called observe_probe_macro with non-block expression.");
+        };
+
+        let expn = expr.span.ctxt().outer_expn_data();
+        let rustc_span::ExpnKind::Macro(rustc_span::MacroKind::Bang, name) = expn.kind else {
+            return;
+        };
+        if name.as_str() != PROBE_MACRO {
+            return;
+        }
+
+        let inits = block.stmts.iter().map(|stmt| match stmt.kind {
+            rustc_hir::StmtKind::Let(rustc_hir::LetStmt {
+                init: Some(init), ..
+            }) => init,
+            _ => panic!("`{PROBE_MACRO}!` expanded to an unexpected statement."),
+        });
+        let mut probe_name = None;
+        let mut vars = Vec::new();
+        for init in inits {
+            match init.kind {
+                rustc_hir::ExprKind::Lit(lit) => {
+                    let rustc_ast::LitKind::Str(sym, _) = lit.node else {
+                        panic!("`{PROBE_MACRO}!` expects a string literal as the probe name.");
+                    };
+                    probe_name = Some(sym.to_string());
+                }
+                rustc_hir::ExprKind::AddrOf(_, _, var) => vars.push(var),
+                _ => panic!("`{PROBE_MACRO}!` expanded to an unexpected statement."),
+            }
+        }
+        let probe_name =
+            probe_name.unwrap_or_else(|| panic!("`{PROBE_MACRO}!` is missing a probe name."));
+
+        let owner = expr.hir_id.owner.def_id;
+        let typeck = self.tcx.typeck(owner);
+        let sm = self.tcx.sess.source_map();
+        let vars = vars
+            .into_iter()
+            .filter_map(|var| Some((sm.span_to_snippet(var.span).ok()?, typeck.expr_ty(var))))
+            .collect();
+        self.record_probe(expn.call_site, owner, &probe_name, vars);
+    }
+
+    /// If the `let` statement carries a `#[datir::probe]` attribute, records the base ppt name of
+    /// the enclosing function against the span of the statement.
+    pub fn observe_probe_attr(&mut self, local: &rustc_hir::LetStmt) {
+        use rustc_ast::attr::AttributeExt;
+
+        let path = [
+            rustc_span::Symbol::intern(DATIR_TOOL),
+            rustc_span::Symbol::intern(PROBE_ATTR),
+        ];
+        let Some(attr) = self
+            .tcx
+            .hir_attrs(local.hir_id)
+            .iter()
+            .find(|attr| attr.path_matches(&path))
+        else {
+            return;
+        };
+        let name = match attr.meta_item_list().as_deref() {
+            Some(
+                [
+                    rustc_ast::MetaItemInner::Lit(rustc_ast::MetaItemLit {
+                        kind: rustc_ast::LitKind::Str(name, _),
+                        ..
+                    }),
+                ],
+            ) => name.to_string(),
+            _ => panic!("`#[{DATIR_TOOL}::{PROBE_ATTR}]` expects a single string literal name."),
+        };
+
+        let owner = local.hir_id.owner.def_id;
+        let typeck = self.tcx.typeck(owner);
+        let mut vars = Vec::new();
+        local.pat.walk(|pat| {
+            if let rustc_hir::PatKind::Binding(_, hir_id, ident, _) = pat.kind {
+                vars.push((ident.to_string(), typeck.node_type(hir_id)));
+            }
+            true
+        });
+        self.record_probe(local.span, owner, &name, vars);
+    }
+
+    /// Records a probe named `name` at `span`, within the body owned by `owner`, binding `vars`
+    /// (their names and types).
+    fn record_probe(
+        &mut self,
+        span: rustc_span::Span,
+        owner: rustc_span::def_id::LocalDefId,
+        name: &str,
+        vars: Vec<(String, rustc_middle::ty::Ty<'tcx>)>,
+    ) {
+        let base_ppt_name = DeclsFile::ppt_base_name(self.tcx, owner);
+
+        // probes sharing a name share their site, and so their declaration.
+        let declared = self
+            .first_pass
+            .probe_ppts
+            .entry(probes::probe_ppt_name(&base_ppt_name, name))
+            .or_default();
+        for (var, ty) in vars {
+            let ty = ty.peel_refs();
+            if !ty.can_be_tupled() || declared.iter().any(|declared| declared.name == var) {
+                continue;
+            }
+            declared.push(ProbeVar {
+                name: var,
+                dec_type: ty.to_string(),
+                rep_type: rep_type(ty),
+            });
+        }
+
+        self.first_pass.probes.record(
+            span,
+            self.tcx.sess.source_map(),
+            ProbeSite { base_ppt_name },
+        );
+    }
+}
+
+/// Daikon representation type of the tupleable type `ty`.
+fn rep_type(ty: rustc_middle::ty::Ty<'_>) -> &'static str {
+    if ty.is_bool() {
+        "boolean"
+    } else if ty.is_floating_point() {
+        "double"
+    } else if types::is_string(ty) {
+        "java.lang.String"
+    } else {
+        "int"
+    }
+}
@@ -3,7 +3,10 @@
 //! See the top-level comment in [crate::callbacks::gather::analyze_hir] for more information as to
 //! why this is necessary.
 
-use crate::{callbacks::gather::analyze_hir::AnalyzeHirVisitor, callbacks::types::CanBeTupled};
+use crate::{
+    callbacks::gather::analyze_hir::AnalyzeHirVisitor,
+    callbacks::types::{self, CanBeTupled},
+};
 
 impl<'tcx, 'a> AnalyzeHirVisitor<'tcx, 'a> {
     /// If `expr`'s adjusted type is `&T` / `&mut T` with `T` either tupleable or an
@@ -12,6 +15,9 @@ impl<'tcx, 'a> AnalyzeHirVisitor<'tcx, 'a> {
     /// Pass 2 uses this to normalize all four post-instrumentation operand shapes
     /// (`Tagged<T>`, `&Tagged<T>`, `TaggedRef<T>`, `TaggedRefMut<T>`) into a uniform
     /// `TaggedRef<T>` / `TaggedRefMut<T>` via `.share()` / `.reborrow()`.
+    ///
+    /// A shared `&String` which is deref-coerced into a `&str` is additionally recorded in
+    /// [`FirstPassInfo::str_deref_coercions`](crate::callbacks::gather::first_pass_info::FirstPassInfo::str_deref_coercions).
     pub fn observe_ref_normalization(&mut self, expr: &rustc_hir::Expr<'tcx>) {
         let ldid = expr.hir_id.owner.def_id;
         let typeck = self.tcx.typeck(ldid);
@@ -40,5 +46,16 @@ impl<'tcx, 'a> AnalyzeHirVisitor<'tcx, 'a> {
         self.first_pass
             .ref_to_tupleable
             .record(expr.span, self.tcx.sess.source_map(), ast_mutbl);
+
+        // `&String` coerced to `&str`, which `TaggedRef<String>` can't be.
+        if !mutbl.is_mut()
+            && !referent.is_str()
+            && types::is_string(referent)
+            && typeck.expr_ty_adjusted(expr).peel_refs().is_str()
+        {
+            self.first_pass
+                .str_deref_coercions
+                .mark(expr.span, self.tcx.sess.source_map());
+        }
     }
 }
@@ -0,0 +1,153 @@
+//! Decides which crate-local `macro_rules!` macros have their expansions instrumented.
+//!
+//! Pass 2 runs before expansion, and instruments the transcriber of every expression macro
+//! recorded here (see [crate::callbacks::macros]), alongside the arguments of each of its
+//! invocations, see [crate::callbacks::instrument::macros]. An instrumented transcriber expands
+//! into instrumented code wherever it is invoked, so an expression macro is only recorded if
+//! every one of its expansions lies within the body of an instrumented function. It is left
+//! uninstrumented if expanded:
+//! - within a skipped function or a `const fn`, which are compiled without instrumentation.
+//! - within a `const` / `static` initializer, an array length, or any other const context.
+//! - in pattern, type or item position, e.g. `match x { zero!() => .. }`.
+
+use std::collections::HashSet;
+
+use rustc_middle::ty::TyCtxt;
+use rustc_span::def_id::DefId;
+
+use crate::callbacks::{gather::first_pass_info::FirstPassInfo, macros};
+
+/// Records every expression macro of the crate whose expansions can all be instrumented.
+pub fn find_expression_macros(tcx: TyCtxt<'_>, first_pass: &mut FirstPassInfo) {
+    let mut defs = Vec::new();
+    let mut other_names = HashSet::new();
+    for item_id in tcx.hir_free_items() {
+        let item = tcx.hir_item(item_id);
+        let rustc_hir::ItemKind::Macro(ident, def, _) = item.kind else {
+            continue;
+        };
+        if macros::is_expression_macro(def) {
+            defs.push((item.owner_id.to_def_id(), ident.to_string(), item.span));
+        } else {
+            other_names.insert(ident.to_string());
+        }
+    }
+    if defs.is_empty() {
+        return;
+    }
+
+    let mut visitor = ExpansionVisitor {
+        tcx,
+        first_pass,
+        const_depth: 0,
+        excluded: HashSet::new(),
+    };
+    tcx.hir_walk_toplevel_module(&mut visitor);
+    let excluded = visitor.excluded;
+
+    // invocations are matched to definitions by name alone, so a name shared with any macro
+    // which is left uninstrumented leaves every macro of that name uninstrumented.
+    other_names.extend(
+        defs.iter()
+            .filter(|(def_id, ..)| excluded.contains(def_id))
+            .map(|(_, name, _)| name.clone()),
+    );
+    for (_, name, span) in defs {
+        if other_names.contains(&name) {
+            continue;
+        }
+        first_pass
+            .expression_macros
+            .mark(span, tcx.sess.source_map());
+        first_pass.expression_macro_names.insert(name);
+    }
+}
+
+/// Visitor finding the crate-local macros expanded outside of instrumented function bodies.
+struct ExpansionVisitor<'tcx, 'a> {
+    tcx: TyCtxt<'tcx>,
+    first_pass: &'a FirstPassInfo,
+    /// Number of const contexts (array lengths, inline consts, ...) currently visited.
+    const_depth: usize,
+    /// Macros with at least one expansion which can't be instrumented.
+    excluded: HashSet<DefId>,
+}
+
+impl ExpansionVisitor<'_, '_> {
+    /// Whether the expression identified by `hir_id` lies within an instrumented function body.
+    fn instruments(&self, hir_id: rustc_hir::HirId) -> bool {
+        let owner = hir_id.owner.to_def_id();
+        self.const_depth == 0
+            && self.first_pass.fns.contains(&owner)
+            && !self.tcx.is_const_fn(owner)
+    }
+
+    /// Excludes the macro the node at `span` is the root of the expansion of, if any. Used on
+    /// patterns, types and items, which expression macros may only produce within an expression.
+    fn exclude_root(&mut self, span: rustc_span::Span, hir_id: rustc_hir::HirId) {
+        let Some(def_id) = local_macro(span) else {
+            return;
+        };
+        let parent_span = self.tcx.hir_span(self.tcx.parent_hir_id(hir_id));
+        if parent_span.ctxt() != span.ctxt() {
+            self.excluded.insert(def_id);
+        }
+    }
+}
+
+impl<'tcx> rustc_hir::intravisit::Visitor<'tcx> for ExpansionVisitor<'tcx, '_> {
+    type NestedFilter = rustc_middle::hir::nested_filter::All;
+
+    fn maybe_tcx(&mut self) -> Self::MaybeTyCtxt {
+        self.tcx
+    }
+
+    /// Excludes every local macro `expr` was expanded from (directly, or through other macros),
+    /// unless it lies within an instrumented function body.
+    fn visit_expr(&mut self, expr: &'tcx rustc_hir::Expr<'tcx>) {
+        if !self.instruments(expr.hir_id) {
+            let mut span = expr.span;
+            while span.from_expansion() {
+                self.excluded.extend(local_macro(span));
+                span = span.ctxt().outer_expn_data().call_site;
+            }
+        }
+        rustc_hir::intravisit::walk_expr(self, expr);
+    }
+
+    fn visit_pat(&mut self, pat: &'tcx rustc_hir::Pat<'tcx>) {
+        self.exclude_root(pat.span, pat.hir_id);
+        rustc_hir::intravisit::walk_pat(self, pat);
+    }
+
+    fn visit_ty(&mut self, ty: &'tcx rustc_hir::Ty<'tcx, rustc_hir::AmbigArg>) {
+        self.exclude_root(ty.span, ty.hir_id);
+        rustc_hir::intravisit::walk_ty(self, ty);
+    }
+
+    fn visit_item(&mut self, item: &'tcx rustc_hir::Item<'tcx>) {
+        self.exclude_root(item.span, item.hir_id());
+        rustc_hir::intravisit::walk_item(self, item);
+    }
+
+    fn visit_anon_const(&mut self, constant: &'tcx rustc_hir::AnonConst) {
+        self.const_depth += 1;
+        rustc_hir::intravisit::walk_anon_const(self, constant);
+        self.const_depth -= 1;
+    }
+
+    fn visit_inline_const(&mut self, constant: &'tcx rustc_hir::ConstBlock) {
+        self.const_depth += 1;
+        rustc_hir::intravisit::walk_inline_const(self, constant);
+        self.const_depth -= 1;
+    }
+}
+
+/// The crate-local `macro_rules!` macro `span` was directly expanded from, if any.
+fn local_macro(span: rustc_span::Span) -> Option<DefId> {
+    let expn = span.ctxt().outer_expn_data();
+    let rustc_span::ExpnKind::Macro(rustc_span::MacroKind::Bang, _) = expn.kind else {
+        return None;
+    };
+    expn.macro_def_id.filter(|def_id| def_id.is_local())
+}
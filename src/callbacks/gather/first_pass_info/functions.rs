@@ -12,6 +12,12 @@
 //! Futher, there are multiple points where either pass needs to ask "is this function
 //! instrumented?", with different HIR/AST information available. The [`FnIndex`] also provides
 //! methods to answer that question, in constant time.
+//!
+//! Functions left out of instrumentation by the user (see [crate::callbacks::gather::scope]) are
+//! registered separately, so that generated names never clash with theirs, and so that their
+//! bodies can be told apart from those of other uninstrumented code. Instrumented functions on the
+//! public API boundary, which keep their untagged signature (see [crate::callbacks::fn_headers]),
+//! are additionally marked as such.
 
 use crate::callbacks::gather::type_key::TypeKey;
 
@@ -41,6 +47,10 @@ pub enum FnNamespace<'a> {
 struct ModEntry {
     free_fns: std::collections::HashMap<String, FnBasePptName>,
     methods: std::collections::HashMap<TypeKey, std::collections::HashMap<String, FnBasePptName>>,
+    /// Names of the skipped fns/methods, with the type key of the methods.
+    skipped: std::collections::HashSet<(Option<TypeKey>, String)>,
+    /// Names of the instrumented fns/methods preserving their ABI, keyed like `skipped`.
+    preserved_abi: std::collections::HashSet<(Option<TypeKey>, String)>,
 }
 
 impl ModEntry {
@@ -64,6 +74,15 @@ impl ModEntry {
             FnNamespace::Method(tk) => self.methods.entry(tk.clone()).or_default(),
         }
     }
+
+    /// Key of a skipped (or ABI preserving) fn/method named `ident` within the input namespace.
+    fn name_key(ns: FnNamespace, ident: &str) -> (Option<TypeKey>, String) {
+        let type_key = match ns {
+            FnNamespace::Free => None,
+            FnNamespace::Method(tk) => Some(tk.clone()),
+        };
+        (type_key, ident.to_string())
+    }
 }
 
 /// Registry of every fn/method that the gather pass wants the instrument pass to transform,
@@ -76,6 +95,12 @@ pub struct FnIndex {
     /// Pass-1 HIR call analysis hits this when a typeck-resolved
     /// `DefId` is available.
     by_def_id: std::collections::HashSet<rustc_span::def_id::DefId>,
+
+    /// Every `DefId` registered through [`FnIndex::record_skipped`].
+    skipped_def_ids: std::collections::HashSet<rustc_span::def_id::DefId>,
+
+    /// Every `DefId` registered through [`FnIndex::record_preserved_abi`].
+    preserved_abi_def_ids: std::collections::HashSet<rustc_span::def_id::DefId>,
 }
 
 impl FnIndex {
@@ -97,24 +122,96 @@ impl FnIndex {
         self.by_def_id.insert(def_id);
     }
 
+    /// Record that `def_id` (with display `ident`) lives at `mod_path`, and was left out of
+    /// instrumentation by the user.
+    pub fn record_skipped(
+        &mut self,
+        mod_path: ModPath,
+        ns: FnNamespace,
+        ident: rustc_span::Ident,
+        def_id: rustc_span::def_id::DefId,
+    ) {
+        self.mods
+            .entry(mod_path)
+            .or_default()
+            .skipped
+            .insert(ModEntry::name_key(ns, ident.as_str()));
+        self.skipped_def_ids.insert(def_id);
+    }
+
+    /// Record that the already recorded `def_id` (with display `ident`), living at `mod_path`,
+    /// keeps its untagged signature.
+    pub fn record_preserved_abi(
+        &mut self,
+        mod_path: ModPath,
+        ns: FnNamespace,
+        ident: rustc_span::Ident,
+        def_id: rustc_span::def_id::DefId,
+    ) {
+        self.mods
+            .entry(mod_path)
+            .or_default()
+            .preserved_abi
+            .insert(ModEntry::name_key(ns, ident.as_str()));
+        self.preserved_abi_def_ids.insert(def_id);
+    }
+
     /// Look up the recorded `FnBasePptName` at `(mod_path, ns, ident)`.
     pub fn lookup(&self, mod_path: &str, ns: FnNamespace, ident: &str) -> Option<&FnBasePptName> {
         self.mods.get(mod_path)?.slot(ns)?.get(ident)
     }
 
-    /// Returns the set of fn/method names defined in the `(mod_path, ns)` slot. 
+    /// Returns true if the fn/method at `(mod_path, ns, ident)` was skipped.
+    pub fn is_skipped(&self, mod_path: &str, ns: FnNamespace, ident: &str) -> bool {
+        self.mods
+            .get(mod_path)
+            .is_some_and(|m| m.skipped.contains(&ModEntry::name_key(ns, ident)))
+    }
+
+    /// Returns true if the instrumented fn/method at `(mod_path, ns, ident)` keeps its untagged
+    /// signature.
+    pub fn preserves_abi(&self, mod_path: &str, ns: FnNamespace, ident: &str) -> bool {
+        self.mods
+            .get(mod_path)
+            .is_some_and(|m| m.preserved_abi.contains(&ModEntry::name_key(ns, ident)))
+    }
+
+    /// Returns the set of fn/method names defined in the `(mod_path, ns)` slot, including the
+    /// skipped ones.
     /// 
     /// Used by the shim generation process to choose a non-clashing inner name.
     pub fn names_in(&self, mod_path: &str, ns: FnNamespace) -> std::collections::HashSet<String> {
-        self.mods
-            .get(mod_path)
-            .and_then(|m| m.slot(ns))
+        let Some(entry) = self.mods.get(mod_path) else {
+            return Default::default();
+        };
+        let mut names: std::collections::HashSet<String> = entry
+            .slot(ns)
             .map(|s| s.keys().cloned().collect())
-            .unwrap_or_default()
+            .unwrap_or_default();
+        let (type_key, _) = ModEntry::name_key(ns, "");
+        names.extend(
+            entry
+                .skipped
+                .iter()
+                .filter(|(tk, _)| *tk == type_key)
+                .map(|(_, name)| name.clone()),
+        );
+        names
     }
 
     /// Returns true if the `def_id` was registered as a tracked function.
     pub fn contains(&self, def_id: &rustc_span::def_id::DefId) -> bool {
         self.by_def_id.contains(def_id)
     }
+
+    /// Returns true if the `def_id` was registered as a skipped function.
+    pub fn contains_skipped(&self, def_id: &rustc_span::def_id::DefId) -> bool {
+        self.skipped_def_ids.contains(def_id)
+    }
+
+    /// Returns true if the `def_id` was registered as a tracked function keeping its untagged
+    /// signature.
+    pub fn contains_preserved_abi(&self, def_id: &rustc_span::def_id::DefId) -> bool {
+        self.preserved_abi_def_ids.contains(def_id)
+    }
 }
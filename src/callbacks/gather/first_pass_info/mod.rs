@@ -20,23 +20,93 @@
 use crate::callbacks::gather::first_pass_info::span_facts::SpanFacts;
 
 mod functions;
+mod ret_shape;
 mod span_facts;
 mod span_key;
 
 pub use functions::{FnNamespace, ModPath};
+pub use ret_shape::RetShape;
 
 /// Payload for `untracked_fn_calls`: information about a call to an
 /// untracked function, recorded by pass 1 against the call's syntactic span.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct UntrackedCall {
-    /// Whether the return type at the call site is tupleable (i.e. a tracked
-    /// primitive).
-    pub ret_is_tupleable: bool,
-    // FIXME: these function calls could return complex types, like structs,
-    // which can be tupled but that requires defining a new struct with
-    // Tagged variants of all fields, and that's hard to do, ignoring for
-    // now. hopefully it won't be a problem. We almost definitely want
-    // more fields in this struct
+    /// Shape of the return type at the call site, describing which parts of the
+    /// returned value need to be tagged.
+    pub ret_shape: RetShape,
+    /// Whether each argument is untupled before being passed. Only tagged values are; anything
+    /// else (local structs, std handles such as a `&Waker`) is passed as it is.
+    pub untupled_args: Vec<bool>,
+    /// Whether the receiver of a method call is untupled as well. Only set for the methods of
+    /// `str` / `String`, which are otherwise called on a `Tagged<String>` / `TaggedRef<str>`.
+    pub untupled_receiver: bool,
+}
+
+/// Payload for `calls_from_skipped`: a call made by a skipped function (see
+/// [crate::callbacks::gather::scope]) to an instrumented one.
+#[derive(Debug, Clone)]
+pub struct CallFromSkipped {
+    /// Whether each argument is a tupleable primitive, which is tagged before being passed.
+    pub tagged_args: Vec<bool>,
+    /// Shape of the return type at the call site, describing which parts of the returned
+    /// value need to be untupled.
+    pub ret_shape: RetShape,
+}
+
+/// Payload for `object_ppts`: the `:::OBJECT` and `:::CLASS` program points of a user-defined
+/// struct, recorded by pass 1 against the span of the struct's identifier.
+#[derive(Debug, Clone)]
+pub struct ObjectPpt {
+    /// `DeclsFile`-formatted base ppt name of the struct (everything before `:::OBJECT`).
+    pub base_ppt_name: String,
+    /// The `const` / `static` items defined within the struct's module, Rust's closest
+    /// equivalent to the static fields of a class, which are bound to its `:::CLASS` site.
+    pub class_globals: Vec<GlobalItem>,
+}
+
+/// Payload for `global_reads`: a read of a `const` / `static` item of tupleable type.
+#[derive(Debug, Clone)]
+pub struct GlobalRead {
+    /// Key identifying the item at runtime, see [`GlobalItem::key`]. `None` for trait associated
+    /// consts, whose value depends on the implementing type, which are tagged like literals.
+    pub key: Option<String>,
+}
+
+/// A module-level `const` / `static` item of tupleable type, defined within the crate.
+#[derive(Debug, Clone)]
+pub struct GlobalItem {
+    /// Key identifying the item at runtime (`<crate>::<path>`), shared by every read of the item.
+    pub key: String,
+    /// Name of the variable the `.decls` file declares for the item (`::<path>`).
+    pub var_name: String,
+}
+
+/// Payload for `probes`: a user-placed probe point, see [crate::callbacks::codegen::probes].
+#[derive(Debug, Clone)]
+pub struct ProbeSite {
+    /// `DeclsFile`-formatted base ppt name of the function enclosing the probe.
+    pub base_ppt_name: String,
+}
+
+/// A variable of tupleable type bound by a user-placed probe, declared at the probe's ppt.
+#[derive(Debug, Clone)]
+pub struct ProbeVar {
+    pub name: String,
+    /// Type of the variable, as written in Rust (`u32`, `bool`, ...).
+    pub dec_type: String,
+    /// Daikon representation type of the variable (`int`, `boolean`, ...).
+    pub rep_type: &'static str,
+}
+
+/// Original source location of the function or struct owning a base ppt name. Lines and columns
+/// are 1-based. Reported alongside each ppt by the JSON output of instrumented binaries.
+#[derive(Debug, Clone)]
+pub struct PptSpan {
+    pub file: String,
+    pub line_start: usize,
+    pub col_start: usize,
+    pub line_end: usize,
+    pub col_end: usize,
 }
 
 /// Contains all information that is going to be passed between the
@@ -50,13 +120,78 @@ pub struct FirstPassInfo {
     /// Registry of every fn/method to instrument.
     pub fns: functions::FnIndex,
 
+    /// Def path hashes of the skipped fns/methods, which identify them within the compilations
+    /// of dependent crates, see [`DatirConfig::skipped_dependency_fns`].
+    ///
+    /// [`DatirConfig::skipped_dependency_fns`]: crate::config::DatirConfig::skipped_dependency_fns
+    pub skipped_fn_hashes: std::collections::HashSet<rustc_span::def_id::DefPathHash>,
+
+    /// `:::OBJECT` and `:::CLASS` program points of user-defined structs, keyed by the span of
+    /// the struct's identifier.
+    pub object_ppts: SpanFacts<ObjectPpt>,
+
+    /// Source location of every instrumented function, and of every struct, keyed by base ppt
+    /// name.
+    pub ppt_spans: std::collections::BTreeMap<String, PptSpan>,
+
+    /// Every module-level global of the crate, bound at each program point which declares it.
+    pub globals: Vec<GlobalItem>,
+
+    /// Reads of `const` / `static` items of tupleable type, keyed by path-expression span.
+    pub global_reads: SpanFacts<GlobalRead>,
+
+    /// User-placed probe points, keyed by the span of the `datir_probe!` invocation, or of the
+    /// `let` statement carrying a `#[datir::probe]` attribute.
+    pub probes: SpanFacts<ProbeSite>,
+
+    /// Variables of tupleable type bound by the probe sites, keyed by ppt name. decls-gen
+    /// declares no probe ppts, so these are appended to the `.decls` file it wrote, see
+    /// [`DatirConfig::decls_path`].
+    ///
+    /// [`DatirConfig::decls_path`]: crate::config::DatirConfig::decls_path
+    pub probe_ppts: std::collections::BTreeMap<String, Vec<ProbeVar>>,
+
+    /// Arguments of std macros (`format!`, `assert!`, `matches!`, ...) whose type is tupleable,
+    /// or a reference to a tupleable type, keyed by the span of the argument. Pass 2 untuples
+    /// these where the macro needs the untagged value, e.g. to format it.
+    pub tagged_macro_args: SpanFacts<()>,
+
+    /// Names of the variables captured by a format string (`"{x}"`) whose type is tupleable, or
+    /// a reference to a tupleable type, keyed by the span of the macro invocation.
+    pub format_captures: SpanFacts<Vec<String>>,
+
+    /// Crate-local expression macros (see [crate::callbacks::macros]) whose transcribers are
+    /// instrumented, keyed by the span of the `macro_rules!` item.
+    pub expression_macros: SpanFacts<()>,
+
+    /// Names of the macros in `expression_macros`, whose invocations have their arguments
+    /// instrumented. Names shared with any other crate-local macro are left out.
+    pub expression_macro_names: std::collections::HashSet<String>,
+
     /// Calls to untracked functions, keyed by call-expression span.
     pub untracked_fn_calls: SpanFacts<UntrackedCall>,
 
+    /// Method calls to skipped methods, and to the methods of `str` / `String` which the runtime
+    /// doesn't shadow, keyed by the span of the method name.
+    pub untracked_method_calls: SpanFacts<UntrackedCall>,
+
+    /// Calls made by skipped functions to instrumented ones, keyed by the span of the called
+    /// path, or of the method name.
+    pub calls_from_skipped: SpanFacts<CallFromSkipped>,
+
+    /// Field accesses made by skipped functions to fields of crate-local types declared with a
+    /// tupleable type, keyed by field-expression span. These fields hold tagged values, which
+    /// the skipped code untuples.
+    pub skipped_field_accesses: SpanFacts<()>,
+
     /// Indexing expressions where a range is used as the index. These are places where a 
     /// `.subslice()` call must be inserted.
     pub index_by_range: SpanFacts<()>,
 
+    /// Indexing expressions where a range is used as the index into a `str` / `String`. These
+    /// are places where a `.substr()` call must be inserted.
+    pub index_str_by_range: SpanFacts<()>,
+
     /// Spans of unary `*` expressions whose operand's type is `&T` / `&mut T`
     /// with `T` tupleable. Post-instrumentation these operate on a
     /// `TaggedRef` / `TaggedRefMut`, and a raw `*` would strip the tag.
@@ -86,11 +221,21 @@ pub struct FirstPassInfo {
     ///   about marking is harmless.
     pub ref_to_tupleable: SpanFacts<rustc_ast::Mutability>,
 
+    /// Spans of `&String`-typed expressions which are deref-coerced into a `&str`. Once
+    /// instrumented, these are `TaggedRef<String>`s, which pass 2 converts to the expected
+    /// `TaggedRef<str>` through an `.as_str()` call.
+    pub str_deref_coercions: SpanFacts<()>,
+
     /// Spans of match target expressions which are tagged types. These types 
     /// require untupling, so that the patterns within each arm of the statement
     /// can actually match on the target.
     pub match_on_tagged: SpanFacts<()>,
 
+    /// Spans of comparison expressions whose operands are of a compound type, such as
+    /// `Option<u32>`. These compare through the type's `PartialEq` / `PartialOrd` impl, rather
+    /// than on the ids and values of tagged operands.
+    pub compound_comparisons: SpanFacts<()>,
+
     /// Spans of literal / range sub-patterns whose pattern type is tupleable.
     /// Post-instrumentation, the position holding such a sub-pattern has type
     /// `Tagged<T>` rather than `T`, so a bare literal/range no longer
@@ -0,0 +1,93 @@
+//! Defines [`RetShape`], the payload describing what an untracked function call returns.
+//!
+//! Untracked functions are compiled without instrumentation, so whatever they return holds plain
+//! `T`s where instrumented code expects `Tagged<T>`s. For a bare primitive this is fixed by
+//! wrapping the call in `ATI::track(..)`, but compound return values (tuples, arrays, `Option`,
+//! `Result`, and local generic structs) have their primitives nested inside. The Gather
+//! compilation records the full shape of the return type, so the Instrument compilation can
+//! rebuild the value with a fresh id per leaf.
+
+/// Shape of a value returned by an untracked call, restricted to the parts that need tagging.
+///
+/// Constructed through [`RetShape::tuple`], [`RetShape::tagged_array`], [`RetShape::option`],
+/// [`RetShape::result`] and [`RetShape::structure`], which collapse shapes that contain nothing to tag into
+/// [`RetShape::Opaque`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RetShape {
+    /// Nothing inside requires tagging, the value is passed through untouched.
+    Opaque,
+    /// A tupleable primitive, becomes `Tagged<T>`.
+    Leaf,
+    /// A borrowed string `&str`, becomes a `TaggedRef<str>`.
+    Str,
+    /// A tuple, each element is tagged according to its own shape.
+    Tuple(Vec<RetShape>),
+    /// An array `[T; N]`. Instrumented code represents arrays as `Tagged<[T; N]>` regardless of
+    /// the element type, so this is never opaque.
+    Array(Box<RetShape>),
+    /// An array field `[T; N]` of a local generic struct. The struct definition already wraps it
+    /// into `Tagged<[T; N]>`, so only its elements are tagged, in place.
+    TaggedArray(Box<RetShape>),
+    /// An `Option<T>`, the inner value is tagged through `Option::map`.
+    Option(Box<RetShape>),
+    /// A `Result<T, E>`, tagged through `Result::map` and `Result::map_err`.
+    Result(Box<RetShape>, Box<RetShape>),
+    /// A struct defined in the instrumented crate, rebuilt field by field through a struct
+    /// expression. `path` is the crate-rooted path to the struct (`crate::m::Pair`).
+    Struct {
+        path: String,
+        fields: Vec<(String, RetShape)>,
+    },
+}
+
+impl RetShape {
+    /// Whether any part of a value of this shape needs to be tagged.
+    pub fn needs_tagging(&self) -> bool {
+        !matches!(self, RetShape::Opaque)
+    }
+
+    /// Shape of a tuple with the given element shapes.
+    pub fn tuple(elems: Vec<RetShape>) -> RetShape {
+        if elems.iter().any(RetShape::needs_tagging) {
+            RetShape::Tuple(elems)
+        } else {
+            RetShape::Opaque
+        }
+    }
+
+    /// Shape of an already tagged array field, whose elements have shape `inner`.
+    pub fn tagged_array(inner: RetShape) -> RetShape {
+        if inner.needs_tagging() {
+            RetShape::TaggedArray(Box::new(inner))
+        } else {
+            RetShape::Opaque
+        }
+    }
+
+    /// Shape of an `Option<T>`, where `T` has shape `inner`.
+    pub fn option(inner: RetShape) -> RetShape {
+        if inner.needs_tagging() {
+            RetShape::Option(Box::new(inner))
+        } else {
+            RetShape::Opaque
+        }
+    }
+
+    /// Shape of a `Result<T, E>`, where `T` has shape `ok` and `E` has shape `err`.
+    pub fn result(ok: RetShape, err: RetShape) -> RetShape {
+        if ok.needs_tagging() || err.needs_tagging() {
+            RetShape::Result(Box::new(ok), Box::new(err))
+        } else {
+            RetShape::Opaque
+        }
+    }
+
+    /// Shape of a local struct at `path`, with the given field names and shapes.
+    pub fn structure(path: String, fields: Vec<(String, RetShape)>) -> RetShape {
+        if fields.iter().any(|(_, shape)| shape.needs_tagging()) {
+            RetShape::Struct { path, fields }
+        } else {
+            RetShape::Opaque
+        }
+    }
+}
@@ -32,20 +32,30 @@ impl<F> Default for SpanFacts<F> {
     }
 }
 
-impl<F> SpanFacts<F> {
+impl<F: Clone> SpanFacts<F> {
     /// Record a fact at `span`. No-op if the span doesn't map to a stable
     /// `SpanKey` (dummy spans, unmapped expansion sites, etc.), as this means
     /// this fact will be innaccessible during the instrument compilation.
+    ///
+    /// Facts about code expanded from a crate-local `macro_rules!` macro are also recorded at
+    /// the span's position within the macro's transcriber, see
+    /// [`SpanKey::from_transcriber_span`].
     pub fn record(
         &mut self,
         span: rustc_span::Span,
         sm: &rustc_span::source_map::SourceMap,
         fact: F,
     ) {
+        if let Some(key) = SpanKey::from_transcriber_span(span, sm) {
+            self.inner.insert(key, fact.clone());
+        }
         if let Some(key) = SpanKey::from_span(span, sm) {
             self.inner.insert(key, fact);
         }
     }
+}
+
+impl<F> SpanFacts<F> {
 
     /// Look up a fact at `span`.
     pub fn get(
@@ -15,6 +15,11 @@
 //! Spans inside macro expansions / desugarings are normalized via
 //! `source_callsite()` so a query on a syntactic AST node still hits the
 //! appropriate entry.
+//!
+//! Spans inside the expansion of a crate-local `macro_rules!` macro additionally map to their
+//! position within the macro's transcriber, see [`SpanKey::from_transcriber_span`]. Pass 2
+//! instruments the transcribers of these macros (see [crate::callbacks::macros]), where this
+//! position is the only span it sees.
 
 use std::path::PathBuf;
 
@@ -63,7 +68,38 @@ impl SpanKey {
         span: rustc_span::Span,
         sm: &rustc_span::source_map::SourceMap,
     ) -> Option<Self> {
-        let span = span.source_callsite();
+        Self::from_resolved_span(span.source_callsite(), sm)
+    }
+
+    /// Resolve `span`, if expanded from a crate-local `macro_rules!` macro, to the key of its
+    /// position within that macro's transcriber. Spans expanded from other macros invoked by the
+    /// transcriber resolve to the position of the invocation.
+    ///
+    /// Every expansion of the macro shares the key, so facts recorded against it must hold for
+    /// each invocation (the last one recorded wins).
+    pub fn from_transcriber_span(
+        span: rustc_span::Span,
+        sm: &rustc_span::source_map::SourceMap,
+    ) -> Option<Self> {
+        let mut span = span;
+        while span.from_expansion() {
+            let expn = span.ctxt().outer_expn_data();
+            let is_local_bang_macro =
+                matches!(expn.kind, rustc_span::ExpnKind::Macro(rustc_span::MacroKind::Bang, _))
+                    && expn.macro_def_id.is_some_and(|def_id| def_id.is_local());
+            if is_local_bang_macro {
+                return Self::from_resolved_span(span, sm);
+            }
+            span = expn.call_site;
+        }
+        None
+    }
+
+    /// Key of `span`, which is already resolved to source code.
+    fn from_resolved_span(
+        span: rustc_span::Span,
+        sm: &rustc_span::source_map::SourceMap,
+    ) -> Option<Self> {
         if span.is_dummy() {
             return None;
         }
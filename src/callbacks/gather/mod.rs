@@ -7,13 +7,21 @@
 //! more specific information on what facts are collected.
 
 mod analyze_hir;
+mod expression_macros;
 pub mod first_pass_info;
+mod scope;
 pub mod type_key;
 
 use crate::{
-    callbacks::gather::analyze_hir::AnalyzeHirVisitor,
-    callbacks::gather::first_pass_info::{FirstPassInfo, FnNamespace, ModPath},
+    callbacks::codegen::probes,
+    callbacks::fn_headers,
+    callbacks::gather::analyze_hir::{AnalyzeHirVisitor, global_key},
+    callbacks::gather::first_pass_info::{
+        FirstPassInfo, FnNamespace, GlobalItem, ModPath, ObjectPpt, PptSpan,
+    },
+    callbacks::gather::scope::InstrumentationScope,
     callbacks::gather::type_key::TypeKey,
+    callbacks::types::CanBeTupled,
     config::DatirConfig,
 };
 use decls_gen::{DeclsFile, VarIdent};
@@ -58,7 +66,9 @@ impl GatherAtiInfo {
     ///
     /// If this is a valid function, store the `base_ppt_name` in FirstPassInfo,
     /// keyed by the functions file location / module path, for use in the second
-    /// compilation.
+    /// compilation, alongside the function's source location. Functions on the public API
+    /// boundary are recorded as keeping their untagged signature as well, see
+    /// [`fn_headers::preserves_abi`].
     fn record_fn<'tcx>(
         &mut self,
         tcx: TyCtxt<'tcx>,
@@ -135,6 +145,36 @@ impl GatherAtiInfo {
         // The function is valid, and capable of being instrumented.
         // Determine in what file / module this function is located, and store it in
         // FirstPassInfo.
+        let mod_path = mod_path_of(tcx, local_def_id);
+        let ns = match &type_key {
+            None => FnNamespace::Free,
+            Some(tk) => FnNamespace::Method(tk),
+        };
+        let def_id = local_def_id.to_def_id();
+        if let Some(span) = ppt_span(tcx, tcx.source_span(local_def_id)) {
+            self.first_pass
+                .ppt_spans
+                .insert(base_ppt_name.clone(), span);
+        }
+        if fn_headers::preserves_abi(tcx, def_id, &self.config) {
+            self.first_pass
+                .fns
+                .record_preserved_abi(mod_path.clone(), ns, ident, def_id);
+        }
+        self.first_pass
+            .fns
+            .record(mod_path, ns, ident, def_id, base_ppt_name);
+    }
+
+    /// Registers that a function was left out of instrumentation by the user, see
+    /// [crate::callbacks::gather::scope]. Its body and signature are left untouched.
+    fn record_skipped_fn<'tcx>(
+        &mut self,
+        tcx: TyCtxt<'tcx>,
+        local_def_id: LocalDefId,
+        ident: rustc_span::Ident,
+        type_key: Option<TypeKey>,
+    ) {
         let mod_path = mod_path_of(tcx, local_def_id);
         let ns = match &type_key {
             None => FnNamespace::Free,
@@ -142,16 +182,25 @@ impl GatherAtiInfo {
         };
         self.first_pass
             .fns
-            .record(mod_path, ns, ident, local_def_id.to_def_id(), base_ppt_name);
+            .record_skipped(mod_path, ns, ident, local_def_id.to_def_id());
+        self.first_pass
+            .skipped_fn_hashes
+            .insert(tcx.def_path_hash(local_def_id.to_def_id()));
     }
 
     /// Finds and records all functions/methods that are going to be instrumented.
     /// 
     /// Each function signature is validated against the previously loaded `DeclsFile` stored 
-    /// within the DATIR configuration.
+    /// within the DATIR configuration. Functions outside of the [`InstrumentationScope`] are
+    /// recorded as skipped instead.
     ///
     /// Stores all functions-to-be-instrumented in FirstPassInfo.
     fn find_instrumented_functions<'tcx>(&mut self, tcx: rustc_middle::ty::TyCtxt<'tcx>) {
+        let config = self.config.clone();
+        let scope = InstrumentationScope::new(tcx, &config);
+        let in_scope =
+            |ldid: LocalDefId| scope.instruments(ldid, &DeclsFile::ppt_base_name(tcx, ldid));
+
         for local_def_id in tcx.hir_body_owners() {
             let node = tcx.hir_node_by_def_id(local_def_id);
             match node {
@@ -159,14 +208,21 @@ impl GatherAtiInfo {
                     kind: rustc_hir::ItemKind::Fn { ident, .. },
                     ..
                 }) => {
-                    self.record_fn(tcx, local_def_id, *ident, None);
+                    if in_scope(local_def_id) {
+                        self.record_fn(tcx, local_def_id, *ident, None);
+                    } else {
+                        self.record_skipped_fn(tcx, local_def_id, *ident, None);
+                    }
                 }
 
+                // Methods keeping their untagged signature (`const fn`, `extern "C" fn`) are
+                // left uninstrumented, as method-call expressions do not untag the arguments
+                // passed to untracked methods.
                 rustc_hir::Node::ImplItem(rustc_hir::ImplItem {
                     ident,
                     kind: rustc_hir::ImplItemKind::Fn(_, _),
                     ..
-                }) => {
+                }) if !fn_headers::keeps_untagged_signature(tcx, local_def_id.to_def_id()) => {
                     let type_key = TypeKey::try_from_hir(tcx, local_def_id).unwrap_or_else(|| {
                         panic!(
                             "Could not derive TypeKey for impl method {local_def_id:?}, \
@@ -174,7 +230,11 @@ impl GatherAtiInfo {
                         )
                     });
 
-                    self.record_fn(tcx, local_def_id, *ident, Some(type_key));
+                    if in_scope(local_def_id) {
+                        self.record_fn(tcx, local_def_id, *ident, Some(type_key));
+                    } else {
+                        self.record_skipped_fn(tcx, local_def_id, *ident, Some(type_key));
+                    }
                 }
 
                 // All other items should just be ignored, we are just
@@ -218,6 +278,70 @@ impl GatherAtiInfo {
             }
         }
     }
+
+    /// Records the `:::OBJECT` and `:::CLASS` program points of every user-defined struct, so
+    /// that the instrument compilation can fold each observed value of the struct into them, and
+    /// the struct's source location.
+    fn find_object_types<'tcx>(&mut self, tcx: TyCtxt<'tcx>) {
+        for item_id in tcx.hir_free_items() {
+            let item = tcx.hir_item(item_id);
+            let rustc_hir::ItemKind::Struct(ident, ..) = item.kind else {
+                continue;
+            };
+
+            let base_ppt_name = DeclsFile::ppt_base_name(tcx, item.owner_id.def_id);
+            if let Some(span) = ppt_span(tcx, item.span) {
+                self.first_pass
+                    .ppt_spans
+                    .insert(base_ppt_name.clone(), span);
+            }
+            let module = tcx.parent_module_from_def_id(item.owner_id.def_id);
+            let class_globals = tcx
+                .hir_free_items()
+                .filter(|&item_id| tcx.parent_module_from_def_id(item_id.owner_id.def_id) == module)
+                .filter_map(|item_id| global_item(tcx, tcx.hir_item(item_id)))
+                .collect();
+            self.first_pass.object_ppts.record(
+                ident.span,
+                tcx.sess.source_map(),
+                ObjectPpt {
+                    base_ppt_name,
+                    class_globals,
+                },
+            );
+        }
+    }
+
+    /// Records every module-level `const` / `static` item of tupleable type, so that the
+    /// instrument compilation can bind them at each program point which declares them.
+    fn find_globals<'tcx>(&mut self, tcx: TyCtxt<'tcx>) {
+        for item_id in tcx.hir_free_items() {
+            if let Some(global) = global_item(tcx, tcx.hir_item(item_id)) {
+                self.first_pass.globals.push(global);
+            }
+        }
+    }
+}
+
+/// Returns the global `item` defines, if it is a `const` / immutable `static` item of tupleable
+/// type.
+fn global_item<'tcx>(tcx: TyCtxt<'tcx>, item: &rustc_hir::Item<'tcx>) -> Option<GlobalItem> {
+    if !matches!(
+        item.kind,
+        rustc_hir::ItemKind::Const(..) | rustc_hir::ItemKind::Static(rustc_ast::Mutability::Not, ..)
+    ) {
+        return None;
+    }
+
+    let def_id = item.owner_id.to_def_id();
+    if !tcx.type_of(def_id).instantiate_identity().can_be_tupled() {
+        return None;
+    }
+
+    Some(GlobalItem {
+        key: global_key(tcx, def_id),
+        var_name: format!("::{}", tcx.def_path_str(def_id)),
+    })
 }
 
 /// This trait is required to be defined so that this struct
@@ -228,13 +352,16 @@ impl rustc_driver::Callbacks for GatherAtiInfo {
         config.opts.unstable_opts.no_codegen = true;
     }
 
-    /// No-op, we do not need to perform any modifications to the loaded AST at
-    /// this stage, we just need to collect information on the HIR/MIR.
+    /// Declares the probe syntax (`datir_probe!`, `#[datir::probe]`), so that probes expand
+    /// like they do within the instrument compilation. Otherwise, we do not need to perform any
+    /// modifications to the loaded AST at this stage, we just need to collect information on the
+    /// HIR/MIR.
     fn after_crate_root_parsing(
         &mut self,
-        _compiler: &interface::Compiler,
-        _krate: &mut ast::Crate,
+        compiler: &interface::Compiler,
+        krate: &mut ast::Crate,
     ) -> Compilation {
+        probes::declare_probe_syntax(&compiler.sess.psess, krate);
         Compilation::Continue
     }
 
@@ -244,6 +371,10 @@ impl rustc_driver::Callbacks for GatherAtiInfo {
     ///      the `base_ppt_name` associated with each one within the program's .decls file.
     ///      This mapping is important so that the second pass creates program points with
     ///      appropriate names that can be merged into the .decls file.
+    ///      The same is done for the `:::OBJECT` / `:::CLASS` program points of every struct,
+    ///      and for every global which could be bound at a program point. Crate-local macros
+    ///      whose expansions can be instrumented are recorded as well, see
+    ///      [crate::callbacks::gather::expression_macros].
     ///
     ///   2. Find all locations (code spans) detailed in [crate::callbacks::gather::analyze_hir].
     fn after_expansion<'tcx>(
@@ -253,11 +384,15 @@ impl rustc_driver::Callbacks for GatherAtiInfo {
     ) -> Compilation {
         // 1.
         self.find_instrumented_functions(tcx);
+        self.find_object_types(tcx);
+        self.find_globals(tcx);
+        expression_macros::find_expression_macros(tcx, &mut self.first_pass);
 
         // 2.
         let mut find_calls_visitor = AnalyzeHirVisitor {
             tcx,
             first_pass: &mut self.first_pass,
+            config: &self.config,
         };
         tcx.hir_walk_toplevel_module(&mut find_calls_visitor);
 
@@ -304,3 +439,23 @@ fn mod_path_of<'tcx>(tcx: TyCtxt<'tcx>, ldid: LocalDefId) -> ModPath {
         tcx.def_path_str(parent_mod.to_def_id())
     }
 }
+
+/// Original source location of `span`, or `None` if it doesn't lie within a real file.
+fn ppt_span(tcx: TyCtxt<'_>, span: rustc_span::Span) -> Option<PptSpan> {
+    let sm = tcx.sess.source_map();
+    let lo = sm.lookup_char_pos(span.lo());
+    let hi = sm.lookup_char_pos(span.hi());
+    let rustc_span::FileName::Real(rfn) = &lo.file.name else {
+        return None;
+    };
+    Some(PptSpan {
+        file: rfn
+            .path(rustc_span::RemapPathScopeComponents::MACRO)
+            .display()
+            .to_string(),
+        line_start: lo.line,
+        col_start: lo.col.0 + 1,
+        line_end: hi.line,
+        col_end: hi.col.0 + 1,
+    })
+}
@@ -0,0 +1,103 @@
+//! Decides which functions of the crate are instrumented.
+//!
+//! By default, every function and method is. Hot inner loops, generated code or test helpers can
+//! be left out of instrumentation through two attributes, placed on functions, impl blocks or
+//! modules (inline `#![datir::skip]` attributes work on modules as well):
+//! - `#[datir::skip]` leaves the item, and everything defined within it, uninstrumented.
+//! - `#[datir::only]` restricts instrumentation to the marked items. Once a single item of the
+//!   crate is marked, every function outside of all marked items is left uninstrumented.
+//!
+//! The `--skip` and `--only` command line filters (see [`DatirConfig::skip_ppts`]) act the same
+//! way, matching glob patterns against the base ppt name of each function. A skip always wins
+//! over an only. The entry function of the crate is always instrumented, as its shim is what
+//! reports the analysis.
+//!
+//! Skipped functions are compiled as they were written: with their original, untagged signature
+//! and body. Calls across the boundary are converted the same way calls to untracked functions
+//! are:
+//! - Instrumented code calling a skipped function untuples the arguments, and tags the returned
+//!   value, see [crate::callbacks::gather::analyze_hir].
+//! - Skipped code calling an instrumented function tags the arguments with fresh ids, and
+//!   untuples the returned value, see [crate::callbacks::instrument::skipped].
+//!
+//! Allowlisted dependencies are scoped by their own attributes, and by the same filters. Calls
+//! into the functions they skip are converted the same way, see [crate::dependencies].
+//!
+//! Structs and enums are always instrumented, so their fields hold tagged values. Skipped code
+//! accessing a field of primitive type (e.g. a skipped method reading `self.len`) untuples it,
+//! see [crate::callbacks::gather::analyze_hir]. Accesses to fields holding tagged values in any
+//! other way (`(u32, u32)`, `[u32; N]`, `Vec<u32>`, ...) are rejected with an error, and skipped
+//! code can't construct the crate's structs.
+//!
+//! [`DatirConfig::skip_ppts`]: crate::config::DatirConfig::skip_ppts
+
+use rustc_ast::attr::AttributeExt;
+use rustc_hir::def_id::LocalDefId;
+use rustc_middle::ty::TyCtxt;
+
+use crate::{callbacks::codegen::probes::DATIR_TOOL, config::DatirConfig};
+
+/// Name of the attribute leaving an item uninstrumented.
+pub const SKIP_ATTR: &str = "skip";
+
+/// Name of the attribute restricting instrumentation to the marked items.
+pub const ONLY_ATTR: &str = "only";
+
+/// The set of functions the user asked to instrument.
+pub struct InstrumentationScope<'tcx, 'a> {
+    tcx: TyCtxt<'tcx>,
+    config: &'a DatirConfig,
+    /// Whether any `#[datir::only]` attribute or `--only` filter was given.
+    restricted: bool,
+    /// The entry function of the crate, if any.
+    entry_fn: Option<rustc_span::def_id::DefId>,
+}
+
+impl<'tcx, 'a> InstrumentationScope<'tcx, 'a> {
+    /// Collects the `#[datir::only]` attributes of the crate, alongside the filters of `config`.
+    pub fn new(tcx: TyCtxt<'tcx>, config: &'a DatirConfig) -> Self {
+        let restricted = !config.only_ppts.is_empty()
+            || tcx
+                .hir_crate_items(())
+                .definitions()
+                .chain(std::iter::once(rustc_hir::def_id::CRATE_DEF_ID))
+                .any(|ldid| has_datir_attr(tcx, ldid, ONLY_ATTR));
+
+        Self {
+            tcx,
+            config,
+            restricted,
+            entry_fn: tcx.entry_fn(()).map(|(def_id, _)| def_id),
+        }
+    }
+
+    /// Whether the function identified by `ldid`, with base ppt name `base_ppt_name`, is
+    /// instrumented.
+    pub fn instruments(&self, ldid: LocalDefId, base_ppt_name: &str) -> bool {
+        if self.entry_fn == Some(ldid.to_def_id()) {
+            return true;
+        }
+        if self.marked(ldid, SKIP_ATTR) || self.config.skips_ppt(base_ppt_name) {
+            return false;
+        }
+
+        !self.restricted || self.marked(ldid, ONLY_ATTR) || self.config.selects_ppt(base_ppt_name)
+    }
+
+    /// Whether `ldid`, or any item it is defined within, carries `#[datir::{attr}]`.
+    fn marked(&self, ldid: LocalDefId, attr: &str) -> bool {
+        std::iter::successors(Some(ldid), |&ldid| self.tcx.opt_local_parent(ldid))
+            .any(|ldid| has_datir_attr(self.tcx, ldid, attr))
+    }
+}
+
+/// Whether the definition `ldid` carries `#[datir::{attr}]`.
+fn has_datir_attr(tcx: TyCtxt<'_>, ldid: LocalDefId, attr: &str) -> bool {
+    let path = [
+        rustc_span::Symbol::intern(DATIR_TOOL),
+        rustc_span::Symbol::intern(attr),
+    ];
+    tcx.hir_attrs(tcx.local_def_id_to_hir_id(ldid))
+        .iter()
+        .any(|a| a.path_matches(&path))
+}
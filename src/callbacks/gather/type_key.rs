@@ -12,16 +12,22 @@
 //! two different path representations. Therefore, use [TypeKey::try_from_ast] and
 //! [TypeKey::try_from_hir] appropriately, before passing the result to the
 //! `FnIndex`.
+//!
+//! Generic arguments are part of the key, so `impl Foo<u32>` and `impl Foo<u64>` are kept
+//! apart. Const generic arguments are rendered from a small expression grammar (literals,
+//! paths, negation, binary operators and single expression blocks), which is shared between the
+//! AST and HIR renderers. Literal suffixes are dropped, as `Buf<3>` and `Buf<3usize>` name the
+//! same type. Associated item constraints (`Trait<Item = u32>`) are rendered as `Item=u32`.
 
 /// A cross-compilation stable key representing a `(self_type, of_trait?)` pair.
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub struct TypeKey {
-    /// `::`-joined path of the impl's self type,
-    /// Generic args are dropped (`impl Foo<u32>` and `impl<T> Foo<T>`
-    /// both produce "Foo").
+    /// `::`-joined path of the impl's self type, including generic args
+    /// (`impl Foo<u32>` produces "Foo<u32>", `impl<const N: usize> Buf<N>` produces
+    /// "Buf<N>" and `impl Buf<4>` produces "Buf<4>").
     pub self_path: String,
     /// `Some(path)` for `impl Trait for T`, `None` for inherent impls. Same
-    /// `::`-joined ident-only format as `self_path`.
+    /// `::`-joined format as `self_path`.
     pub trait_path: Option<String>,
 }
 
@@ -92,9 +98,9 @@ impl TypeKey {
             return None;
         };
 
-        let self_path_str = hir_ty_canonical(self_ty)?;
+        let self_path_str = hir_ty_canonical(tcx, self_ty)?;
         let trait_path_str = match of_trait {
-            Some(header) => Some(hir_path_canonical(header.trait_ref.path)?),
+            Some(header) => Some(hir_path_canonical(tcx, header.trait_ref.path)?),
             None => None,
         };
 
@@ -126,17 +132,13 @@ fn ast_segment_canonical(seg: &rustc_ast::PathSegment) -> Option<String> {
 
     let mut rendered = Vec::new();
     for arg in args.args.iter() {
-        let rustc_ast::AngleBracketedArg::Arg(generic_arg) = arg else {
-            return None;
-        };
-        let s = match generic_arg {
-            rustc_ast::GenericArg::Lifetime(lt) => lt.ident.name.to_string(),
-            rustc_ast::GenericArg::Type(ty) => ast_ty_canonical(ty)?,
-            rustc_ast::GenericArg::Const(_) => panic!(
-                "DATIR does not support const generic arguments in impl-block paths \
-                 (encountered in segment `{}`); see ast_segment_canonical",
-                seg.ident.name
-            ),
+        let s = match arg {
+            rustc_ast::AngleBracketedArg::Arg(generic_arg) => {
+                ast_generic_arg_canonical(generic_arg)?
+            }
+            rustc_ast::AngleBracketedArg::Constraint(constraint) => {
+                ast_constraint_canonical(constraint)?
+            }
         };
         rendered.push(s);
     }
@@ -148,41 +150,137 @@ fn ast_segment_canonical(seg: &rustc_ast::PathSegment) -> Option<String> {
     }
 }
 
-/// Canonicalizes an AST Path type name
-fn ast_ty_canonical(ty: &rustc_ast::Ty) -> Option<String> {
-    if matches!(ty.kind, rustc_ast::TyKind::Infer) {
-        panic!(
-            "DATIR does not support inferred (`_`) generic arguments in impl-block \
-             paths; see ast_ty_canonical"
-        );
+/// Canonicalizes a single generic argument of an AST path segment.
+fn ast_generic_arg_canonical(arg: &rustc_ast::GenericArg) -> Option<String> {
+    match arg {
+        rustc_ast::GenericArg::Lifetime(lt) => Some(lt.ident.name.to_string()),
+        rustc_ast::GenericArg::Type(ty) => ast_ty_canonical(ty),
+        rustc_ast::GenericArg::Const(anon) => ast_const_expr_canonical(&anon.value),
     }
-    let rustc_ast::TyKind::Path(_, path) = &ty.kind else {
+}
+
+/// Canonicalizes an associated item constraint, as in the `Item = u32` of
+/// `Iterator<Item = u32>`. Bound constraints (`Item: Copy`) are not supported.
+fn ast_constraint_canonical(constraint: &rustc_ast::AssocItemConstraint) -> Option<String> {
+    let rustc_ast::AssocItemConstraintKind::Equality { term } = &constraint.kind else {
         return None;
     };
-    ast_path_canonical(path)
+    let mut name = constraint.ident.name.to_string();
+    if let Some(gen_args) = &constraint.gen_args {
+        let rustc_ast::GenericArgs::AngleBracketed(args) = gen_args else {
+            return None;
+        };
+        let mut rendered = Vec::new();
+        for arg in args.args.iter() {
+            let rustc_ast::AngleBracketedArg::Arg(generic_arg) = arg else {
+                return None;
+            };
+            rendered.push(ast_generic_arg_canonical(generic_arg)?);
+        }
+        name = format!("{name}<{}>", rendered.join(","));
+    }
+    let term = match term {
+        rustc_ast::Term::Ty(ty) => ast_ty_canonical(ty)?,
+        rustc_ast::Term::Const(anon) => ast_const_expr_canonical(&anon.value)?,
+    };
+    Some(format!("{name}={term}"))
+}
+
+/// Canonicalizes the expression of a const generic argument. Only literals, paths, negation,
+/// binary operators and blocks wrapping a single such expression are supported, which covers
+/// the const arguments that appear in impl headers in practice.
+///
+/// Binary operations are always parenthesized, as the HIR drops the user's parentheses.
+fn ast_const_expr_canonical(expr: &rustc_ast::Expr) -> Option<String> {
+    match &expr.kind {
+        rustc_ast::ExprKind::Lit(token_lit) => {
+            let lit_kind = rustc_ast::LitKind::from_token_lit(*token_lit).ok()?;
+            lit_kind_canonical(&lit_kind)
+        }
+        rustc_ast::ExprKind::Path(None, path) => ast_path_canonical(path),
+        rustc_ast::ExprKind::Paren(inner) => ast_const_expr_canonical(inner),
+        rustc_ast::ExprKind::Unary(rustc_ast::UnOp::Neg, inner) => {
+            Some(format!("-{}", ast_const_expr_canonical(inner)?))
+        }
+        rustc_ast::ExprKind::Binary(op, lhs, rhs) => Some(format!(
+            "({}{}{})",
+            ast_const_expr_canonical(lhs)?,
+            op.node.as_str(),
+            ast_const_expr_canonical(rhs)?
+        )),
+        rustc_ast::ExprKind::Block(block, None) => {
+            let [stmt] = block.stmts.as_slice() else {
+                return None;
+            };
+            let rustc_ast::StmtKind::Expr(inner) = &stmt.kind else {
+                return None;
+            };
+            ast_const_expr_canonical(inner)
+        }
+        _ => None,
+    }
+}
+
+/// Renders a literal used as a const generic argument, shared by the AST and HIR renderers.
+/// Integer suffixes are dropped.
+fn lit_kind_canonical(lit_kind: &rustc_ast::LitKind) -> Option<String> {
+    match lit_kind {
+        rustc_ast::LitKind::Int(n, _) => Some(n.get().to_string()),
+        rustc_ast::LitKind::Bool(b) => Some(b.to_string()),
+        rustc_ast::LitKind::Char(c) => Some(format!("{c:?}")),
+        _ => None,
+    }
+}
+
+/// Canonicalizes an AST Path type name. Inferred types (`_`) are rendered as `_`.
+fn ast_ty_canonical(ty: &rustc_ast::Ty) -> Option<String> {
+    match &ty.kind {
+        rustc_ast::TyKind::Infer => Some("_".to_string()),
+        rustc_ast::TyKind::Path(_, path) => ast_path_canonical(path),
+        _ => None,
+    }
 }
 
 /// HIR counterpart to `ast_path_canonical`. Creates a
 /// `::`-joined `ident<args>` form string.
 ///
-/// Returns None on non-`AngleBracketed` args, associated-type
-/// constraints, const generic args, and non-path types as type args.
-fn hir_path_canonical(path: &rustc_hir::Path<'_>) -> Option<String> {
-    // FIXME: support above.
+/// Returns None on non-`AngleBracketed` args, bound constraints, const generic args outside
+/// of the grammar supported by `ast_const_expr_canonical`, and non-path types as type args.
+fn hir_path_canonical<'tcx>(
+    tcx: rustc_middle::ty::TyCtxt<'tcx>,
+    path: &rustc_hir::Path<'tcx>,
+) -> Option<String> {
     let mut parts = Vec::with_capacity(path.segments.len());
     for seg in path.segments.iter() {
-        parts.push(hir_segment_canonical(seg)?);
+        parts.push(hir_segment_canonical(tcx, seg)?);
     }
     Some(parts.join("::"))
 }
 
 /// Constructs the canonical representation of a single HIR path segment.
-fn hir_segment_canonical(seg: &rustc_hir::PathSegment<'_>) -> Option<String> {
+fn hir_segment_canonical<'tcx>(
+    tcx: rustc_middle::ty::TyCtxt<'tcx>,
+    seg: &rustc_hir::PathSegment<'tcx>,
+) -> Option<String> {
     let ident = seg.ident.name.to_string();
     let Some(args) = seg.args else {
         return Some(ident);
     };
 
+    let rendered = hir_generic_args_canonical(tcx, args)?;
+    if rendered.is_empty() {
+        Some(ident)
+    } else {
+        Some(format!("{ident}<{}>", rendered.join(",")))
+    }
+}
+
+/// Renders the generic args and constraints of a HIR path segment (or of an associated item
+/// constraint), in source order. Constraints always follow args, so the order matches the AST.
+fn hir_generic_args_canonical<'tcx>(
+    tcx: rustc_middle::ty::TyCtxt<'tcx>,
+    args: &rustc_hir::GenericArgs<'tcx>,
+) -> Option<Vec<String>> {
     if !args
         .parenthesized
         .eq(&rustc_hir::GenericArgsParentheses::No)
@@ -194,37 +292,88 @@ fn hir_segment_canonical(seg: &rustc_hir::PathSegment<'_>) -> Option<String> {
     for arg in args.args.iter() {
         let s = match arg {
             rustc_hir::GenericArg::Lifetime(lt) => lt.ident.name.to_string(),
-            rustc_hir::GenericArg::Type(ty) => hir_ty_canonical(ty.as_unambig_ty())?,
-            rustc_hir::GenericArg::Const(_) => panic!(
-                "DATIR does not support const generic arguments in impl-block paths \
-                 (encountered in segment `{}`); see hir_segment_canonical",
-                seg.ident.name
-            ),
-            rustc_hir::GenericArg::Infer(_) => panic!(
-                "DATIR does not support inferred (`_`) generic arguments in impl-block \
-                 paths (encountered in segment `{}`); see hir_segment_canonical",
-                seg.ident.name
-            ),
+            rustc_hir::GenericArg::Type(ty) => hir_ty_canonical(tcx, ty.as_unambig_ty())?,
+            rustc_hir::GenericArg::Const(ct) => hir_const_arg_canonical(tcx, ct.as_unambig_ct())?,
+            rustc_hir::GenericArg::Infer(_) => "_".to_string(),
         };
         rendered.push(s);
     }
 
-    // FIXME: not really sure what to do with constraints, skipping for now.
-    if !args.constraints.is_empty() {
-        return None;
+    for constraint in args.constraints.iter() {
+        let rustc_hir::AssocItemConstraintKind::Equality { term } = &constraint.kind else {
+            return None;
+        };
+        let constraint_args = hir_generic_args_canonical(tcx, constraint.gen_args)?;
+        let name = if constraint_args.is_empty() {
+            constraint.ident.name.to_string()
+        } else {
+            format!("{}<{}>", constraint.ident.name, constraint_args.join(","))
+        };
+        let term = match term {
+            rustc_hir::Term::Ty(ty) => hir_ty_canonical(tcx, ty)?,
+            rustc_hir::Term::Const(ct) => hir_const_arg_canonical(tcx, ct)?,
+        };
+        rendered.push(format!("{name}={term}"));
     }
 
-    if rendered.is_empty() {
-        Some(ident)
-    } else {
-        Some(format!("{ident}<{}>", rendered.join(",")))
+    Some(rendered)
+}
+
+/// HIR counterpart to `ast_const_expr_canonical`. Bare paths to const params are lowered to
+/// `ConstArgKind::Path`, everything else is an anon const whose body is rendered.
+fn hir_const_arg_canonical<'tcx>(
+    tcx: rustc_middle::ty::TyCtxt<'tcx>,
+    ct: &rustc_hir::ConstArg<'tcx>,
+) -> Option<String> {
+    match &ct.kind {
+        rustc_hir::ConstArgKind::Path(rustc_hir::QPath::Resolved(None, path)) => {
+            hir_path_canonical(tcx, path)
+        }
+        rustc_hir::ConstArgKind::Anon(anon) => {
+            hir_const_expr_canonical(tcx, tcx.hir_body(anon.body).value)
+        }
+        rustc_hir::ConstArgKind::Infer(..) => Some("_".to_string()),
+        _ => None,
+    }
+}
+
+/// Renders the body of an anon const. Must agree with `ast_const_expr_canonical`.
+fn hir_const_expr_canonical<'tcx>(
+    tcx: rustc_middle::ty::TyCtxt<'tcx>,
+    expr: &rustc_hir::Expr<'tcx>,
+) -> Option<String> {
+    match &expr.kind {
+        rustc_hir::ExprKind::Lit(lit) => lit_kind_canonical(&lit.node),
+        rustc_hir::ExprKind::Path(rustc_hir::QPath::Resolved(None, path)) => {
+            hir_path_canonical(tcx, path)
+        }
+        rustc_hir::ExprKind::Unary(rustc_hir::UnOp::Neg, inner) => {
+            Some(format!("-{}", hir_const_expr_canonical(tcx, inner)?))
+        }
+        rustc_hir::ExprKind::Binary(op, lhs, rhs) => Some(format!(
+            "({}{}{})",
+            hir_const_expr_canonical(tcx, lhs)?,
+            op.node.as_str(),
+            hir_const_expr_canonical(tcx, rhs)?
+        )),
+        rustc_hir::ExprKind::Block(block, None) => match (block.stmts, block.expr) {
+            ([], Some(inner)) => hir_const_expr_canonical(tcx, inner),
+            _ => None,
+        },
+        _ => None,
     }
 }
 
 /// Constructs the canonical representation of a HIR type
-fn hir_ty_canonical(ty: &rustc_hir::Ty<'_>) -> Option<String> {
-    let rustc_hir::TyKind::Path(rustc_hir::QPath::Resolved(_, path)) = ty.kind else {
-        return None;
-    };
-    hir_path_canonical(path)
+fn hir_ty_canonical<'tcx>(
+    tcx: rustc_middle::ty::TyCtxt<'tcx>,
+    ty: &rustc_hir::Ty<'tcx>,
+) -> Option<String> {
+    match ty.kind {
+        rustc_hir::TyKind::Infer(..) => Some("_".to_string()),
+        rustc_hir::TyKind::Path(rustc_hir::QPath::Resolved(_, path)) => {
+            hir_path_canonical(tcx, path)
+        }
+        _ => None,
+    }
 }
@@ -0,0 +1,187 @@
+//! Maps diagnostics of the Instrument compilation back to the user's original source.
+//!
+//! rustc compiles the pretty-printed output of the [`TransformingFileLoader`], so the spans of
+//! its diagnostics point at lines of a file that only exists in memory. [`OriginNotingEmitter`]
+//! wraps rustc's emitter, and moves every error reported within an instrumented file to the
+//! original file and line the failing code stems from, or, if the code was generated by DATIR, to
+//! the closest original code enclosing it. The instrumented code the error was reported at is
+//! kept in a note, which also names the transformation that generated it, if any. The origins
+//! themselves are recorded by the file loader, see [`SpanMaps`].
+//!
+//! [`TransformingFileLoader`]: crate::callbacks::instrument::file_loader::TransformingFileLoader
+
+use crate::callbacks::instrument::file_loader::{Origin, OriginalLoc, SpanMaps};
+
+/// Emitter which forwards every diagnostic to `inner`, after moving errors reported within
+/// instrumented files to their original location.
+pub struct OriginNotingEmitter {
+    inner: Box<rustc_errors::DynEmitter>,
+    span_maps: std::sync::Arc<SpanMaps>,
+    /// Original versions of the instrumented files, loaded into the source map under a virtual
+    /// name on their first error, keyed by path.
+    originals:
+        std::collections::HashMap<std::path::PathBuf, std::sync::Arc<rustc_span::SourceFile>>,
+}
+
+impl OriginNotingEmitter {
+    /// Wraps the emitter of `psess`, so that its errors are reported at their original location.
+    pub fn install(
+        psess: &mut rustc_session::parse::ParseSess,
+        span_maps: std::sync::Arc<SpanMaps>,
+    ) {
+        psess.dcx().wrap_emitter(move |inner| {
+            Box::new(Self {
+                inner,
+                span_maps,
+                originals: std::collections::HashMap::new(),
+            })
+        });
+    }
+
+    /// Returns the path of the instrumented file holding the code at `span`, alongside the
+    /// origin of that code, if known.
+    fn origin(&self, span: rustc_span::Span) -> Option<(std::path::PathBuf, Origin)> {
+        let sm = self.inner.source_map()?;
+        let span = span.source_callsite();
+        if span.is_dummy() {
+            return None;
+        }
+
+        let loc = sm.lookup_char_pos(span.lo());
+        if self
+            .originals
+            .values()
+            .any(|original| std::sync::Arc::ptr_eq(original, &loc.file))
+        {
+            return None;
+        }
+        let rustc_span::FileName::Real(rfn) = &loc.file.name else {
+            return None;
+        };
+        let path = rfn.path(rustc_span::RemapPathScopeComponents::MACRO);
+        let origin = self.span_maps.origin(path, loc.line)?;
+        Some((path.to_path_buf(), origin))
+    }
+
+    /// Describes `origin`, the origin of code within the instrumented file at `path`.
+    fn describe_origin(path: &std::path::Path, origin: &Origin) -> String {
+        let at = |loc: &OriginalLoc| format!("{}:{}:{}", path.display(), loc.line, loc.col);
+        match origin {
+            Origin::Original(loc) => {
+                format!("DATIR: this code was instrumented from `{}`", at(loc))
+            }
+            Origin::Generated {
+                transformation,
+                enclosing: Some(loc),
+            } => format!(
+                "DATIR: this code was generated by the `{transformation}` transformation, \
+                 while instrumenting `{}`",
+                at(loc)
+            ),
+            Origin::Generated {
+                transformation,
+                enclosing: None,
+            } => format!(
+                "DATIR: this code was generated by the `{transformation}` transformation, \
+                 within `{}`",
+                path.display()
+            ),
+        }
+    }
+
+    /// Returns a span covering the original code at `loc` up to the end of its line, within the
+    /// original version of the instrumented file at `path`.
+    ///
+    /// The original file is read from disk, and loaded into the source map under a virtual name,
+    /// as its instrumented version already holds the path.
+    fn original_span(
+        &mut self,
+        path: &std::path::Path,
+        loc: OriginalLoc,
+    ) -> Option<rustc_span::Span> {
+        let original = match self.originals.get(path) {
+            Some(original) => original.clone(),
+            None => {
+                let sm = self.inner.source_map()?;
+                let source = std::fs::read_to_string(path).ok()?;
+                let name =
+                    rustc_span::FileName::Real(rustc_span::RealFileName::from_virtual_path(path));
+                let original = sm.new_source_file(name, source.clone());
+                // the source map deduplicates files by name, which must not have yielded the
+                // instrumented file back.
+                if original.src.as_deref() != Some(&source) {
+                    return None;
+                }
+                self.originals.insert(path.to_path_buf(), original.clone());
+                original
+            }
+        };
+
+        let source = original.src.as_deref()?;
+        let line_start = source
+            .split_inclusive('\n')
+            .take(loc.line.checked_sub(1)?)
+            .map(str::len)
+            .sum::<usize>();
+        let line = source[line_start..].lines().next()?;
+        let col = line
+            .char_indices()
+            .nth(loc.col.checked_sub(1)?)
+            .map_or(line.len(), |(col, _)| col);
+        let lo = original.start_pos + rustc_span::BytePos((line_start + col) as u32);
+        let hi =
+            original.start_pos + rustc_span::BytePos((line_start + line.trim_end().len()) as u32);
+        Some(rustc_span::Span::with_root_ctxt(lo, hi.max(lo)))
+    }
+}
+
+impl rustc_errors::emitter::Emitter for OriginNotingEmitter {
+    fn emit_diagnostic(
+        &mut self,
+        mut diag: rustc_errors::DiagInner,
+        registry: &rustc_errors::registry::Registry,
+    ) {
+        if diag.is_error()
+            && let Some(span) = diag.span.primary_span()
+            && let Some((path, origin)) = self.origin(span)
+        {
+            let loc = match &origin {
+                Origin::Original(loc) => Some(*loc),
+                Origin::Generated { enclosing, .. } => *enclosing,
+            };
+            // the error moves to the original code, its instrumented location (and labels) to
+            // the note.
+            let note_span = match loc.and_then(|loc| self.original_span(&path, loc)) {
+                Some(original) => {
+                    std::mem::replace(&mut diag.span, rustc_errors::MultiSpan::from_span(original))
+                }
+                None => rustc_errors::MultiSpan::new(),
+            };
+            diag.children.push(rustc_errors::Subdiag {
+                level: rustc_errors::Level::Note,
+                messages: vec![(
+                    Self::describe_origin(&path, &origin).into(),
+                    rustc_errors::Style::NoStyle,
+                )],
+                span: note_span,
+            });
+        }
+        self.inner.emit_diagnostic(diag, registry);
+    }
+
+    fn source_map(&self) -> Option<&rustc_span::source_map::SourceMap> {
+        self.inner.source_map()
+    }
+
+    fn translator(&self) -> &rustc_errors::translation::Translator {
+        self.inner.translator()
+    }
+
+    fn should_show_explain(&self) -> bool {
+        self.inner.should_show_explain()
+    }
+
+    fn supports_color(&self) -> bool {
+        self.inner.supports_color()
+    }
+}
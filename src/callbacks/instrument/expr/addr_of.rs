@@ -5,6 +5,9 @@
 //! runtime library's `.subslice()` / `.subslice_mut()` method call, to push the indexing operation
 //! down into the collection's representation (for instance, into a `TaggedRef<[T]>`).
 //!
+//! Strings indexed by a range (`&s[a..b]`) use the runtime library's `.substr()` method call
+//! instead, which unions the range with the string's length.
+//!
 //! Otherwise, if the first pass determined that this reference is being taken to some type
 //! whose post-instrumentation shape is `Tagged<T>` / `Tagged<[T; N]>` / `TaggedRef<[T]>`,
 //! emit `.share()` / `.reborrow()` to produce the corresponding `TaggedRef<T>` /
@@ -48,6 +51,27 @@ pub fn transform_addr_of(visitor: &mut InstrumentingVisitor, addr_of_expr: &mut
         return;
     }
 
+    // Same as above, for a string. Mutable string slices are not supported.
+    if visitor
+        .first_pass
+        .index_str_by_range
+        .contains(referent.span, visitor.psess.source_map())
+    {
+        let rustc_ast::ExprKind::Index(idx_recv, idx_expr, _) = &referent.kind else {
+            panic!(
+                "First pass identified {:?} as the span of a string index-by-range, yet \
+                 second pass found a non-index expression: {:?}",
+                referent.span,
+                rustc_ast_pretty::pprust::expr_to_string(referent)
+            );
+        };
+        let recv_src = rustc_ast_pretty::pprust::expr_to_string(idx_recv);
+        let idx_src = rustc_ast_pretty::pprust::expr_to_string(idx_expr);
+        let code = format!("{recv_src}.substr({idx_src})");
+        *addr_of_expr = parsing::parse_expr(visitor.psess, code);
+        return;
+    }
+
     // Reference to a type whose post-instrumentation shape needs normalization
     // into a `TaggedRef` / `TaggedRefMut`.
     let Some(recorded_mutbl) = visitor
@@ -6,7 +6,10 @@
 
 use rustc_ast_pretty::pprust;
 
-use crate::{callbacks::instrument::instrument_visitor::InstrumentingVisitor, callbacks::parsing};
+use crate::{
+    callbacks::codegen::runtime, callbacks::instrument::instrument_visitor::InstrumentingVisitor,
+    callbacks::parsing,
+};
 
 /// Invoked whenever the visitor runs into a ExprKind::Assign.
 ///
@@ -75,8 +78,9 @@ pub fn transform_assign_op(
 
     let bin_op: rustc_ast::BinOpKind = op.node.into();
     let code = format!(
-        "{{ let mut __ati_lhs = {}; __ati_lhs.assign(Tagged(*__ati_lhs.0, *__ati_lhs.1) {} {}); }}",
+        "{{ let mut __ati_lhs = {}; __ati_lhs.assign({}(*__ati_lhs.0, *__ati_lhs.1) {} {}); }}",
         pprust::expr_to_string(inner),
+        runtime::runtime_path("Tagged"),
         bin_op.as_str(),
         pprust::expr_to_string(rhs),
     );
@@ -1,19 +1,36 @@
 //! Defines a function to transform a single function or method call AST expression.
 //!
 //! If the first pass determined that this expression is an invocation of an untracked function,
-//! then all inputs need to be untupled, and the return value (might) need tupling.
+//! then all inputs need to be untupled, and the return value (might) need tupling. Compound
+//! return values are rebuilt according to the [`RetShape`] recorded by the first pass, so that
+//! `u32::checked_sub(a, b)` becomes `Option<Tagged<u32>>` and a returned `(usize, usize)`
+//! becomes `(Tagged<usize>, Tagged<usize>)`, each leaf receiving a fresh id.
+//!
+//! Method calls to skipped methods (see [crate::callbacks::gather::scope]) are treated the same
+//! way, and so are calls to the methods of `str` / `String`, whose receiver is untupled too. Calls made by skipped functions to instrumented ones are converted the other way around,
+//! see [`transform_call_from_skipped`].
 //!
 //! The Path which identifies the function being invoked could also have generic types within
 //! it, which require tupleing as well.
 
-use crate::callbacks::instrument::{expr::common, instrument_visitor::InstrumentingVisitor, types};
+use crate::callbacks::{
+    codegen::runtime,
+    gather::first_pass_info::{CallFromSkipped, RetShape},
+    instrument::{expr::common, instrument_visitor::InstrumentingVisitor, types},
+    parsing,
+};
+
+/// Name of the binding holding the raw return value of an untracked call, while the tagged
+/// equivalent is being built.
+const RAW_RET_BINDING: &str = "__ati_ret";
 
 /// Invoked whenever the visitor runs into a ExprKind::Call.
 ///
 /// Updates turbofish generics (`f::<u32>` -> `f::<Tagged<u32>>`).
 /// If pass 1 marked this as an untracked call, untuples each argument
 /// (`x` -> `x.1`) in place and, if the return is tupleable, wraps the
-/// call in `ATI::track(...)`.
+/// call in `ATI::track(...)`. Compound return values are rebuilt by
+/// [`tag_structured_return`]. Arguments which aren't tagged are passed as they are.
 pub fn transform_call(visitor: &mut InstrumentingVisitor, call_expr: &mut rustc_ast::Expr) {
     let rustc_ast::ExprKind::Call(func, args) = &mut call_expr.kind else {
         return;
@@ -34,35 +51,254 @@ pub fn transform_call(visitor: &mut InstrumentingVisitor, call_expr: &mut rustc_
     else {
         return;
     };
-    let ret_tupleable = call.ret_is_tupleable;
+    let call = call.clone();
+
+    untuple_args(args, &call.untupled_args);
+    tag_return(visitor, call_expr, &call.ret_shape);
+}
 
-    for arg_expr in args.iter_mut() {
-        common::untuple(arg_expr);
+/// Untuples each of `args` marked in `untupled`, in place.
+fn untuple_args(args: &mut [Box<rustc_ast::Expr>], untupled: &[bool]) {
+    for (arg_expr, untupled) in args.iter_mut().zip(untupled) {
+        if *untupled {
+            common::untuple(arg_expr);
+        }
     }
+}
 
-    // FIXME: again, this is a bit wrong. We are currently ignoring the tracked/untracked
-    // boundary, but you can imagine that an untracked func call returns some struct, which
-    // itself contains values that need to be converted into Tagged<T>s. Right now, that
-    // case is entirely ignored, this works properly if the returned value is a simple
-    // primitive.
-    if ret_tupleable {
-        common::tuple(call_expr);
+/// Tags the value returned by an untracked call, in place, according to its shape.
+fn tag_return(visitor: &InstrumentingVisitor, call_expr: &mut rustc_ast::Expr, shape: &RetShape) {
+    match shape {
+        RetShape::Opaque => {}
+        RetShape::Leaf => common::tuple(call_expr),
+        shape => tag_structured_return(visitor, call_expr, shape),
+    }
+}
+
+/// Rebuilds the compound value returned by an untracked call, in place, as
+/// `{ let __ati_ret = call; <tagged equivalent of __ati_ret> }`.
+fn tag_structured_return(
+    visitor: &InstrumentingVisitor,
+    call_expr: &mut rustc_ast::Expr,
+    shape: &RetShape,
+) {
+    let rebuilt = tagged_equivalent(shape, RAW_RET_BINDING, 0);
+    rebuild_returned(visitor.psess, call_expr, &rebuilt);
+}
+
+/// Replaces `call_expr` with `{ let __ati_ret = call; <rebuilt> }` in place, where `rebuilt`
+/// is the source of an expression converting `__ati_ret`.
+fn rebuild_returned(
+    psess: &rustc_session::parse::ParseSess,
+    call_expr: &mut rustc_ast::Expr,
+    rebuilt: &str,
+) {
+    let mut block = parsing::parse_expr(
+        psess,
+        format!("{{ let {RAW_RET_BINDING} = (); {rebuilt} }}"),
+    );
+
+    // swap the placeholder initializer for the original call.
+    let rustc_ast::ExprKind::Block(body, _) = &mut block.kind else {
+        panic!("Parsed return value rebuild is not a block expression");
+    };
+    let rustc_ast::StmtKind::Let(local) = &mut body.stmts[0].kind else {
+        panic!("Parsed return value rebuild does not start with a let statement");
+    };
+    let rustc_ast::LocalKind::Init(init) = &mut local.kind else {
+        panic!("Parsed return value rebuild has an uninitialized let statement");
+    };
+    **init = std::mem::replace(call_expr, rustc_ast::Expr::dummy());
+    *call_expr = block;
+}
+
+/// Renders an expression which converts the raw value `value` of shape `shape` into its tagged
+/// equivalent. `depth` keeps closure parameters of nested `map` calls distinct.
+///
+/// Inverse of [`untagged_equivalent`].
+fn tagged_equivalent(shape: &RetShape, value: &str, depth: usize) -> String {
+    let elem = format!("__ati_elem_{depth}");
+    match shape {
+        RetShape::Opaque => value.to_string(),
+        RetShape::Leaf => format!("{}({value})", runtime::runtime_path("ATI::track")),
+        RetShape::Str => format!("{}({value})", runtime::runtime_path("ATI::track_str")),
+        RetShape::Tuple(elems) => {
+            let rendered: Vec<String> = elems
+                .iter()
+                .enumerate()
+                .map(|(i, e)| tagged_equivalent(e, &format!("{value}.{i}"), depth + 1))
+                .collect();
+            if rendered.len() == 1 {
+                format!("({},)", rendered[0])
+            } else {
+                format!("({})", rendered.join(", "))
+            }
+        }
+        RetShape::Array(inner) => format!(
+            "{}({value}.map(|{elem}| {}))",
+            runtime::runtime_path("ATI::track"),
+            tagged_equivalent(inner, &elem, depth + 1)
+        ),
+        RetShape::TaggedArray(inner) => format!(
+            "{}({value}.0, {value}.1.map(|{elem}| {}))",
+            runtime::runtime_path("Tagged"),
+            tagged_equivalent(inner, &elem, depth + 1)
+        ),
+        RetShape::Option(inner) => format!(
+            "{value}.map(|{elem}| {})",
+            tagged_equivalent(inner, &elem, depth + 1)
+        ),
+        RetShape::Result(ok, err) => {
+            let mut rendered = value.to_string();
+            if ok.needs_tagging() {
+                let ok = tagged_equivalent(ok, &elem, depth + 1);
+                rendered = format!("{rendered}.map(|{elem}| {ok})");
+            }
+            if err.needs_tagging() {
+                let err = tagged_equivalent(err, &elem, depth + 1);
+                rendered = format!("{rendered}.map_err(|{elem}| {err})");
+            }
+            rendered
+        }
+        RetShape::Struct { path, fields } => {
+            let rendered: Vec<String> = fields
+                .iter()
+                .map(|(name, f)| {
+                    let field = tagged_equivalent(f, &format!("{value}.{name}"), depth + 1);
+                    format!("{name}: {field}")
+                })
+                .collect();
+            format!("{path} {{ {} }}", rendered.join(", "))
+        }
+    }
+}
+
+/// Converts a (method) call made by a skipped function to an instrumented one, in place.
+///
+/// Each argument pass 1 marked as a tupleable primitive is tagged (`x` -> `ATI::track(x)`), and
+/// the returned value is untupled (`call` -> `call.1`). Compound return values are rebuilt
+/// through [`untagged_equivalent`].
+pub fn transform_call_from_skipped(
+    psess: &rustc_session::parse::ParseSess,
+    call_expr: &mut rustc_ast::Expr,
+    call: &CallFromSkipped,
+) {
+    let args = match &mut call_expr.kind {
+        rustc_ast::ExprKind::Call(_, args)
+        | rustc_ast::ExprKind::MethodCall(box rustc_ast::MethodCall { args, .. }) => args,
+        _ => panic!("Called transform_call_from_skipped with non-call expression."),
+    };
+    for (arg_expr, tagged) in args.iter_mut().zip(&call.tagged_args) {
+        if *tagged {
+            common::tuple(arg_expr);
+        }
+    }
+
+    match &call.ret_shape {
+        RetShape::Opaque => {}
+        RetShape::Leaf => common::untuple(call_expr),
+        shape => {
+            let rebuilt = untagged_equivalent(shape, RAW_RET_BINDING, 0);
+            rebuild_returned(psess, call_expr, &rebuilt);
+        }
+    }
+}
+
+/// Renders an expression which converts the tagged value `value` of shape `shape` into its raw
+/// equivalent. `depth` keeps closure parameters of nested `map` calls distinct.
+///
+/// Inverse of [`tagged_equivalent`].
+fn untagged_equivalent(shape: &RetShape, value: &str, depth: usize) -> String {
+    let elem = format!("__ati_elem_{depth}");
+    match shape {
+        RetShape::Opaque => value.to_string(),
+        RetShape::Leaf | RetShape::Str => format!("{value}.1"),
+        RetShape::Tuple(elems) => {
+            let rendered: Vec<String> = elems
+                .iter()
+                .enumerate()
+                .map(|(i, e)| untagged_equivalent(e, &format!("{value}.{i}"), depth + 1))
+                .collect();
+            if rendered.len() == 1 {
+                format!("({},)", rendered[0])
+            } else {
+                format!("({})", rendered.join(", "))
+            }
+        }
+        RetShape::Array(inner) => format!(
+            "{value}.1.map(|{elem}| {})",
+            untagged_equivalent(inner, &elem, depth + 1)
+        ),
+        RetShape::TaggedArray(inner) => format!(
+            "{}({value}.0, {value}.1.map(|{elem}| {}))",
+            runtime::runtime_path("Tagged"),
+            untagged_equivalent(inner, &elem, depth + 1)
+        ),
+        RetShape::Option(inner) => format!(
+            "{value}.map(|{elem}| {})",
+            untagged_equivalent(inner, &elem, depth + 1)
+        ),
+        RetShape::Result(ok, err) => {
+            let mut rendered = value.to_string();
+            if ok.needs_tagging() {
+                let ok = untagged_equivalent(ok, &elem, depth + 1);
+                rendered = format!("{rendered}.map(|{elem}| {ok})");
+            }
+            if err.needs_tagging() {
+                let err = untagged_equivalent(err, &elem, depth + 1);
+                rendered = format!("{rendered}.map_err(|{elem}| {err})");
+            }
+            rendered
+        }
+        RetShape::Struct { path, fields } => {
+            let rendered: Vec<String> = fields
+                .iter()
+                .map(|(name, f)| {
+                    let field = untagged_equivalent(f, &format!("{value}.{name}"), depth + 1);
+                    format!("{name}: {field}")
+                })
+                .collect();
+            format!("{path} {{ {} }}", rendered.join(", "))
+        }
     }
 }
 
 /// Invoked whenever the visitor runs into ExprKind::MethodCall.
 ///
-/// Updates turbofish generics on the method segment.
+/// Updates turbofish generics on the method segment. If pass 1 marked this as a call to a
+/// skipped method, untuples the arguments and tags the return value like [`transform_call`].
+///
+/// Calls to the methods of `str` / `String` are made on the untupled receiver
+/// (`s.trim()` -> `ATI::track_str(s.1.trim())`). Their turbofish generics are left as written
+/// (`s.parse::<u32>()`), as the returned value is tagged afterwards.
 pub fn transform_method_call(
-    _visitor: &mut InstrumentingVisitor,
+    visitor: &mut InstrumentingVisitor,
     method_expr: &mut rustc_ast::Expr,
 ) {
-    let rustc_ast::ExprKind::MethodCall(box rustc_ast::MethodCall { seg, .. }) =
-        &mut method_expr.kind
+    let rustc_ast::ExprKind::MethodCall(box rustc_ast::MethodCall {
+        seg, receiver, args, ..
+    }) = &mut method_expr.kind
+    else {
+        return;
+    };
+
+    let Some(call) = visitor
+        .first_pass
+        .untracked_method_calls
+        .get(seg.ident.span, visitor.psess.source_map())
     else {
+        tuple_generic_args_in_segment(seg);
         return;
     };
-    tuple_generic_args_in_segment(seg);
+    let call = call.clone();
+
+    if call.untupled_receiver {
+        common::untuple(receiver);
+    } else {
+        tuple_generic_args_in_segment(seg);
+    }
+    untuple_args(args, &call.untupled_args);
+    tag_return(visitor, method_expr, &call.ret_shape);
 }
 
 /// Recursively transforms all type generic arguments in a path segment.
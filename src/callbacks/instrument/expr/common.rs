@@ -7,26 +7,29 @@
 //! insert a reborrow operation on top of some expression, and determine whether some condition
 //! expression contains a let-binding within it.
 
-use crate::callbacks::instrument::instrument_visitor::InstrumentingVisitor;
+use crate::callbacks::{codegen::runtime, instrument::instrument_visitor::InstrumentingVisitor};
 
 /// Wraps an expression `e` of type `T` as `ATI::track(e)` of type `Tagged<T>` in place.
 pub fn tuple(expr: &mut rustc_ast::Expr) {
     let mut ati_track = rustc_ast::Expr::dummy();
-    ati_track.kind = rustc_ast::ExprKind::Path(
+    ati_track.kind =
+        rustc_ast::ExprKind::Path(None, runtime::runtime_ast_path(&["ATI", "track"], None));
+
+    let inner = std::mem::replace(expr, rustc_ast::Expr::dummy());
+    expr.kind = rustc_ast::ExprKind::Call(Box::new(ati_track), [Box::new(inner)].into());
+}
+
+/// Wraps a string literal `e` of type `&str` as `ATI::track_str(e)` of type `TaggedRef<str>` in
+/// place.
+pub fn tuple_str(expr: &mut rustc_ast::Expr) {
+    let mut ati_track_str = rustc_ast::Expr::dummy();
+    ati_track_str.kind = rustc_ast::ExprKind::Path(
         None,
-        rustc_ast::Path {
-            segments: [
-                rustc_ast::PathSegment::from_ident(rustc_span::Ident::from_str("ATI")),
-                rustc_ast::PathSegment::from_ident(rustc_span::Ident::from_str("track")),
-            ]
-            .into(),
-            tokens: None,
-            span: rustc_span::DUMMY_SP,
-        },
+        runtime::runtime_ast_path(&["ATI", "track_str"], None),
     );
 
     let inner = std::mem::replace(expr, rustc_ast::Expr::dummy());
-    expr.kind = rustc_ast::ExprKind::Call(Box::new(ati_track), [Box::new(inner)].into());
+    expr.kind = rustc_ast::ExprKind::Call(Box::new(ati_track_str), [Box::new(inner)].into());
 }
 
 /// Takes a `Tagged<T>` expression and unwraps it to just `T` via `.1` field access in place.
@@ -66,6 +69,31 @@ pub fn normalize_tagged_ref(
     }));
 }
 
+/// If `expr`'s span was marked by pass 1 in `str_deref_coercions`, converts the
+/// `TaggedRef<String>` it evaluates to into the `TaggedRef<str>` expected in its place, via
+/// `(expr).as_str()`.
+pub fn coerce_tagged_str(
+    visitor: &crate::callbacks::instrument::instrument_visitor::InstrumentingVisitor,
+    expr: &mut rustc_ast::Expr,
+) {
+    if !visitor
+        .first_pass
+        .str_deref_coercions
+        .contains(expr.span, visitor.psess.source_map())
+    {
+        return;
+    }
+
+    let inner = std::mem::replace(expr, rustc_ast::Expr::dummy());
+    expr.span = inner.span;
+    expr.kind = rustc_ast::ExprKind::MethodCall(Box::new(rustc_ast::MethodCall {
+        seg: rustc_ast::PathSegment::from_ident(rustc_span::Ident::from_str("as_str")),
+        receiver: Box::new(inner),
+        args: [].into(),
+        span: rustc_span::DUMMY_SP,
+    }));
+}
+
 /// Walk a pattern and force every `Ident` binding's mutability to `mut`.
 pub fn pat_force_mut_bindings(pat: &mut rustc_ast::Pat) {
     use rustc_ast::PatKind;
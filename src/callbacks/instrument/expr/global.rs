@@ -0,0 +1,56 @@
+//! Defines functions to tuple reads of `const` and `static` items, by using the runtime libraries
+//! `ATI::track_global` function.
+//!
+//! Items themselves keep their untagged types, their initializers may be evaluated at compile
+//! time, where the runtime library is unavailable. Instead, every read of such an item is tagged
+//! with the id the runtime associates with that item, so that all reads of `MAX` interact with
+//! each other, like reads of a variable would.
+
+use crate::callbacks::{
+    codegen::runtime,
+    instrument::{expr::common, instrument_visitor::InstrumentingVisitor},
+};
+
+/// Invoked whenever the visitor runs into an `ExprKind::Path`.
+///
+/// If the path was found to read a `const` / `static` item of a tuplable type:
+///       MAX --> `ATI::track_global(r"krate::MAX", MAX)`
+/// type: `T` --> `Tagged<T>`
+/// If the item can't be identified across reads (e.g. `T::ZERO`), it is tagged like a literal:
+///       T::ZERO --> `ATI::track(T::ZERO)`
+/// Any other path is left untouched.
+pub fn transform_global_read(visitor: &mut InstrumentingVisitor, path_expr: &mut rustc_ast::Expr) {
+    let Some(read) = visitor
+        .first_pass
+        .global_reads
+        .get(path_expr.span, visitor.psess.source_map())
+    else {
+        return;
+    };
+
+    let Some(key) = &read.key else {
+        common::tuple(path_expr);
+        return;
+    };
+
+    let mut track_global = rustc_ast::Expr::dummy();
+    track_global.kind = rustc_ast::ExprKind::Path(
+        None,
+        runtime::runtime_ast_path(&["ATI", "track_global"], None),
+    );
+
+    let key = rustc_ast::Expr {
+        kind: rustc_ast::ExprKind::Lit(rustc_ast::token::Lit::new(
+            rustc_ast::token::LitKind::StrRaw(0),
+            rustc_span::Symbol::intern(key),
+            None,
+        )),
+        ..rustc_ast::Expr::dummy()
+    };
+
+    let inner = std::mem::replace(path_expr, rustc_ast::Expr::dummy());
+    path_expr.kind = rustc_ast::ExprKind::Call(
+        Box::new(track_global),
+        [Box::new(key), Box::new(inner)].into(),
+    );
+}
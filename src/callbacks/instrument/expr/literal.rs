@@ -1,5 +1,5 @@
 //! Defines functions to tuple Literal expressions, by using the runtime libraries
-//! `ATI::track` function.
+//! `ATI::track` function, or `ATI::track_str` for string literals.
 //!
 //! Only literals of types that are tuplable should be tupled.
 
@@ -15,6 +15,9 @@ use crate::{
 /// If lit type can be tupled (e.g. integer types):
 ///       a --> `ATI::track(a)`
 /// type: `T` --> `Tagged<T>`
+/// If lit is a string literal:
+///       "a" --> `ATI::track_str("a")`
+/// type: `&str` --> `TaggedRef<str>`
 /// If lit cannot be tupled:
 ///       a --> a
 /// type: `T` --> `T`
@@ -30,5 +33,12 @@ pub fn transform_literal(_visitor: &mut InstrumentingVisitor, lit_expr: &mut rus
         return;
     }
 
+    if matches!(
+        lit.kind,
+        rustc_ast::token::LitKind::Str | rustc_ast::token::LitKind::StrRaw(_)
+    ) {
+        common::tuple_str(lit_expr);
+        return;
+    }
     common::tuple(lit_expr);
 }
@@ -22,21 +22,31 @@
 //!   assign operation is a "place" expression, which needs to be instrumented differently than 
 //!   normal expressions. See [`transform_lhs_place_expr`] below for more information.
 //! - Literals of type `T` are turned into `Tagged<T>` by dynamically assigning them a tag, via
-//!   the runtime libraries `ATI::track(<lit>)`.
+//!   the runtime libraries `ATI::track(<lit>)`. String literals are turned into a
+//!   `TaggedRef<str>` via `ATI::track_str(<lit>)`.
+//! - Reads of `const` / `static` items are tagged with an Id shared by all reads of the item, via
+//!   the runtime libraries `ATI::track_global(<key>, <path>)`. The items themselves stay untagged.
 //! - Arrays are tracked via the runtime libraries `ATI::track(<array>)`, this adds an
 //!   Id which corresponds to the length of the array expression.
 //! - References (to tuplable primitives) are converted to `TaggedRef` / `TaggedRefMuts` via the
 //!   `.share()/.reborrow()` defined within the runtime library.
 //! - Calls to uninstrumented method and function calls have thier inputs "untupled", and
 //!   return value tupled, if the call was found to return a tuplable value by the Gather pass.
+//!   Calls made by skipped functions to instrumented ones are converted the other way around,
+//!   via [`transform_call_from_skipped`].
 //! - If/While conditions are appropriately untupled, to have the condition evaluate to a boolean
 //!   after performing any merges required by the evaluation of the boolean itself. This requires
 //!   knowing if the condition has a let-pattern binding within it, via 
 //!   [common::contains_let_chain].
 //! - Match statements have the match-target untupled, if the match is on a `Tagged`, `TaggedRef`,
 //!   or `TaggedRefMut`. Otherwise, they are untouched.
+//! - Invocations of well-known std macros (`vec!`, `assert!`, `format!`, ...) and of crate-local
+//!   expression macros have their arguments parsed and instrumented, see
+//!   [crate::callbacks::instrument::macros]. `FormatArgs` nodes only exist after expansion.
 
-use crate::callbacks::instrument::{instrument_visitor::InstrumentingVisitor, item::data_types};
+use crate::callbacks::instrument::{
+    instrument_visitor::InstrumentingVisitor, item::data_types, macros,
+};
 
 mod addr_of;
 mod array;
@@ -44,11 +54,15 @@ mod assign;
 mod call;
 mod common;
 mod control_flow;
+mod global;
 mod index;
 mod literal;
 mod ops;
 mod range;
 
+pub use call::transform_call_from_skipped;
+pub use common::{tuple, untuple};
+
 /// Mutates the input expression in place, to track value interactions during runtime.
 pub fn transform_expr<'session>(
     visitor: &mut InstrumentingVisitor<'session>,
@@ -155,11 +169,25 @@ pub fn transform_expr<'session>(
             range::transform_range(visitor, expr);
         }
 
+        // MAX, COUNTER, <path>
+        rustc_ast::ExprKind::Path(..) => {
+            global::transform_global_read(visitor, expr);
+        }
+
         // |args| <body>
         rustc_ast::ExprKind::Closure(..) => {
             data_types::transform_closure(visitor, expr);
         }
 
+        // <path>!(<>, <>, ...)
+        rustc_ast::ExprKind::MacCall(..) => {
+            macros::transform_mac_call_expr(visitor, expr);
+        }
+
+        // <expr>?, the `Option` / `Result` operand already carries tagged payloads, which `?`
+        // and its error conversion hand over unchanged.
+        rustc_ast::ExprKind::Try(..) => {}
+
         // No special transformation on the rest of these exprs
         rustc_ast::ExprKind::ConstBlock(..)
         | rustc_ast::ExprKind::Tup(..)
@@ -172,16 +200,13 @@ pub fn transform_expr<'session>(
         | rustc_ast::ExprKind::TryBlock(..)
         | rustc_ast::ExprKind::Field(..)
         | rustc_ast::ExprKind::Underscore
-        | rustc_ast::ExprKind::Path(..)
         | rustc_ast::ExprKind::Break(..)
         | rustc_ast::ExprKind::Continue(..)
         | rustc_ast::ExprKind::Ret(..)
         | rustc_ast::ExprKind::InlineAsm(..)
         | rustc_ast::ExprKind::OffsetOf(..)
-        | rustc_ast::ExprKind::MacCall(..)
         | rustc_ast::ExprKind::Struct(..)
         | rustc_ast::ExprKind::Paren(..)
-        | rustc_ast::ExprKind::Try(..)
         | rustc_ast::ExprKind::Yield(..)
         | rustc_ast::ExprKind::Yeet(..)
         | rustc_ast::ExprKind::Become(..)
@@ -191,6 +216,10 @@ pub fn transform_expr<'session>(
         | rustc_ast::ExprKind::Err(..)
         | rustc_ast::ExprKind::Dummy => {}
     }
+
+    // Regardless of expr kind, a `&String` deref-coerced into a `&str` must be converted, as
+    // `TaggedRef<String>` doesn't coerce into `TaggedRef<str>`.
+    common::coerce_tagged_str(visitor, expr);
 }
 
 /// Walk a place expression (an Assign / AssignOp LHS) without value-instrumenting its
@@ -9,6 +9,8 @@
 //!    expression evaluation will not interact with any value.
 //! 2. Comparison operators (`==`, `>`, `<=`, etc...). These operators produce an interaction
 //!    between the lhs and rhs, and the resulting boolean is a new value which receives a new Id.
+//!    Operands of a compound type (e.g. `Option<u32>`) compare through their own `PartialEq` /
+//!    `PartialOrd` impl instead, which interacts the tagged values they contain.
 //! 3. Arithmetic operators (`+`, `&`, etc...). These operators produce an interaction between the
 //!    lhs, rhs, and the output. These operators rely on `std::ops` trait implementations to perform
 //!    both tag merging and value computation.
@@ -24,6 +26,7 @@ use rustc_ast::BinOpKind;
 use rustc_ast_pretty::pprust;
 
 use crate::{
+    callbacks::codegen::runtime,
     callbacks::instrument::{expr::common as expr_common, instrument_visitor::InstrumentingVisitor},
     callbacks::parsing,
 };
@@ -63,6 +66,27 @@ pub fn transform_binary(visitor: &mut InstrumentingVisitor, binary_expr: &mut ru
         return;
     }
 
+    // Compound operands carry no tag of their own. Comparing them interacts the tagged values
+    // within, through the runtime's comparison impls on the tagged wrappers.
+    if visitor
+        .first_pass
+        .compound_comparisons
+        .contains(binary_expr.span, visitor.psess.source_map())
+    {
+        let lhs_str = pprust::expr_to_string(lhs);
+        let rhs_str = pprust::expr_to_string(rhs);
+        let op_str = op.node.as_str();
+        let block_str = format!(
+            r#"{{
+                let __ati_cmp = {lhs_str} {op_str} {rhs_str};
+                let __ati_id = ATI_ANALYSIS.lock().unwrap().make_id();
+                Tagged(__ati_id, __ati_cmp)
+            }}"#
+        );
+        *binary_expr = parsing::parse_expr(visitor.psess, block_str);
+        return;
+    }
+
     // The block we emit binds lhs/rhs into locals, which moves them.
     // For TaggedRefMut operands (move-only), reborrow first so any later
     // use of the same source binding still compiles.
@@ -82,11 +106,12 @@ pub fn transform_binary(visitor: &mut InstrumentingVisitor, binary_expr: &mut ru
 
     // FIXME: Kind of stupid to go from lhs op rhs to lhs op rhs in arithmetic case
     let block_str = match op_type(op.node) {
-        // Comparisons interact the two operands but not the result.
+        // Comparisons interact the two operands but not the result. Operands are borrowed, as
+        // comparing doesn't move them (e.g. a `Tagged<String>`).
         OpKind::Comparison => format!(
             r#"{{
-                let __ati_lhs = {lhs_str};
-                let __ati_rhs = {rhs_str};
+                let __ati_lhs = &({lhs_str});
+                let __ati_rhs = &({rhs_str});
                 ATI_ANALYSIS.lock().unwrap().union_and_get_id(&__ati_lhs.0, &__ati_rhs.0);
                 let __ati_id = ATI_ANALYSIS.lock().unwrap().make_id();
                 Tagged(__ati_id, __ati_lhs.1 {op_str} __ati_rhs.1)
@@ -140,9 +165,12 @@ pub fn transform_unary(visitor: &mut InstrumentingVisitor, unary_expr: &mut rust
     let rustc_ast::ExprKind::Unary(_, inner) = &unary_expr.kind else {
         unreachable!();
     };
+    // `TaggedRef` holds its id, while `TaggedRefMut` borrows it.
     let code = format!(
-        "{{ let __tr = {}; Tagged(*__tr.0, *__tr.1) }}",
+        "{{ let __tr = {}; {}({}::id(&__tr), *__tr.1) }}",
         pprust::expr_to_string(inner),
+        runtime::runtime_path("Tagged"),
+        runtime::runtime_path("TagTuple"),
     );
     *unary_expr = parsing::parse_expr(visitor.psess, code);
 }
@@ -3,7 +3,10 @@
 
 use rustc_ast_pretty::pprust;
 
-use crate::{callbacks::instrument::instrument_visitor::InstrumentingVisitor, callbacks::parsing};
+use crate::{
+    callbacks::codegen::runtime, callbacks::instrument::instrument_visitor::InstrumentingVisitor,
+    callbacks::parsing,
+};
 
 /// Invoked whenever the visitor runs into an `ExprKind::Range`.
 ///
@@ -16,28 +19,29 @@ pub fn transform_range(visitor: &mut InstrumentingVisitor, range_expr: &mut rust
     };
 
     let is_inclusive = matches!(limits, rustc_ast::RangeLimits::Closed);
+    let ati = runtime::runtime_path("ATI");
     let code = match (lo.as_ref(), hi.as_ref(), is_inclusive) {
         (Some(lo), Some(hi), false) => format!(
-            "ATI::track_range({}, {})",
+            "{ati}::track_range({}, {})",
             pprust::expr_to_string(lo),
             pprust::expr_to_string(hi),
         ),
         (Some(lo), Some(hi), true) => format!(
-            "ATI::track_range_inclusive({}, {})",
+            "{ati}::track_range_inclusive({}, {})",
             pprust::expr_to_string(lo),
             pprust::expr_to_string(hi),
         ),
         (Some(lo), None, _) => {
-            format!("ATI::track_range_from({})", pprust::expr_to_string(lo))
+            format!("{ati}::track_range_from({})", pprust::expr_to_string(lo))
         }
         (None, Some(hi), false) => {
-            format!("ATI::track_range_to({})", pprust::expr_to_string(hi))
+            format!("{ati}::track_range_to({})", pprust::expr_to_string(hi))
         }
         (None, Some(hi), true) => format!(
-            "ATI::track_range_to_inclusive({})",
+            "{ati}::track_range_to_inclusive({})",
             pprust::expr_to_string(hi),
         ),
-        (None, None, _) => "ATI::track_range_full()".to_string(),
+        (None, None, _) => format!("{ati}::track_range_full()"),
     };
     *range_expr = parsing::parse_expr(visitor.psess, code);
 }
@@ -25,7 +25,8 @@ pub struct FileContents {
 /// 
 /// All files loaded by DATIR are either root files (`main.rs` or `lib.rs`` ), dependancy files
 /// (imported by some other file, but within the currently compiled crate), or untracked files
-/// (imported, but external to the currently compiled crate).
+/// (imported, but external to the currently compiled crate). Files of an allowlisted dependency
+/// are tracked while that dependency is being compiled, even if they live within `.cargo`.
 #[derive(Debug)]
 pub enum FileType {
     /// Represents the tracked crate root file.
@@ -42,15 +43,22 @@ impl FileContents {
     /// Given the source contents (usually read in from a standard rustc file loader), a path to 
     /// the file within the filesystem, and a root directory (the directory where `main.rs` or 
     /// `lib.rs` is defined), the returned [`FileContents`] will capture whether or not this
-    /// file requires instrumentation.
-    pub fn new(source: String, path: &std::path::Path, root_dir: Option<&std::path::Path>) -> Self {
+    /// file requires instrumentation. `instrumented_dependency` marks the files of an allowlisted
+    /// dependency, which are tracked wherever they live.
+    pub fn new(
+        source: String,
+        path: &std::path::Path,
+        root_dir: Option<&std::path::Path>,
+        instrumented_dependency: bool,
+    ) -> Self {
         let path_str = path.to_str().unwrap();
 
         // non .rs files, or std library files, external crates, etc.
         let (file_type, module_path) = if path.extension().and_then(|s| s.to_str()) != Some("rs")
-            || path_str.contains("/.rustup/")
-            || path_str.contains("/.cargo/")
-            || path_str.contains("/rustc/")
+            || (!instrumented_dependency
+                && (path_str.contains("/.rustup/")
+                    || path_str.contains("/.cargo/")
+                    || path_str.contains("/rustc/")))
         {
             (FileType::Untracked, String::new())
         } else if path_str.ends_with("main.rs") || path_str.ends_with("lib.rs") {
@@ -36,13 +36,20 @@
 //!
 //! This incurs a slight runtime cost in requiring the pipeline to construct another AST, but allows
 //! for instrumentation of non-root files utilizing standard rustc `Visitor`/`MutVisitor` patterns.
+//!
+//! As rustc only ever sees the printed source string, the spans of its diagnostics point into text
+//! the user never sees. Step 4 therefore also records the origin of every printed line in a
+//! [`SpanMap`], which [crate::callbacks::instrument::diagnostics] uses to map diagnostics back to
+//! the original source.
 
 mod files;
+mod span_map;
 mod transforming_passes;
 
 use crate::callbacks::parsing;
 use crate::config::DatirConfig;
 use files::{FileContents, FileType};
+pub use span_map::{Origin, OriginalLoc, SpanMaps};
 pub use transforming_passes::Passes;
 
 /// File loader responsible for loading files from disk and applying a transformation to them.
@@ -62,6 +69,8 @@ pub struct TransformingFileLoader {
     /// Discovered on the first Root file read and used to compute
     /// relative module paths for dep files.
     root_dir: std::sync::OnceLock<std::path::PathBuf>,
+    /// Origins of the lines of every transformed file.
+    span_maps: std::sync::Arc<SpanMaps>,
 }
 
 /// Implements the necessary trait to use the custom loader as a
@@ -99,12 +108,17 @@ impl rustc_span::source_map::FileLoader for TransformingFileLoader {
 
 impl TransformingFileLoader {
     /// Constructor
-    pub fn new(passes: Passes, config: std::sync::Arc<DatirConfig>) -> Self {
+    pub fn new(
+        passes: Passes,
+        config: std::sync::Arc<DatirConfig>,
+        span_maps: std::sync::Arc<SpanMaps>,
+    ) -> Self {
         Self {
             inner: rustc_span::source_map::RealFileLoader,
             passes,
             config,
             root_dir: std::sync::OnceLock::new(),
+            span_maps,
         }
     }
 
@@ -112,7 +126,8 @@ impl TransformingFileLoader {
     /// 
     /// Given a loaded source file (represented as a string, within `file`), parses it into
     /// an AST, executes each of the AST-transforming passes over it, then converts the
-    /// modified AST back into a source string representation.
+    /// modified AST back into a source string representation, recording the origin of each
+    /// line of that representation.
     fn transform_source(&self, file: FileContents, path: &std::path::Path) -> String {
         let psess = rustc_session::parse::ParseSess::new();
         let mut krate = parsing::parse_crate(&psess, file.source, Some(path));
@@ -127,13 +142,18 @@ impl TransformingFileLoader {
             pass(&psess, &mut krate, &file.module_path);
         }
 
-        let output = ast_to_source(&krate);
-
         if self.config.print_transformed_ast {
             self.config.log(
                 "TransformedAst",
                 format!("======== {path:?} ========\n{krate:#?}\n"),
             );
+        }
+
+        let (output, span_map) =
+            span_map::print_with_origins(&psess, &mut krate, path, ast_to_source);
+        self.span_maps.register(path, span_map);
+
+        if self.config.print_transformed_ast {
             self.config.log(
                 "TransformedSource",
                 format!("======== {path:?} ========\n{output}\n"),
@@ -150,7 +170,12 @@ impl TransformingFileLoader {
     /// for use in computing relative module paths for dependancy files.
     fn load_file_contents(&self, path: &std::path::Path) -> std::io::Result<FileContents> {
         let source = rustc_span::source_map::FileLoader::read_file(&self.inner, path)?;
-        let file = FileContents::new(source, path, self.root_dir.get().map(|p| p.as_path()));
+        let file = FileContents::new(
+            source,
+            path,
+            self.root_dir.get().map(|p| p.as_path()),
+            self.config.is_instrumented_dependency_file(path),
+        );
 
         // If this is the root file, remember its parent directory
         if matches!(file.file_type, FileType::Root)
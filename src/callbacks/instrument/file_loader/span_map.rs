@@ -0,0 +1,278 @@
+//! Records, for every line of an instrumented file, where that line came from.
+//!
+//! The [super::TransformingFileLoader] hands rustc a pretty-printed version of the transformed
+//! AST, so every span rustc reports during the Instrument compilation points into text that only
+//! exists in memory. To map those spans back, the transformed AST is annotated right before being
+//! printed: every item, statement, `let` binding and match arm is given a marker attribute
+//! (`#[__datir_origin_N]`), where `N` indexes the [`Origin`] of that node. The printed text is
+//! then scanned for markers, which are stripped, recording the origin of each printed line.
+//!
+//! A node either stems from the original file, in which case its original line and column are
+//! recorded, or was generated by DATIR, in which case the transformation which generated it (see
+//! [crate::callbacks::parsing::generating_transformation]) is recorded alongside the location of
+//! the closest enclosing original node. Nodes built directly as AST (with dummy spans) inherit
+//! the origin of the line they are printed on.
+
+use rustc_ast::mut_visit::MutVisitor;
+
+use crate::callbacks::parsing;
+
+/// Prefix of the marker attributes attached to annotated nodes.
+const MARKER_PREFIX: &str = "__datir_origin_";
+
+/// A line and column within the original source file (both 1-based).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OriginalLoc {
+    pub line: usize,
+    pub col: usize,
+}
+
+/// Where a line of an instrumented file came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Origin {
+    /// The line holds (possibly transformed) code written by the user.
+    Original(OriginalLoc),
+    /// The line holds code generated by DATIR.
+    Generated {
+        /// The transformation which generated the code.
+        transformation: String,
+        /// The closest original node enclosing the generated code, if any.
+        enclosing: Option<OriginalLoc>,
+    },
+}
+
+/// Origins of every line of a single instrumented file.
+#[derive(Debug, Default)]
+pub struct SpanMap {
+    /// `lines[i]` is the origin of line `i + 1`, if known.
+    lines: Vec<Option<Origin>>,
+}
+
+impl SpanMap {
+    /// Returns the origin of the (1-based) `line` within the instrumented file.
+    pub fn origin(&self, line: usize) -> Option<&Origin> {
+        self.lines.get(line.checked_sub(1)?)?.as_ref()
+    }
+}
+
+/// The [`SpanMap`]s of every instrumented file, shared between the file loader, which records
+/// them, and the diagnostic emitter, which consults them.
+#[derive(Debug, Default)]
+pub struct SpanMaps(std::sync::Mutex<std::collections::HashMap<std::path::PathBuf, SpanMap>>);
+
+impl SpanMaps {
+    /// Records `map` as the span map of the file at `path`.
+    pub fn register(&self, path: &std::path::Path, map: SpanMap) {
+        self.0.lock().unwrap().insert(path.to_path_buf(), map);
+    }
+
+    /// Returns the origin of `line` within the instrumented file at `path`.
+    pub fn origin(&self, path: &std::path::Path, line: usize) -> Option<Origin> {
+        self.0.lock().unwrap().get(path)?.origin(line).cloned()
+    }
+}
+
+/// Pretty-prints `krate`, the transformed AST of the file at `path`, returning the printed
+/// source alongside the origin of each of its lines.
+///
+/// `krate` is annotated with marker attributes in the process, and must not be used afterwards.
+pub fn print_with_origins(
+    psess: &rustc_session::parse::ParseSess,
+    krate: &mut rustc_ast::Crate,
+    path: &std::path::Path,
+    print: impl FnOnce(&rustc_ast::Crate) -> String,
+) -> (String, SpanMap) {
+    let mut annotator = OriginAnnotator {
+        psess,
+        path,
+        origins: Vec::new(),
+        enclosing: Vec::new(),
+    };
+    annotator.visit_crate(krate);
+
+    strip_markers(&print(krate), &annotator.origins)
+}
+
+/// Visitor attaching a marker attribute to every node whose origin is known.
+struct OriginAnnotator<'a> {
+    psess: &'a rustc_session::parse::ParseSess,
+    /// Path of the file being instrumented.
+    path: &'a std::path::Path,
+    /// Origin of every marker attached so far, indexed by marker number.
+    origins: Vec<Origin>,
+    /// Locations of the original nodes enclosing the node being visited.
+    enclosing: Vec<OriginalLoc>,
+}
+
+impl OriginAnnotator<'_> {
+    /// Resolves the origin of a node spanning `span`.
+    fn origin(&self, span: rustc_span::Span) -> Option<Origin> {
+        if span.is_dummy() {
+            return None;
+        }
+
+        let loc = self.psess.source_map().lookup_char_pos(span.lo());
+        if let Some(transformation) = parsing::generating_transformation(&loc.file.name) {
+            return Some(Origin::Generated {
+                transformation,
+                enclosing: self.enclosing.last().copied(),
+            });
+        }
+
+        match &loc.file.name {
+            rustc_span::FileName::Real(rfn)
+                if rfn.path(rustc_span::RemapPathScopeComponents::MACRO) == self.path =>
+            {
+                Some(Origin::Original(OriginalLoc {
+                    line: loc.line,
+                    col: loc.col.0 + 1,
+                }))
+            }
+            _ => None,
+        }
+    }
+
+    /// Attaches a marker for the node spanning `span` to `attrs`, then visits the node's
+    /// children with `walk`, considering the node as enclosing them if it is original.
+    fn annotate(
+        &mut self,
+        attrs: &mut rustc_ast::AttrVec,
+        span: rustc_span::Span,
+        walk: impl FnOnce(&mut Self),
+    ) {
+        let Some(origin) = self.origin(span) else {
+            walk(self);
+            return;
+        };
+
+        let marker = parsing::parse_expr(
+            self.psess,
+            format!("#[{MARKER_PREFIX}{}] ()", self.origins.len()),
+        )
+        .attrs
+        .pop()
+        .unwrap();
+        attrs.insert(0, marker);
+
+        let is_original = match &origin {
+            Origin::Original(loc) => {
+                self.enclosing.push(*loc);
+                true
+            }
+            Origin::Generated { .. } => false,
+        };
+        self.origins.push(origin);
+
+        walk(self);
+
+        if is_original {
+            self.enclosing.pop();
+        }
+    }
+}
+
+impl MutVisitor for OriginAnnotator<'_> {
+    fn visit_item(&mut self, item: &mut rustc_ast::Item) {
+        let span = item.span;
+        let mut attrs = std::mem::take(&mut item.attrs);
+        self.annotate(&mut attrs, span, |this| {
+            rustc_ast::mut_visit::walk_item(this, item)
+        });
+        item.attrs = attrs;
+    }
+
+    fn visit_assoc_item(
+        &mut self,
+        item: &mut rustc_ast::AssocItem,
+        ctxt: rustc_ast::visit::AssocCtxt,
+    ) {
+        let span = item.span;
+        let mut attrs = std::mem::take(&mut item.attrs);
+        self.annotate(&mut attrs, span, |this| {
+            rustc_ast::mut_visit::walk_assoc_item(this, item, ctxt)
+        });
+        item.attrs = attrs;
+    }
+
+    fn visit_local(&mut self, local: &mut rustc_ast::Local) {
+        let span = local.span;
+        let mut attrs = std::mem::take(&mut local.attrs);
+        self.annotate(&mut attrs, span, |this| {
+            rustc_ast::mut_visit::walk_local(this, local)
+        });
+        local.attrs = attrs;
+    }
+
+    fn visit_arm(&mut self, arm: &mut rustc_ast::Arm) {
+        let span = arm.span;
+        let mut attrs = std::mem::take(&mut arm.attrs);
+        self.annotate(&mut attrs, span, |this| {
+            rustc_ast::mut_visit::walk_arm(this, arm)
+        });
+        arm.attrs = attrs;
+    }
+
+    /// Expression statements are annotated through the attributes of their expression. `let`
+    /// bindings and items are annotated by [`Self::visit_local`] and [`Self::visit_item`].
+    fn flat_map_stmt(
+        &mut self,
+        mut stmt: rustc_ast::Stmt,
+    ) -> smallvec::SmallVec<[rustc_ast::Stmt; 1]> {
+        if let rustc_ast::StmtKind::Expr(expr) | rustc_ast::StmtKind::Semi(expr) = &mut stmt.kind {
+            let span = stmt.span;
+            let mut attrs = std::mem::take(&mut expr.attrs);
+            self.annotate(&mut attrs, span, |this| this.visit_expr(expr));
+            expr.attrs = attrs;
+            return smallvec::smallvec![stmt];
+        }
+        rustc_ast::mut_visit::walk_flat_map_stmt(self, stmt)
+    }
+}
+
+/// Removes every marker from `printed`, returning the cleaned source alongside the origin of
+/// each of its lines.
+///
+/// A line takes the origin of the first marker printed on it. Lines holding only markers (the
+/// outer attributes of an item or arm) pass their first marker on to the next line. Lines without
+/// any marker inherit the origin of the last marker printed before them.
+fn strip_markers(printed: &str, origins: &[Origin]) -> (String, SpanMap) {
+    let mut source = String::with_capacity(printed.len());
+    let mut map = SpanMap::default();
+    let mut pending: Option<usize> = None;
+    let mut current: Option<usize> = None;
+
+    for line in printed.lines() {
+        let mut cleaned = String::with_capacity(line.len());
+        let mut markers = Vec::new();
+        let mut rest = line;
+        while let Some(start) = rest.find(&format!("#[{MARKER_PREFIX}")) {
+            cleaned.push_str(&rest[..start]);
+            let after = &rest[start + 2 + MARKER_PREFIX.len()..];
+            let end = after.find(']').expect("unterminated DATIR origin marker");
+            markers.push(
+                after[..end]
+                    .parse::<usize>()
+                    .expect("malformed DATIR origin marker"),
+            );
+            rest = after[end + 1..]
+                .strip_prefix(' ')
+                .unwrap_or(&after[end + 1..]);
+        }
+        cleaned.push_str(rest);
+
+        if !markers.is_empty() && cleaned.trim().is_empty() {
+            pending = pending.or(markers.first().copied());
+            current = markers.last().copied();
+            continue;
+        }
+
+        let origin = pending.take().or(markers.first().copied()).or(current);
+        current = markers.last().copied().or(origin);
+        map.lines.push(origin.map(|i| origins[i].clone()));
+
+        source.push_str(&cleaned);
+        source.push('\n');
+    }
+
+    (source, map)
+}
@@ -27,8 +27,8 @@
 //! by "tupling" each atomic value (i.e. `u32 --> Tagged<u32> == (Id, u32)`) with an Id, which will
 //! uniquely identify that specific value. Then, whenever this tagged value interacts with another
 //! (usually in a Binary expression, but see `./expr/ops.rs` for specifics), the corresponding Ids
-//! will be merged within a Union-Find structure, which is stored within an injected `ATI_ANALYSIS`
-//! global. See [crate::ati::ati] for a description of how the global interaction state is managed.
+//! will be merged within a Union-Find structure, which is stored within the runtime's `ATI_ANALYSIS`
+//! global. See `datir_runtime::ati` for a description of how the global interaction state is managed.
 //!
 //! Arrays, slices, and ranges are all composed of "inner" types, yet the collection itself also
 //! receives a separate tag for the length. This means we represent arrays/slices as:
@@ -131,16 +131,20 @@
 //! 2. [super::expr] for how individual expressions are transformed.
 //! 3. [super::item] for how individual items are transformed.
 //! 4. [super::hoisting] for how the hoisting mechanism works.
-//! 5. [crate::ati::ati] for how global analysis state is managed.
-//! 6. [crate::ati::arrays] for how arrays and slices are indexed with usizes and ranges.
-//! 7. [crate::ati::tagged_ops] for how interactions between values are observed and recorded.
-//! 8. [crate::ati::tagged] for `Tagged` / `TaggedRef` / `TaggedRefMut` definitions and further
+//! 5. `datir_runtime::ati` for how global analysis state is managed.
+//! 6. `datir_runtime::arrays` for how arrays and slices are indexed with usizes and ranges.
+//! 7. `datir_runtime::tagged_ops` for how interactions between values are observed and recorded.
+//! 8. `datir_runtime::tagged` for `Tagged` / `TaggedRef` / `TaggedRefMut` definitions and further
 //!    formalization.
-//! 9. [crate::ati::iterators] for how tagged arrays and slices are used in for loops.
+//! 9. `datir_runtime::iterators` for how tagged arrays and slices are used in for loops.
+//! 10. [super::probes] for how user-placed probe points are replaced with site updates.
+//! 11. [super::macros] for how macro invocations and crate-local macro definitions are
+//!     instrumented.
 
 use crate::{
+    callbacks::codegen::ConstEvalBodies,
     callbacks::gather::first_pass_info::FirstPassInfo,
-    callbacks::instrument::{expr, hoisting, item, types},
+    callbacks::instrument::{expr, hoisting, item, macros, probes, types},
     config::DatirConfig,
 };
 
@@ -151,6 +155,10 @@ pub struct InstrumentingVisitor<'a> {
     pub first_pass: &'a FirstPassInfo,
     pub psess: &'a rustc_session::parse::ParseSess,
     pub mod_path: String,
+    /// Uninstrumented copies of the bodies of every instrumented `const fn`, keyed by the span of
+    /// the fn item. Code generation evaluates these in const contexts, see
+    /// [crate::callbacks::codegen::generate_shims].
+    pub const_eval_bodies: ConstEvalBodies,
 }
 
 impl<'a> InstrumentingVisitor<'a> {
@@ -166,6 +174,7 @@ impl<'a> InstrumentingVisitor<'a> {
             first_pass,
             psess,
             mod_path: mod_path.into(),
+            const_eval_bodies: Default::default(),
         }
     }
 }
@@ -187,6 +196,10 @@ impl<'a> rustc_ast::mut_visit::MutVisitor for InstrumentingVisitor<'a> {
     /// that allows all existing patterns to still function on any transformed type.
     fn visit_pat(&mut self, _node: &mut rustc_ast::Pat) { }
 
+    /// Defining this stops us from modifying the arguments of attributes, such as the string
+    /// literal of `#[must_use = "..."]`, which are evaluated at compile time.
+    fn visit_attribute(&mut self, _node: &mut rustc_ast::Attribute) {}
+
     /// Transform `let x: ty` statements, into `let x: Tag(ty)`.
     fn visit_local(&mut self, local: &mut rustc_ast::Local) {
         if let Some(ty) = &mut local.ty {
@@ -209,13 +222,28 @@ impl<'a> rustc_ast::mut_visit::MutVisitor for InstrumentingVisitor<'a> {
     /// After transforming all expressions, iterate through all statements and
     /// hoist any necessary method calls.
     /// The hoisting transformation happens after the statement is walked and transformed.
-    fn flat_map_stmt(&mut self, stmt: rustc_ast::Stmt) -> smallvec::SmallVec<[rustc_ast::Stmt; 1]> {
+    ///
+    /// User-placed probes are replaced with (or followed by) updates of their sites. The
+    /// arguments of other macro statements are instrumented, see [`macros::transform_mac_call`].
+    fn flat_map_stmt(
+        &mut self,
+        mut stmt: rustc_ast::Stmt,
+    ) -> smallvec::SmallVec<[rustc_ast::Stmt; 1]> {
+        if let Some(probe) = probes::expand_probe_macro(self, &stmt) {
+            return smallvec::smallvec![probe];
+        }
+        let probe = probes::probe_after_let(self, &stmt);
+        if let rustc_ast::StmtKind::MacCall(mac_stmt) = &mut stmt.kind {
+            macros::transform_mac_call(self, &mut mac_stmt.mac);
+        }
+
         let mut stmts = rustc_ast::mut_visit::walk_flat_map_stmt(self, stmt);
-        if stmts.len() != 1 {
-            return stmts;
+        if stmts.len() == 1 {
+            let stmt = stmts.pop().unwrap();
+            stmts = hoisting::maybe_hoist_binding(self, stmt);
         }
 
-        let stmt = stmts.pop().unwrap();
-        hoisting::maybe_hoist_binding(self, stmt)
+        stmts.extend(probe);
+        stmts
     }
 }
@@ -2,12 +2,16 @@
 //! Methods, and Traits.
 //!
 //! Input and return types are recursively tupled, as defined by
-//! [types::recursively_transform_ast_type].
+//! [types::recursively_transform_ast_type], unless the function keeps its untagged signature (see
+//! [crate::callbacks::fn_headers]), either because of its header, or because it lies on the public
+//! API boundary.
 //!
 //! Bodies are walked and transformed, via the transformation defined in [crate::callbacks::instrument::expr].
 //!
 //! If the Instrument compilation encounters a function or method which the Gather pass determined
 //! should not be instrumented (via [crate::callbacks::gather::first_pass_info]), then the function is skipped.
+//! Functions the user skipped (see [crate::callbacks::gather::scope]) only have their calls to
+//! instrumented functions converted, see [crate::callbacks::instrument::skipped].
 //!
 //! Further, given that the Instrument compilation happens after the Gather compilation is able to
 //! run all code analysis, we know that the compiled crate is semantically correct. Therefore, it
@@ -17,15 +21,16 @@
 
 use rustc_ast_pretty::pprust;
 
+use crate::callbacks::fn_headers;
 use crate::callbacks::gather::{first_pass_info::FnNamespace, type_key};
-use crate::callbacks::instrument::{instrument_visitor::InstrumentingVisitor, types};
+use crate::callbacks::instrument::{instrument_visitor::InstrumentingVisitor, skipped, types};
 
 /// Walks the body, then wraps parameter and return types in `Tagged<T>`
 /// for free functions that pass 1 observed.
 pub fn transform_fn(visitor: &mut InstrumentingVisitor, fn_item: &mut rustc_ast::Item) {
     let rustc_ast::ItemKind::Fn(box rustc_ast::Fn {
         ident,
-        sig: rustc_ast::FnSig { decl, .. },
+        sig: rustc_ast::FnSig { header, decl, .. },
         body,
         ..
     }) = &mut fn_item.kind
@@ -40,14 +45,41 @@ pub fn transform_fn(visitor: &mut InstrumentingVisitor, fn_item: &mut rustc_ast:
         .lookup(&visitor.mod_path, FnNamespace::Free, ident.as_str())
         .is_none()
     {
+        if let Some(body) = body
+            && visitor
+                .first_pass
+                .fns
+                .is_skipped(&visitor.mod_path, FnNamespace::Free, ident.as_str())
+        {
+            skipped::transform_skipped_body(visitor, body);
+        }
         return;
     }
 
+    // `const fn`s still have to be evaluated, uninstrumented, in const contexts.
+    if matches!(header.constness, rustc_ast::Const::Yes(_))
+        && let Some(body) = body
+    {
+        visitor.const_eval_bodies.insert(fn_item.span, body.clone());
+    }
+
     // instrument the function body
     if let Some(body) = body {
         rustc_ast::mut_visit::walk_block(visitor, body);
     }
 
+    // `const fn`s, `extern "C" fn`s and the public API boundary keep their signature, their
+    // values are only tagged by the shim, within the body.
+    if fn_headers::ast_keeps_untagged_signature(header)
+        || visitor.first_pass.fns.preserves_abi(
+            &visitor.mod_path,
+            FnNamespace::Free,
+            ident.as_str(),
+        )
+    {
+        return;
+    }
+
     for param in &mut decl.inputs {
         // make every parameter binding mutable...
         if matches!(
@@ -126,6 +158,15 @@ pub fn transform_impl(visitor: &mut InstrumentingVisitor, impl_item: &mut rustc_
             )
             .is_none()
         {
+            if let Some(body) = body
+                && visitor.first_pass.fns.is_skipped(
+                    &visitor.mod_path,
+                    FnNamespace::Method(&type_key),
+                    ident.as_str(),
+                )
+            {
+                skipped::transform_skipped_body(visitor, body);
+            }
             continue;
         }
 
@@ -133,6 +174,15 @@ pub fn transform_impl(visitor: &mut InstrumentingVisitor, impl_item: &mut rustc_
             rustc_ast::mut_visit::walk_block(visitor, body);
         }
 
+        // methods on the public API boundary keep their signature, like free functions.
+        if visitor.first_pass.fns.preserves_abi(
+            &visitor.mod_path,
+            FnNamespace::Method(&type_key),
+            ident.as_str(),
+        ) {
+            continue;
+        }
+
         for param in &mut decl.inputs {
             if !matches!(param.ty.peel_refs().kind, rustc_ast::TyKind::ImplicitSelf) {
                 types::recursively_transform_ast_type(&mut param.ty);
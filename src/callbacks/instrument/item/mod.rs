@@ -14,8 +14,9 @@
 //! Trait definitions have default function implementations instrumented.
 //! Impl blocks have each method instrumented, and input parameter and return types transformed
 //! Items within submodules are recursively transformed.
+//! Definitions of crate-local expression macros have their transcribers instrumented.
 
-use crate::callbacks::instrument::instrument_visitor::InstrumentingVisitor;
+use crate::callbacks::instrument::{instrument_visitor::InstrumentingVisitor, macros};
 
 mod bodies;
 pub mod data_types;
@@ -41,6 +42,9 @@ pub fn transform_item<'session>(
         rustc_ast::ItemKind::Enum(..) => {
             data_types::transform_enum(visitor, item);
         },
+        rustc_ast::ItemKind::MacroDef(..) => {
+            macros::transform_macro_def(visitor, item);
+        },
 
         // recurse into submodules, updating the visitors
         // active mod path.
@@ -75,7 +79,6 @@ pub fn transform_item<'session>(
         | rustc_ast::ItemKind::Union(..)
         | rustc_ast::ItemKind::TraitAlias(..)
         | rustc_ast::ItemKind::MacCall(..)
-        | rustc_ast::ItemKind::MacroDef(..)
         | rustc_ast::ItemKind::Delegation(..)
         | rustc_ast::ItemKind::Mod(..)  // unloaded modules
         | rustc_ast::ItemKind::DelegationMac(..) => {}
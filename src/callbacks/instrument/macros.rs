@@ -0,0 +1,363 @@
+//! Defines how macro invocations, and the definitions of crate-local macros, are instrumented.
+//!
+//! The instrument compilation runs before expansion, where the arguments of a macro invocation
+//! are an opaque stream of tokens. Two kinds of macros have their arguments parsed as expressions
+//! and instrumented, see [`transform_mac_call`]:
+//! - Well-known std macros, see [`StdMacro`]. Where the macro needs the untagged value of an
+//!   argument (a condition, a repeat count, a value to format), it is untupled. Pass 1 recorded
+//!   which of the formatted arguments are tagged, alongside the variables captured by format
+//!   strings (`"{x}"`), which are passed explicitly (`x = (x).1`).
+//! - Crate-local expression macros (see [crate::callbacks::macros]), which pass 1 recorded in
+//!   `expression_macro_names`. Their transcribers are instrumented as well, see
+//!   [`transform_macro_def`], so that the code they expand into is.
+//!
+//! Instrumented arguments are spliced back into the source of the invocation, which is parsed
+//! again, leaving everything else (format strings, patterns) as it was written.
+//!
+//! Transcribers are instrumented once, for every invocation. Pass 1 records facts about the code
+//! within them at their position within the transcriber, which is shared by every expansion, see
+//! [crate::callbacks::gather::first_pass_info]. Expressions beginning or ending with a
+//! metavariable (e.g. `&$x`) span both the transcriber and the invocation once expanded, and
+//! are not matched with their facts.
+
+use rustc_ast::token::{Delimiter, Token, TokenKind};
+use rustc_ast::tokenstream::{DelimSpacing, DelimSpan, Spacing, TokenStream, TokenTree};
+use rustc_ast_pretty::pprust;
+
+use crate::callbacks::{
+    instrument::{expr, instrument_visitor::InstrumentingVisitor},
+    macros, parsing,
+};
+
+/// Prefix of the identifiers standing in for the metavariables of a transcriber (`$x` becomes
+/// `__ati_mv_x`), while it is instrumented.
+const METAVAR_PREFIX: &str = "__ati_mv_";
+
+/// Shape of the arguments of a well-known std macro.
+enum StdMacro {
+    /// `format!`, `println!`, `panic!`, ...: a format string followed by format arguments, each
+    /// untupled if tagged. `write!` / `writeln!` first take `leading` destination arguments.
+    Format { leading: usize },
+    /// `assert!` / `debug_assert!`: an untupled condition, optionally followed by a message.
+    Assert,
+    /// `assert_eq!`, `assert_ne!` and their debug variants: two compared values, optionally
+    /// followed by a message. The compared values stay tagged, as comparing them unions their ids.
+    AssertCmp,
+    /// `vec![a, b, c]` and `vec![elem; count]`, where `count` is untupled.
+    Vec,
+    /// `matches!(value, pattern)`, where `value` is untupled if tagged.
+    Matches,
+    /// `dbg!(a, b)`, whose arguments are printed tagged.
+    Dbg,
+}
+
+impl StdMacro {
+    /// The std macro invoked as `name!`, if well-known.
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "format" | "format_args" | "print" | "println" | "eprint" | "eprintln" | "panic"
+            | "unreachable" | "todo" | "unimplemented" => Some(Self::Format { leading: 0 }),
+            "write" | "writeln" => Some(Self::Format { leading: 1 }),
+            "assert" | "debug_assert" => Some(Self::Assert),
+            "assert_eq" | "assert_ne" | "debug_assert_eq" | "debug_assert_ne" => {
+                Some(Self::AssertCmp)
+            }
+            "vec" => Some(Self::Vec),
+            "matches" => Some(Self::Matches),
+            "dbg" => Some(Self::Dbg),
+            _ => None,
+        }
+    }
+}
+
+/// Instruments the arguments of the macro invocation `expr`. The `String` built by `format!` is
+/// tagged with a fresh id, like the value returned by an untracked call.
+pub fn transform_mac_call_expr(visitor: &mut InstrumentingVisitor, expr: &mut rustc_ast::Expr) {
+    let rustc_ast::ExprKind::MacCall(mac) = &mut expr.kind else {
+        panic!("Called transform_mac_call_expr with non-macro expression.");
+    };
+    transform_mac_call(visitor, mac);
+
+    if mac.path.segments.last().is_some_and(|seg| seg.ident.as_str() == "format")
+        && !visitor.first_pass.expression_macro_names.contains("format")
+    {
+        expr::tuple(expr);
+    }
+}
+
+/// Instruments the arguments of `mac`, if it invokes a well-known std macro, or a crate-local
+/// expression macro. Invocations of any other macro are left untouched.
+pub fn transform_mac_call(visitor: &mut InstrumentingVisitor, mac: &mut rustc_ast::MacCall) {
+    let first_pass = visitor.first_pass;
+    let psess = visitor.psess;
+    let sm = psess.source_map();
+    let Some(name) = mac.path.segments.last().map(|seg| seg.ident.to_string()) else {
+        return;
+    };
+
+    let mut edits = Vec::new();
+    let mut appended = Vec::new();
+    if mac.path.segments.len() == 1 && first_pass.expression_macro_names.contains(&name) {
+        let Some((mut args, _)) = parse_args(visitor, &mac.args.tokens, usize::MAX) else {
+            return;
+        };
+        for arg in args.iter_mut() {
+            edits.push(instrument_arg(visitor, arg, false));
+        }
+    } else {
+        let Some(kind) = StdMacro::from_name(&name) else {
+            return;
+        };
+        let limit = match kind {
+            // the pattern following the value is left as written.
+            StdMacro::Matches => 1,
+            _ => usize::MAX,
+        };
+        let Some((mut args, repeat)) = parse_args(visitor, &mac.args.tokens, limit) else {
+            return;
+        };
+
+        let (leading, format_args) = match kind {
+            StdMacro::Format { leading } => (vec![false; leading], true),
+            StdMacro::Assert => (vec![true], true),
+            StdMacro::AssertCmp => (vec![false, false], true),
+            StdMacro::Vec => (vec![false, repeat], false),
+            StdMacro::Matches => {
+                let tagged = args
+                    .first()
+                    .is_some_and(|arg| first_pass.tagged_macro_args.contains(arg.span, sm));
+                (vec![tagged], false)
+            }
+            StdMacro::Dbg => (Vec::new(), false),
+        };
+        for (i, arg) in args.iter_mut().enumerate() {
+            let untuple = match leading.get(i) {
+                Some(untuple) => *untuple,
+                // the format string is left as written.
+                None if format_args && i == leading.len() => continue,
+                None if format_args => first_pass.tagged_macro_args.contains(arg.span, sm),
+                None => false,
+            };
+            edits.push(instrument_arg(visitor, arg, untuple));
+        }
+
+        if let Some(captures) = first_pass.format_captures.get(mac.span(), sm) {
+            appended.extend(captures.iter().map(|name| format!("{name} = ({name}).1")));
+        }
+    }
+
+    let args_span = mac.args.dspan.entire();
+    let Some(mut src) = splice_source(sm, args_span, edits) else {
+        return;
+    };
+    if !appended.is_empty() {
+        let close = src
+            .pop()
+            .unwrap_or_else(|| panic!("Macro arguments have no delimiter."));
+        let separator = if src.trim_end().ends_with(',') || src.len() == 1 {
+            ""
+        } else {
+            ", "
+        };
+        src = format!("{src}{separator}{}{close}", appended.join(", "));
+    }
+
+    let path = pprust::path_to_string(&mac.path);
+    let rustc_ast::ExprKind::MacCall(parsed) =
+        parsing::parse_expr(psess, format!("{path}!{src}")).kind
+    else {
+        panic!("Instrumented invocation of `{path}!` did not parse as a macro invocation.");
+    };
+    mac.args = parsed.args;
+}
+
+/// Instruments the transcribers of `item`, the definition of a crate-local macro, if pass 1
+/// recorded it as an expression macro.
+///
+/// Each transcriber is parsed as the statements of a block, with metavariables standing in for
+/// the expressions they are bound to, and instrumented like any other block.
+pub fn transform_macro_def(visitor: &mut InstrumentingVisitor, item: &mut rustc_ast::Item) {
+    let psess = visitor.psess;
+    let sm = psess.source_map();
+    if !visitor.first_pass.expression_macros.contains(item.span, sm) {
+        return;
+    }
+    let rustc_ast::ItemKind::MacroDef(ident, def) = &mut item.kind else {
+        panic!("Called transform_macro_def with non-macro item.");
+    };
+    let Some(rules) = macros::macro_rules(&def.body.tokens) else {
+        return;
+    };
+
+    let mut edits = Vec::new();
+    for rule in rules {
+        let Some(mut block) = parse_transcriber(psess, rule.transcriber) else {
+            return;
+        };
+        expr::transform_expr(visitor, &mut block);
+
+        let src = pprust::expr_to_string(&block).replace(METAVAR_PREFIX, "$");
+        let stmts = src
+            .trim()
+            .strip_prefix('{')
+            .and_then(|src| src.strip_suffix('}'))
+            .unwrap_or_else(|| panic!("Instrumented transcriber of `{ident}!` is not a block."));
+        let span = rule
+            .transcriber_span
+            .open
+            .between(rule.transcriber_span.close);
+        edits.push((span, stmts.to_string()));
+    }
+
+    let Some(body) = splice_source(sm, def.body.dspan.entire(), edits) else {
+        return;
+    };
+    let terminator = if def.body.delim == Delimiter::Brace {
+        ""
+    } else {
+        ";"
+    };
+    let items = parsing::parse_items(
+        psess,
+        format!("macro_rules! {ident} {body}{terminator}"),
+        None,
+    );
+    let Some(rustc_ast::ItemKind::MacroDef(_, parsed)) = items.into_iter().next().map(|i| i.kind)
+    else {
+        panic!("Instrumented definition of `{ident}!` did not parse as a macro definition.");
+    };
+    def.body = parsed.body;
+}
+
+/// Parses at most `limit` comma-separated expressions from the arguments `tokens` of a macro
+/// invocation. Named format arguments (`name = expr`) and `vec!`'s `elem; count` are accepted,
+/// the latter being returned as `true`.
+///
+/// Returns `None` if any argument is not an expression, or if tokens follow the last argument
+/// (unless `limit` arguments were parsed).
+fn parse_args(
+    visitor: &InstrumentingVisitor,
+    tokens: &TokenStream,
+    limit: usize,
+) -> Option<(Vec<rustc_ast::Expr>, bool)> {
+    let mut parser = rustc_parse::parser::Parser::new(visitor.psess, tokens.clone(), None);
+    let mut args = Vec::new();
+    let mut repeat = false;
+    while parser.token.kind != TokenKind::Eof && args.len() < limit {
+        if parser.token.ident().is_some() && parser.look_ahead(1, |t| t.kind == TokenKind::Eq) {
+            parser.bump();
+            parser.bump();
+        }
+        match parser.parse_expr() {
+            Ok(arg) => args.push(*arg),
+            Err(diag) => {
+                diag.cancel();
+                return None;
+            }
+        }
+
+        match parser.token.kind {
+            TokenKind::Comma => parser.bump(),
+            TokenKind::Semi if args.len() == 1 && !repeat => {
+                repeat = true;
+                parser.bump();
+            }
+            _ if parser.token.kind == TokenKind::Eof || args.len() == limit => {}
+            _ => return None,
+        }
+    }
+    Some((args, repeat))
+}
+
+/// Instruments `arg`, returning its original span alongside the source replacing it, which is
+/// untupled if `untuple` is set.
+fn instrument_arg(
+    visitor: &mut InstrumentingVisitor,
+    arg: &mut rustc_ast::Expr,
+    untuple: bool,
+) -> (rustc_span::Span, String) {
+    let span = arg.span;
+    expr::transform_expr(visitor, arg);
+
+    let src = pprust::expr_to_string(arg);
+    (span, if untuple { format!("({src}).1") } else { src })
+}
+
+/// Returns the source of `span`, with the source of each of `edits` (spans within `span`)
+/// replaced. Returns `None` if the source isn't available, e.g. within generated code.
+fn splice_source(
+    sm: &rustc_span::source_map::SourceMap,
+    span: rustc_span::Span,
+    mut edits: Vec<(rustc_span::Span, String)>,
+) -> Option<String> {
+    let mut src = sm.span_to_snippet(span).ok()?;
+    edits.sort_by_key(|(edit, _)| edit.lo());
+    for (edit, replacement) in edits.into_iter().rev() {
+        if !span.contains(edit) {
+            return None;
+        }
+        let lo = (edit.lo() - span.lo()).0 as usize;
+        let hi = (edit.hi() - span.lo()).0 as usize;
+        src.replace_range(lo..hi, &replacement);
+    }
+    Some(src)
+}
+
+/// Parses the tokens of a transcriber as the statements of a block, with each metavariable `$x`
+/// replaced by the identifier `__ati_mv_x`. Returns `None` if they aren't valid statements.
+fn parse_transcriber(
+    psess: &rustc_session::parse::ParseSess,
+    transcriber: &TokenStream,
+) -> Option<rustc_ast::Expr> {
+    let block = TokenTree::Delimited(
+        DelimSpan::dummy(),
+        DelimSpacing::new(Spacing::Alone, Spacing::Alone),
+        Delimiter::Brace,
+        replace_metavars(transcriber),
+    );
+    let mut parser = rustc_parse::parser::Parser::new(psess, TokenStream::new(vec![block]), None);
+    match parser.parse_expr() {
+        Ok(block) if parser.token.kind == TokenKind::Eof => Some(*block),
+        Ok(_) => None,
+        Err(diag) => {
+            diag.cancel();
+            None
+        }
+    }
+}
+
+/// Replaces each metavariable `$x` within `tokens` by the identifier `__ati_mv_x`, spanning
+/// `$x`.
+fn replace_metavars(tokens: &TokenStream) -> TokenStream {
+    let mut trees = Vec::new();
+    let mut iter = tokens.iter().peekable();
+    while let Some(tree) = iter.next() {
+        match tree {
+            TokenTree::Token(dollar, _) if dollar.kind == TokenKind::Dollar => match iter.peek() {
+                Some(TokenTree::Token(name, spacing)) if name.ident().is_some() => {
+                    let (ident, _) = name.ident().unwrap();
+                    let placeholder = rustc_span::Ident::new(
+                        rustc_span::Symbol::intern(&format!("{METAVAR_PREFIX}{}", ident.name)),
+                        dollar.span.to(name.span),
+                    );
+                    trees.push(TokenTree::Token(
+                        Token::from_ast_ident(placeholder),
+                        *spacing,
+                    ));
+                    iter.next();
+                }
+                _ => trees.push(tree.clone()),
+            },
+            TokenTree::Delimited(span, spacing, delim, inner) => {
+                trees.push(TokenTree::Delimited(
+                    *span,
+                    *spacing,
+                    *delim,
+                    replace_metavars(inner),
+                ));
+            }
+            TokenTree::Token(..) => trees.push(tree.clone()),
+        }
+    }
+    TokenStream::new(trees)
+}
@@ -11,29 +11,37 @@
 //! 2. Define new function shims to perform site management, associating formals, return values,
 //!    and globals with each ENTER and EXIT program point declared with the `.decls` file.
 //! 3. Generate necessary trait implementations for user-defined compound types.
-//! 4. Inject the runtime library into the crate root file, and import the root file in all
-//!    dependancy files to make all ATI-specific types and globals available everywhere within the
-//!    crate.
+//! 4. Link the runtime library (the `datir_runtime` crate) into the crate root file, and import
+//!    its prelude in every module so generated method calls on runtime traits resolve. All other
+//!    runtime items are referred to through fully qualified paths, see [codegen::runtime].
 //! 5. Inject into the crate root file feature attributes to make necessary unstable features
 //!    available for use.
 //!
 //! Steps 1-3 happen within the [`TransformingFileLoader`] constructed within the below `config`
 //! callback of [`TransformAbstractSyntaxTreeCallbacks`]. Step 4 is split between the code 
-//! generation step within the file loader (for all root imports within dependancies), and 
-//! `after_crate_root_parsing` (to inject the runtime library) into the main file. Step 5 also 
+//! generation step within the file loader (for the prelude imports within every module), and 
+//! `after_crate_root_parsing` (to declare the runtime crate) in the main file. Step 5 also 
 //! takes place in `after_crate_root_parsing`.
+//!
+//! Should the instrumented crate fail to compile, errors are noted with the original location of
+//! the failing code, and with the transformation which generated it, see [diagnostics].
 
+mod diagnostics;
 mod expr;
 mod file_loader;
 mod hoisting;
 mod instrument_visitor;
 mod item;
+mod macros;
+mod probes;
+mod skipped;
 mod types;
 
 use crate::{
-    callbacks::codegen::{self, define_types},
+    callbacks::codegen::{self, runtime},
     callbacks::gather::first_pass_info::FirstPassInfo,
-    callbacks::instrument::file_loader::{Passes, TransformingFileLoader},
+    callbacks::instrument::diagnostics::OriginNotingEmitter,
+    callbacks::instrument::file_loader::{Passes, SpanMaps, TransformingFileLoader},
     callbacks::instrument::instrument_visitor::InstrumentingVisitor,
     config::DatirConfig,
 };
@@ -46,6 +54,9 @@ const REQUIRED_CRATE_ATTRIBUTES: &[&str] = &[
     "#![feature(unsize)]",
     "#![feature(coerce_unsized)]",
     "#![feature(random)]", // only used when --release is specified
+    "#![feature(core_intrinsics)]", // only used by `const fn` shims
+    "#![feature(const_eval_select)]",
+    "#![allow(internal_features)]",
 ];
 
 /// Callback struct used to transform the ASTs of all instrumented files.
@@ -67,7 +78,8 @@ impl TransformAbstractSyntaxTreeCallbacks {
 impl rustc_driver::Callbacks for TransformAbstractSyntaxTreeCallbacks {
     /// Define the transformations performed by the custom file loader,
     /// and then register this compiler invocation to use it instead of the
-    /// default one.
+    /// default one. Also wraps the diagnostic emitter, so that errors within transformed files
+    /// point back to the original source.
     fn config(&mut self, config: &mut rustc_interface::interface::Config) {
         let first_pass = self.first_pass.clone();
         let datir_config = self.config.clone();
@@ -85,28 +97,42 @@ impl rustc_driver::Callbacks for TransformAbstractSyntaxTreeCallbacks {
                 rustc_ast::mut_visit::MutVisitor::visit_crate(&mut visitor, krate);
 
                 // create all required function stubs, which perform site management
-                codegen::generate_shims(&datir_config, &first_pass, krate, module_path, psess);
+                codegen::generate_shims(
+                    &datir_config,
+                    &first_pass,
+                    &visitor.const_eval_bodies,
+                    krate,
+                    module_path,
+                    psess,
+                );
             },
         ));
 
         // use custom file loader to run passes over AST before continuing compilation
+        let span_maps = std::sync::Arc::new(SpanMaps::default());
         config.file_loader = Some(Box::new(TransformingFileLoader::new(
             passes,
             self.config.clone(),
+            span_maps.clone(),
         )));
+
+        // map diagnostics pointing at the transformed sources back to the original ones
+        config.psess_created = Some(Box::new(move |psess| {
+            OriginNotingEmitter::install(psess, span_maps)
+        }));
     }
 
-    /// Defines necessary types (namely `Tagged<T>`, but also globals like `ATI_ANALYSIS`)
-    /// in the root file. All other files will import these types from the root.
-    /// Further enables all necessary unstable features.
+    /// Declares the runtime crate (which defines `Tagged<T>`, but also globals like
+    /// `ATI_ANALYSIS`) in the root file, enables all necessary unstable features, and declares
+    /// the probe syntax for any probe left unresolved.
     fn after_crate_root_parsing(
         &mut self,
         compiler: &rustc_interface::interface::Compiler,
         krate: &mut rustc_ast::Crate,
     ) -> rustc_driver::Compilation {
-        let cwd = std::env::current_dir().unwrap();
-        inject_files_in_dir(&cwd.join("src/ati"), &compiler.sess.psess, krate);
+        runtime::link_runtime_crate(&compiler.sess.psess, krate);
         inject_crate_attributes(&compiler.sess.psess, krate);
+        codegen::probes::declare_probe_syntax(&compiler.sess.psess, krate);
 
         rustc_driver::Compilation::Continue
     }
@@ -129,34 +155,9 @@ impl rustc_driver::Callbacks for TransformAbstractSyntaxTreeCallbacks {
     }
 }
 
-/// Injects every `.rs` file in `dir` (except `mod.rs`) into `krate` via
-/// [`define_types::define_types_from_file`].
-fn inject_files_in_dir(
-    dir: &std::path::Path,
-    psess: &rustc_session::parse::ParseSess,
-    krate: &mut rustc_ast::Crate,
-) {
-    let entries = std::fs::read_dir(dir)
-        .unwrap_or_else(|e| panic!("failed to read ati directory {dir:?}: {e}"));
-
-    for entry in entries {
-        let path = entry.unwrap().path();
-        // skip non .rs files
-        if path.extension().and_then(|s| s.to_str()) != Some("rs") {
-            continue;
-        }
-
-        // skip the mod.rs file
-        if path.file_name().and_then(|s| s.to_str()) == Some("mod.rs") {
-            continue;
-        }
-        define_types::define_types_from_file(&path, psess, krate);
-    }
-}
-
 /// Injects every attribute in [`REQUIRED_CRATE_ATTRIBUTES`] into `krate`.
 fn inject_crate_attributes(psess: &rustc_session::parse::ParseSess, krate: &mut rustc_ast::Crate) {
     for attr in REQUIRED_CRATE_ATTRIBUTES {
-        define_types::add_crate_attribute(attr, psess, krate);
+        runtime::add_crate_attribute(attr, psess, krate);
     }
 }
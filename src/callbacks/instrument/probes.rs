@@ -0,0 +1,121 @@
+//! Defines functions used by the [`InstrumentingVisitor`] to replace user-placed probe points
+//! with site updates.
+//!
+//! Probes are found within the statements of a body, before expansion:
+//! - `datir_probe!("name", a, b);` is replaced by an update of the probe's site, binding `a` and
+//!   `b`, see [`expand_probe_macro`].
+//! - `#[datir::probe("name")] let <pat> = ..;` is followed by an update of the probe's site,
+//!   binding every variable `<pat>` introduces, see [`probe_after_let`].
+//!
+//! Only probes for which the gather pass recorded an enclosing function are replaced. See
+//! [crate::callbacks::codegen::probes] for the probe syntax, and the names of probe sites.
+
+use rustc_ast_pretty::pprust;
+
+use crate::callbacks::{
+    codegen::probes::{self, DATIR_TOOL, PROBE_ATTR, PROBE_MACRO},
+    instrument::instrument_visitor::InstrumentingVisitor,
+    parsing,
+};
+
+/// If `stmt` is a `datir_probe!` invocation, returns the statement replacing it.
+pub fn expand_probe_macro(
+    visitor: &InstrumentingVisitor,
+    stmt: &rustc_ast::Stmt,
+) -> Option<rustc_ast::Stmt> {
+    let rustc_ast::StmtKind::MacCall(mac_stmt) = &stmt.kind else {
+        return None;
+    };
+    let mac = &mac_stmt.mac;
+    if !(mac.path.segments.len() == 1 && mac.path.segments[0].ident.as_str() == PROBE_MACRO) {
+        return None;
+    }
+    let probe = visitor
+        .first_pass
+        .probes
+        .get(mac.span(), visitor.psess.source_map())?;
+
+    // the arguments were matched by the macro in pass 1, parse them as the elements of a tuple.
+    let args = pprust::tts_to_string(&mac.args.tokens);
+    let args = parsing::parse_expr(
+        visitor.psess,
+        format!("({},)", args.trim_end().trim_end_matches(',')),
+    );
+    let rustc_ast::ExprKind::Tup(args) = args.kind else {
+        panic!("Arguments of `{PROBE_MACRO}!` did not parse as a tuple.");
+    };
+    let (name, vars) = args
+        .split_first()
+        .unwrap_or_else(|| panic!("`{PROBE_MACRO}!` is missing a probe name."));
+
+    let vars = vars
+        .iter()
+        .map(|var| pprust::expr_to_string(var))
+        .collect::<Vec<_>>();
+    Some(probes::build_probe_stmt(
+        visitor.psess,
+        &probe.base_ppt_name,
+        &probe_name(name),
+        &vars,
+    ))
+}
+
+/// If `stmt` is a `let` statement carrying a `#[datir::probe]` attribute, returns the statement
+/// which must follow it.
+pub fn probe_after_let(
+    visitor: &InstrumentingVisitor,
+    stmt: &rustc_ast::Stmt,
+) -> Option<rustc_ast::Stmt> {
+    use rustc_ast::attr::AttributeExt;
+
+    let rustc_ast::StmtKind::Let(local) = &stmt.kind else {
+        return None;
+    };
+    let attr = local.attrs.iter().find(|attr| {
+        attr.path_matches(&[
+            rustc_span::Symbol::intern(DATIR_TOOL),
+            rustc_span::Symbol::intern(PROBE_ATTR),
+        ])
+    })?;
+    let probe = visitor
+        .first_pass
+        .probes
+        .get(local.span, visitor.psess.source_map())?;
+
+    let name = match attr.meta_item_list().as_deref() {
+        Some(
+            [
+                rustc_ast::MetaItemInner::Lit(rustc_ast::MetaItemLit {
+                    kind: rustc_ast::LitKind::Str(name, _),
+                    ..
+                }),
+            ],
+        ) => name.to_string(),
+        _ => panic!("`#[{DATIR_TOOL}::{PROBE_ATTR}]` expects a single string literal name."),
+    };
+
+    let mut vars = Vec::new();
+    local.pat.walk(&mut |pat| {
+        if let rustc_ast::PatKind::Ident(_, ident, _) = &pat.kind {
+            vars.push(ident.to_string());
+        }
+        true
+    });
+    Some(probes::build_probe_stmt(
+        visitor.psess,
+        &probe.base_ppt_name,
+        &name,
+        &vars,
+    ))
+}
+
+/// Returns the name of a probe, given the first argument of its `datir_probe!` invocation.
+fn probe_name(name: &rustc_ast::Expr) -> String {
+    let rustc_ast::ExprKind::Lit(lit) = &name.kind else {
+        panic!("`{PROBE_MACRO}!` expects a string literal as the probe name.");
+    };
+    match rustc_ast::LitKind::from_token_lit(*lit) {
+        Ok(rustc_ast::LitKind::Str(name, _)) => name.to_string(),
+        _ => panic!("`{PROBE_MACRO}!` expects a string literal as the probe name."),
+    }
+}
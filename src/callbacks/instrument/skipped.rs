@@ -0,0 +1,52 @@
+//! Defines how the bodies of skipped functions (see [crate::callbacks::gather::scope]) are
+//! transformed.
+//!
+//! Skipped functions are compiled as they were written, so their values stay untagged. The only
+//! exceptions are the calls they make to instrumented functions, which take and return tagged
+//! values, and the fields of the crate's structs, which hold tagged values. Pass 1 recorded each
+//! of these calls, which are converted at the boundary (see [`expr::transform_call_from_skipped`]),
+//! and each of these field accesses, which are untupled.
+
+use rustc_ast::mut_visit::MutVisitor;
+
+use crate::callbacks::instrument::{expr, instrument_visitor::InstrumentingVisitor};
+
+/// Converts every call to an instrumented function within `body`, the body of a skipped
+/// function, and untuples its accesses to tagged fields, leaving the rest of it untouched.
+pub fn transform_skipped_body(visitor: &InstrumentingVisitor, body: &mut rustc_ast::Block) {
+    SkippedBodyVisitor { visitor }.visit_block(body);
+}
+
+/// Visitor rewriting the calls recorded in `calls_from_skipped`, and the field accesses recorded
+/// in `skipped_field_accesses`.
+struct SkippedBodyVisitor<'a, 'b> {
+    visitor: &'a InstrumentingVisitor<'b>,
+}
+
+impl MutVisitor for SkippedBodyVisitor<'_, '_> {
+    fn visit_expr(&mut self, expr: &mut rustc_ast::Expr) {
+        rustc_ast::mut_visit::walk_expr(self, expr);
+
+        let sm = self.visitor.psess.source_map();
+        if matches!(expr.kind, rustc_ast::ExprKind::Field(..))
+            && self
+                .visitor
+                .first_pass
+                .skipped_field_accesses
+                .contains(expr.span, sm)
+        {
+            expr::untuple(expr);
+            return;
+        }
+
+        let span = match &expr.kind {
+            rustc_ast::ExprKind::Call(func, _) => func.span,
+            rustc_ast::ExprKind::MethodCall(call) => call.seg.ident.span,
+            _ => return,
+        };
+        let Some(call) = self.visitor.first_pass.calls_from_skipped.get(span, sm) else {
+            return;
+        };
+        expr::transform_call_from_skipped(self.visitor.psess, expr, call);
+    }
+}
@@ -11,8 +11,11 @@
 //! pointer, DATIR currently only supports slices that are stored behind references (as opposed
 //! to other pointer types, like `Box`/`Arc`/etc). A reference to a slice (`&[T]`) becomes
 //! `TaggedRef<[T]>`, after recursively tupling the inner type.
+//!
+//! Strings are tupled as a whole: `String` becomes `Tagged<String>`, and `&str` becomes
+//! `TaggedRef<str>`. Like slices, a `str` behind any other pointer type is left untagged.
 
-use crate::callbacks::types::CanBeTupled;
+use crate::callbacks::{codegen::runtime, types::CanBeTupled};
 
 mod array;
 mod path;
@@ -28,8 +31,10 @@ pub fn recursively_transform_ast_type(target_ty: &mut rustc_ast::Ty) {
         return;
     }
 
-    // we recursed down to a simple primitive!
-    if target_ty.can_be_tupled() {
+    // we recursed down to a simple primitive! A bare `str` is left as is, like a slice, as it
+    // can only be tagged behind a reference.
+    if target_ty.can_be_tupled() && target_ty.kind.is_simple_path() != Some(rustc_span::sym::str)
+    {
         transform_primitive(target_ty);
         return;
     }
@@ -89,25 +94,15 @@ pub fn recursively_transform_ast_type(target_ty: &mut rustc_ast::Ty) {
 /// Converts an atomic primitive type `T` to a `Tagged<T>` in place.
 /// This is the base case op for the recursive tupling op.
 pub(super) fn transform_primitive(ty: &mut rustc_ast::Ty) {
+    let args = rustc_ast::AngleBracketed(rustc_ast::AngleBracketedArgs {
+        span: rustc_span::DUMMY_SP,
+        args: [rustc_ast::AngleBracketedArg::Arg(rustc_ast::GenericArg::Type(
+            Box::new(ty.clone()),
+        ))]
+        .into(),
+    });
     ty.kind = rustc_ast::TyKind::Path(
         None,
-        rustc_ast::Path {
-            segments: [rustc_ast::PathSegment {
-                ident: rustc_span::Ident::from_str("Tagged"),
-                id: rustc_ast::DUMMY_NODE_ID,
-                args: Some(Box::new(rustc_ast::AngleBracketed(
-                    rustc_ast::AngleBracketedArgs {
-                        span: rustc_span::DUMMY_SP,
-                        args: [rustc_ast::AngleBracketedArg::Arg(
-                            rustc_ast::GenericArg::Type(Box::new(ty.clone())),
-                        )]
-                        .into(),
-                    },
-                ))),
-            }]
-            .into(),
-            span: rustc_span::DUMMY_SP,
-            tokens: None,
-        },
+        runtime::runtime_ast_path(&["Tagged"], Some(Box::new(args))),
     );
 }
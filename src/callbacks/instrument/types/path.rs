@@ -51,9 +51,18 @@ pub fn transform_path(target_ty: &mut rustc_ast::Ty) {
                             rustc_ast::GenericArg::Const(_)
                             | rustc_ast::GenericArg::Lifetime(_) => {}
                         },
-                        rustc_ast::AngleBracketedArg::Constraint(_) => {
-                            todo!("Constraint is a trait?")
+                        // `Iterator<Item = u32>`, the constrained type is tupled like any
+                        // other generic argument. Bound constraints carry no types to tuple.
+                        rustc_ast::AngleBracketedArg::Constraint(rustc_ast::AssocItemConstraint {
+                            kind:
+                                rustc_ast::AssocItemConstraintKind::Equality {
+                                    term: rustc_ast::Term::Ty(ty),
+                                },
+                            ..
+                        }) => {
+                            recursively_transform_ast_type(ty);
                         }
+                        rustc_ast::AngleBracketedArg::Constraint(_) => {}
                     }
                 }
             }
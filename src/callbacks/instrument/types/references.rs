@@ -13,7 +13,8 @@
 use rustc_ast_pretty::pprust;
 
 use crate::{
-    callbacks::instrument::types::recursively_transform_ast_type, callbacks::types::CanBeTupled,
+    callbacks::codegen::runtime, callbacks::instrument::types::recursively_transform_ast_type,
+    callbacks::types::CanBeTupled,
 };
 
 /// Recursively transforms a reference type, taking &T -> TaggedRef<Tag(T)> when necessary,
@@ -74,10 +75,6 @@ fn wrap_ty_as_tagged_ref(outer_ty: &mut rustc_ast::Ty, mutable: bool) {
         ),
     };
 
-    // Construct new name of wrapped type
-    let name = if mutable { "TaggedRefMut" } else { "TaggedRef" };
-    let mut seg = rustc_ast::PathSegment::from_ident(rustc_span::Ident::from_str(name));
-
     // Construct generic type parameters of TaggedRef
     let mut args: Vec<rustc_ast::AngleBracketedArg> = Vec::new();
     if let Some(lt) = lifetime {
@@ -88,20 +85,15 @@ fn wrap_ty_as_tagged_ref(outer_ty: &mut rustc_ast::Ty, mutable: bool) {
     args.push(rustc_ast::AngleBracketedArg::Arg(
         rustc_ast::GenericArg::Type(Box::new(inner)),
     ));
-    seg.args = Some(Box::new(rustc_ast::GenericArgs::AngleBracketed(
-        rustc_ast::AngleBracketedArgs {
-            span: rustc_span::DUMMY_SP,
-            args: args.into(),
-        },
-    )));
+    let args = rustc_ast::GenericArgs::AngleBracketed(rustc_ast::AngleBracketedArgs {
+        span: rustc_span::DUMMY_SP,
+        args: args.into(),
+    });
 
     // Write transformed type into target
+    let name = if mutable { "TaggedRefMut" } else { "TaggedRef" };
     outer_ty.kind = rustc_ast::TyKind::Path(
         None,
-        rustc_ast::Path {
-            span: rustc_span::DUMMY_SP,
-            segments: [seg].into(),
-            tokens: None,
-        },
+        runtime::runtime_ast_path(&[name], Some(Box::new(args))),
     );
 }
@@ -0,0 +1,106 @@
+//! Provides helpers shared by both compilations to recognize crate-local `macro_rules!` macros
+//! whose expansions can be instrumented, called expression macros.
+//!
+//! The Instrument compilation runs before expansion, where a macro definition is an opaque stream
+//! of tokens. An expression macro is one where:
+//! - Every rule matches a (possibly empty) comma-separated list of `$name:expr` fragments, e.g.
+//!   `($a:expr, $b:expr) => { .. }`. Every invocation then parses as a list of expressions.
+//! - No transcriber contains a repetition (`$( .. )*`). Once each `$name` is replaced with a plain
+//!   identifier, every transcriber then parses as the statements of a block.
+//!
+//! The Gather compilation further excludes the macros which are expanded anywhere but within an
+//! instrumented function body, see [crate::callbacks::gather::analyze_hir].
+
+use rustc_ast::token::TokenKind;
+use rustc_ast::tokenstream::{DelimSpan, TokenStream, TokenTree};
+
+/// A single `(matcher) => { transcriber }` rule of a `macro_rules!` definition.
+pub struct MacroRule<'a> {
+    /// Tokens within the delimiters of the matcher.
+    pub matcher: &'a TokenStream,
+    /// Tokens within the delimiters of the transcriber.
+    pub transcriber: &'a TokenStream,
+    /// Spans of the delimiters of the transcriber.
+    pub transcriber_span: DelimSpan,
+}
+
+/// Splits the body of a `macro_rules!` definition into its rules, returning `None` if the body
+/// is malformed.
+pub fn macro_rules(body: &TokenStream) -> Option<Vec<MacroRule<'_>>> {
+    let mut rules = Vec::new();
+    let mut trees = body.iter();
+    while let Some(matcher) = trees.next() {
+        let TokenTree::Delimited(.., matcher) = matcher else {
+            return None;
+        };
+        let Some(TokenTree::Token(arrow, _)) = trees.next() else {
+            return None;
+        };
+        let Some(TokenTree::Delimited(transcriber_span, _, _, transcriber)) = trees.next() else {
+            return None;
+        };
+        if arrow.kind != TokenKind::FatArrow {
+            return None;
+        }
+        rules.push(MacroRule {
+            matcher,
+            transcriber,
+            transcriber_span: *transcriber_span,
+        });
+
+        match trees.next() {
+            Some(TokenTree::Token(semi, _)) if semi.kind == TokenKind::Semi => {}
+            None => break,
+            Some(_) => return None,
+        }
+    }
+    Some(rules)
+}
+
+/// Whether `def` is a `macro_rules!` expression macro, see the module documentation.
+pub fn is_expression_macro(def: &rustc_ast::MacroDef) -> bool {
+    if !def.macro_rules {
+        return false;
+    }
+    let Some(rules) = macro_rules(&def.body.tokens) else {
+        return false;
+    };
+    !rules.is_empty()
+        && rules.iter().all(|rule| {
+            matches_expression_list(rule.matcher) && !contains_repetition(rule.transcriber)
+        })
+}
+
+/// Whether `matcher` is a comma-separated list of `$name:expr` fragments.
+fn matches_expression_list(matcher: &TokenStream) -> bool {
+    let trees = matcher.iter().collect::<Vec<_>>();
+    trees
+        .split(|tree| is_token(tree, TokenKind::Comma))
+        .all(|fragment| {
+            matches!(
+                fragment,
+                [dollar, TokenTree::Token(name, _), colon, TokenTree::Token(kind, _)]
+                    if is_token(dollar, TokenKind::Dollar)
+                        && name.ident().is_some()
+                        && is_token(colon, TokenKind::Colon)
+                        && kind.ident().is_some_and(|(kind, _)| kind.as_str() == "expr")
+            )
+        })
+        || trees.is_empty()
+}
+
+/// Whether `transcriber` contains a `$( .. )` repetition, at any depth.
+fn contains_repetition(transcriber: &TokenStream) -> bool {
+    let trees = transcriber.iter().collect::<Vec<_>>();
+    trees.windows(2).any(|pair| {
+        is_token(pair[0], TokenKind::Dollar) && matches!(pair[1], TokenTree::Delimited(..))
+    }) || trees.iter().any(|tree| match tree {
+        TokenTree::Delimited(.., inner) => contains_repetition(inner),
+        TokenTree::Token(..) => false,
+    })
+}
+
+/// Whether `tree` is a single token of kind `kind`.
+fn is_token(tree: &TokenTree, kind: TokenKind) -> bool {
+    matches!(tree, TokenTree::Token(token, _) if token.kind == kind)
+}
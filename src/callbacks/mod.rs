@@ -10,8 +10,10 @@
 //! and traits are implemented for user-defined types. This final step is governed by the codegen 
 //! module.
 
-mod codegen;
+pub mod codegen;
+mod fn_headers;
 pub mod gather;
 pub mod instrument;
+mod macros;
 pub mod parsing;
-mod types;
+pub mod types;
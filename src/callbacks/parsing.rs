@@ -1,7 +1,17 @@
 //! Provides helper functions to construct rustc expressions/items/etc from Strings, used by
 //! [crate::callbacks::codegen], [crate::callbacks::instrument].
+//!
+//! Code parsed without a file path is generated by DATIR. Its spans are placed within a
+//! [`rustc_span::FileName::Custom`] source file, whose name records where within DATIR the code
+//! was generated (see [`generated_source_name`]). This lets diagnostics pointing at generated
+//! code name the transformation responsible for it, see
+//! [crate::callbacks::instrument::diagnostics].
+
+/// Prefix of the names given to the source files of generated code.
+pub const GENERATED_SOURCE_PREFIX: &str = "datir:";
 
 /// Construct a rustc parser within the current parse session.
+#[track_caller]
 fn create_parser<'a>(
     psess: &'a rustc_session::parse::ParseSess,
     contents: String,
@@ -15,7 +25,10 @@ fn create_parser<'a>(
             Some(path) => {
                 rustc_span::FileName::Real(rustc_span::RealFileName::from_virtual_path(path))
             }
-            None => rustc_span::FileName::anon_source_code(&contents),
+            None => rustc_span::FileName::Custom(generated_source_name(
+                std::panic::Location::caller(),
+                &contents,
+            )),
         },
         contents,
         rustc_parse::lexer::StripTokens::Nothing,
@@ -23,7 +36,47 @@ fn create_parser<'a>(
     .unwrap()
 }
 
+/// Names the source file holding code generated at `location`, e.g.
+/// `datir:src/callbacks/instrument/expr/range.rs:45#1f2e...`.
+///
+/// The source map deduplicates source files by name, so the name must also include a hash of
+/// `contents`, as the same location generates different code for every expression it handles.
+fn generated_source_name(location: &std::panic::Location<'_>, contents: &str) -> String {
+    use std::hash::{Hash, Hasher};
+
+    let mut hasher = std::hash::DefaultHasher::new();
+    contents.hash(&mut hasher);
+    format!(
+        "{GENERATED_SOURCE_PREFIX}{}:{}#{:016x}",
+        location.file(),
+        location.line(),
+        hasher.finish()
+    )
+}
+
+/// Recovers the DATIR transformation which generated code within the source file `name`,
+/// returning `None` if the file does not hold generated code.
+///
+/// The transformation is named by the DATIR module that generated the code
+/// (e.g. `instrument::expr::range`), followed by the exact location within that module.
+pub fn generating_transformation(name: &rustc_span::FileName) -> Option<String> {
+    let rustc_span::FileName::Custom(name) = name else {
+        return None;
+    };
+    let location = name.strip_prefix(GENERATED_SOURCE_PREFIX)?;
+    let location = location.split_once('#').map_or(location, |(location, _)| location);
+    let (file, _line) = location.rsplit_once(':')?;
+
+    let module = file
+        .trim_start_matches("src/callbacks/")
+        .trim_end_matches(".rs")
+        .trim_end_matches("/mod")
+        .replace('/', "::");
+    Some(format!("{module} ({location})"))
+}
+
 /// Parses a string `contents` into a vector of rustc_ast::Items.
+#[track_caller]
 pub fn parse_items(
     psess: &rustc_session::parse::ParseSess,
     contents: String,
@@ -52,6 +105,7 @@ pub fn parse_items(
 }
 
 /// Parses a string `contents` into a rustc_ast::Expr.
+#[track_caller]
 pub fn parse_expr(psess: &rustc_session::parse::ParseSess, contents: String) -> rustc_ast::Expr {
     let mut parser = create_parser(psess, contents, None);
 
@@ -65,6 +119,7 @@ pub fn parse_expr(psess: &rustc_session::parse::ParseSess, contents: String) ->
 }
 
 /// Parses a string `contents` into top-level inner attributes.
+#[track_caller]
 pub fn parse_single_unstable_compiler_attribute(
     psess: &rustc_session::parse::ParseSess,
     contents: String,
@@ -81,6 +136,7 @@ pub fn parse_single_unstable_compiler_attribute(
 }
 
 /// Parses a string `contents` into a rustc_ast::Stmt.
+#[track_caller]
 pub fn parse_stmt(psess: &rustc_session::parse::ParseSess, contents: String) -> rustc_ast::Stmt {
     let wrapped = format!("{{ {contents} }}");
     let mut parser = create_parser(psess, wrapped, None);
@@ -101,6 +157,7 @@ pub fn parse_stmt(psess: &rustc_session::parse::ParseSess, contents: String) ->
 }
 
 /// Parses a string `contents`, into an ast-represented Crate.
+#[track_caller]
 pub fn parse_crate(
     psess: &rustc_session::parse::ParseSess,
     contents: String,
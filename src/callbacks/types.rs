@@ -5,6 +5,9 @@
 //! which allows these different type representations to have a single method defined which
 //! answers the question.
 //!
+//! Strings (`str` and `String`) are tupled as a whole, like arrays, with the tag standing for the
+//! string's length. Their contents are not tracked.
+//!
 //! IMPORTANT NOTE: The below implementations need to remain in sync for consistency.
 
 /// Determines whether a type is a tracked primitive that can be wrapped in `Tagged<T>`.
@@ -40,6 +43,8 @@ impl CanBeTupled for rustc_ast::Ty {
                 | rustc_span::sym::usize
                 | rustc_span::sym::bool
                 | rustc_span::sym::char
+                | rustc_span::sym::str
+                | rustc_span::sym::String
         )
     }
 }
@@ -47,7 +52,11 @@ impl CanBeTupled for rustc_ast::Ty {
 impl CanBeTupled for rustc_middle::ty::Ty<'_> {
     /// Returns true if the MIR type can be directly wrapped in `Tagged<T>`.
     fn can_be_tupled(&self) -> bool {
-        self.is_integral() || self.is_floating_point() || self.is_bool() || self.is_char()
+        self.is_integral()
+            || self.is_floating_point()
+            || self.is_bool()
+            || self.is_char()
+            || is_string(*self)
     }
 }
 
@@ -58,6 +67,20 @@ impl CanBeTupled for rustc_ast::token::Lit {
             rustc_ast::token::LitKind::Integer
             | rustc_ast::token::LitKind::Float
             | rustc_ast::token::LitKind::Bool
-            | rustc_ast::token::LitKind::Char)
+            | rustc_ast::token::LitKind::Char
+            | rustc_ast::token::LitKind::Str
+            | rustc_ast::token::LitKind::StrRaw(_))
+    }
+}
+
+/// Returns true if the MIR type is `str` or `String`. These are tupleable, but unlike the other
+/// primitives, they are not `Copy`, and `str` is unsized.
+pub fn is_string(ty: rustc_middle::ty::Ty<'_>) -> bool {
+    match ty.kind() {
+        rustc_middle::ty::Str => true,
+        rustc_middle::ty::Adt(adt, _) => rustc_middle::ty::tls::with(|tcx| {
+            tcx.is_diagnostic_item(rustc_span::sym::String, adt.did())
+        }),
+        _ => false,
     }
 }
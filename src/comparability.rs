@@ -0,0 +1,262 @@
+//! `datir comparability <ATI_PATH> -d <DECLS_PATH>`: writes the abstract types of an `.ati` file
+//! (written by a `--release` run, or by `datir merge`) into the `comparability` of every variable
+//! of a `.decls` file, producing a `.decls` file Daikon can use as is.
+//!
+//! The `.decls` file is rewritten line by line, only replacing `comparability` lines, so that the
+//! rest of what decls-gen produced is kept verbatim. Within each ppt reached by the run, variables
+//! sharing an abstract type are given the same comparability number, numbered from 1 in the order
+//! the `.decls` file declares them.
+//!
+//! Arrays are collapsed the way Daikon expects. The runtime binds every element of an array on
+//! its own (`arr[0]`, `arr[1]`, ...), while the `.decls` file declares a single `arr[..]`
+//! variable. Its comparability is written as `ELEMENTS[INDEX]`, where `ELEMENTS` merges the
+//! abstract types of every element, and `INDEX` is the abstract type of `arr.length`, which
+//! indices into the array interact with. Variables which the run never bound (e.g. the
+//! `hashcode` of an array) are given a comparability number of their own. Ppts which the run
+//! never reached keep their original comparability.
+
+use std::collections::BTreeMap;
+
+use crate::{
+    DatirError, args,
+    ati_file::{self, AtiFile},
+    union_find::UnionFind,
+};
+
+/// A variable declared by a ppt of the `.decls` file.
+struct DeclaredVar {
+    /// Name of the variable, e.g. `arr[..]`.
+    name: String,
+    /// Index of the variable's `comparability` line, or of its last line if it has none.
+    line: usize,
+    /// Whether `line` is a `comparability` line, which is replaced rather than followed.
+    has_comparability: bool,
+}
+
+/// A ppt declared by the `.decls` file.
+struct DeclaredPpt {
+    name: String,
+    vars: Vec<DeclaredVar>,
+}
+
+/// Rewrites the `comparability` lines of the `.decls` file contents `decls`, given the abstract
+/// types of `ati`. See the module documentation.
+pub fn apply(decls: &str, ati: &AtiFile) -> String {
+    let lines: Vec<&str> = decls.lines().collect();
+
+    // Partitions of the ati file, under the names the .decls file uses.
+    let partitions: BTreeMap<String, BTreeMap<String, u64>> = ati
+        .ppts
+        .iter()
+        .map(|(ppt, vars)| {
+            let vars = vars
+                .iter()
+                .map(|(var, id)| (ati_file::unescape(var), *id))
+                .collect();
+            (ati_file::unescape(ppt), vars)
+        })
+        .collect();
+
+    // Replacement of each rewritten line, and lines inserted after a variable's last line.
+    let mut replaced: BTreeMap<usize, String> = BTreeMap::new();
+    let mut inserted: BTreeMap<usize, String> = BTreeMap::new();
+    for ppt in declared_ppts(&lines) {
+        let Some(partition) = partitions.get(&ppt.name) else {
+            continue;
+        };
+        for (var, comparability) in ppt.vars.iter().zip(ppt_comparability(&ppt, partition)) {
+            let line = format!("  comparability {comparability}");
+            if var.has_comparability {
+                replaced.insert(var.line, line);
+            } else {
+                inserted.insert(var.line, line);
+            }
+        }
+    }
+
+    let mut output = String::with_capacity(decls.len());
+    for (i, line) in lines.iter().enumerate() {
+        let line = match replaced.get(&i) {
+            Some(replacement) => replacement.as_str(),
+            // Comparability numbers are only meaningful to Daikon under implicit comparability.
+            None if line.trim() == "var-comparability none" => "var-comparability implicit",
+            None => line,
+        };
+        output.push_str(line);
+        output.push('\n');
+        if let Some(insertion) = inserted.get(&i) {
+            output.push_str(insertion);
+            output.push('\n');
+        }
+    }
+    output
+}
+
+/// Collects the ppts and variables declared by the `.decls` file `lines`.
+fn declared_ppts(lines: &[&str]) -> Vec<DeclaredPpt> {
+    let mut ppts: Vec<DeclaredPpt> = Vec::new();
+    let mut in_ppt = false;
+    for (i, line) in lines.iter().enumerate() {
+        if let Some(name) = line.strip_prefix("ppt ") {
+            in_ppt = true;
+            ppts.push(DeclaredPpt {
+                name: name.to_string(),
+                vars: Vec::new(),
+            });
+            continue;
+        }
+        // Ppts end on a blank line. Header lines (`decl-version`, ...) precede the first one.
+        in_ppt &= !line.trim().is_empty();
+        let Some(ppt) = ppts.last_mut().filter(|_| in_ppt) else {
+            continue;
+        };
+
+        if let Some(name) = line.strip_prefix("variable ") {
+            ppt.vars.push(DeclaredVar {
+                name: name.to_string(),
+                line: i,
+                has_comparability: false,
+            });
+        } else if let Some(var) = ppt.vars.last_mut()
+            && !var.has_comparability
+        {
+            var.line = i;
+            var.has_comparability = line.trim_start().starts_with("comparability ");
+        }
+    }
+    ppts
+}
+
+/// Computes the comparability of every variable of `ppt`, in declaration order, given the
+/// partition the run observed at that ppt.
+fn ppt_comparability(ppt: &DeclaredPpt, partition: &BTreeMap<String, u64>) -> Vec<String> {
+    // One union-find entry per abstract type of the partition, and one per unbound variable.
+    let mut classes = UnionFind::new(0);
+    let mut class_of_id: BTreeMap<u64, usize> = BTreeMap::new();
+    let mut class_of = |ids: Vec<u64>, classes: &mut UnionFind| -> usize {
+        let ids: Vec<usize> = ids
+            .into_iter()
+            .map(|id| *class_of_id.entry(id).or_insert_with(|| classes.push()))
+            .collect();
+        let Some(&first) = ids.first() else {
+            return classes.push();
+        };
+        for &other in &ids[1..] {
+            classes.union(first, other);
+        }
+        first
+    };
+
+    // The element and (for arrays) index class of each variable. An array's elements all share
+    // its element class, so binding them to different abstract types merges those types.
+    let var_classes: Vec<(usize, Option<usize>)> = ppt
+        .vars
+        .iter()
+        .map(|var| match var.name.rfind("[..]") {
+            None => (
+                class_of(
+                    partition.get(&var.name).copied().into_iter().collect(),
+                    &mut classes,
+                ),
+                None,
+            ),
+            Some(last_index) => {
+                let elements = matching_ids(&var.name, partition);
+                let length =
+                    matching_ids(&format!("{}.length", &var.name[..last_index]), partition);
+                (
+                    class_of(elements, &mut classes),
+                    Some(class_of(length, &mut classes)),
+                )
+            }
+        })
+        .collect();
+
+    // Number the classes in declaration order.
+    let mut numbers: BTreeMap<usize, usize> = BTreeMap::new();
+    let mut number = |class: usize, classes: &mut UnionFind| {
+        let root = classes.find(class);
+        let next = numbers.len() + 1;
+        *numbers.entry(root).or_insert(next)
+    };
+    var_classes
+        .into_iter()
+        .map(|(elements, index)| {
+            let elements = number(elements, &mut classes);
+            match index {
+                None => elements.to_string(),
+                Some(index) => format!("{elements}[{}]", number(index, &mut classes)),
+            }
+        })
+        .collect()
+}
+
+/// Ids of every variable of `partition` which `pattern` declares, where each `[..]` of `pattern`
+/// stands for any index (e.g. `arr[..]` declares `arr[0]`, `arr[1]`, ...).
+fn matching_ids(pattern: &str, partition: &BTreeMap<String, u64>) -> Vec<u64> {
+    let segments: Vec<&str> = pattern.split("[..]").collect();
+    let matches = |mut name: &str| {
+        for (i, segment) in segments.iter().enumerate() {
+            let Some(rest) = name.strip_prefix(segment) else {
+                return false;
+            };
+            name = rest;
+            if i + 1 == segments.len() {
+                return name.is_empty();
+            }
+            // `[..]` matches `[<digits>]`.
+            let Some(rest) = name.strip_prefix('[') else {
+                return false;
+            };
+            let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+            let Some(rest) = rest[digits..].strip_prefix(']') else {
+                return false;
+            };
+            if digits == 0 {
+                return false;
+            }
+            name = rest;
+        }
+        unreachable!("split always yields at least one segment")
+    };
+    partition
+        .iter()
+        .filter(|(name, _)| matches(name))
+        .map(|(_, id)| *id)
+        .collect()
+}
+
+/// Entry point of `datir comparability`. `raw_args` are the arguments following the subcommand
+/// name.
+pub fn main(program: &str, raw_args: Vec<String>) -> Result<(), DatirError> {
+    let args = args::comparability_arg_init(program).parse_or_exit(raw_args);
+    let ati_path = std::path::PathBuf::from(
+        args.get_value("ati")
+            .expect("parser guarantees `ati` is present"),
+    );
+    let decls_path = std::path::PathBuf::from(
+        args.get_value("decls-path")
+            .expect("parser guarantees `decls-path` is present"),
+    );
+    // Defaults to a sibling of the .decls file, rather than overwriting it.
+    let output = match args.get_value("output") {
+        Some(output) => std::path::PathBuf::from(output),
+        None => decls_path.with_extension("comparable.decls"),
+    };
+
+    let ati =
+        AtiFile::read(&ati_path).unwrap_or_else(|e| panic!("Unable to parse {ati_path:?}: {e}"));
+    let decls = std::fs::read_to_string(&decls_path)
+        .unwrap_or_else(|e| panic!("Unable to read decls file {decls_path:?}: {e}"));
+    if !decls.lines().any(|line| line.starts_with("ppt ")) {
+        return Err(DatirError::BadInput(
+            "The given .decls file declares no program points.",
+        ));
+    }
+
+    std::fs::write(&output, apply(&decls, &ati))
+        .unwrap_or_else(|e| panic!("Unable to write decls file {output:?}: {e}"));
+    println!("Wrote {}", output.display());
+
+    Ok(())
+}
@@ -12,6 +12,9 @@
 //!   create output .ati files to the specified output directory.
 //!
 //! Internally, an instance of this structure should be available just about everywhere.
+//!
+//! Dependencies are only instrumented when explicitly allowlisted through
+//! [`DatirConfig::instrumented_crates`], see [crate::dependencies].
 
 use std::io::Write;
 use std::path::Path;
@@ -37,9 +40,167 @@ pub struct DatirConfig {
     pub ati_output_dir: Option<std::path::PathBuf>,
     /// DeclsFile associated with crate being instrumented.
     pub decls_file: decls_gen::DeclsFile,
+    /// Path of the `.decls` file decls-gen wrote for the crate, to which the ppts of user-placed
+    /// probes are appended. None if the `.decls` file was given with `--decls-path`, in which
+    /// case it is left as is.
+    pub decls_path: Option<std::path::PathBuf>,
+    /// Allowlist of dependencies which are instrumented alongside the crate root. Empty by
+    /// default, meaning every dependency is treated as untracked.
+    pub instrumented_crates: Vec<InstrumentedCrate>,
+    /// Directory in which the shared runtime library and instrumented dependencies are built.
+    /// Removed once [`crate::run`] returns, see [`RemoveDirOnDrop`].
+    pub build_dir: std::path::PathBuf,
+    /// Recursive depth used when generating `.decls` files for instrumented dependencies.
+    /// `None` uses decls-gen's default.
+    pub decls_depth: Option<usize>,
+    /// Whether every `return` and tail expression of a function is observed as its own
+    /// `:::EXITnn` site, alongside the aggregate `:::EXIT` site.
+    pub exit_ppt_per_return: bool,
+    /// Whether every observed value of a user-defined struct is also bound to the struct's
+    /// `:::OBJECT` site, which reports the abstract types of its fields once per type.
+    pub object_ppts: bool,
+    /// Glob patterns over base ppt names (e.g. `*::parse::*`). Matching functions are left
+    /// uninstrumented, like those marked `#[datir::skip]`.
+    pub skip_ppts: Vec<String>,
+    /// Glob patterns over base ppt names. If any are given, only matching functions (and those
+    /// marked `#[datir::only]`) are instrumented.
+    pub only_ppts: Vec<String>,
+    /// Whether functions exported from the crate keep their original, untagged signatures, so
+    /// that the instrumented crate remains usable by uninstrumented dependants.
+    pub preserve_public_abi: bool,
+    /// Whether the instrumented binary also reports program-wide abstract types, grouping the
+    /// variables of every program point, see the runtime's `global_types` module.
+    pub global_types: bool,
+    /// Format of the analysis output of the instrumented binary.
+    pub output_format: OutputFormat,
+    /// Reviewed partition (an `.ati` file or a saved report) which the instrumented binary loads
+    /// at startup and checks every union against, see the runtime's `contract` module.
+    pub contract: Option<std::path::PathBuf>,
+    /// How the instrumented binary reports violations of [`DatirConfig::contract`].
+    pub contract_mode: ContractMode,
+    /// Functions of the allowlisted dependencies which were left out of instrumentation by their
+    /// own Gather compilation, identified by def path hash. Filled in while the dependencies are
+    /// built, see [crate::dependencies].
+    pub skipped_dependency_fns: std::collections::HashSet<rustc_span::def_id::DefPathHash>,
+}
+
+/// Format in which instrumented binaries write their analysis.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// The text report on stdout, or `.ati` files in `--release` mode.
+    #[default]
+    Text,
+    /// Versioned JSON Lines, on stdout or as `.jsonl` files in `--release` mode, see the
+    /// runtime's `json_output` module.
+    Json,
+}
+
+impl OutputFormat {
+    /// Parses the value of `--format`.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "text" => Some(Self::Text),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+}
+
+/// How instrumented binaries report violations of an abstract type contract.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ContractMode {
+    /// A warning on stderr.
+    #[default]
+    Warn,
+    /// A line appended to the `.violations.log` file next to the contract.
+    Log,
+    /// A panic.
+    Panic,
+}
+
+impl ContractMode {
+    /// Parses the value of `--contract-mode`.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "warn" => Some(Self::Warn),
+            "log" => Some(Self::Log),
+            "panic" => Some(Self::Panic),
+            _ => None,
+        }
+    }
+}
+
+/// A dependency which is instrumented with the same passes as the crate root.
+#[derive(Debug, Clone)]
+pub struct InstrumentedCrate {
+    /// Crate name, as used within `--extern` (so `-` is already replaced with `_`).
+    pub name: String,
+    /// Path to the crate root file of the dependency (usually its `src/lib.rs`).
+    pub root: std::path::PathBuf,
+}
+
+impl InstrumentedCrate {
+    /// Parses a `NAME=PATH` allowlist entry.
+    pub fn parse(spec: &str) -> Option<Self> {
+        let (name, root) = spec.split_once('=')?;
+        if name.is_empty() || root.is_empty() {
+            return None;
+        }
+        Some(Self {
+            name: name.replace('-', "_"),
+            root: std::path::PathBuf::from(root),
+        })
+    }
+
+    /// Parses a comma separated `NAME=PATH[,NAME=PATH...]` allowlist, as passed to
+    /// `--instrument-crates`.
+    pub fn parse_allowlist(allowlist: &str) -> Vec<Self> {
+        allowlist
+            .split(',')
+            .map(|spec| {
+                Self::parse(spec).unwrap_or_else(|| {
+                    panic!("Unable to interpret `{spec}` as a NAME=PATH dependency.")
+                })
+            })
+            .collect()
+    }
+
+    /// Directory containing all of the dependency's source files.
+    pub fn source_dir(&self) -> &std::path::Path {
+        self.root.parent().unwrap_or(std::path::Path::new("."))
+    }
 }
 
 impl DatirConfig {
+    /// Configuration shared by every mode: no debug output, ATI output printed to stdout, no
+    /// instrumented dependencies, and every optional analysis disabled.
+    fn new(decls_file: decls_gen::DeclsFile) -> Self {
+        Self {
+            log_dir: None,
+            print_transformed_ast: false,
+            print_original_ast: false,
+            print_first_pass_info: false,
+            print_function_signatures: false,
+            print_config: false,
+            ati_output_dir: None,
+            decls_file,
+            decls_path: None,
+            instrumented_crates: Vec::new(),
+            build_dir: default_build_dir(),
+            decls_depth: None,
+            exit_ppt_per_return: false,
+            object_ppts: false,
+            skip_ppts: Vec::new(),
+            only_ppts: Vec::new(),
+            preserve_public_abi: false,
+            global_types: false,
+            output_format: OutputFormat::Text,
+            contract: None,
+            contract_mode: ContractMode::Warn,
+            skipped_dependency_fns: std::collections::HashSet::new(),
+        }
+    }
+
     /// Configuration intended to be used for development.
     pub fn debug(decls_file: decls_gen::DeclsFile) -> Self {
         // make sure log directory exists and is empty
@@ -56,39 +217,80 @@ impl DatirConfig {
             print_first_pass_info: true,
             print_function_signatures: true,
             print_config: true,
-            ati_output_dir: None,
-            decls_file,
+            ..Self::new(decls_file)
         }
     }
 
     /// Configuration intended to be used for unit test invocations.
     pub fn test(decls_file: decls_gen::DeclsFile) -> Self {
-        Self {
-            log_dir: None,
-            print_transformed_ast: false,
-            print_original_ast: false,
-            print_first_pass_info: false,
-            print_function_signatures: false,
-            print_config: false,
-            ati_output_dir: None,
-            decls_file,
-        }
+        Self::new(decls_file)
     }
 
     /// Simple configuration intended to be used for consumer use.
     pub fn release(decls_file: decls_gen::DeclsFile, ati_output_dir: std::path::PathBuf) -> Self {
         Self {
-            log_dir: None,
-            print_transformed_ast: false,
-            print_original_ast: false,
-            print_first_pass_info: false,
-            print_function_signatures: false,
-            print_config: false,
             ati_output_dir: Some(ati_output_dir),
+            ..Self::new(decls_file)
+        }
+    }
+
+    /// Configuration used to instrument one of the allowlisted dependencies. Identical to
+    /// `self`, except for the `.decls` file, which describes the dependency instead.
+    pub fn for_dependency(&self, decls_file: decls_gen::DeclsFile) -> Self {
+        Self {
+            log_dir: self.log_dir.clone(),
+            print_transformed_ast: self.print_transformed_ast,
+            print_original_ast: self.print_original_ast,
+            print_first_pass_info: self.print_first_pass_info,
+            print_function_signatures: self.print_function_signatures,
+            print_config: self.print_config,
+            ati_output_dir: self.ati_output_dir.clone(),
             decls_file,
+            decls_path: None,
+            instrumented_crates: self.instrumented_crates.clone(),
+            build_dir: self.build_dir.clone(),
+            decls_depth: self.decls_depth,
+            exit_ppt_per_return: self.exit_ppt_per_return,
+            object_ppts: self.object_ppts,
+            skip_ppts: self.skip_ppts.clone(),
+            only_ppts: self.only_ppts.clone(),
+            preserve_public_abi: self.preserve_public_abi,
+            global_types: self.global_types,
+            output_format: self.output_format,
+            contract: self.contract.clone(),
+            contract_mode: self.contract_mode,
+            skipped_dependency_fns: self.skipped_dependency_fns.clone(),
         }
     }
 
+    /// Whether the crate named `crate_name` is one of the allowlisted dependencies.
+    pub fn instruments_crate(&self, crate_name: &str) -> bool {
+        self.instrumented_crates.iter().any(|c| c.name == crate_name)
+    }
+
+    /// Whether `path` belongs to one of the allowlisted dependencies.
+    pub fn is_instrumented_dependency_file(&self, path: &Path) -> bool {
+        self.instrumented_crates
+            .iter()
+            .any(|c| path.starts_with(c.source_dir()))
+    }
+
+    /// Whether the function with base ppt name `base_ppt_name` matches one of
+    /// [`DatirConfig::skip_ppts`].
+    pub fn skips_ppt(&self, base_ppt_name: &str) -> bool {
+        self.skip_ppts
+            .iter()
+            .any(|pattern| glob_matches(pattern, base_ppt_name))
+    }
+
+    /// Whether the function with base ppt name `base_ppt_name` matches one of
+    /// [`DatirConfig::only_ppts`].
+    pub fn selects_ppt(&self, base_ppt_name: &str) -> bool {
+        self.only_ppts
+            .iter()
+            .any(|pattern| glob_matches(pattern, base_ppt_name))
+    }
+
     /// Logs a message.
     /// 
     /// The message is given a prefix to make it easier to grep / otherwise find.
@@ -113,3 +315,53 @@ impl DatirConfig {
         }
     }
 }
+
+/// Default location of [`DatirConfig::build_dir`]. Every invocation builds the runtime, so the
+/// directory is unique per process to let several invocations run side by side.
+pub fn default_build_dir() -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("datir-build-{}", std::process::id()))
+}
+
+/// Removes a build directory once dropped, so that the per-process directories of
+/// [`default_build_dir`] don't accumulate. Dropping also happens when a compilation fails, as
+/// rustc unwinds on errors.
+pub struct RemoveDirOnDrop(pub std::path::PathBuf);
+
+impl Drop for RemoveDirOnDrop {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Matches `text` against a glob `pattern`, where `*` matches any (possibly empty) sequence of
+/// characters, including `::` and `/`, and `?` matches a single character.
+fn glob_matches(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let text = text.chars().collect::<Vec<_>>();
+
+    // position of the last `*` seen, and the text position it is currently matched up to.
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                // let the last `*` swallow one more character.
+                Some((star, matched)) => {
+                    backtrack = Some((star, matched + 1));
+                    p = star + 1;
+                    t = matched + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
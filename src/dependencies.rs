@@ -0,0 +1,338 @@
+//! Instruments the allowlisted dependencies of the crate being analyzed.
+//!
+//! By default, every dependency is untracked: values passed into them are untupled, and values
+//! coming out of them receive fresh tags, so any interaction happening inside a dependency is
+//! invisible. When [`DatirConfig::instrumented_crates`] is non-empty, each listed dependency is
+//! instead compiled with the same Gather and Instrument passes as the crate root.
+//!
+//! All instrumented crates share a single `ATI_ANALYSIS`, living in the runtime crate
+//! ([`RUNTIME_CRATE_NAME`], see `runtime/`), which is compiled once per DATIR invocation and
+//! linked into every instrumented crate. Dependencies are then compiled in the listed order,
+//! each of them three times:
+//! 1. A plain compilation, producing the uninstrumented rlib which later Gather compilations
+//!    typecheck against (the Gather pass must observe the original signatures).
+//! 2. A Gather compilation, exactly like the one performed on the crate root.
+//! 3. An Instrument compilation, producing the instrumented rlib which later Instrument
+//!    compilations link against.
+//!
+//! Calls into an instrumented dependency are not treated as untracked by the Gather pass (see
+//! `callbacks::gather::analyze_hir::call`), so tagged values cross the crate boundary as is.
+//! Calls into the functions a dependency's Gather pass left out of instrumentation (see
+//! `callbacks::gather::scope`) are, so those are carried over to the later compilations.
+//!
+//! Each dependency is compiled with the edition its manifest declares, read through
+//! `cargo metadata` (see [`read_manifest`]). Its own dependencies are only available to rustc if
+//! they are allowlisted as well, and listed before it: every other dependency is rejected.
+//! Features aren't passed to rustc, so optional dependencies are disabled, and ignored.
+
+use crate::{
+    DatirError, callbacks,
+    config::{DatirConfig, InstrumentedCrate},
+};
+use serde_json::Value;
+
+/// Crate name of the shared runtime library.
+pub const RUNTIME_CRATE_NAME: &str = "datir_runtime";
+
+/// Edition the runtime crate is written in, see `runtime/Cargo.toml`.
+const RUNTIME_EDITION: &str = "2024";
+
+/// Extra rustc arguments required by the compilations of the crate root, once all allowlisted
+/// dependencies have been built.
+#[derive(Debug, Default)]
+pub struct DependencyArgs {
+    /// `--extern` / `-L` arguments pointing at the uninstrumented rlibs, for the Gather pass.
+    pub gather: Vec<String>,
+    /// `--extern` / `-L` arguments pointing at the runtime and instrumented rlibs, for the
+    /// Instrument pass.
+    pub instrument: Vec<String>,
+    /// Functions the dependencies' Gather passes left out of instrumentation, see
+    /// [`DatirConfig::skipped_dependency_fns`].
+    pub skipped_fns: std::collections::HashSet<rustc_span::def_id::DefPathHash>,
+}
+
+/// Callbacks used for compilations that DATIR does not need to observe. Only declares the probe
+/// syntax, so that dependencies placing probes still compile.
+struct PlainCompilation;
+
+impl rustc_driver::Callbacks for PlainCompilation {
+    fn after_crate_root_parsing(
+        &mut self,
+        compiler: &rustc_interface::interface::Compiler,
+        krate: &mut rustc_ast::Crate,
+    ) -> rustc_driver::Compilation {
+        callbacks::codegen::probes::declare_probe_syntax(&compiler.sess.psess, krate);
+        rustc_driver::Compilation::Continue
+    }
+}
+
+/// Builds the shared runtime library and every allowlisted dependency, in order.
+///
+/// Returns the arguments which must be passed to the compilations of the crate root. The
+/// Instrument pass always links the runtime, even if no dependency is instrumented.
+pub fn build_dependencies(config: &DatirConfig) -> Result<DependencyArgs, DatirError> {
+    let mut args = DependencyArgs::default();
+
+    let runtime_dir = config.build_dir.join("runtime");
+    let plain_dir = config.build_dir.join("plain");
+    let instrumented_dir = config.build_dir.join("instrumented");
+    for dir in [&runtime_dir, &plain_dir, &instrumented_dir] {
+        let _ = std::fs::remove_dir_all(dir);
+        std::fs::create_dir_all(dir)
+            .map_err(|_| DatirError::BadInput("Unable to create DATIR build directory."))?;
+    }
+
+    let runtime_rlib = build_runtime(&runtime_dir)?;
+    args.gather
+        .push(format!("-Ldependency={}", path_str(&plain_dir)?));
+    args.instrument.extend([
+        format!("--extern={RUNTIME_CRATE_NAME}={}", path_str(&runtime_rlib)?),
+        format!("-Ldependency={}", path_str(&runtime_dir)?),
+        format!("-Ldependency={}", path_str(&instrumented_dir)?),
+    ]);
+
+    for (i, dep) in config.instrumented_crates.iter().enumerate() {
+        let base_args = rlib_args(&config.instrumented_crates, i)?;
+
+        // 1. uninstrumented rlib, for the Gather pass of everything depending on `dep`.
+        let mut plain_args = base_args.clone();
+        plain_args.extend(args.gather.iter().cloned());
+        plain_args.push(format!("--out-dir={}", path_str(&plain_dir)?));
+        rustc_driver::run_compiler(&plain_args, &mut PlainCompilation);
+
+        // 2. + 3. the same two passes that are run on the crate root.
+        let mut dep_config = config.for_dependency(dependency_decls(config, dep)?);
+        dep_config.skipped_dependency_fns = args.skipped_fns.clone();
+        let dep_config = std::sync::Arc::new(dep_config);
+        let mut gather_args = base_args.clone();
+        gather_args.extend(args.gather.iter().cloned());
+        let mut gather_info = callbacks::gather::GatherAtiInfo::new(dep_config.clone());
+        rustc_driver::run_compiler(&gather_args, &mut gather_info);
+        let first_pass = gather_info.into_first_pass_info();
+        args.skipped_fns
+            .extend(first_pass.skipped_fn_hashes.iter().copied());
+
+        let mut instrument_args = base_args;
+        instrument_args.extend(args.instrument.iter().cloned());
+        instrument_args.push(format!("--out-dir={}", path_str(&instrumented_dir)?));
+        let mut cbs = callbacks::instrument::TransformAbstractSyntaxTreeCallbacks::new(
+            first_pass, dep_config,
+        );
+        rustc_driver::run_compiler(&instrument_args, &mut cbs);
+
+        let rlib_name = format!("lib{}.rlib", dep.name);
+        args.gather.push(format!(
+            "--extern={}={}",
+            dep.name,
+            path_str(&plain_dir.join(&rlib_name))?
+        ));
+        args.instrument.push(format!(
+            "--extern={}={}",
+            dep.name,
+            path_str(&instrumented_dir.join(&rlib_name))?
+        ));
+    }
+
+    if config.print_config {
+        config.log("DependencyArgs", format!("{args:#?}"));
+    }
+
+    Ok(args)
+}
+
+/// Builds every allowlisted dependency in `crates` without instrumenting it, in order, for
+/// `datir static`. Each dependency is handed to `analyze` alongside the rustc arguments compiling
+/// it, before being compiled into `plain_dir`.
+///
+/// Returns the arguments which link the built rlibs into the compilation of the crate root.
+pub fn build_plain_dependencies(
+    crates: &[InstrumentedCrate],
+    plain_dir: &std::path::Path,
+    mut analyze: impl FnMut(&InstrumentedCrate, &[String]),
+) -> Result<Vec<String>, DatirError> {
+    let _ = std::fs::remove_dir_all(plain_dir);
+    std::fs::create_dir_all(plain_dir)
+        .map_err(|_| DatirError::BadInput("Unable to create DATIR build directory."))?;
+
+    let mut args = vec![format!("-Ldependency={}", path_str(plain_dir)?)];
+    for (i, dep) in crates.iter().enumerate() {
+        let mut dep_args = rlib_args(crates, i)?;
+        dep_args.extend(args.iter().cloned());
+        analyze(dep, &dep_args);
+
+        dep_args.push(format!("--out-dir={}", path_str(plain_dir)?));
+        rustc_driver::run_compiler(&dep_args, &mut PlainCompilation);
+        args.push(format!(
+            "--extern={}={}",
+            dep.name,
+            path_str(&plain_dir.join(format!("lib{}.rlib", dep.name)))?
+        ));
+    }
+
+    Ok(args)
+}
+
+/// Returns the rustc arguments compiling `crates[i]` into an rlib, with the edition its manifest
+/// declares. Its own dependencies must be allowlisted before it, see [`read_manifest`].
+fn rlib_args(crates: &[InstrumentedCrate], i: usize) -> Result<Vec<String>, DatirError> {
+    let dep = &crates[i];
+    let manifest = read_manifest(dep)?;
+    if let Some(missing) = manifest
+        .dependencies
+        .iter()
+        .find(|name| !crates[..i].iter().any(|c| &c.name == *name))
+    {
+        return Err(DatirError::UnsupportedDependency(format!(
+            "`{}`: it depends on `{missing}`, which must be passed to --instrument-crates \
+            before it.",
+            dep.name
+        )));
+    }
+
+    Ok(vec![
+        "datir".to_string(),
+        path_str(&dep.root)?.to_string(),
+        "--crate-type=rlib".to_string(),
+        format!("--crate-name={}", dep.name),
+        format!("--edition={}", manifest.edition),
+    ])
+}
+
+/// Compiles the runtime crate (`runtime/src/lib.rs`) into `{runtime_dir}/libdatir_runtime.rlib`,
+/// returning the path to the rlib.
+///
+/// The runtime is compiled by the same compiler as the instrumented crates, rather than by
+/// cargo, so that the rlib metadata is guaranteed to be compatible with them.
+fn build_runtime(runtime_dir: &std::path::Path) -> Result<std::path::PathBuf, DatirError> {
+    let runtime_root = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("runtime/src/lib.rs");
+
+    let runtime_args = vec![
+        "datir".to_string(),
+        path_str(&runtime_root)?.to_string(),
+        "--crate-type=rlib".to_string(),
+        format!("--crate-name={RUNTIME_CRATE_NAME}"),
+        format!("--edition={RUNTIME_EDITION}"),
+        format!("--out-dir={}", path_str(runtime_dir)?),
+    ];
+    rustc_driver::run_compiler(&runtime_args, &mut PlainCompilation);
+
+    Ok(runtime_dir.join(format!("lib{RUNTIME_CRATE_NAME}.rlib")))
+}
+
+/// What the manifest of an allowlisted dependency declares.
+struct DependencyManifest {
+    /// Edition the dependency is written in.
+    edition: String,
+    /// Crate names (as used within `--extern`) of its non-optional, normal dependencies.
+    dependencies: Vec<String>,
+}
+
+/// Reads the manifest of `dep` through `cargo metadata`. This is the `Cargo.toml` closest to
+/// `dep.root`, which must declare a package whose library target is rooted at `dep.root`.
+fn read_manifest(dep: &InstrumentedCrate) -> Result<DependencyManifest, DatirError> {
+    let unsupported = |reason: &str| {
+        DatirError::UnsupportedDependency(format!(
+            "`{}` ({}): {reason}",
+            dep.name,
+            dep.root.display()
+        ))
+    };
+    let root = dep
+        .root
+        .canonicalize()
+        .map_err(|_| unsupported("the crate root does not exist."))?;
+    let manifest_path = root
+        .ancestors()
+        .map(|dir| dir.join("Cargo.toml"))
+        .find(|path| path.is_file())
+        .ok_or_else(|| unsupported("no Cargo.toml declares the crate."))?;
+
+    let output = std::process::Command::new("cargo")
+        .args([
+            "metadata",
+            "--no-deps",
+            "--format-version=1",
+            "--manifest-path",
+        ])
+        .arg(&manifest_path)
+        .output()
+        .map_err(|_| unsupported("unable to run `cargo metadata`."))?;
+    if !output.status.success() {
+        return Err(unsupported(&format!(
+            "`cargo metadata` failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    let metadata: Value = serde_json::from_slice(&output.stdout)
+        .map_err(|e| unsupported(&format!("unable to parse `cargo metadata`: {e}")))?;
+
+    let is_lib_root = |target: &Value| {
+        target
+            .get("src_path")
+            .and_then(Value::as_str)
+            .is_some_and(|src_path| std::path::Path::new(src_path) == root)
+            && target
+                .get("kind")
+                .and_then(Value::as_array)
+                .is_some_and(|kinds| kinds.iter().any(|kind| kind.as_str() == Some("lib")))
+    };
+    let (package, lib) = metadata
+        .get("packages")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .find_map(|package| {
+            let targets = package.get("targets").and_then(Value::as_array)?;
+            Some((package, targets.iter().find(|target| is_lib_root(target))?))
+        })
+        .ok_or_else(|| unsupported("the crate root is not the library target of a package."))?;
+
+    // a target may override the edition of its package.
+    let edition = lib
+        .get("edition")
+        .and_then(Value::as_str)
+        .ok_or_else(|| unsupported("the library target declares no edition."))?
+        .to_string();
+    let dependencies = package
+        .get("dependencies")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter(|dependency| {
+            dependency.get("kind").is_some_and(Value::is_null)
+                && dependency.get("optional").and_then(Value::as_bool) != Some(true)
+        })
+        .filter_map(|dependency| {
+            let name = match dependency.get("rename").and_then(Value::as_str) {
+                Some(rename) => rename,
+                None => dependency.get("name")?.as_str()?,
+            };
+            Some(name.replace('-', "_"))
+        })
+        .collect();
+
+    Ok(DependencyManifest {
+        edition,
+        dependencies,
+    })
+}
+
+/// Generates the `.decls` file of an instrumented dependency, writing it next to the other
+/// build artifacts so that it can be handed to Daikon alongside the crate root's.
+fn dependency_decls(
+    config: &DatirConfig,
+    dep: &InstrumentedCrate,
+) -> Result<decls_gen::DeclsFile, DatirError> {
+    let decls_file = decls_gen::DeclsFile::from_source_file(&dep.root, config.decls_depth);
+    decls_file
+        .write_to_file(&config.build_dir.join(format!("{}.decls", dep.name)))
+        .map_err(|_| DatirError::BadInput("Unable to write dependency decls file."))?;
+    Ok(decls_file)
+}
+
+/// Converts `path` to a string, so it can be passed to rustc.
+fn path_str(path: &std::path::Path) -> Result<&str, DatirError> {
+    path.to_str().ok_or(DatirError::BadInput(
+        "Unable to parse dependency path as UTF-8 string.",
+    ))
+}
@@ -0,0 +1,177 @@
+//! `datir diff <OLD> <NEW>`: compares the partitions of two runs, e.g. before and after a change
+//! to the code or to the inputs of a test, and lists the variables whose abstract type changed.
+//!
+//! Either side may be an `.ati` file (written by a `--release` run, or by `datir merge`) or the
+//! saved stdout of a run, see [AtiFile::read_any]. Ids are never compared directly, as they are
+//! only meaningful within a single partition: a variable only changed if the set of variables
+//! sharing its abstract type did. Classes are compared over the variables both sides hold, so
+//! that a variable appearing or disappearing (listed on its own) doesn't also mark every other
+//! variable of its class as changed.
+//!
+//! Exits with a non-zero status if the partitions differ in any way, including ppts or variables
+//! which only one side holds, so that it can gate CI on unexpected changes of abstract types.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{
+    DatirError, args,
+    ati_file::{self, AtiFile},
+};
+
+/// How a ppt held by both partitions differs.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct PptDiff {
+    /// Variables only the new partition holds.
+    pub added: Vec<String>,
+    /// Variables only the old partition holds.
+    pub removed: Vec<String>,
+    /// Variables whose class changed, alongside the variables of their old and new class (both
+    /// including the variable itself).
+    pub changed: Vec<(String, Vec<String>, Vec<String>)>,
+}
+
+impl PptDiff {
+    /// Whether the ppt is the same on both sides.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Differences between two partitions, ppts in lexicographic order.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Diff {
+    /// Ppts only the old partition holds.
+    pub removed: Vec<String>,
+    /// Ppts only the new partition holds.
+    pub added: Vec<String>,
+    /// Ppts held by both which differ.
+    pub changed: BTreeMap<String, PptDiff>,
+}
+
+impl Diff {
+    /// Number of ppts which differ.
+    pub fn len(&self) -> usize {
+        self.removed.len() + self.added.len() + self.changed.len()
+    }
+
+    /// Whether the partitions are the same.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Compares two partitions, see the module documentation.
+pub fn diff(old: &AtiFile, new: &AtiFile) -> Diff {
+    let mut diff = Diff::default();
+    for (ppt, old_vars) in &old.ppts {
+        match new.ppts.get(ppt) {
+            Some(new_vars) => {
+                let ppt_diff = diff_ppt(old_vars, new_vars);
+                if !ppt_diff.is_empty() {
+                    diff.changed.insert(ppt.clone(), ppt_diff);
+                }
+            }
+            None => diff.removed.push(ppt.clone()),
+        }
+    }
+    diff.added = new
+        .ppts
+        .keys()
+        .filter(|ppt| !old.ppts.contains_key(*ppt))
+        .cloned()
+        .collect();
+    diff
+}
+
+/// Compares the partitions of a single ppt.
+fn diff_ppt(old: &BTreeMap<String, u64>, new: &BTreeMap<String, u64>) -> PptDiff {
+    let common: Vec<&String> = old.keys().filter(|var| new.contains_key(*var)).collect();
+
+    // Variables of each class, restricted to the variables both sides hold.
+    let classes = |vars: &BTreeMap<String, u64>| {
+        let mut classes: BTreeMap<u64, BTreeSet<&String>> = BTreeMap::new();
+        for var in common.iter() {
+            classes.entry(vars[*var]).or_default().insert(*var);
+        }
+        classes
+    };
+    let old_classes = classes(old);
+    let new_classes = classes(new);
+
+    PptDiff {
+        added: new
+            .keys()
+            .filter(|var| !old.contains_key(*var))
+            .cloned()
+            .collect(),
+        removed: old
+            .keys()
+            .filter(|var| !new.contains_key(*var))
+            .cloned()
+            .collect(),
+        changed: common
+            .iter()
+            .filter_map(|var| {
+                let old_class = &old_classes[&old[*var]];
+                let new_class = &new_classes[&new[*var]];
+                (old_class != new_class).then(|| {
+                    let names =
+                        |class: &BTreeSet<&String>| class.iter().map(|v| (*v).clone()).collect();
+                    ((*var).clone(), names(old_class), names(new_class))
+                })
+            })
+            .collect(),
+    }
+}
+
+/// Renders a class as `{a, b}`, with unescaped names.
+pub fn describe_class(class: &[String]) -> String {
+    let names: Vec<String> = class.iter().map(|var| ati_file::unescape(var)).collect();
+    format!("{{{}}}", names.join(", "))
+}
+
+/// Entry point of `datir diff`. `raw_args` are the arguments following the subcommand name.
+pub fn main(program: &str, raw_args: Vec<String>) -> Result<(), DatirError> {
+    let args = args::diff_arg_init(program).parse_or_exit(raw_args);
+    let read = |name| {
+        let path = std::path::PathBuf::from(
+            args.get_value(name)
+                .expect("parser guarantees both partitions are present"),
+        );
+        AtiFile::read_any(&path).unwrap_or_else(|e| panic!("Unable to parse {path:?}: {e}"))
+    };
+    let old = read("old");
+    let new = read("new");
+
+    let diff = diff(&old, &new);
+    for ppt in diff.removed.iter() {
+        println!("- {}", ati_file::unescape(ppt));
+    }
+    for ppt in diff.added.iter() {
+        println!("+ {}", ati_file::unescape(ppt));
+    }
+    for (ppt, ppt_diff) in diff.changed.iter() {
+        println!("{}", ati_file::unescape(ppt));
+        for var in ppt_diff.removed.iter() {
+            println!("  - {}", ati_file::unescape(var));
+        }
+        for var in ppt_diff.added.iter() {
+            println!("  + {}", ati_file::unescape(var));
+        }
+        for (var, old_class, new_class) in ppt_diff.changed.iter() {
+            println!(
+                "  {}: {} -> {}",
+                ati_file::unescape(var),
+                describe_class(old_class),
+                describe_class(new_class)
+            );
+        }
+    }
+
+    if diff.is_empty() {
+        println!("The partitions are identical.");
+        return Ok(());
+    }
+    let ppts: BTreeSet<&String> = old.ppts.keys().chain(new.ppts.keys()).collect();
+    Err(DatirError::PartitionsDiffer(diff.len(), ppts.len()))
+}
@@ -0,0 +1,148 @@
+//! Reading of the program-wide abstract types reported by binaries instrumented with
+//! `--global-types`.
+//!
+//! They come in two forms. Without `--release`, they follow the per-site report on stdout:
+//! ```text
+//! ===ATI-GLOBAL-TYPES-START===
+//! type <id> (<name>)
+//! <ppt name> <var name>
+//! ...
+//! ---
+//! ```
+//! With `--release`, each run writes them as a `.types.json` file next to its `.ati` file:
+//! `{"types": [{"id", "name", "members": [{"ppt", "var"}, ...]}, ...]}`. Either form holds the
+//! same types, see `runtime::global_types`. Binaries instrumented with `--format json` instead
+//! output them as `global_type` records among their JSON Lines, see `runtime::json_output`.
+
+use serde_json::Value;
+
+/// Marker preceding the program-wide types within the stdout of an instrumented binary.
+const REPORT_START: &str = "===ATI-GLOBAL-TYPES-START===";
+
+/// A program-wide abstract type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GlobalType {
+    /// Number of the type, unique within the run which reported it.
+    pub id: usize,
+    /// Name the runtime suggested for the type.
+    pub name: String,
+    /// Every `(ppt name, variable name)` of this type.
+    pub members: Vec<(String, String)>,
+}
+
+/// Reads the program-wide types at `path`, in either form.
+pub fn read(path: &std::path::Path) -> Result<Vec<GlobalType>, String> {
+    let contents = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    if contents.trim_start().starts_with("{\"record\"") {
+        parse_json_lines(&contents)
+    } else if contents.trim_start().starts_with('{') {
+        parse_json(&contents)
+    } else {
+        parse_report(&contents)
+    }
+}
+
+/// Parses the types following the `===ATI-GLOBAL-TYPES-START===` marker of a report. Errors name
+/// the offending line.
+pub fn parse_report(contents: &str) -> Result<Vec<GlobalType>, String> {
+    let mut lines = contents.lines().enumerate();
+    if !lines.any(|(_, line)| line == REPORT_START) {
+        return Err(format!(
+            "no `{REPORT_START}` line, was the binary built with --global-types?"
+        ));
+    }
+
+    let mut types = Vec::new();
+    let mut current: Option<GlobalType> = None;
+    for (line_idx, line) in lines {
+        let malformed = || format!("malformed line {}: {line:?}", line_idx + 1);
+        match current.as_mut() {
+            None if line.trim().is_empty() => {}
+            None => {
+                // `type <id> (<name>)`
+                let (id, name) = line
+                    .strip_prefix("type ")
+                    .and_then(|rest| rest.split_once(" ("))
+                    .and_then(|(id, name)| Some((id.parse().ok()?, name.strip_suffix(')')?)))
+                    .ok_or_else(malformed)?;
+                current = Some(GlobalType {
+                    id,
+                    name: name.to_string(),
+                    members: Vec::new(),
+                });
+            }
+            Some(_) if line == "---" => types.extend(current.take()),
+            Some(ty) => {
+                // Ppt names never contain spaces, variable names may (e.g. `&mut x`).
+                let (ppt, var) = line.split_once(' ').ok_or_else(malformed)?;
+                ty.members.push((ppt.to_string(), var.to_string()));
+            }
+        }
+    }
+    match current {
+        Some(ty) => Err(format!("type {} is missing its closing `---`", ty.id)),
+        None => Ok(types),
+    }
+}
+
+/// Parses the contents of a `.types.json` file.
+pub fn parse_json(contents: &str) -> Result<Vec<GlobalType>, String> {
+    let json: Value = serde_json::from_str(contents).map_err(|e| e.to_string())?;
+    let types = json
+        .get("types")
+        .and_then(Value::as_array)
+        .ok_or_else(|| "malformed .types.json file: no `types` array".to_string())?;
+    types.iter().map(parse_json_type).collect()
+}
+
+/// Parses the `global_type` records of the JSON Lines output, skipping every other record.
+/// Errors name the offending line.
+pub fn parse_json_lines(contents: &str) -> Result<Vec<GlobalType>, String> {
+    let mut types = Vec::new();
+    for (line_idx, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record: Value =
+            serde_json::from_str(line).map_err(|e| format!("line {}: {e}", line_idx + 1))?;
+        if record.get("record").and_then(Value::as_str) == Some("global_type") {
+            types
+                .push(parse_json_type(&record).map_err(|e| format!("line {}: {e}", line_idx + 1))?);
+        }
+    }
+    if types.is_empty() {
+        return Err(
+            "no `global_type` record, was the binary built with --global-types?".to_string(),
+        );
+    }
+    Ok(types)
+}
+
+/// Parses a single `{"id", "name", "members"}` object.
+fn parse_json_type(ty: &Value) -> Result<GlobalType, String> {
+    let malformed = |what: &str| format!("malformed program-wide type: {what}");
+    let members = ty
+        .get("members")
+        .and_then(Value::as_array)
+        .ok_or_else(|| malformed("type without `members`"))?
+        .iter()
+        .map(|member| {
+            let field = |key: &str| member.get(key).and_then(Value::as_str).map(str::to_string);
+            field("ppt")
+                .zip(field("var"))
+                .ok_or_else(|| malformed("member without `ppt` or `var`"))
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(GlobalType {
+        id: ty
+            .get("id")
+            .and_then(Value::as_u64)
+            .ok_or_else(|| malformed("type without `id`"))? as usize,
+        name: ty
+            .get("name")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string(),
+        members,
+    })
+}
@@ -9,7 +9,16 @@
 //! (namely some type information), which the second compilation uses to actually mutate the
 //! AST and to add in dynamic instrumentation.
 //! 
-//! See [callbacks] for information about the two compilation steps.
+//! See [callbacks] for information about the two compilation steps, and [dependencies] for how
+//! allowlisted dependencies are instrumented alongside the crate root.
+//!
+//! `datir merge` instead combines the `.ati` files written by many runs of a binary instrumented in
+//! `--release` mode, see [merge], and `datir comparability` writes the abstract types of such a
+//! file into the `.decls` file, see [comparability]. `datir suggest` proposes newtypes for the
+//! program-wide abstract types reported by a `--global-types` run, see [suggest]. `datir diff`
+//! lists the variables whose abstract type differs between two runs, see [diff]. `datir static`
+//! infers abstract types without running the crate, by unification over its MIR, and compares
+//! them with those of a run, see [static_ati].
 //!
 //! See --help for usage instructions.
 
@@ -19,30 +28,6 @@
 // allows using the `box` keyword in patterns to match on std::Box
 #![feature(box_patterns)]
 
-// allows performing trait specialization, which the runtime ati library
-// makes heavy use of to dispatch the appropriate function during monomorphization.
-// In other words, if we define some trait MyTrait, and then implement:
-// > impl<T>    MyTrait for T          { fn foo() ... }
-// > impl<T>    MyTrait for &T         { fn foo() ... }
-// > impl<T, N> MyTrait for [T; N]     { fn foo() ... }
-// > impl<T>    MyTrait for Wrapper<T> { fn foo() ... }
-// we can always call T.foo(), regardless of what T is, as there is a
-// default implementation for all generic Ts. However, if T.foo() is invoked,
-// and T is actually a Wrapper<T>, then it will dispatch the foo() defined
-// for Wrapper<T> rather than for T. Without min_specialization, those trait
-// implementations would overlap.
-// Note: full_specialization is unsound, and also unnecessary here.
-// Another note: This feature is only necessary if `mod ati` is uncommented below.
-#![feature(min_specialization)]
-
-#![feature(step_trait)]
-
-// allows defining a Dynamically Sized Type (used for representing Tagged References
-// to Unsized types like [T]) while allowing automatic coercion from a Sized type.
-// Note: These features are only necessary if `mod ati` is uncommented below.
-#![feature(unsize)]
-#![feature(coerce_unsized)]
-
 // All linked in rustc_private crates
 extern crate rustc_ast;
 extern crate rustc_ast_pretty;
@@ -58,25 +43,41 @@ extern crate smallvec;
 extern crate thin_vec;
 
 pub use crate::config::DatirConfig;
+use datir_runtime::ati_file;
 use decls_gen::DeclsFile;
 
-// include so VsCode's rust-analyzer extension runs static analysis on the runtime library
-mod ati;
-
 mod args;
 mod callbacks;
+mod comparability;
 mod config;
+mod dependencies;
+mod diff;
+mod global_types_file;
+mod merge;
+mod static_ati;
+mod suggest;
+mod union_find;
 
-/// Errors produced by [`run`].
+/// Errors produced by [`run`] and by the subcommands.
 #[derive(Debug)]
 pub enum DatirError {
     BadInput(&'static str),
+    /// `datir diff` found differences at the first number of ppts, out of the second.
+    PartitionsDiffer(usize, usize),
+    /// An allowlisted dependency can't be instrumented, for the given reason.
+    UnsupportedDependency(String),
 }
 
 impl std::fmt::Display for DatirError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             DatirError::BadInput(msg) => write!(f, "{msg}"),
+            DatirError::PartitionsDiffer(differing, total) => {
+                write!(f, "The partitions differ at {differing} of {total} program points.")
+            }
+            DatirError::UnsupportedDependency(reason) => {
+                write!(f, "Unable to instrument dependency {reason}")
+            }
         }
     }
 }
@@ -85,7 +86,7 @@ impl std::fmt::Display for DatirError {
 /// producing an instrumented binary at `output` (or rustc's default location
 /// if `None`). Use this entrypoint to invoke DATIR programmatically.
 pub fn run(
-    config: DatirConfig,
+    mut config: DatirConfig,
     target: &std::path::Path,
     output: Option<&std::path::Path>,
 ) -> Result<(), DatirError> {
@@ -110,14 +111,29 @@ pub fn run(
         config.log("Config", format!("{:#?}", config));
     }
 
+    // The runtime and allowlisted dependencies are built first, so the root compilations can
+    // link them. The instrumented binary doesn't need any of them once linked.
+    let _build_dir = config::RemoveDirOnDrop(config.build_dir.clone());
+    let dependency_args = dependencies::build_dependencies(&config)?;
+    let mut gather_args = rustc_args.clone();
+    gather_args.extend(dependency_args.gather);
+    rustc_args.extend(dependency_args.instrument);
+    config.skipped_dependency_fns = dependency_args.skipped_fns;
+
     // The gather compilation
     // panics on compilation failure, therefore by the time the instrument
     // compilation starts, we know we are working with a semantically correct rust program
     let config = std::sync::Arc::new(config);
     let mut gather_info = callbacks::gather::GatherAtiInfo::new(config.clone());
-    rustc_driver::run_compiler(&rustc_args, &mut gather_info);
+    rustc_driver::run_compiler(&gather_args, &mut gather_info);
     let first_pass = gather_info.into_first_pass_info();
 
+    // decls-gen declares no probe ppts, so they are appended to the .decls file it wrote.
+    if let Some(decls_path) = &config.decls_path {
+        callbacks::codegen::probes::append_probe_decls(decls_path, &first_pass.probe_ppts)
+            .map_err(|_| DatirError::BadInput("Unable to declare probe ppts in the decls file."))?;
+    }
+
     // The instrument compilation
     let mut cbs = callbacks::instrument::TransformAbstractSyntaxTreeCallbacks::new(
         first_pass,
@@ -135,6 +151,25 @@ pub fn main() {
     let program = std::env::args()
         .next()
         .unwrap_or_else(|| "datir".to_string());
+
+    // Subcommands operate on the output of instrumented binaries, rather than on a crate.
+    let subcommand_args = || std::env::args().skip(2).collect();
+    let subcommand = match std::env::args().nth(1).as_deref() {
+        Some("merge") => Some(merge::main(&program, subcommand_args())),
+        Some("comparability") => Some(comparability::main(&program, subcommand_args())),
+        Some("suggest") => Some(suggest::main(&program, subcommand_args())),
+        Some("diff") => Some(diff::main(&program, subcommand_args())),
+        Some("static") => Some(static_ati::main(&program, subcommand_args())),
+        _ => None,
+    };
+    if let Some(result) = subcommand {
+        if let Err(e) = result {
+            eprintln!("datir: {e}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
     let args = args::datir_arg_init(&program).parse_env();
 
     // Get path to main/lib.rs file being instrumented.
@@ -143,9 +178,17 @@ pub fn main() {
             .expect("parser guarantees `file` is present"),
     );
 
+    let depth = (!args.is_present("test")).then(|| {
+        args.get_value("rec-depth")
+            .expect("Rec Depth did not have a value (even though it is default specified)")
+            .parse::<usize>()
+            .expect("Unable to interpret rec-depth as an integer.")
+    });
+
     // Generate / parse related .decls file. When generating fresh, also
     // write it to disk, so subsequent runs can reuse it via
     // --decls-path.
+    let mut decls_path = None;
     let decls_file = if let Some(path) = args.get_value("decls-path") {
         let decls_path = std::path::PathBuf::from(path);
         match DeclsFile::from_decls_file(&decls_path) {
@@ -155,22 +198,17 @@ pub fn main() {
             ),
         }
     } else {
-        let depth = (!args.is_present("test")).then(|| {
-            args.get_value("rec-depth")
-                .expect("Rec Depth did not have a value (even though it is default specified)")
-                .parse::<usize>()
-                .expect("Unable to interpret rec-depth as an integer.")
-        });
-
+        let path = target_path.with_extension("decls");
         let decls_file = DeclsFile::from_source_file(&target_path, depth);
         decls_file
-            .write_to_file(&target_path.with_extension("decls"))
+            .write_to_file(&path)
             .expect("unable to write decls file to disk");
+        decls_path = Some(path);
         decls_file
     };
 
     // Construct config based on mode.
-    let config = if let Some(dir_path) = args.get_value("release") {
+    let mut config = if let Some(dir_path) = args.get_value("release") {
         let raw = std::path::PathBuf::from(dir_path);
         let _ = std::fs::remove_dir_all(&raw);
         std::fs::create_dir_all(&raw).expect("Unable to create ATI output directory.");
@@ -182,6 +220,46 @@ pub fn main() {
     } else {
         DatirConfig::debug(decls_file)
     };
+    config.decls_depth = depth;
+    config.decls_path = decls_path;
+    config.exit_ppt_per_return = args.is_present("exit-per-return");
+    config.object_ppts = args.is_present("object-ppts");
+    config.preserve_public_abi = args.is_present("preserve-public-abi");
+    config.global_types = args.is_present("global-types");
+    let format = args
+        .get_value("format")
+        .expect("Format did not have a value (even though it is default specified)");
+    config.output_format = config::OutputFormat::parse(format).unwrap_or_else(|| {
+        panic!("Unable to interpret `{format}` as an output format, expected `text` or `json`.")
+    });
+    // The instrumented binary may run from anywhere, so the contract is located absolutely.
+    config.contract = args.get_value("contract").map(|path| {
+        std::fs::canonicalize(path)
+            .unwrap_or_else(|e| panic!("Unable to locate abstract type contract {path:?}: {e}"))
+    });
+    let contract_mode = args
+        .get_value("contract-mode")
+        .expect("Contract mode did not have a value (even though it is default specified)");
+    config.contract_mode = config::ContractMode::parse(contract_mode).unwrap_or_else(|| {
+        panic!(
+            "Unable to interpret `{contract_mode}` as a contract mode, expected `warn`, `log` or \
+             `panic`."
+        )
+    });
+
+    // Parse the glob filters selecting which functions are instrumented.
+    let globs = |name: &str| -> Vec<String> {
+        args.get_value(name)
+            .map(|globs| globs.split(',').map(str::to_string).collect())
+            .unwrap_or_default()
+    };
+    config.skip_ppts = globs("skip");
+    config.only_ppts = globs("only");
+
+    // Parse the allowlist of dependencies to instrument alongside the root.
+    if let Some(allowlist) = args.get_value("instrument-crates") {
+        config.instrumented_crates = config::InstrumentedCrate::parse_allowlist(allowlist);
+    }
 
     let output_path = args.get_value("output").map(std::path::PathBuf::from);
 
@@ -0,0 +1,135 @@
+//! `datir merge <DIR>`: combines the `.ati` files written by many executions of a binary
+//! instrumented in `--release` mode into a single `.ati` file.
+//!
+//! Each execution writes the partition of every program point it reached to its own file. A ppt's
+//! merged partition follows the multi-run semantics of the paper: two variables share an abstract
+//! type if they shared one within any run, and the classes a variable was part of across
+//! different runs are unioned (so `a ~ b` in one run and `b ~ c` in another gives `a ~ b ~ c`).
+//! A variable which was only observed by some runs is still part of the merged partition.
+//!
+//! Abstract types are renumbered within each ppt of the merged file, in order of their
+//! lexicographically smallest variable, as ids of different runs are unrelated. The number of runs
+//! which reached each ppt is printed once merging is done.
+
+use std::collections::BTreeMap;
+
+use crate::{DatirError, args, ati_file::AtiFile, union_find::UnionFind};
+
+/// Result of merging several `.ati` files.
+#[derive(Debug, Default)]
+pub struct MergedAti {
+    /// Combined partition of every ppt reached by at least one run.
+    pub ati: AtiFile,
+    /// Number of runs which reached each ppt, keyed by ppt name.
+    pub runs_per_ppt: BTreeMap<String, usize>,
+}
+
+/// Merges the partitions of several runs, see the module documentation.
+pub fn merge(runs: &[AtiFile]) -> MergedAti {
+    let mut merged = MergedAti::default();
+
+    // Every run reaching a ppt, alongside the partition it observed there.
+    let mut ppts: BTreeMap<&str, Vec<&BTreeMap<String, u64>>> = BTreeMap::new();
+    for run in runs {
+        for (ppt, vars) in &run.ppts {
+            ppts.entry(ppt).or_default().push(vars);
+        }
+    }
+
+    for (ppt, observed) in ppts {
+        merged.runs_per_ppt.insert(ppt.to_string(), observed.len());
+        merged
+            .ati
+            .ppts
+            .insert(ppt.to_string(), merge_ppt(&observed));
+    }
+
+    merged
+}
+
+/// Unions the per-run partitions of a single ppt.
+fn merge_ppt(observed: &[&BTreeMap<String, u64>]) -> BTreeMap<String, u64> {
+    // Every variable seen in any run, in lexicographic order.
+    let vars: Vec<&String> = observed
+        .iter()
+        .flat_map(|vars| vars.keys())
+        .collect::<std::collections::BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    let index: BTreeMap<&String, usize> = vars.iter().enumerate().map(|(i, v)| (*v, i)).collect();
+    let mut classes = UnionFind::new(vars.len());
+
+    // Within a run, variables listed with the same id share an abstract type. Their classes are
+    // unioned across runs, as variables are identified by name rather than by id.
+    for run in observed {
+        let mut first_of_class: BTreeMap<u64, usize> = BTreeMap::new();
+        for (var, id) in run.iter() {
+            let var = index[var];
+            let first = *first_of_class.entry(*id).or_insert(var);
+            classes.union(first, var);
+        }
+    }
+
+    // Number the classes in order of their smallest variable.
+    let mut class_ids: BTreeMap<usize, u64> = BTreeMap::new();
+    let mut partition = BTreeMap::new();
+    for (i, var) in vars.iter().enumerate() {
+        let root = classes.find(i);
+        let next_id = class_ids.len() as u64;
+        let id = *class_ids.entry(root).or_insert(next_id);
+        partition.insert((*var).clone(), id);
+    }
+    partition
+}
+
+/// Entry point of `datir merge`. `raw_args` are the arguments following the subcommand name.
+pub fn main(program: &str, raw_args: Vec<String>) -> Result<(), DatirError> {
+    let args = args::merge_arg_init(program).parse_or_exit(raw_args);
+    let dir = std::path::PathBuf::from(
+        args.get_value("dir")
+            .expect("parser guarantees `dir` is present"),
+    );
+    // Defaults to a sibling of the directory, so that merging again doesn't read the output.
+    let output = match args.get_value("output") {
+        Some(output) => std::path::PathBuf::from(output),
+        None => std::fs::canonicalize(&dir)
+            .unwrap_or_else(|e| panic!("Unable to read ATI output directory {dir:?}: {e}"))
+            .with_extension("ati"),
+    };
+
+    let mut paths: Vec<_> = std::fs::read_dir(&dir)
+        .unwrap_or_else(|e| panic!("Unable to read ATI output directory {dir:?}: {e}"))
+        .map(|entry| {
+            entry
+                .expect("Unable to read ATI output directory entry.")
+                .path()
+        })
+        .filter(|path| path.extension().is_some_and(|ext| ext == "ati") && *path != output)
+        .collect();
+    if paths.is_empty() {
+        return Err(DatirError::BadInput(
+            "No .ati files found in the given directory.",
+        ));
+    }
+    paths.sort();
+
+    let runs: Vec<AtiFile> = paths
+        .iter()
+        .map(|path| AtiFile::read(path).unwrap_or_else(|e| panic!("Unable to parse {path:?}: {e}")))
+        .collect();
+    let merged = merge(&runs);
+
+    let mut file = std::fs::File::create(&output)
+        .unwrap_or_else(|e| panic!("Unable to create merged .ati file {output:?}: {e}"));
+    merged
+        .ati
+        .write(&mut file)
+        .expect("Unable to write merged .ati file.");
+
+    println!("Merged {} runs into {}", runs.len(), output.display());
+    for (ppt, count) in &merged.runs_per_ppt {
+        println!("{ppt}: {count}/{} runs", runs.len());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,524 @@
+//! Flow-insensitive unification over the MIR of every function, see [crate::static_ati].
+//!
+//! Bodies are analyzed as borrowck reads them (`mir_promoted`: as built, with constant
+//! expressions such as `&5` promoted out of the body), before any optimization: const
+//! propagation, inlining or dead code removal would otherwise make the partition depend on the
+//! optimization level.
+//!
+//! Each body holds a union-find over its places, down to the tupleable primitives they hold:
+//! a place is a local followed by a [Path] of dereferences, fields, and array elements (all
+//! collapsed into a single element, like Daikon's `arr[..]`) or lengths. Statements union the
+//! places the runtime would see interact:
+//! - moves, copies and casts union their source and destination,
+//! - taking a reference unions the referent with what the reference points to,
+//! - arithmetic unions both operands with the result, comparisons only union the operands, and
+//!   shifts only union the shifted operand with the result,
+//! - tuples, structs and arrays union each operand with the field (or element) it builds,
+//! - indexing unions the index with the length of the array.
+//!
+//! Calls to other analyzed functions are polymorphic, like Lackwit's: each function is
+//! summarized by the partition of its interface (the primitives its parameters and return
+//! value hold), which is instantiated at every call site, unioning the arguments (and the
+//! destination) its body unions. Summaries are recomputed until none changes. Calls to anything
+//! else (e.g. the standard library) union nothing, like calls to untracked functions do at
+//! runtime.
+//!
+//! Then, like a site binding every value a variable holds, the interface of each function is
+//! unioned wherever some call site passes it arguments of the same class, until no interface
+//! changes.
+
+use std::collections::HashMap;
+
+use rustc_hir::def_id::LocalDefId;
+use rustc_middle::{
+    mir::{self, Local, Operand, Place, Rvalue},
+    ty::{self, Ty, TyCtxt},
+};
+
+use crate::{callbacks::types::CanBeTupled, union_find::UnionFind};
+
+/// Types nested deeper than this are not expanded, so that recursive types terminate.
+const MAX_DEPTH: usize = 6;
+
+/// A step from a place to a place it holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+enum Step {
+    Deref,
+    Field(usize),
+    /// Every element of an array or slice.
+    Elem,
+    /// The length of an array or slice.
+    Len,
+}
+
+type Path = Vec<Step>;
+
+/// A place of a body, as a local followed by the steps leading to it.
+type Key = (Local, Path);
+
+/// A tupleable primitive held by a value of some type.
+#[derive(Debug, Clone)]
+struct Leaf {
+    path: Path,
+    /// Suffix of the name the runtime binds the primitive under, relative to the name of the
+    /// value (e.g. `.f`), or `None` if it isn't bound under a name of its own (elements and
+    /// lengths of arrays).
+    suffix: Option<String>,
+}
+
+/// Lists the tupleable primitives held by a value of type `ty`, through references, tuples,
+/// crate-local structs and arrays.
+fn leaves<'tcx>(tcx: TyCtxt<'tcx>, ty: Ty<'tcx>, depth: usize) -> Vec<Leaf> {
+    if ty.can_be_tupled() {
+        return vec![Leaf {
+            path: Vec::new(),
+            suffix: Some(String::new()),
+        }];
+    }
+    if depth == MAX_DEPTH {
+        return Vec::new();
+    }
+
+    let mut held = Vec::new();
+    let mut nest = |step: Step, suffix: Option<String>, inner: Ty<'tcx>| {
+        for leaf in leaves(tcx, inner, depth + 1) {
+            let mut path = vec![step];
+            path.extend(leaf.path);
+            held.push(Leaf {
+                path,
+                suffix: suffix.clone().zip(leaf.suffix).map(|(a, b)| a + &b),
+            });
+        }
+    };
+    match ty.kind() {
+        ty::Ref(_, inner, _) => nest(Step::Deref, Some(String::new()), *inner),
+        ty::Tuple(tys) => {
+            for (i, inner) in tys.iter().enumerate() {
+                nest(Step::Field(i), Some(format!(".{i}")), inner);
+            }
+        }
+        ty::Adt(adt, args) if adt.is_struct() && adt.did().is_local() => {
+            for (i, field) in adt.non_enum_variant().fields.iter().enumerate() {
+                nest(
+                    Step::Field(i),
+                    Some(format!(".{}", field.name)),
+                    field.ty(tcx, args),
+                );
+            }
+        }
+        ty::Array(elem, _) | ty::Slice(elem) => {
+            nest(Step::Elem, None, *elem);
+            held.push(Leaf {
+                path: vec![Step::Len],
+                suffix: None,
+            });
+        }
+        _ => {}
+    }
+    held
+}
+
+/// Partition of the interface of a function: every primitive its parameters and return value
+/// (`_0`) hold, alongside its class, numbered by first appearance.
+type Summary = Vec<(Key, usize)>;
+
+/// A call to an analyzed function.
+#[derive(Debug, Clone)]
+struct CallSite {
+    callee: LocalDefId,
+    /// Primitives of the callee's interface, alongside the place of the caller they were
+    /// instantiated with.
+    bound: Vec<(Key, Key)>,
+}
+
+/// Places of a single body, unioned as described in the module documentation.
+#[derive(Debug, Default)]
+struct BodyTypes {
+    nodes: HashMap<Key, usize>,
+    uf: UnionFind,
+    calls: Vec<CallSite>,
+    /// Primitives of the interface of the function, see [Summary].
+    interface: Vec<(Key, Option<String>)>,
+}
+
+impl BodyTypes {
+    /// Returns the node of `key`, creating it on first use.
+    fn node(&mut self, key: Key) -> usize {
+        let BodyTypes { nodes, uf, .. } = self;
+        *nodes.entry(key).or_insert_with(|| uf.push())
+    }
+
+    /// Unions the places `a` and `b`, returning whether they were of different classes.
+    fn unify(&mut self, a: Key, b: Key) -> bool {
+        let (a, b) = (self.node(a), self.node(b));
+        let (a, b) = (self.uf.find(a), self.uf.find(b));
+        if a == b {
+            return false;
+        }
+        self.uf.union(a, b);
+        true
+    }
+
+    /// Unions every place of `keys` together.
+    fn unify_all(&mut self, keys: impl IntoIterator<Item = Key>) {
+        let mut keys = keys.into_iter();
+        let Some(first) = keys.next() else {
+            return;
+        };
+        for key in keys {
+            self.unify(first.clone(), key);
+        }
+    }
+
+    /// Partition of the interface, numbered canonically.
+    fn summary(&mut self) -> Summary {
+        let mut numbers: HashMap<usize, usize> = HashMap::new();
+        let interface: Vec<Key> = self.interface.iter().map(|(key, _)| key.clone()).collect();
+        interface
+            .into_iter()
+            .map(|key| {
+                let node = self.node(key.clone());
+                let root = self.uf.find(node);
+                let next = numbers.len();
+                (key, *numbers.entry(root).or_insert(next))
+            })
+            .collect()
+    }
+}
+
+/// Builds the [BodyTypes] of a single body, given the summaries of the functions it calls.
+struct BodyVisitor<'a, 'tcx> {
+    tcx: TyCtxt<'tcx>,
+    body: &'tcx mir::Body<'tcx>,
+    summaries: &'a HashMap<LocalDefId, Summary>,
+    types: BodyTypes,
+}
+
+impl<'a, 'tcx> BodyVisitor<'a, 'tcx> {
+    fn visit(mut self) -> BodyTypes {
+        let body = self.body;
+        for local in (0..=body.arg_count).map(Local::from_usize) {
+            for leaf in leaves(self.tcx, body.local_decls[local].ty, 0) {
+                let key = (local, leaf.path);
+                self.types.node(key.clone());
+                self.types.interface.push((key, leaf.suffix));
+            }
+        }
+
+        for data in body.basic_blocks.iter() {
+            for statement in data.statements.iter() {
+                if let mir::StatementKind::Assign(box (place, rvalue)) = &statement.kind {
+                    self.assign(*place, rvalue);
+                }
+            }
+            if let mir::TerminatorKind::Call {
+                func,
+                args,
+                destination,
+                ..
+            } = &data.terminator().kind
+            {
+                let args: Vec<&Operand<'tcx>> = args.iter().map(|arg| &arg.node).collect();
+                self.call(func, &args, *destination);
+            }
+        }
+        self.types
+    }
+
+    /// Returns the key of `place`, unioning every index it is projected through with the length
+    /// of the indexed array. Places within enums are not tracked.
+    fn key(&mut self, place: Place<'tcx>) -> Option<Key> {
+        let mut path = Vec::new();
+        for elem in place.projection.iter() {
+            match elem {
+                mir::ProjectionElem::Deref => path.push(Step::Deref),
+                mir::ProjectionElem::Field(field, _) => path.push(Step::Field(field.as_usize())),
+                mir::ProjectionElem::Index(index) => {
+                    let mut len = path.clone();
+                    len.push(Step::Len);
+                    self.types.unify((index, Vec::new()), (place.local, len));
+                    path.push(Step::Elem);
+                }
+                mir::ProjectionElem::ConstantIndex { .. } => path.push(Step::Elem),
+                mir::ProjectionElem::Downcast(..) => return None,
+                _ => {}
+            }
+        }
+        Some((place.local, path))
+    }
+
+    /// Key of the place `operand` reads, if it isn't a constant.
+    fn operand_key(&mut self, operand: &Operand<'tcx>) -> Option<Key> {
+        self.key(operand.place()?)
+    }
+
+    /// Unions the primitives held by `a` (of type `a_ty`) and `b` (of type `b_ty`), wherever both
+    /// hold one.
+    fn unify_values(&mut self, a: Key, a_ty: Ty<'tcx>, b: Key, b_ty: Ty<'tcx>) {
+        let b_leaves: Vec<Path> = leaves(self.tcx, b_ty, 0)
+            .into_iter()
+            .map(|leaf| leaf.path)
+            .collect();
+        for leaf in leaves(self.tcx, a_ty, 0) {
+            if !b_leaves.contains(&leaf.path) {
+                continue;
+            }
+            let (mut a, mut b) = (a.clone(), b.clone());
+            a.1.extend(leaf.path.iter().copied());
+            b.1.extend(leaf.path);
+            self.types.unify(a, b);
+        }
+    }
+
+    /// Unions the value of `operand` with the place `dest` (of type `dest_ty`).
+    fn unify_operand(&mut self, dest: Key, dest_ty: Ty<'tcx>, operand: &Operand<'tcx>) {
+        if let Some(src) = self.operand_key(operand) {
+            let src_ty = operand.ty(&self.body.local_decls, self.tcx);
+            self.unify_values(dest, dest_ty, src, src_ty);
+        }
+    }
+
+    fn assign(&mut self, place: Place<'tcx>, rvalue: &Rvalue<'tcx>) {
+        let Some(dest) = self.key(place) else {
+            return;
+        };
+        let dest_ty = place.ty(&self.body.local_decls, self.tcx).ty;
+        let step = |step: Step| {
+            let mut key = dest.clone();
+            key.1.push(step);
+            key
+        };
+
+        match rvalue {
+            Rvalue::Use(operand) | Rvalue::Cast(_, operand, _) => {
+                self.unify_operand(dest, dest_ty, operand)
+            }
+            Rvalue::CopyForDeref(src) => self.unify_operand(dest, dest_ty, &Operand::Copy(*src)),
+            Rvalue::Ref(_, _, referent) => {
+                let Some(src) = self.key(*referent) else {
+                    return;
+                };
+                let src_ty = referent.ty(&self.body.local_decls, self.tcx).ty;
+                self.unify_values(step(Step::Deref), src_ty, src, src_ty);
+            }
+            Rvalue::BinaryOp(op, operands) => {
+                let (lhs, rhs) = &**operands;
+                let (lhs, rhs) = (self.operand_key(lhs), self.operand_key(rhs));
+                match op {
+                    mir::BinOp::Shl
+                    | mir::BinOp::ShlUnchecked
+                    | mir::BinOp::Shr
+                    | mir::BinOp::ShrUnchecked => {
+                        self.types.unify_all(lhs.into_iter().chain([dest]))
+                    }
+                    mir::BinOp::Eq
+                    | mir::BinOp::Ne
+                    | mir::BinOp::Lt
+                    | mir::BinOp::Le
+                    | mir::BinOp::Gt
+                    | mir::BinOp::Ge
+                    | mir::BinOp::Cmp => self.types.unify_all(lhs.into_iter().chain(rhs)),
+                    mir::BinOp::Offset => {}
+                    // Overflow-checked arithmetic produces a `(result, overflowed)` tuple.
+                    mir::BinOp::AddWithOverflow
+                    | mir::BinOp::SubWithOverflow
+                    | mir::BinOp::MulWithOverflow => self
+                        .types
+                        .unify_all(lhs.into_iter().chain(rhs).chain([step(Step::Field(0))])),
+                    _ => self
+                        .types
+                        .unify_all(lhs.into_iter().chain(rhs).chain([dest])),
+                }
+            }
+            Rvalue::UnaryOp(mir::UnOp::PtrMetadata, operand) => {
+                if let Some(mut src) = self.operand_key(operand) {
+                    src.1.extend([Step::Deref, Step::Len]);
+                    self.types.unify(dest, src);
+                }
+            }
+            Rvalue::UnaryOp(_, operand) => self.unify_operand(dest, dest_ty, operand),
+            Rvalue::Repeat(operand, _) => {
+                let elem_ty = operand.ty(&self.body.local_decls, self.tcx);
+                self.unify_operand(step(Step::Elem), elem_ty, operand);
+            }
+            Rvalue::Aggregate(kind, operands) => {
+                let fields = match **kind {
+                    mir::AggregateKind::Array(_) => false,
+                    mir::AggregateKind::Tuple => true,
+                    mir::AggregateKind::Adt(def_id, _, _, _, None)
+                        if self.tcx.adt_def(def_id).is_struct() =>
+                    {
+                        true
+                    }
+                    _ => return,
+                };
+                for (i, operand) in operands.iter().enumerate() {
+                    let target = step(if fields { Step::Field(i) } else { Step::Elem });
+                    let operand_ty = operand.ty(&self.body.local_decls, self.tcx);
+                    self.unify_operand(target, operand_ty, operand);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Instantiates the summary of the called function, if it is analyzed.
+    fn call(&mut self, func: &Operand<'tcx>, args: &[&Operand<'tcx>], destination: Place<'tcx>) {
+        let Some((def_id, _)) = func.const_fn_def() else {
+            return;
+        };
+        let Some(callee) = def_id.as_local() else {
+            return;
+        };
+        let summaries = self.summaries;
+        let Some(summary) = summaries.get(&callee) else {
+            return;
+        };
+
+        let mut classes: HashMap<usize, Vec<Key>> = HashMap::new();
+        let mut bound = Vec::new();
+        for ((local, path), class) in summary.iter() {
+            let caller = if *local == mir::RETURN_PLACE {
+                self.key(destination)
+            } else {
+                args.get(local.as_usize() - 1)
+                    .and_then(|arg| self.operand_key(arg))
+            };
+            let Some(mut caller) = caller else {
+                continue;
+            };
+            caller.1.extend(path.iter().copied());
+            bound.push(((*local, path.clone()), caller.clone()));
+            classes.entry(*class).or_default().push(caller);
+        }
+        for members in classes.into_values() {
+            self.types.unify_all(members);
+        }
+        self.types.calls.push(CallSite { callee, bound });
+    }
+}
+
+/// The places of every analyzed function, unioned as described in the module documentation.
+pub struct StaticTypes {
+    bodies: HashMap<LocalDefId, BodyTypes>,
+}
+
+impl StaticTypes {
+    /// Analyzes the bodies of `fns`.
+    ///
+    /// Must be called before the analysis of the crate: the promoted MIR of each body is cloned,
+    /// as borrowck steals it.
+    pub fn infer<'tcx>(tcx: TyCtxt<'tcx>, fns: &[LocalDefId]) -> Self {
+        let promoted: HashMap<LocalDefId, &'tcx mir::Body<'tcx>> = fns
+            .iter()
+            .map(|&ldid| {
+                let body = tcx.mir_promoted(ldid).0.borrow().clone();
+                (ldid, &*tcx.arena.alloc(body))
+            })
+            .collect();
+
+        // Summaries only grow coarser, so this terminates.
+        let mut summaries: HashMap<LocalDefId, Summary> = HashMap::new();
+        let mut bodies: HashMap<LocalDefId, BodyTypes>;
+        loop {
+            bodies = fns
+                .iter()
+                .map(|&ldid| {
+                    let visitor = BodyVisitor {
+                        tcx,
+                        body: promoted[&ldid],
+                        summaries: &summaries,
+                        types: BodyTypes::default(),
+                    };
+                    (ldid, visitor.visit())
+                })
+                .collect();
+            let mut changed = false;
+            for (ldid, types) in bodies.iter_mut() {
+                let summary = types.summary();
+                if summaries.get(ldid) != Some(&summary) {
+                    summaries.insert(*ldid, summary);
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+
+        // Unions the interface of every callee whose arguments share a class at a call site.
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for caller in fns {
+                let calls = bodies[caller].calls.clone();
+                for call in calls {
+                    let mut classes: HashMap<usize, Vec<Key>> = HashMap::new();
+                    for (callee_key, caller_key) in call.bound {
+                        let caller_types = bodies.get_mut(caller).unwrap();
+                        let node = caller_types.node(caller_key);
+                        let root = caller_types.uf.find(node);
+                        classes.entry(root).or_default().push(callee_key);
+                    }
+                    let callee_types = bodies.get_mut(&call.callee).unwrap();
+                    for members in classes.into_values() {
+                        for member in members.iter().skip(1) {
+                            changed |= callee_types.unify(members[0].clone(), member.clone());
+                        }
+                    }
+                }
+            }
+        }
+
+        Self { bodies }
+    }
+
+    /// Partition of the variables the runtime binds at a site of `ldid`: the parameters, named
+    /// after the patterns of the HIR, minus those `dead` reports, and, if `with_return`, the
+    /// return value. Types are numbered canonically, like the runtime does.
+    pub fn partition(
+        &mut self,
+        tcx: TyCtxt<'_>,
+        ldid: LocalDefId,
+        with_return: bool,
+        dead: impl Fn(&str) -> bool,
+    ) -> Vec<(String, usize)> {
+        let params: Vec<Option<String>> = tcx
+            .hir_body_owned_by(ldid)
+            .params
+            .iter()
+            .map(|param| Some(param.pat.simple_ident()?.name.to_string()))
+            .collect();
+        let types = self.bodies.get_mut(&ldid).unwrap();
+
+        let mut vars: Vec<(String, usize)> = Vec::new();
+        let interface = types.interface.clone();
+        for ((local, path), suffix) in interface {
+            let base = if local == mir::RETURN_PLACE {
+                if !with_return {
+                    continue;
+                }
+                decls_gen::decls::RETURN_VAR_NAME.to_string()
+            } else {
+                match &params[local.as_usize() - 1] {
+                    Some(name) if !dead(name) => name.clone(),
+                    _ => continue,
+                }
+            };
+            let Some(suffix) = suffix else {
+                continue;
+            };
+            let node = types.node((local, path));
+            vars.push((base + &suffix, types.uf.find(node)));
+        }
+
+        vars.sort();
+        let mut numbers: HashMap<usize, usize> = HashMap::new();
+        vars.into_iter()
+            .map(|(var, root)| {
+                let next = numbers.len();
+                (var, *numbers.entry(root).or_insert(next))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,239 @@
+//! `datir static <FILE>`: infers the abstract types of the crate rooted at `FILE` statically,
+//! without running it, and optionally compares them with the partition observed by a run.
+//!
+//! Like Lackwit, the analysis is a flow-insensitive unification over the MIR of every function
+//! with ENTER and EXIT ppts, see [mir]. Its partitions are those of the variables the runtime
+//! binds at these ppts (formals and their fields, live formals and `return` at EXIT), numbered
+//! canonically the same way, so that they can be read, merged or diffed like the output of a
+//! run. They are printed as a report, or written as an `.ati` file with `--output`. The
+//! dependencies passed with `--instrument-crates` are analyzed the same way, each on its own.
+//!
+//! The static partition holds for every execution, so it is usually coarser than a run's, which
+//! only unions the values it saw interact: a variable whose class is larger statically points at
+//! an interaction no run exercised (or at a branch the analysis can't rule out). Conversely, a
+//! class larger dynamically points at interactions the analysis can't see, e.g. through calls to
+//! the standard library, or values the runtime tracks through enums. `--compare` lists both.
+
+mod mir;
+
+use std::collections::BTreeMap;
+
+use decls_gen::DeclsFile;
+use rustc_driver::Compilation;
+use rustc_hir::def::DefKind;
+use rustc_interface::interface;
+use rustc_middle::ty::TyCtxt;
+
+use crate::{
+    DatirError, args,
+    ati_file::{self, AtiFile},
+    callbacks::codegen::probes,
+    config::{self, InstrumentedCrate},
+    dependencies, diff,
+};
+use mir::StaticTypes;
+
+/// Marker preceding the comparison with a run, following the report.
+const COMPARISON_START: &str = "===STATIC-DYNAMIC-COMPARISON===";
+
+/// Callbacks of the compilation inferring the abstract types of the crate.
+struct StaticCallbacks {
+    decls_file: DeclsFile,
+    partition: Option<AtiFile>,
+}
+
+impl StaticCallbacks {
+    /// Infers the partition of the ENTER and EXIT ppt of every function the `.decls` file
+    /// declares.
+    fn infer(&self, tcx: TyCtxt<'_>) -> AtiFile {
+        let fns: Vec<_> = tcx
+            .hir_body_owners()
+            .filter(|&ldid| matches!(tcx.def_kind(ldid), DefKind::Fn | DefKind::AssocFn))
+            .filter(|&ldid| {
+                let base = DeclsFile::ppt_base_name(tcx, ldid);
+                self.decls_file.enter_ppt(&base).is_some()
+                    && self.decls_file.exit_ppt(&base).is_some()
+            })
+            .collect();
+        let mut types = StaticTypes::infer(tcx, &fns);
+
+        let mut ati = AtiFile::default();
+        for ldid in fns {
+            let base = DeclsFile::ppt_base_name(tcx, ldid);
+            let exit_ppt = self
+                .decls_file
+                .exit_ppt(&base)
+                .expect("only functions with an EXIT ppt are analyzed");
+            let enter = types.partition(tcx, ldid, false, |_| false);
+            // Like the shims, formals which are dead at EXIT aren't bound to it.
+            let exit = types.partition(tcx, ldid, true, |formal| {
+                exit_ppt
+                    .var_decl(formal.to_string())
+                    .is_some_and(|decl| decl.is_uninit())
+            });
+
+            for (kind, vars) in [("ENTER", enter), ("EXIT", exit)] {
+                let vars = vars
+                    .into_iter()
+                    .map(|(var, id)| (ati_file::escape(&var), id as u64))
+                    .collect();
+                ati.ppts
+                    .insert(ati_file::escape(&format!("{base}:::{kind}")), vars);
+            }
+        }
+        ati
+    }
+}
+
+impl rustc_driver::Callbacks for StaticCallbacks {
+    fn config(&mut self, config: &mut interface::Config) {
+        config.opts.unstable_opts.no_codegen = true;
+    }
+
+    /// Declares the probe syntax, so that crates placing probes compile.
+    fn after_crate_root_parsing(
+        &mut self,
+        compiler: &interface::Compiler,
+        krate: &mut rustc_ast::Crate,
+    ) -> Compilation {
+        probes::declare_probe_syntax(&compiler.sess.psess, krate);
+        Compilation::Continue
+    }
+
+    /// Infers the partition before the analysis of the crate, which steals the bodies the
+    /// inference reads, see [StaticTypes::infer].
+    fn after_expansion<'tcx>(
+        &mut self,
+        _compiler: &interface::Compiler,
+        tcx: TyCtxt<'tcx>,
+    ) -> Compilation {
+        self.partition = Some(self.infer(tcx));
+        Compilation::Continue
+    }
+
+    /// The analysis still runs, so that errors within the crate are reported.
+    fn after_analysis<'tcx>(
+        &mut self,
+        _compiler: &interface::Compiler,
+        _tcx: TyCtxt<'tcx>,
+    ) -> Compilation {
+        Compilation::Stop
+    }
+}
+
+/// Infers the partition of the crate compiled by `rustc_args`, for the ppts of `decls_file`.
+fn infer_crate(decls_file: DeclsFile, rustc_args: &[String]) -> AtiFile {
+    let mut callbacks = StaticCallbacks {
+        decls_file,
+        partition: None,
+    };
+    rustc_driver::run_compiler(rustc_args, &mut callbacks);
+    callbacks
+        .partition
+        .expect("The compilation of the crate stopped before its analysis.")
+}
+
+/// Prints `ati` the way an instrumented binary prints its report.
+fn print_report(ati: &AtiFile) {
+    println!("===ATI-ANALYSIS-START===");
+    for (ppt, vars) in ati.ppts.iter() {
+        println!("{}", ati_file::unescape(ppt));
+        for (var, id) in vars.iter() {
+            println!("{} -> {id}", ati_file::unescape(var));
+        }
+        println!("---");
+    }
+}
+
+/// Prints where the static partition `inferred` and the partition `observed` by a run disagree,
+/// over the ppts and variables both hold.
+fn print_comparison(inferred: &AtiFile, observed: &AtiFile) {
+    println!("{COMPARISON_START}");
+    let diff = diff::diff(inferred, observed);
+    for ppt in diff.removed.iter() {
+        println!("not reached by the run: {}", ati_file::unescape(ppt));
+    }
+    for ppt in diff.added.iter() {
+        println!("not analyzed statically: {}", ati_file::unescape(ppt));
+    }
+
+    let mut disagreements: BTreeMap<&String, Vec<String>> = BTreeMap::new();
+    for (ppt, ppt_diff) in diff.changed.iter() {
+        for (var, static_class, dynamic_class) in ppt_diff.changed.iter() {
+            disagreements.entry(ppt).or_default().push(format!(
+                "  {}: static {}, dynamic {}",
+                ati_file::unescape(var),
+                diff::describe_class(static_class),
+                diff::describe_class(dynamic_class)
+            ));
+        }
+    }
+    if disagreements.is_empty() {
+        println!("The static and dynamic partitions agree.");
+        return;
+    }
+    for (ppt, lines) in disagreements.iter() {
+        println!("{}", ati_file::unescape(ppt));
+        for line in lines {
+            println!("{line}");
+        }
+    }
+    println!(
+        "The static and dynamic partitions disagree at {} of {} program points.",
+        disagreements.len(),
+        inferred.ppts.len()
+    );
+}
+
+/// Entry point of `datir static`. `raw_args` are the arguments following the subcommand name.
+pub fn main(program: &str, raw_args: Vec<String>) -> Result<(), DatirError> {
+    let args = args::static_arg_init(program).parse_or_exit(raw_args);
+    let file = args
+        .get_value("file")
+        .expect("parser guarantees `file` is present")
+        .to_string();
+    let observed = args.get_value("compare").map(|path| {
+        let path = std::path::PathBuf::from(path);
+        AtiFile::read_any(&path).unwrap_or_else(|e| panic!("Unable to parse {path:?}: {e}"))
+    });
+
+    // Allowlisted dependencies are analyzed on their own, with their own `.decls` file, as their
+    // bodies aren't available to the analysis of the crate root. Calls into them union nothing.
+    let crates = args
+        .get_value("instrument-crates")
+        .map(InstrumentedCrate::parse_allowlist)
+        .unwrap_or_default();
+    let mut inferred = AtiFile::default();
+    let build_dir = config::RemoveDirOnDrop(config::default_build_dir());
+    let plain_dir = build_dir.0.join("plain");
+    let dependency_args =
+        dependencies::build_plain_dependencies(&crates, &plain_dir, |dep, rustc_args| {
+            let decls_file = DeclsFile::from_source_file(&dep.root, None);
+            let partition = infer_crate(decls_file, rustc_args);
+            inferred.ppts.extend(partition.ppts);
+        })?;
+
+    // Ppt names embed the path of the crate root, so `file` must be spelled the way it was when
+    // the crate was instrumented.
+    let decls_file = DeclsFile::from_source_file(&std::path::PathBuf::from(&file), None);
+    let mut rustc_args = vec!["datir".to_string(), file];
+    rustc_args.extend(dependency_args);
+    let partition = infer_crate(decls_file, &rustc_args);
+    inferred.ppts.extend(partition.ppts);
+
+    match args.get_value("output") {
+        Some(output) => {
+            let output = std::path::PathBuf::from(output);
+            let mut writer = std::fs::File::create(&output)
+                .unwrap_or_else(|e| panic!("Unable to create .ati file {output:?}: {e}"));
+            inferred
+                .write(&mut writer)
+                .expect("Unable to write the inferred .ati file.");
+        }
+        None => print_report(&inferred),
+    }
+    if let Some(observed) = observed {
+        print_comparison(&inferred, &observed);
+    }
+    Ok(())
+}
@@ -0,0 +1,323 @@
+//! Resolves the `(ppt, variable)` members of a program-wide abstract type to the places of the
+//! source code which declare their type.
+//!
+//! Members are resolved through the HIR of the crate, by matching ppt names against the base ppt
+//! names decls-gen gives to functions and structs (see [DeclsFile::ppt_base_name]):
+//! - a formal of a `:::ENTER` / `:::EXIT` ppt resolves to the type of the parameter, and
+//!   `return` to the return type of the function,
+//! - `x.f` (or `this.f` at an `:::OBJECT` ppt) resolves to the type of the field `f` of the
+//!   struct `x` holds, following nested fields through their types,
+//! - a variable bound by a `:::PROBE_*` ppt which isn't a formal resolves to its `let`
+//!   statement, which gains a type annotation if it has none.
+//!
+//! Only types written as a primitive (possibly behind references, e.g. `&mut usize`) resolve, as
+//! those are what a newtype replaces. Anything else (array elements, string lengths, members
+//! within external crates, ...) is reported as unresolved, alongside the reason why.
+
+use std::collections::HashMap;
+
+use decls_gen::DeclsFile;
+use rustc_hir::def_id::LocalDefId;
+use rustc_middle::ty::{Ty, TyCtxt};
+use rustc_span::Span;
+
+use crate::callbacks::codegen::probes;
+
+/// A byte range of a source file, alongside the line / column information rustfix expects.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SourceSpan {
+    pub file: String,
+    pub byte_start: usize,
+    pub byte_end: usize,
+    /// 1-based line of `byte_start`.
+    pub line_start: usize,
+    pub line_end: usize,
+    /// 1-based column (in chars) of `byte_start`.
+    pub column_start: usize,
+    pub column_end: usize,
+    /// Contents of the source line `line_start`.
+    pub text: String,
+}
+
+/// Where the type of a member is declared.
+#[derive(Debug, Clone)]
+pub struct Location {
+    /// What is declared, e.g. "parameter `len` of `sum`".
+    pub description: String,
+    /// Name of the parameter, field or local, or `return`.
+    pub ident: String,
+    /// Primitive type currently declared, e.g. `usize`.
+    pub primitive: String,
+    /// Span of the primitive type, or, for a `let` without a type annotation, the empty span
+    /// following its binding, where one can be inserted.
+    pub span: SourceSpan,
+    /// Whether the type is written at `span`, rather than inferred by the compiler.
+    pub annotated: bool,
+}
+
+/// Resolves members of program-wide abstract types to [Location]s.
+pub struct Resolver<'tcx> {
+    tcx: TyCtxt<'tcx>,
+    /// Every function and method with a body, keyed by base ppt name.
+    fns: HashMap<String, LocalDefId>,
+    /// Every free struct, keyed by base ppt name.
+    structs: HashMap<String, LocalDefId>,
+}
+
+impl<'tcx> Resolver<'tcx> {
+    /// Indexes the functions and structs of the crate by base ppt name.
+    pub fn new(tcx: TyCtxt<'tcx>) -> Self {
+        let fns = tcx
+            .hir_body_owners()
+            .filter(|&ldid| tcx.hir_node_by_def_id(ldid).fn_decl().is_some())
+            .map(|ldid| (DeclsFile::ppt_base_name(tcx, ldid), ldid))
+            .collect();
+        let structs = tcx
+            .hir_free_items()
+            .map(|item_id| tcx.hir_item(item_id))
+            .filter(|item| matches!(item.kind, rustc_hir::ItemKind::Struct(..)))
+            .map(|item| {
+                let ldid = item.owner_id.def_id;
+                (DeclsFile::ppt_base_name(tcx, ldid), ldid)
+            })
+            .collect();
+        Self { tcx, fns, structs }
+    }
+
+    /// Names of every item of the crate, which suggested newtypes must not shadow.
+    pub fn item_names(&self) -> std::collections::HashSet<String> {
+        self.tcx
+            .hir_free_items()
+            .filter_map(|item_id| self.tcx.hir_item(item_id).kind.ident())
+            .map(|ident| ident.to_string())
+            .collect()
+    }
+
+    /// Resolves the variable `var` of the ppt `ppt`, or gives the reason it can't be.
+    pub fn resolve(&self, ppt: &str, var: &str) -> Result<Location, &'static str> {
+        let (base, kind) = ppt
+            .rsplit_once(":::")
+            .ok_or("not the name of a program point")?;
+
+        if kind == "OBJECT" {
+            let ldid = *self
+                .structs
+                .get(base)
+                .ok_or("struct not found within the crate")?;
+            let fields: Vec<&str> = var
+                .strip_prefix("this.")
+                .ok_or("not a field of the struct")?
+                .split('.')
+                .collect();
+            return self.field_location(self.tcx.type_of(ldid).instantiate_identity(), &fields);
+        }
+
+        let ldid = *self
+            .fns
+            .get(base)
+            .ok_or("function not found within the crate")?;
+        let fn_name = self.tcx.item_name(ldid.to_def_id());
+        let decl = self
+            .tcx
+            .hir_node_by_def_id(ldid)
+            .fn_decl()
+            .expect("only functions are indexed");
+
+        if var == "return" {
+            let rustc_hir::FnRetTy::Return(ty) = decl.output else {
+                return Err("function returns nothing");
+            };
+            let (span, primitive) = primitive_ty(ty)?;
+            return Ok(self.location(
+                span,
+                primitive,
+                format!("return type of `{fn_name}`"),
+                "return",
+                true,
+            ));
+        }
+
+        let mut segments = var.split('.');
+        let root = segments.next().unwrap_or_default();
+        let fields: Vec<&str> = segments.collect();
+        let body = self.tcx.hir_body_owned_by(ldid);
+
+        if let Some(i) = body.params.iter().position(|param| {
+            param
+                .pat
+                .simple_ident()
+                .is_some_and(|id| id.as_str() == root)
+        }) {
+            if !fields.is_empty() {
+                let ty = self
+                    .tcx
+                    .fn_sig(ldid)
+                    .instantiate_identity()
+                    .skip_binder()
+                    .inputs()[i];
+                return self.field_location(ty, &fields);
+            }
+            let (span, primitive) = primitive_ty(&decl.inputs[i])?;
+            return Ok(self.location(
+                span,
+                primitive,
+                format!("parameter `{root}` of `{fn_name}`"),
+                root,
+                true,
+            ));
+        }
+
+        if !kind.starts_with(probes::PROBE_PPT_KIND) {
+            return Err("not a parameter or field");
+        }
+        let mut finder = FindLocal {
+            name: root,
+            found: None,
+        };
+        rustc_hir::intravisit::Visitor::visit_body(&mut finder, body);
+        let local = finder
+            .found
+            .ok_or("local not declared by a `let` statement")?;
+        let inferred = self.tcx.typeck(ldid).node_type(local.pat.hir_id);
+        if !fields.is_empty() {
+            return self.field_location(inferred, &fields);
+        }
+        let description = format!("local `{root}` of `{fn_name}`");
+        match local.ty {
+            Some(ty) => {
+                let (span, primitive) = primitive_ty(ty)?;
+                Ok(self.location(span, primitive, description, root, true))
+            }
+            None if inferred.is_primitive() => Ok(self.location(
+                local.pat.span.shrink_to_hi(),
+                inferred.to_string(),
+                description,
+                root,
+                false,
+            )),
+            None => Err("local not of a primitive type"),
+        }
+    }
+
+    /// Resolves the field reached by following `fields` from a value of type `ty`.
+    fn field_location(&self, mut ty: Ty<'tcx>, fields: &[&str]) -> Result<Location, &'static str> {
+        for (i, name) in fields.iter().enumerate() {
+            let rustc_middle::ty::Adt(adt, _) = ty.peel_refs().kind() else {
+                return Err("field of a value which isn't a struct");
+            };
+            if !adt.is_struct() {
+                return Err("field of a value which isn't a struct");
+            }
+            let field = adt
+                .non_enum_variant()
+                .fields
+                .iter()
+                .find(|field| field.name.as_str() == *name)
+                .ok_or("unknown field")?;
+            if i + 1 < fields.len() {
+                ty = self.tcx.type_of(field.did).instantiate_identity();
+                continue;
+            }
+
+            let ldid = field
+                .did
+                .as_local()
+                .ok_or("field of a struct defined outside of the crate")?;
+            let rustc_hir::Node::Field(def) = self.tcx.hir_node_by_def_id(ldid) else {
+                return Err("field of a struct defined outside of the crate");
+            };
+            let (span, primitive) = primitive_ty(def.ty)?;
+            let struct_name = self.tcx.item_name(adt.did());
+            return Ok(self.location(
+                span,
+                primitive,
+                format!("field `{name}` of `{struct_name}`"),
+                name,
+                true,
+            ));
+        }
+        Err("not a field")
+    }
+
+    fn location(
+        &self,
+        span: Span,
+        primitive: impl ToString,
+        description: String,
+        ident: &str,
+        annotated: bool,
+    ) -> Location {
+        Location {
+            description,
+            ident: ident.to_string(),
+            primitive: primitive.to_string(),
+            span: source_span(self.tcx, span),
+            annotated,
+        }
+    }
+}
+
+/// The span and name of the primitive type `ty` is written as, behind any references.
+fn primitive_ty<'hir>(ty: &'hir rustc_hir::Ty<'hir>) -> Result<(Span, &'static str), &'static str> {
+    if ty.span.from_expansion() {
+        return Err("type written by a macro");
+    }
+    match &ty.kind {
+        rustc_hir::TyKind::Ref(_, rustc_hir::MutTy { ty, .. }) => primitive_ty(ty),
+        rustc_hir::TyKind::Path(rustc_hir::QPath::Resolved(None, path)) => match path.res {
+            // Unsized, so can't be wrapped as is.
+            rustc_hir::def::Res::PrimTy(rustc_hir::PrimTy::Str) => Err("type is `str`"),
+            rustc_hir::def::Res::PrimTy(prim) => Ok((ty.span, prim.name_str())),
+            _ => Err("type is not a primitive"),
+        },
+        _ => Err("type is not a primitive"),
+    }
+}
+
+/// Locates `span` within its source file.
+fn source_span(tcx: TyCtxt<'_>, span: Span) -> SourceSpan {
+    let sm = tcx.sess.source_map();
+    let lo = sm.lookup_char_pos(span.lo());
+    let hi = sm.lookup_char_pos(span.hi());
+    let file = match &lo.file.name {
+        rustc_span::FileName::Real(rfn) => rfn
+            .path(rustc_span::RemapPathScopeComponents::MACRO)
+            .display()
+            .to_string(),
+        name => panic!("Source file {name:?} of a declared type is not a real file."),
+    };
+    SourceSpan {
+        file,
+        byte_start: sm.lookup_byte_offset(span.lo()).pos.0 as usize,
+        byte_end: sm.lookup_byte_offset(span.hi()).pos.0 as usize,
+        line_start: lo.line,
+        line_end: hi.line,
+        column_start: lo.col.0 + 1,
+        column_end: hi.col.0 + 1,
+        text: lo
+            .file
+            .get_line(lo.line - 1)
+            .map(|line| line.to_string())
+            .unwrap_or_default(),
+    }
+}
+
+/// Finds the first `let` statement of a body binding `name`.
+struct FindLocal<'a, 'tcx> {
+    name: &'a str,
+    found: Option<&'tcx rustc_hir::LetStmt<'tcx>>,
+}
+
+impl<'tcx> rustc_hir::intravisit::Visitor<'tcx> for FindLocal<'_, 'tcx> {
+    fn visit_local(&mut self, local: &'tcx rustc_hir::LetStmt<'tcx>) {
+        if self.found.is_none()
+            && local
+                .pat
+                .simple_ident()
+                .is_some_and(|ident| ident.as_str() == self.name)
+        {
+            self.found = Some(local);
+        }
+        rustc_hir::intravisit::walk_local(self, local);
+    }
+}
@@ -0,0 +1,340 @@
+//! `datir suggest <FILE> -t <TYPES_PATH>`: proposes newtype declarations for the program-wide
+//! abstract types reported by a `--global-types` run of the crate rooted at `FILE`.
+//!
+//! Every member of a program-wide type is resolved to the place declaring its type (a parameter,
+//! a return type, a struct field, or a local bound by a probe), see [locations]. The types of
+//! those places can then be replaced by a newtype, keeping values of different abstract types
+//! apart at compile time. Types which must be declared the same (e.g. two functions whose
+//! parameters are of different abstract types, but read the same struct field) share a newtype.
+//!
+//! A newtype is only suggested for types whose places all declare the same primitive, and only
+//! if some other type is declared as that primitive too (a program with a single abstract type
+//! of `usize` gains nothing from wrapping it). Each is named after the parameter, field or local
+//! name most of its places share.
+//!
+//! Suggestions are listed on stdout, and written as rustc JSON diagnostics which rustfix can
+//! apply, see [rustfix]. Only declarations are rewritten: function bodies building or reading
+//! values of the newtypes still have to be adjusted by hand.
+
+mod locations;
+mod rustfix;
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use rustc_driver::Compilation;
+use rustc_interface::interface;
+use rustc_middle::ty::TyCtxt;
+
+use crate::{
+    DatirError, args,
+    callbacks::codegen::probes,
+    global_types_file::{self, GlobalType},
+    union_find::UnionFind,
+};
+use locations::{Location, Resolver, SourceSpan};
+
+/// A newtype to introduce.
+#[derive(Debug)]
+pub struct Newtype {
+    pub name: String,
+    /// Primitive type wrapped by the newtype.
+    pub primitive: String,
+    /// Ids of the program-wide abstract types the newtype stands for.
+    pub type_ids: Vec<usize>,
+    /// Places whose type becomes the newtype, in source order.
+    pub locations: Vec<Location>,
+}
+
+impl Newtype {
+    /// Source code declaring the newtype.
+    pub fn declaration(&self) -> String {
+        // Floats are neither `Eq`, `Ord` nor `Hash`.
+        let derives = if self.primitive.starts_with('f') {
+            "Debug, Clone, Copy, PartialEq, PartialOrd"
+        } else {
+            "Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash"
+        };
+        format!(
+            "\n#[derive({derives})]\npub struct {}(pub {});\n",
+            self.name, self.primitive
+        )
+    }
+}
+
+/// Newtypes to suggest, and why the other types aren't.
+#[derive(Debug, Default)]
+pub struct Plan {
+    pub newtypes: Vec<Newtype>,
+    /// Why some program-wide types are left as they are.
+    pub skipped: Vec<String>,
+    /// Members which couldn't be resolved to a place, with the reason why.
+    pub unresolved: BTreeSet<(String, String, &'static str)>,
+}
+
+/// Decides which newtypes to suggest, see the module documentation.
+fn plan(types: &[GlobalType], resolver: &Resolver<'_>) -> Plan {
+    let mut plan = Plan::default();
+
+    // Places of each type, deduplicated (a formal is declared once, but bound at ENTER and EXIT).
+    let located: Vec<BTreeMap<SourceSpan, Location>> = types
+        .iter()
+        .map(|ty| {
+            let mut places = BTreeMap::new();
+            for (ppt, var) in ty.members.iter() {
+                match resolver.resolve(ppt, var) {
+                    Ok(location) => {
+                        places.insert(location.span.clone(), location);
+                    }
+                    Err(reason) => {
+                        plan.unresolved.insert((ppt.clone(), var.clone(), reason));
+                    }
+                }
+            }
+            places
+        })
+        .collect();
+
+    // A place declares a single type, so types sharing a place have to share a newtype.
+    let mut classes = UnionFind::new(types.len());
+    let mut owner: BTreeMap<&SourceSpan, usize> = BTreeMap::new();
+    for (i, places) in located.iter().enumerate() {
+        for span in places.keys() {
+            let first = *owner.entry(span).or_insert(i);
+            classes.union(first, i);
+        }
+    }
+    let mut groups: BTreeMap<usize, (Vec<usize>, BTreeMap<SourceSpan, Location>)> = BTreeMap::new();
+    for (i, places) in located.iter().enumerate() {
+        if places.is_empty() {
+            plan.skipped.push(format!(
+                "type {}: no member is declared by a parameter, field or local",
+                types[i].id
+            ));
+            continue;
+        }
+        let (ids, group_places) = groups.entry(classes.find(i)).or_default();
+        ids.push(types[i].id);
+        group_places.extend(places.iter().map(|(span, loc)| (span.clone(), loc.clone())));
+    }
+
+    // Groups declared as a single primitive, alongside how many there are of each primitive.
+    let mut candidates = Vec::new();
+    let mut per_primitive: BTreeMap<String, usize> = BTreeMap::new();
+    for (ids, places) in groups.into_values() {
+        let primitives: BTreeSet<&str> =
+            places.values().map(|loc| loc.primitive.as_str()).collect();
+        let described = describe_ids(&ids);
+        if primitives.len() > 1 {
+            let primitives: Vec<String> = primitives.iter().map(|p| format!("`{p}`")).collect();
+            plan.skipped.push(format!(
+                "{described}: declared as {}",
+                primitives.join(" and ")
+            ));
+            continue;
+        }
+        let primitive = primitives.into_iter().next().unwrap().to_string();
+        *per_primitive.entry(primitive.clone()).or_default() += 1;
+        candidates.push((ids, primitive, places));
+    }
+
+    let mut taken = resolver.item_names();
+    for (ids, primitive, places) in candidates {
+        if per_primitive[&primitive] < 2 {
+            plan.skipped.push(format!(
+                "{}: the only abstract type declared as `{primitive}`",
+                describe_ids(&ids)
+            ));
+            continue;
+        }
+        let locations: Vec<Location> = places.into_values().collect();
+        let name = newtype_name(&locations, ids[0], &mut taken);
+        plan.newtypes.push(Newtype {
+            name,
+            primitive,
+            type_ids: ids,
+            locations,
+        });
+    }
+    plan
+}
+
+/// Describes a group of program-wide types, e.g. "types 0, 3".
+fn describe_ids(ids: &[usize]) -> String {
+    let ids: Vec<String> = ids.iter().map(usize::to_string).collect();
+    match ids.len() {
+        1 => format!("type {}", ids[0]),
+        _ => format!("types {}", ids.join(", ")),
+    }
+}
+
+/// Names a newtype after the ident most of its `locations` share (ties broken
+/// lexicographically), in UpperCamelCase, e.g. `Len` for parameters named `len`. Return types
+/// don't name anything, so are ignored. Names in `taken` are avoided, and `taken` is updated.
+fn newtype_name(locations: &[Location], id: usize, taken: &mut HashSet<String>) -> String {
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for loc in locations.iter().filter(|loc| loc.ident != "return") {
+        *counts.entry(loc.ident.as_str()).or_default() += 1;
+    }
+    // `max_by_key` keeps the last maximum, iterate in reverse to keep the smallest ident.
+    let ident = counts
+        .into_iter()
+        .rev()
+        .max_by_key(|(_, count)| *count)
+        .map(|(ident, _)| ident)
+        .unwrap_or_default();
+
+    let camel: String = ident
+        .split('_')
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .unwrap()
+                .to_uppercase()
+                .chain(chars)
+                .collect::<String>()
+        })
+        .collect();
+    // Tuple struct fields (`0`, `1`, ...) and return values don't make for names.
+    let base = match camel.chars().next() {
+        Some(c) if c.is_alphabetic() => camel,
+        _ => format!("Type{id}"),
+    };
+
+    let mut name = base.clone();
+    let mut suffix = 2;
+    while taken.contains(&name) {
+        name = format!("{base}{suffix}");
+        suffix += 1;
+    }
+    taken.insert(name.clone());
+    name
+}
+
+/// The empty span at the end of the file at `path`, where newtypes are declared.
+fn end_of_file(path: &str) -> SourceSpan {
+    let contents = std::fs::read_to_string(path)
+        .unwrap_or_else(|e| panic!("Unable to read source file {path:?}: {e}"));
+    let last_line = contents.rsplit('\n').next().unwrap_or_default();
+    let line = contents.matches('\n').count() + 1;
+    let column = last_line.chars().count() + 1;
+    SourceSpan {
+        file: path.to_string(),
+        byte_start: contents.len(),
+        byte_end: contents.len(),
+        line_start: line,
+        line_end: line,
+        column_start: column,
+        column_end: column,
+        text: last_line.to_string(),
+    }
+}
+
+/// Callbacks of the compilation resolving the members of program-wide types.
+struct SuggestCallbacks {
+    types: Vec<GlobalType>,
+    plan: Option<Plan>,
+}
+
+impl rustc_driver::Callbacks for SuggestCallbacks {
+    fn config(&mut self, config: &mut interface::Config) {
+        config.opts.unstable_opts.no_codegen = true;
+    }
+
+    /// Declares the probe syntax, so that crates placing probes compile.
+    fn after_crate_root_parsing(
+        &mut self,
+        compiler: &interface::Compiler,
+        krate: &mut rustc_ast::Crate,
+    ) -> Compilation {
+        probes::declare_probe_syntax(&compiler.sess.psess, krate);
+        Compilation::Continue
+    }
+
+    fn after_analysis<'tcx>(
+        &mut self,
+        _compiler: &interface::Compiler,
+        tcx: TyCtxt<'tcx>,
+    ) -> Compilation {
+        self.plan = Some(plan(&self.types, &Resolver::new(tcx)));
+        Compilation::Stop
+    }
+}
+
+/// Entry point of `datir suggest`. `raw_args` are the arguments following the subcommand name.
+pub fn main(program: &str, raw_args: Vec<String>) -> Result<(), DatirError> {
+    let args = args::suggest_arg_init(program).parse_or_exit(raw_args);
+    let file = args
+        .get_value("file")
+        .expect("parser guarantees `file` is present")
+        .to_string();
+    let types_path = std::path::PathBuf::from(
+        args.get_value("types")
+            .expect("parser guarantees `types` is present"),
+    );
+    let output = match args.get_value("output") {
+        Some(output) => std::path::PathBuf::from(output),
+        None => std::path::Path::new(&file).with_extension("suggestions.json"),
+    };
+
+    let types = global_types_file::read(&types_path)
+        .unwrap_or_else(|e| panic!("Unable to parse {types_path:?}: {e}"));
+    if types.is_empty() {
+        return Err(DatirError::BadInput(
+            "The given report holds no program-wide abstract types.",
+        ));
+    }
+
+    // Ppt names embed the path of the crate root, so `file` must be spelled the way it was when
+    // the crate was instrumented.
+    let mut callbacks = SuggestCallbacks { types, plan: None };
+    rustc_driver::run_compiler(&["datir".to_string(), file.clone()], &mut callbacks);
+    let plan = callbacks
+        .plan
+        .expect("The compilation of the crate stopped before its analysis.");
+
+    for ty in plan.newtypes.iter() {
+        println!(
+            "newtype {}({}), for abstract {}:",
+            ty.name,
+            ty.primitive,
+            describe_ids(&ty.type_ids)
+        );
+        for loc in ty.locations.iter() {
+            println!(
+                "  {}:{}:{} {}",
+                loc.span.file, loc.span.line_start, loc.span.column_start, loc.description
+            );
+        }
+    }
+    if !plan.skipped.is_empty() {
+        println!("Left as is:");
+        for reason in plan.skipped.iter() {
+            println!("  {reason}");
+        }
+    }
+    if !plan.unresolved.is_empty() {
+        println!("Unresolved members:");
+        for (ppt, var, reason) in plan.unresolved.iter() {
+            println!("  {ppt} {var}: {reason}");
+        }
+    }
+
+    if plan.newtypes.is_empty() {
+        println!("No newtypes to suggest.");
+        return Ok(());
+    }
+    std::fs::write(
+        &output,
+        rustfix::render(&plan.newtypes, &end_of_file(&file)),
+    )
+    .unwrap_or_else(|e| panic!("Unable to write suggestions {output:?}: {e}"));
+    println!(
+        "Wrote {} newtype suggestions to {}",
+        plan.newtypes.len(),
+        output.display()
+    );
+
+    Ok(())
+}
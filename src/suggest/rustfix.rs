@@ -0,0 +1,134 @@
+//! Writes suggested newtypes as rustc JSON diagnostics, the format `rustfix` (and therefore
+//! `cargo fix`, or editors) consume.
+//!
+//! The declarations of every newtype are inserted at the end of the crate root by one leading
+//! diagnostic, so that no two suggestions insert text at the same position. Each newtype then gets
+//! a diagnostic of its own, whose spans are every place declaring the type of one of its values,
+//! and whose suggestions replace each of those types by the newtype. Written types are replaced
+//! with `MachineApplicable` suggestions, while inferred types of `let` statements are annotated
+//! with `MaybeIncorrect` ones, as the annotation may need the initializer to be wrapped as well.
+
+use serde_json::{Value, json};
+
+use crate::suggest::{Newtype, locations::SourceSpan};
+
+/// Renders the span of a diagnostic. `replacement` is the suggested text and its applicability.
+fn span_json(span: &SourceSpan, label: Option<&str>, replacement: Option<(&str, &str)>) -> Value {
+    // `text` highlights the span within its first line, in 1-based chars.
+    let highlight_end = if span.line_end == span.line_start {
+        span.column_end
+    } else {
+        span.text.chars().count() + 1
+    };
+    json!({
+        "file_name": span.file,
+        "byte_start": span.byte_start,
+        "byte_end": span.byte_end,
+        "line_start": span.line_start,
+        "line_end": span.line_end,
+        "column_start": span.column_start,
+        "column_end": span.column_end,
+        "is_primary": true,
+        "text": [{
+            "text": span.text,
+            "highlight_start": span.column_start,
+            "highlight_end": highlight_end,
+        }],
+        "label": label,
+        "suggested_replacement": replacement.map(|(text, _)| text),
+        "suggestion_applicability": replacement.map(|(_, applicability)| applicability),
+        "expansion": null,
+    })
+}
+
+/// Renders a diagnostic of level `level`.
+fn diagnostic(message: String, level: &str, spans: Vec<Value>, children: Vec<Value>) -> Value {
+    let rendered = (level == "warning").then(|| format!("warning: {message}\n"));
+    json!({
+        "$message_type": "diagnostic",
+        "message": message,
+        "code": null,
+        "level": level,
+        "spans": spans,
+        "children": children,
+        "rendered": rendered,
+    })
+}
+
+/// Renders the suggestions of every newtype, one diagnostic per line. `declarations` is the empty
+/// span at the end of the crate root, where the newtypes are declared.
+pub fn render(newtypes: &[Newtype], declarations: &SourceSpan) -> String {
+    let mut diagnostics = Vec::with_capacity(newtypes.len() + 1);
+
+    let declared: String = newtypes.iter().map(Newtype::declaration).collect();
+    let names: Vec<String> = newtypes.iter().map(|ty| format!("`{}`", ty.name)).collect();
+    diagnostics.push(diagnostic(
+        format!(
+            "abstract types could be declared as newtypes {}",
+            names.join(", ")
+        ),
+        "warning",
+        vec![span_json(declarations, None, None)],
+        vec![diagnostic(
+            "declare the newtypes".to_string(),
+            "help",
+            vec![span_json(
+                declarations,
+                None,
+                Some((&declared, "MachineApplicable")),
+            )],
+            vec![],
+        )],
+    ));
+
+    for ty in newtypes {
+        let spans = ty
+            .locations
+            .iter()
+            .map(|loc| span_json(&loc.span, Some(&loc.description), None))
+            .collect();
+
+        let mut children = Vec::new();
+        let replace = |annotated: bool, replacement: &str, applicability: &str| -> Vec<Value> {
+            ty.locations
+                .iter()
+                .filter(|loc| loc.annotated == annotated)
+                .map(|loc| span_json(&loc.span, None, Some((replacement, applicability))))
+                .collect()
+        };
+        let replaced = replace(true, &ty.name, "MachineApplicable");
+        if !replaced.is_empty() {
+            children.push(diagnostic(
+                format!("replace `{}` with `{}`", ty.primitive, ty.name),
+                "help",
+                replaced,
+                vec![],
+            ));
+        }
+        let annotated = replace(false, &format!(": {}", ty.name), "MaybeIncorrect");
+        if !annotated.is_empty() {
+            children.push(diagnostic(
+                format!("annotate locals with `{}`", ty.name),
+                "help",
+                annotated,
+                vec![],
+            ));
+        }
+
+        diagnostics.push(diagnostic(
+            format!(
+                "{} places share an abstract type, which could be the newtype `{}`",
+                ty.locations.len(),
+                ty.name
+            ),
+            "warning",
+            spans,
+            children,
+        ));
+    }
+
+    diagnostics
+        .iter()
+        .map(|diagnostic| format!("{diagnostic}\n"))
+        .collect()
+}
@@ -0,0 +1,43 @@
+//! Minimal union-find over dense `usize` indices, used to combine partitions read back from
+//! `.ati` files (see [crate::merge] and [crate::comparability]), and to unify the places of MIR
+//! bodies (see [crate::static_ati]).
+//!
+//! The runtime's own `UnionFind` is keyed by the ids its `Tagger` hands out, and maps them onto
+//! dense indices itself, which indices read back from `.ati` files or MIR locals don't need.
+
+/// Union-find over the indices `0..len`, with path halving.
+#[derive(Debug, Default)]
+pub struct UnionFind {
+    /// Parent of each index, roots being their own parent.
+    parent: Vec<usize>,
+}
+
+impl UnionFind {
+    /// Creates a union-find with every index of `0..len` in its own set.
+    pub fn new(len: usize) -> Self {
+        Self {
+            parent: (0..len).collect(),
+        }
+    }
+
+    /// Adds a new index in its own set, returning it.
+    pub fn push(&mut self) -> usize {
+        self.parent.push(self.parent.len());
+        self.parent.len() - 1
+    }
+
+    /// Finds the root of the set containing `x`.
+    pub fn find(&mut self, mut x: usize) -> usize {
+        while self.parent[x] != x {
+            self.parent[x] = self.parent[self.parent[x]];
+            x = self.parent[x];
+        }
+        x
+    }
+
+    /// Merges the sets containing `x` and `y`.
+    pub fn union(&mut self, x: usize, y: usize) {
+        let (x, y) = (self.find(x), self.find(y));
+        self.parent[y] = x;
+    }
+}
@@ -22,9 +22,11 @@ fn assign_tuples() {
         ))
         .register("a.0", 0)
         .register("a.1.0", 1)
+        .register("a.1.1", 6)
         .register("a.2", 2)
         .register("b.0", 3)
         .register("b.1.0", 4)
+        .register("b.1.1", 7)
         .register("b.2", 5),
     );
     expected.register_site(
@@ -33,9 +35,11 @@ fn assign_tuples() {
         ))
         .register("a.0", 0)
         .register("a.1.0", 1)
+        .register("a.1.1", 6)
         .register("a.2", 2)
         .register("b.0", 3)
         .register("b.1.0", 4)
+        .register("b.1.1", 7)
         .register("b.2", 5),
     );
 
@@ -67,6 +67,10 @@ const ANALYSIS_START: &'static str = "===ATI-ANALYSIS-START===\n";
 /// Delimiter used in ATI information between different sites
 const SITE_DELIM: &'static str = "---\n";
 
+/// Delimiter printed after the per-site report when DATIR is run with `--global-types`, denoting
+/// the start of the program-wide abstract types.
+const GLOBAL_TYPES_START: &'static str = "===ATI-GLOBAL-TYPES-START===\n";
+
 /// Helper, pass in "/simple/main.rs:::ENTER" to construct:
 /// /path/from/root_dir/datir/tests/simple/main.rs
 pub fn prefix_with_path_from_root(site_from_tests: &str) -> String {
@@ -77,6 +81,11 @@ pub fn prefix_with_path_from_root(site_from_tests: &str) -> String {
 /// Compiles `{cwd}/{test_dir}/{file_name}.rs` with the added instrumentation
 /// runs it, and returns the section of the stdout stream which contains the ATI info.
 pub fn compile_and_execute(path: &Path) -> String {
+    compile_and_execute_with_args(path, &[])
+}
+
+/// Same as [compile_and_execute], passing `extra_args` to DATIR.
+pub fn compile_and_execute_with_args(path: &Path, extra_args: &[&str]) -> String {
     let invocation_dir = std::env::current_dir().unwrap();
     let full_executable = invocation_dir.join(path);
     let source = full_executable.parent().unwrap().join("main.rs");
@@ -91,6 +100,7 @@ pub fn compile_and_execute(path: &Path) -> String {
             full_executable.to_str().unwrap(),
             "--test",
         ])
+        .args(extra_args)
         .output()
         .unwrap();
 
@@ -113,6 +123,79 @@ pub fn compile_and_execute(path: &Path) -> String {
     return exec_output[(start + ANALYSIS_START.len())..].into();
 }
 
+/// Runs DATIR with the given arguments (e.g. a subcommand, like `merge`), returning its output.
+pub fn run_datir(args: &[&str]) -> std::process::Output {
+    Command::new("cargo")
+        .args(["run", "--"])
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+/// Rewrites the contents of an `.ati` file into the stdout report format, so that they can be
+/// checked using [verify].
+pub fn ati_to_report(ati: &str) -> String {
+    let mut report = String::new();
+    for line in ati.lines() {
+        match line.split_whitespace().collect::<Vec<_>>()[..] {
+            ["ppt", ppt] => report.push_str(&format!("{ppt}\n")),
+            ["var", var, id] => report.push_str(&format!("{var} -> {id}\n")),
+            [] => report.push_str(SITE_DELIM),
+            _ => panic!("Malformed .ati line: {line:?}"),
+        }
+    }
+    report
+}
+
+/// Splits the ati stdout stream of a binary instrumented with `--global-types` into the per-site
+/// report, and the program-wide abstract types following it.
+pub fn split_global_types(ati_stdout: &str) -> (&str, &str) {
+    let start = ati_stdout
+        .find(GLOBAL_TYPES_START)
+        .expect("Program-wide abstract types were not reported");
+    (
+        &ati_stdout[..start],
+        &ati_stdout[(start + GLOBAL_TYPES_START.len())..],
+    )
+}
+
+/// Checks that the program-wide abstract types reported by `--global-types` group exactly the
+/// expected `(ppt, variable)` members, regardless of how each type is numbered or named.
+pub fn verify_global_types(mut global_types: &str, expected: &[Vec<(String, &str)>]) {
+    let mut found: Vec<Vec<(String, String)>> = Vec::new();
+    while let Some(end) = global_types.find(SITE_DELIM) {
+        let mut lines = global_types[..end].lines().filter(|l| !l.is_empty());
+        let header = lines.next().expect("Found type with no header");
+        assert!(header.starts_with("type "), "Malformed type header {header:?}");
+        let mut members: Vec<(String, String)> = lines
+            .map(|line| {
+                let (ppt, var) = line
+                    .split_once(' ')
+                    .unwrap_or_else(|| panic!("Malformed type member {line:?}"));
+                (ppt.to_string(), var.to_string())
+            })
+            .collect();
+        members.sort();
+        found.push(members);
+        global_types = &global_types[(end + SITE_DELIM.len())..];
+    }
+
+    let mut expected: Vec<Vec<(String, String)>> = expected
+        .iter()
+        .map(|ty| {
+            let mut ty: Vec<_> = ty
+                .iter()
+                .map(|(ppt, var)| (ppt.clone(), var.to_string()))
+                .collect();
+            ty.sort();
+            ty
+        })
+        .collect();
+    expected.sort();
+    found.sort();
+    assert_eq!(found, expected, "Program-wide abstract types differ");
+}
+
 /// Checks that the ati stdout stream contains all the expected information,
 /// performing a partition comparison, alongside making sure the right number
 /// of sites were discovered.
@@ -142,8 +225,12 @@ pub fn verify(mut ati_stdout: &str, expected_partition: &HashMap<String, HashMap
                 var_info
             );
 
+            // The units of the abstract type, if any, follow its id, e.g. `x -> 0 [ms]`.
             let var = var_split[0].to_string();
-            let id = str::parse::<usize>(var_split[1]).unwrap_or_else(|_| {
+            let id = var_split[1]
+                .split_once(" [")
+                .map_or(var_split[1], |(id, _)| id);
+            let id = str::parse::<usize>(id).unwrap_or_else(|_| {
                 panic!(
                     "Could not parse var->id line at site {site_name}: {:?}",
                     var_info
@@ -162,6 +249,7 @@ pub fn verify(mut ati_stdout: &str, expected_partition: &HashMap<String, HashMap
         });
 
         verify_site_partition(&site_name, expected_site, &site_ati_output);
+        verify_canonical_numbering(&site_name, &site_ati_output);
 
         found_sites.insert(site_name);
         ati_stdout = &ati_stdout[(end + SITE_DELIM.len())..];
@@ -179,6 +267,24 @@ pub fn verify(mut ati_stdout: &str, expected_partition: &HashMap<String, HashMap
     );
 }
 
+/// Asserts that the ids of a site are numbered canonically: from 0, in order of the smallest
+/// variable of each abstract type, so that equal partitions are reported identically.
+fn verify_canonical_numbering(site_name: &str, actual_site: &HashMap<String, usize>) {
+    let mut vars: Vec<&String> = actual_site.keys().collect();
+    vars.sort();
+    let mut next = 0;
+    for var in vars {
+        let id = actual_site[var];
+        assert!(
+            id <= next,
+            "Non-canonical id {id} of {var} at site {site_name}, expected at most {next}"
+        );
+        if id == next {
+            next += 1;
+        }
+    }
+}
+
 /// Asserts that the partition over variables at a site, induced by the
 /// observed ATI ids, is equivalent to the expected partition.
 ///
@@ -0,0 +1,19 @@
+#![allow(unused)]
+
+#[ignore]
+fn main() {
+    let first = window(&[5, 9, 2], 0, 7);
+}
+
+// Index of the first element of `buf`, from `start` on, which reaches `threshold`.
+fn window(buf: &[u32], start: usize, threshold: u32) -> usize {
+    let mut i = start;
+    while i < buf.len() && buf[i] < threshold {
+        i += 1;
+    }
+    i
+}
+
+fn never_called(x: u32) -> u32 {
+    x
+}
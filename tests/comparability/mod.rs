@@ -0,0 +1,128 @@
+use std::{collections::BTreeMap, path::Path, process::Command};
+
+use crate::common::{delete, run_datir};
+
+/// The `comparability` of every variable of every ppt declared by the `.decls` file `decls`,
+/// keyed by ppt and then by variable.
+fn comparabilities(decls: &str) -> BTreeMap<&str, BTreeMap<&str, &str>> {
+    let mut ppts: BTreeMap<&str, BTreeMap<&str, &str>> = BTreeMap::new();
+    let (mut ppt, mut var) = ("", "");
+    for line in decls.lines() {
+        if let Some(name) = line.strip_prefix("ppt ") {
+            ppt = name;
+            ppts.insert(ppt, BTreeMap::new());
+        } else if let Some(name) = line.strip_prefix("variable ") {
+            var = name;
+        } else if let Some(comparability) = line.trim_start().strip_prefix("comparability ") {
+            ppts.get_mut(ppt).unwrap().insert(var, comparability);
+        }
+    }
+    ppts
+}
+
+/// The variables of the ppt of `ppts` whose name ends with `suffix`.
+fn ppt_ending_with<'a, 'd>(
+    ppts: &'a BTreeMap<&'d str, BTreeMap<&'d str, &'d str>>,
+    suffix: &str,
+) -> &'a BTreeMap<&'d str, &'d str> {
+    ppts.iter()
+        .find(|(name, _)| name.ends_with(suffix))
+        .unwrap_or_else(|| panic!("No ppt ending with `{suffix}` in {:?}", ppts.keys()))
+        .1
+}
+
+/// Writes the abstract types observed by a `--release` run into the `.decls` file decls-gen
+/// wrote for the same program.
+#[test]
+fn comparability() {
+    let test_dir = Path::new(file!()).parent().unwrap();
+    let source = test_dir.join("main.rs");
+    let executable = test_dir.join("comparability.out");
+    let runs = test_dir.join("runs");
+    let decls = test_dir.join("main.decls");
+    let written = test_dir.join("main.comparable.decls");
+    delete(&executable);
+    delete(&written);
+
+    let output = run_datir(&[
+        source.to_str().unwrap(),
+        "-o",
+        executable.to_str().unwrap(),
+        "--release",
+        runs.to_str().unwrap(),
+    ]);
+    assert!(
+        output.status.success(),
+        "Unable to compile {source:?}. Error output:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let run = Command::new(&executable).output().unwrap();
+    assert!(
+        run.status.success(),
+        "Unable to execute {executable:?}. Error output:\n{}",
+        String::from_utf8_lossy(&run.stderr)
+    );
+    let ati = std::fs::read_dir(&runs)
+        .unwrap()
+        .next()
+        .expect("The run wrote no .ati file")
+        .unwrap()
+        .path();
+
+    let output = run_datir(&[
+        "comparability",
+        ati.to_str().unwrap(),
+        "-d",
+        decls.to_str().unwrap(),
+    ]);
+    assert!(
+        output.status.success(),
+        "Unable to write comparability into {decls:?}. Error output:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let original = std::fs::read_to_string(&decls).unwrap();
+    let written = std::fs::read_to_string(&written).unwrap();
+
+    // Besides the comparability header, only `comparability` lines are rewritten.
+    let kept_lines = |decls: &str| -> Vec<String> {
+        decls
+            .lines()
+            .filter(|line| {
+                !line.trim_start().starts_with("comparability ")
+                    && !line.starts_with("var-comparability ")
+            })
+            .map(str::to_string)
+            .collect()
+    };
+    assert_eq!(kept_lines(&written), kept_lines(&original));
+    assert!(
+        written
+            .lines()
+            .any(|line| line == "var-comparability implicit"),
+        "{written}"
+    );
+
+    let original = comparabilities(&original);
+    let written = comparabilities(&written);
+
+    // `buf[0]` and `buf[1]` are compared against `threshold`, and collapse into `buf[..]`, whose
+    // index shares the abstract type of `buf.length` and `start`.
+    let enter = ppt_ending_with(&written, "::window:::ENTER");
+    assert_eq!(enter["buf.length"], enter["start"], "{enter:?}");
+    assert_ne!(enter["threshold"], enter["start"], "{enter:?}");
+    assert_eq!(
+        enter["buf[..]"],
+        format!("{}[{}]", enter["threshold"], enter["start"]),
+        "{enter:?}"
+    );
+
+    let exit = ppt_ending_with(&written, "::window:::EXIT");
+    assert_eq!(exit["start"], exit["return"], "{exit:?}");
+
+    // `never_called` is never reached, and keeps its original comparability.
+    assert_eq!(
+        ppt_ending_with(&written, "::never_called:::ENTER"),
+        ppt_ending_with(&original, "::never_called:::ENTER")
+    );
+}
@@ -0,0 +1,41 @@
+#![allow(unused)]
+
+struct Buf<const N: usize> {
+    len: usize,
+    cap: usize,
+}
+
+impl Buf<4> {
+    fn small(&self, extra: usize, unrelated: usize) -> usize {
+        self.len + extra
+    }
+
+    fn get(&self, i: usize) -> usize {
+        self.len + i
+    }
+}
+
+// different instantiation of the same const param, must not be confused
+// with the impl above.
+impl Buf<{ 2 * 4 }> {
+    fn large(&self, extra: usize, unrelated: usize) -> usize {
+        self.cap + extra
+    }
+}
+
+// same method name as in `impl Buf<4>`, only the const arg of the impls tells them apart.
+impl Buf<8> {
+    fn get(&self, i: usize) -> usize {
+        self.cap + i
+    }
+}
+
+#[ignore]
+fn main() {
+    let s: Buf<4> = Buf { len: 1, cap: 4 };
+    let l: Buf<8> = Buf { len: 2, cap: 8 };
+    s.small(3, 7);
+    l.large(5, 7);
+    s.get(1);
+    l.get(2);
+}
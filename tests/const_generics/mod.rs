@@ -0,0 +1,98 @@
+use std::path::Path;
+
+use crate::common::{
+    ExpectedOutput, ExpectedSite, compile_and_execute, delete, prefix_with_path_from_root, verify,
+};
+
+#[test]
+fn const_generics() {
+    let mut expected = ExpectedOutput::new();
+    expected.register_site(ExpectedSite::new(prefix_with_path_from_root(
+        "const_generics/main.rs::main:::ENTER",
+    )));
+    expected.register_site(ExpectedSite::new(prefix_with_path_from_root(
+        "const_generics/main.rs::main:::EXIT",
+    )));
+
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "const_generics/main.rs::Buf::<4>::small:::ENTER",
+        ))
+        .register("self.len", 0)
+        .register("self.cap", 1)
+        .register("extra", 2)
+        .register("unrelated", 3),
+    );
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "const_generics/main.rs::Buf::<4>::small:::EXIT",
+        ))
+        .register("self.len", 0)
+        .register("self.cap", 1)
+        .register("extra", 0)
+        .register("unrelated", 3)
+        .register("return", 0),
+    );
+
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "const_generics/main.rs::Buf::<{ 2 * 4 }>::large:::ENTER",
+        ))
+        .register("self.len", 0)
+        .register("self.cap", 1)
+        .register("extra", 2)
+        .register("unrelated", 3),
+    );
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "const_generics/main.rs::Buf::<{ 2 * 4 }>::large:::EXIT",
+        ))
+        .register("self.len", 0)
+        .register("self.cap", 1)
+        .register("extra", 1)
+        .register("unrelated", 3)
+        .register("return", 1),
+    );
+
+    // `Buf<4>::get` sums `i` with `len`, while `Buf<8>::get` sums it with `cap`.
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "const_generics/main.rs::Buf::<4>::get:::ENTER",
+        ))
+        .register("self.len", 0)
+        .register("self.cap", 1)
+        .register("i", 2),
+    );
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "const_generics/main.rs::Buf::<4>::get:::EXIT",
+        ))
+        .register("self.len", 0)
+        .register("self.cap", 1)
+        .register("i", 0)
+        .register("return", 0),
+    );
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "const_generics/main.rs::Buf::<8>::get:::ENTER",
+        ))
+        .register("self.len", 0)
+        .register("self.cap", 1)
+        .register("i", 2),
+    );
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "const_generics/main.rs::Buf::<8>::get:::EXIT",
+        ))
+        .register("self.len", 0)
+        .register("self.cap", 1)
+        .register("i", 1)
+        .register("return", 1),
+    );
+
+    let executable = Path::new(file!()).parent().unwrap().join("const_generics.out");
+    delete(&executable);
+
+    let ati_output = compile_and_execute(&executable);
+    verify(&ati_output, expected.inner());
+}
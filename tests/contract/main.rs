@@ -0,0 +1,14 @@
+#![allow(unused)]
+// `width` and `height` are reviewed to be of distinct abstract types, which `scale` breaks by
+// multiplying them.
+
+#[ignore]
+fn main() {
+    let width = 3;
+    let height = 4;
+    let area = scale(width, height);
+}
+
+fn scale(len: usize, factor: usize) -> usize {
+    len * factor
+}
@@ -0,0 +1,51 @@
+use std::path::Path;
+
+use crate::common::{compile_and_execute_with_args, delete, prefix_with_path_from_root};
+
+/// Reports interactions merging variables a `--contract` lists as distinct.
+#[test]
+fn contract() {
+    let test_dir = Path::new(file!()).parent().unwrap();
+    let executable = test_dir.join("contract.out");
+    let contract = test_dir.join("contract.txt");
+    let log = test_dir.join("contract.txt.violations.log");
+    for path in [&executable, &contract, &log] {
+        delete(path);
+    }
+
+    // A reviewed report, which `len * factor` contradicts.
+    let scale_enter = prefix_with_path_from_root("contract/main.rs::scale:::ENTER");
+    std::fs::write(
+        &contract,
+        format!("===ATI-ANALYSIS-START===\n{scale_enter}\nfactor -> 1\nlen -> 0\n---\n"),
+    )
+    .unwrap();
+
+    let report = compile_and_execute_with_args(
+        &executable,
+        &[
+            "--contract",
+            contract.to_str().unwrap(),
+            "--contract-mode",
+            "log",
+        ],
+    );
+    assert!(report.contains(&scale_enter), "Missing report:\n{report}");
+
+    let violations = std::fs::read_to_string(&log).unwrap();
+    let source = std::env::current_dir()
+        .unwrap()
+        .join(test_dir)
+        .join("main.rs");
+    let expected = format!(
+        "abstract type contract violated at {scale_enter}: `factor` and `len` are expected to be \
+         of distinct abstract types, but values they were bound to interacted in `{}` ({}:12:1)",
+        scale_enter.trim_end_matches(":::ENTER"),
+        source.display()
+    );
+    assert_eq!(violations.lines().count(), 1, "{violations}");
+    assert!(
+        violations.contains(&expected),
+        "Expected {expected:?} in contract log:\n{violations}"
+    );
+}
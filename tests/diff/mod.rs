@@ -0,0 +1,54 @@
+use std::path::Path;
+
+use crate::common::run_datir;
+
+/// Diffs `old.ati` against the report of the run named `new`.
+fn diff(new: &str) -> std::process::Output {
+    let test_dir = Path::new(file!()).parent().unwrap();
+    run_datir(&[
+        "diff",
+        test_dir.join("old.ati").to_str().unwrap(),
+        test_dir.join(new).to_str().unwrap(),
+    ])
+}
+
+/// The same partitions, numbered differently, don't differ.
+#[test]
+fn diff_ignores_renumbering() {
+    let output = diff("renumbered.txt");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        output.status.success(),
+        "Renumbered partitions differ:\n{stdout}\nError output:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(stdout.contains("The partitions are identical."), "{stdout}");
+}
+
+/// Split and merged classes, and ppts or variables only one side holds, are listed.
+#[test]
+fn diff_reports_changes() {
+    let output = diff("changed.txt");
+    assert!(!output.status.success(), "Changed partitions don't differ.");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    // `len` moved from `offset`'s class to `factor`'s at ENTER, while the new `step` doesn't
+    // change the classes of EXIT.
+    let expected = "\
+- main.rs::unused:::ENTER
++ main.rs::scale:::EXIT17
+main.rs::scale:::ENTER
+  factor: {factor} -> {factor, len}
+  len: {len, offset} -> {factor, len}
+  offset: {len, offset} -> {offset}
+main.rs::scale:::EXIT
+  + step
+";
+    assert_eq!(stdout, expected);
+    assert!(
+        String::from_utf8_lossy(&output.stderr)
+            .contains("The partitions differ at 4 of 4 program points."),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
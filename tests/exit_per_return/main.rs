@@ -0,0 +1,36 @@
+#![allow(unused)]
+
+#[ignore]
+fn main() {
+    pick(0, 1, 2, 3);
+    pick(1, 4, 5, 6);
+
+    report(5, 1, 2);
+    report(0, 1, 2);
+
+    let mut total = 1;
+    bump(&mut total, Vec::new(), 0);
+    bump(&mut total, Vec::new(), 2);
+}
+
+fn pick(mode: u32, a: u32, b: u32, c: u32) -> u32 {
+    if mode == 0 {
+        return a + b;
+    }
+    a + c
+}
+
+fn report(code: u32, limit: u32, other: u32) {
+    if code > limit {
+        return;
+    }
+    let sum = code + other;
+}
+
+// `count` can't be snapshotted, and `items` isn't `Copy`, so both are bound in place at each exit.
+fn bump(count: &mut u32, items: Vec<u32>, step: u32) {
+    if step == 0 {
+        return;
+    }
+    *count += step;
+}
@@ -0,0 +1,128 @@
+use std::path::Path;
+
+use crate::common::{
+    ExpectedOutput, ExpectedSite, compile_and_execute_with_args, delete,
+    prefix_with_path_from_root, verify,
+};
+
+#[test]
+fn exit_site_per_return() {
+    let mut output = ExpectedOutput::new();
+    output.register_site(ExpectedSite::new(prefix_with_path_from_root(
+        "exit_per_return/main.rs::main:::ENTER",
+    )));
+    output.register_site(ExpectedSite::new(prefix_with_path_from_root(
+        "exit_per_return/main.rs::main:::EXIT",
+    )));
+
+    output.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "exit_per_return/main.rs::pick:::ENTER",
+        ))
+        .register("mode", 0)
+        .register("a", 1)
+        .register("b", 1)
+        .register("c", 2),
+    );
+    // the aggregate exit merges both return paths.
+    output.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "exit_per_return/main.rs::pick:::EXIT",
+        ))
+        .register("mode", 0)
+        .register("a", 1)
+        .register("b", 1)
+        .register("c", 1)
+        .register("return", 1),
+    );
+    output.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "exit_per_return/main.rs::pick:::EXIT18",
+        ))
+        .register("mode", 0)
+        .register("a", 1)
+        .register("b", 1)
+        .register("c", 2)
+        .register("return", 1),
+    );
+    output.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "exit_per_return/main.rs::pick:::EXIT20",
+        ))
+        .register("mode", 0)
+        .register("a", 1)
+        .register("b", 2)
+        .register("c", 1)
+        .register("return", 1),
+    );
+
+    output.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "exit_per_return/main.rs::report:::ENTER",
+        ))
+        .register("code", 0)
+        .register("limit", 0)
+        .register("other", 1),
+    );
+    output.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "exit_per_return/main.rs::report:::EXIT",
+        ))
+        .register("code", 0)
+        .register("limit", 0)
+        .register("other", 0),
+    );
+    // only the first call returns early, before `code` interacts with `other`.
+    output.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "exit_per_return/main.rs::report:::EXIT25",
+        ))
+        .register("code", 0)
+        .register("limit", 0)
+        .register("other", 1),
+    );
+    output.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "exit_per_return/main.rs::report:::EXIT28",
+        ))
+        .register("code", 0)
+        .register("limit", 0)
+        .register("other", 0),
+    );
+
+    // `*count += step` only merges `count` with the `step` of the second call, which returns last.
+    output.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "exit_per_return/main.rs::bump:::ENTER",
+        ))
+        .register("count", 0)
+        .register("step", 0),
+    );
+    output.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "exit_per_return/main.rs::bump:::EXIT",
+        ))
+        .register("count", 0)
+        .register("step", 0),
+    );
+    output.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "exit_per_return/main.rs::bump:::EXIT33",
+        ))
+        .register("count", 0)
+        .register("step", 1),
+    );
+    output.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "exit_per_return/main.rs::bump:::EXIT36",
+        ))
+        .register("count", 0)
+        .register("step", 0),
+    );
+
+    let executable = Path::new(file!()).parent().unwrap().join("exits.out");
+    delete(&executable);
+
+    let ati_output = compile_and_execute_with_args(&executable, &["--exit-per-return"]);
+    verify(&ati_output, output.inner());
+}
@@ -0,0 +1,41 @@
+#![allow(unused)]
+// Functions with qualified headers. Their shims must keep the headers, the ENTER/EXIT sites of
+// `add_async` must be updated as its future is polled, and `double` must remain callable from
+// const contexts.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll, Waker};
+
+const EIGHT: u32 = double(4);
+
+#[ignore]
+fn main() {
+    let mut cx = Context::from_waker(Waker::noop());
+
+    let mut sum = add_async(1, 2);
+    let mut sum = unsafe { Pin::new_unchecked(&mut sum) };
+    let Poll::Ready(x) = sum.as_mut().poll(&mut cx) else {
+        unreachable!()
+    };
+
+    let y = double(x);
+    let z = unsafe { scale(y, 3) };
+    let w = to_c(z);
+}
+
+async fn add_async(a: u32, b: u32) -> u32 {
+    a + b
+}
+
+const fn double(n: u32) -> u32 {
+    n * 2
+}
+
+unsafe fn scale(n: u32, by: u32) -> u32 {
+    n * by
+}
+
+extern "C" fn to_c(n: u32) -> u32 {
+    n + 1
+}
@@ -0,0 +1,79 @@
+use std::path::Path;
+
+use crate::common::{
+    ExpectedOutput, ExpectedSite, compile_and_execute, delete, prefix_with_path_from_root, verify,
+};
+
+#[test]
+fn fn_headers() {
+    let mut expected = ExpectedOutput::new();
+    expected.register_site(ExpectedSite::new(prefix_with_path_from_root(
+        "fn_headers/main.rs::main:::ENTER",
+    )));
+    expected.register_site(ExpectedSite::new(prefix_with_path_from_root(
+        "fn_headers/main.rs::main:::EXIT",
+    )));
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "fn_headers/main.rs::add_async:::ENTER",
+        ))
+        .register("a", 0)
+        .register("b", 1),
+    );
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "fn_headers/main.rs::add_async:::EXIT",
+        ))
+        .register("a", 0)
+        .register("b", 0)
+        .register("return", 0),
+    );
+    // const fn, evaluated at runtime. Its parameter is tagged by the shim.
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "fn_headers/main.rs::double:::ENTER",
+        ))
+        .register("n", 0),
+    );
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "fn_headers/main.rs::double:::EXIT",
+        ))
+        .register("n", 0)
+        .register("return", 0),
+    );
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "fn_headers/main.rs::scale:::ENTER",
+        ))
+        .register("n", 0)
+        .register("by", 1),
+    );
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "fn_headers/main.rs::scale:::EXIT",
+        ))
+        .register("n", 0)
+        .register("by", 0)
+        .register("return", 0),
+    );
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "fn_headers/main.rs::to_c:::ENTER",
+        ))
+        .register("n", 0),
+    );
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "fn_headers/main.rs::to_c:::EXIT",
+        ))
+        .register("n", 0)
+        .register("return", 0),
+    );
+
+    let executable = Path::new(file!()).parent().unwrap().join("fn_headers.out");
+    delete(&executable);
+
+    let ati_output = compile_and_execute(&executable);
+    verify(&ati_output, expected.inner());
+}
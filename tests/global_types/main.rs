@@ -0,0 +1,19 @@
+#![allow(unused)]
+// `sum` and `below` never interact directly, but `below` is called on the result of `sum`, so
+// their formals share a program-wide abstract type, which the per-site report can't show.
+
+#[ignore]
+fn main() {
+    let len = 4;
+    let size = 6;
+    let total = sum(len, size);
+    below(total, 10);
+}
+
+fn sum(a: usize, b: usize) -> usize {
+    a + b
+}
+
+fn below(x: usize, limit: usize) -> bool {
+    x < limit
+}
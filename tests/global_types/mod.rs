@@ -0,0 +1,66 @@
+use std::path::Path;
+
+use crate::common::{
+    ExpectedOutput, ExpectedSite, compile_and_execute_with_args, delete,
+    prefix_with_path_from_root, split_global_types, verify, verify_global_types,
+};
+
+#[test]
+fn global_types() {
+    let mut expected = ExpectedOutput::new();
+    expected.register_site(ExpectedSite::new(prefix_with_path_from_root(
+        "global_types/main.rs::main:::ENTER",
+    )));
+    expected.register_site(ExpectedSite::new(prefix_with_path_from_root(
+        "global_types/main.rs::main:::EXIT",
+    )));
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root("global_types/main.rs::sum:::ENTER"))
+            .register("a", 0)
+            .register("b", 0),
+    );
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root("global_types/main.rs::sum:::EXIT"))
+            .register("a", 0)
+            .register("b", 0)
+            .register("return", 0),
+    );
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root("global_types/main.rs::below:::ENTER"))
+            .register("x", 0)
+            .register("limit", 0),
+    );
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root("global_types/main.rs::below:::EXIT"))
+            .register("x", 0)
+            .register("limit", 0)
+            .register("return", 1),
+    );
+
+    let sum_enter = prefix_with_path_from_root("global_types/main.rs::sum:::ENTER");
+    let sum_exit = prefix_with_path_from_root("global_types/main.rs::sum:::EXIT");
+    let below_enter = prefix_with_path_from_root("global_types/main.rs::below:::ENTER");
+    let below_exit = prefix_with_path_from_root("global_types/main.rs::below:::EXIT");
+    let expected_types = [
+        vec![
+            (sum_enter.clone(), "a"),
+            (sum_enter, "b"),
+            (sum_exit.clone(), "a"),
+            (sum_exit.clone(), "b"),
+            (sum_exit, "return"),
+            (below_enter.clone(), "x"),
+            (below_enter, "limit"),
+            (below_exit.clone(), "x"),
+            (below_exit.clone(), "limit"),
+        ],
+        vec![(below_exit, "return")],
+    ];
+
+    let executable = Path::new(file!()).parent().unwrap().join("global_types.out");
+    delete(&executable);
+
+    let ati_output = compile_and_execute_with_args(&executable, &["--global-types"]);
+    let (sites, global_types) = split_global_types(&ati_output);
+    verify(sites, expected.inner());
+    verify_global_types(global_types, &expected_types);
+}
@@ -0,0 +1,24 @@
+#![allow(unused)]
+// Every read of a `const` / `static` item carries the item's tag, so values compared against the
+// same global interact, while values compared against distinct globals stay apart.
+
+const MAX: u32 = 100;
+static MIN: u32 = 1;
+
+mod limits {
+    pub const STEP: u32 = 5;
+}
+
+#[ignore]
+fn main() {
+    spread(1, 200, 5);
+    capped(9, 2);
+}
+
+fn spread(a: u32, b: u32, c: u32) -> bool {
+    a < MAX && MAX < b && c > MIN
+}
+
+fn capped(v: u32, w: u32) -> u32 {
+    if v > limits::STEP { limits::STEP } else { w }
+}
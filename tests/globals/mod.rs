@@ -0,0 +1,127 @@
+use std::path::Path;
+
+use crate::common::{
+    ExpectedOutput, ExpectedSite, compile_and_execute, compile_and_execute_with_args, delete,
+    prefix_with_path_from_root, verify,
+};
+
+#[test]
+fn globals() {
+    let mut expected = ExpectedOutput::new();
+    expected.register_site(ExpectedSite::new(prefix_with_path_from_root(
+        "globals/main.rs::main:::ENTER",
+    )));
+    expected.register_site(ExpectedSite::new(prefix_with_path_from_root(
+        "globals/main.rs::main:::EXIT",
+    )));
+
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "globals/main.rs::spread:::ENTER",
+        ))
+        .register("a", 0)
+        .register("b", 1)
+        .register("c", 2),
+    );
+    // `a` and `b` are each compared against a separate read of `MAX`.
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root("globals/main.rs::spread:::EXIT"))
+            .register("a", 0)
+            .register("b", 0)
+            .register("c", 1)
+            .register("return", 2),
+    );
+
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "globals/main.rs::capped:::ENTER",
+        ))
+        .register("v", 0)
+        .register("w", 1),
+    );
+    // the returned read of `limits::STEP` interacts with `v`, through the one in the condition.
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root("globals/main.rs::capped:::EXIT"))
+            .register("v", 0)
+            .register("w", 1)
+            .register("return", 0),
+    );
+
+    let executable = Path::new(file!()).parent().unwrap().join("globals.out");
+    delete(&executable);
+
+    let ati_output = compile_and_execute(&executable);
+    verify(&ati_output, expected.inner());
+}
+
+/// Globals which the `.decls` file declares at a program point are bound at its sites. The ppt
+/// names of `declared.decls` are relative to `{source}`, which is filled in with the absolute
+/// path of `main.rs` before compiling.
+#[test]
+fn globals_declared_in_decls() {
+    let test_dir = Path::new(file!()).parent().unwrap();
+    let source = std::env::current_dir()
+        .unwrap()
+        .join(test_dir)
+        .join("main.rs");
+    let decls = test_dir.join("declared_globals.decls");
+    delete(&decls);
+    let template = std::fs::read_to_string(test_dir.join("declared.decls")).unwrap();
+    std::fs::write(
+        &decls,
+        template.replace("{source}", source.to_str().unwrap()),
+    )
+    .unwrap();
+
+    let mut expected = ExpectedOutput::new();
+    expected.register_site(ExpectedSite::new(prefix_with_path_from_root(
+        "globals/main.rs::main:::ENTER",
+    )));
+    expected.register_site(ExpectedSite::new(prefix_with_path_from_root(
+        "globals/main.rs::main:::EXIT",
+    )));
+
+    // nothing has been compared against `MAX` or `MIN` yet on entry.
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "globals/main.rs::spread:::ENTER",
+        ))
+        .register("a", 0)
+        .register("b", 1)
+        .register("c", 2)
+        .register("::MAX", 3)
+        .register("::MIN", 4),
+    );
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root("globals/main.rs::spread:::EXIT"))
+            .register("a", 0)
+            .register("b", 0)
+            .register("::MAX", 0)
+            .register("c", 1)
+            .register("::MIN", 1)
+            .register("return", 2),
+    );
+
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "globals/main.rs::capped:::ENTER",
+        ))
+        .register("v", 0)
+        .register("w", 1)
+        .register("::limits::STEP", 2),
+    );
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root("globals/main.rs::capped:::EXIT"))
+            .register("v", 0)
+            .register("::limits::STEP", 0)
+            .register("return", 0)
+            .register("w", 1),
+    );
+
+    let executable = test_dir.join("globals_declared.out");
+    delete(&executable);
+
+    let ati_output =
+        compile_and_execute_with_args(&executable, &["--decls-path", decls.to_str().unwrap()]);
+    verify(&ati_output, expected.inner());
+}
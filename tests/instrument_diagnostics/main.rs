@@ -0,0 +1,15 @@
+#![allow(unused)]
+// Skipped code can't construct the crate's structs, as their fields hold tagged values once
+// instrumented, so `origin` only fails to compile during the Instrument compilation. It sits on a
+// single line, so that every error within it stems from line 10.
+struct Point {
+    x: u32,
+}
+
+#[datir::skip]
+fn origin() -> Point { Point { x: 0 } }
+
+#[ignore]
+fn main() {
+    origin();
+}
@@ -0,0 +1,43 @@
+use std::path::Path;
+
+use crate::common::{delete, run_datir};
+
+/// Errors within the instrumented crate are reported at the original location of the failing
+/// code, and note the instrumented code they stem from.
+#[test]
+fn instrument_diagnostics() {
+    let test_dir = Path::new(file!()).parent().unwrap();
+    let executable = test_dir.join("instrument_diagnostics.out");
+    delete(&executable);
+
+    let source = std::env::current_dir()
+        .unwrap()
+        .join(test_dir)
+        .join("main.rs");
+    let output = run_datir(&[
+        source.to_str().unwrap(),
+        "-o",
+        executable.to_str().unwrap(),
+        "--test",
+    ]);
+    assert!(
+        !output.status.success(),
+        "The instrumented crate unexpectedly compiled."
+    );
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let origin = format!("{}:10:", source.display());
+    assert!(
+        stderr
+            .lines()
+            .any(|line| line.trim_start().starts_with("--> ") && line.contains(&origin)),
+        "No error is reported at {origin}. Error output:\n{stderr}"
+    );
+    assert!(
+        stderr
+            .lines()
+            .any(|line| line.contains("note: DATIR: this code was")
+                && line.contains(&format!("`{origin}"))),
+        "No error is noted with its origin at {origin}. Error output:\n{stderr}"
+    );
+}
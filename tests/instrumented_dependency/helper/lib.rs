@@ -0,0 +1,8 @@
+pub fn add(a: u32, b: u32) -> u32 {
+    a + b
+}
+
+#[datir::skip]
+pub fn scale(a: u32, factor: u32) -> u32 {
+    a * factor
+}
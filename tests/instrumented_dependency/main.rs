@@ -0,0 +1,13 @@
+extern crate helper;
+
+#[ignore]
+fn main() {
+    foo(1, 2, 3);
+}
+
+fn foo(x: u32, y: u32, z: u32) -> u32 {
+    // helper::scale is skipped, so z loses its id when passed to it
+    helper::scale(z, 2);
+    // helper is instrumented, so x and y interact within helper::add
+    helper::add(x, y)
+}
@@ -0,0 +1,105 @@
+use std::path::Path;
+
+use crate::common::{
+    ExpectedOutput, ExpectedSite, compile_and_execute_with_args, delete,
+    prefix_with_path_from_root, run_datir, verify,
+};
+
+#[test]
+fn instrumented_dependency() {
+    let mut expected = ExpectedOutput::new();
+    expected.register_site(ExpectedSite::new(prefix_with_path_from_root(
+        "instrumented_dependency/main.rs::main:::ENTER",
+    )));
+    expected.register_site(ExpectedSite::new(prefix_with_path_from_root(
+        "instrumented_dependency/main.rs::main:::EXIT",
+    )));
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "instrumented_dependency/main.rs::foo:::ENTER",
+        ))
+        .register("x", 0)
+        .register("y", 1)
+        .register("z", 2),
+    );
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "instrumented_dependency/main.rs::foo:::EXIT",
+        ))
+        .register("x", 0)
+        .register("y", 0)
+        .register("z", 2)
+        .register("return", 0),
+    );
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "instrumented_dependency/helper/lib.rs::add:::ENTER",
+        ))
+        .register("a", 0)
+        .register("b", 1),
+    );
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "instrumented_dependency/helper/lib.rs::add:::EXIT",
+        ))
+        .register("a", 0)
+        .register("b", 0)
+        .register("return", 0),
+    );
+
+    let helper_root = std::env::current_dir()
+        .unwrap()
+        .join("tests/instrumented_dependency/helper/lib.rs");
+    let allowlist = format!("helper={}", helper_root.display());
+
+    let executable = Path::new(file!())
+        .parent()
+        .unwrap()
+        .join("instrumented_dependency.out");
+    delete(&executable);
+
+    let ati_output = compile_and_execute_with_args(
+        &executable,
+        &["--instrument-crates", allowlist.as_str()],
+    );
+    verify(&ati_output, expected.inner());
+}
+
+/// `datir static` analyzes the allowlisted dependency alongside the crate.
+#[test]
+fn static_instrumented_dependency() {
+    let test_dir = std::env::current_dir()
+        .unwrap()
+        .join("tests/instrumented_dependency");
+    let allowlist = format!("helper={}", test_dir.join("helper/lib.rs").display());
+    let output = run_datir(&[
+        "static",
+        test_dir.join("main.rs").to_str().unwrap(),
+        "--instrument-crates",
+        allowlist.as_str(),
+    ]);
+    assert!(
+        output.status.success(),
+        "Unable to infer abstract types statically. Error output:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let ppt = |ppt: &str| prefix_with_path_from_root(&format!("instrumented_dependency/{ppt}"));
+
+    for expected in [
+        format!(
+            "{}\na -> 0\nb -> 1\n---\n",
+            ppt("helper/lib.rs::add:::ENTER")
+        ),
+        format!(
+            "{}\na -> 0\nb -> 0\nreturn -> 0\n---\n",
+            ppt("helper/lib.rs::add:::EXIT")
+        ),
+        format!("{}\n", ppt("main.rs::foo:::ENTER")),
+    ] {
+        assert!(
+            stdout.contains(&expected),
+            "Missing {expected:?} from the static report:\n{stdout}"
+        );
+    }
+}
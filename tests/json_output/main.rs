@@ -0,0 +1,19 @@
+#![allow(unused)]
+// `sum` is reached three times and `pick` once, which the `invocations` of their records count.
+
+#[ignore]
+fn main() {
+    let mut total = 0;
+    for i in 0..3 {
+        total = sum(total, i);
+    }
+    pick(total, 7);
+}
+
+fn sum(acc: usize, x: usize) -> usize {
+    acc + x
+}
+
+fn pick(n: usize, other: usize) -> usize {
+    n
+}
@@ -0,0 +1,108 @@
+use std::path::Path;
+
+use crate::common::{compile_and_execute_with_args, delete};
+
+/// Raw value of the field `key` of the flat JSON object `record`, e.g. `3` or `"sum"`.
+fn field<'a>(record: &'a str, key: &str) -> &'a str {
+    let start = record
+        .find(&format!("\"{key}\":"))
+        .unwrap_or_else(|| panic!("No `{key}` in {record}"))
+        + key.len()
+        + 3;
+    let rest = &record[start..];
+    let end = if rest.starts_with('{') {
+        rest.find('}').unwrap() + 1
+    } else {
+        rest.find([',', '}']).unwrap()
+    };
+    &rest[..end]
+}
+
+/// `--format json` outputs a run record, then a record per site, followed by one record per
+/// variable of the site.
+#[test]
+fn json_output() {
+    let executable = Path::new(file!()).parent().unwrap().join("json_output.out");
+    delete(&executable);
+
+    let output = compile_and_execute_with_args(&executable, &["--format", "json"]);
+    let records: Vec<&str> = output.lines().filter(|line| !line.is_empty()).collect();
+
+    let run = records[0];
+    assert_eq!(field(run, "record"), r#""run""#);
+    assert_eq!(field(run, "format"), r#""datir-ati""#);
+    assert_eq!(field(run, "version"), "2");
+    // the binary is run without arguments, `program` isn't repeated.
+    assert!(
+        field(run, "program").ends_with(r#"json_output.out""#),
+        "{run}"
+    );
+    assert_eq!(field(run, "args"), "[]");
+    let run_id = field(run, "run_id");
+
+    // (ppt, invocations) of every site, each followed by the records of its variables.
+    let mut ppts: Vec<(&str, &str)> = Vec::new();
+    for record in &records[1..] {
+        assert_eq!(field(record, "run_id"), run_id, "{record}");
+        match field(record, "record") {
+            r#""ppt""# => ppts.push((field(record, "ppt"), field(record, "invocations"))),
+            r#""var""# => assert_eq!(
+                ppts.last().map(|(ppt, _)| *ppt),
+                Some(field(record, "ppt")),
+                "Variable record outside of its site: {record}"
+            ),
+            _ => panic!("Unexpected record {record}"),
+        }
+    }
+    let site = |ppt: &str| {
+        *ppts
+            .iter()
+            .find(|(p, _)| p.ends_with(&format!("json_output/main.rs::{ppt}\"")))
+            .unwrap_or_else(|| panic!("No record for {ppt} in:\n{output}"))
+    };
+    // `main` has no variables, but is reported all the same.
+    assert_eq!(site("main:::ENTER").1, "1");
+    assert_eq!(site("main:::EXIT").1, "1");
+    assert_eq!(site("sum:::ENTER").1, "3");
+
+    // (ppt, var, type, invocations, span) of every variable.
+    let vars: Vec<(&str, &str, &str, &str, &str)> = records[1..]
+        .iter()
+        .filter(|record| field(record, "record") == r#""var""#)
+        .map(|record| {
+            (
+                field(record, "ppt"),
+                field(record, "var"),
+                field(record, "type"),
+                field(record, "invocations"),
+                field(record, "span"),
+            )
+        })
+        .collect();
+    let var = |ppt: &str, name: &str| {
+        *vars
+            .iter()
+            .find(|(p, v, ..)| {
+                p.ends_with(&format!("json_output/main.rs::{ppt}\"")) && *v == format!("\"{name}\"")
+            })
+            .unwrap_or_else(|| panic!("No record for {ppt} {name} in:\n{output}"))
+    };
+
+    let (_, _, acc, invocations, span) = var("sum:::ENTER", "acc");
+    let (_, _, x, ..) = var("sum:::ENTER", "x");
+    assert_eq!(acc, x);
+    assert_eq!(invocations, "3");
+    assert!(
+        span.ends_with(
+            r#"json_output/main.rs","line_start":13,"col_start":1,"line_end":15,"col_end":2}"#
+        ),
+        "Unexpected span of `sum`: {span}"
+    );
+
+    let (_, _, n, invocations, _) = var("pick:::EXIT", "n");
+    let (_, _, other, ..) = var("pick:::EXIT", "other");
+    let (_, _, ret, ..) = var("pick:::EXIT", "return");
+    assert_ne!(n, other);
+    assert_eq!(n, ret);
+    assert_eq!(invocations, "1");
+}
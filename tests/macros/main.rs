@@ -0,0 +1,28 @@
+#![allow(unused)]
+// Arguments of std macros are instrumented, so the comparisons made by `assert!` and
+// `assert_eq!` are observed, while printed values stay untagged. `square!` is an expression
+// macro, whose transcriber is instrumented alongside the arguments of each invocation.
+
+macro_rules! square {
+    ($x:expr) => {
+        $x * $x
+    };
+}
+
+#[ignore]
+fn main() {
+    check(3, 5, 7);
+    scale(2, 4);
+}
+
+fn check(i: u32, n: u32, unrelated: u32) {
+    assert!(i < n, "{i} is out of bounds");
+    println!("{} {unrelated}", i);
+}
+
+fn scale(a: u32, b: u32) -> u32 {
+    let sides = vec![a; 2];
+    let area = square!(b);
+    assert_eq!(sides[0] + area, 18);
+    area
+}
@@ -0,0 +1,50 @@
+use std::path::Path;
+
+use crate::common::{
+    ExpectedOutput, ExpectedSite, compile_and_execute, delete, prefix_with_path_from_root, verify,
+};
+
+#[test]
+fn macros() {
+    let mut expected = ExpectedOutput::new();
+    expected.register_site(ExpectedSite::new(prefix_with_path_from_root(
+        "macros/main.rs::main:::ENTER",
+    )));
+    expected.register_site(ExpectedSite::new(prefix_with_path_from_root(
+        "macros/main.rs::main:::EXIT",
+    )));
+
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root("macros/main.rs::check:::ENTER"))
+            .register("i", 0)
+            .register("n", 1)
+            .register("unrelated", 2),
+    );
+    // `assert!` compared `i` and `n`, printing `unrelated` did not interact with either.
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root("macros/main.rs::check:::EXIT"))
+            .register("i", 0)
+            .register("n", 0)
+            .register("unrelated", 1),
+    );
+
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root("macros/main.rs::scale:::ENTER"))
+            .register("a", 0)
+            .register("b", 1),
+    );
+    // `vec!` copied `a` into `sides`, and `square!` multiplied `b`, before `assert_eq!` added
+    // them together.
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root("macros/main.rs::scale:::EXIT"))
+            .register("a", 0)
+            .register("b", 0)
+            .register("return", 0),
+    );
+
+    let executable = Path::new(file!()).parent().unwrap().join("macros.out");
+    delete(&executable);
+
+    let ati_output = compile_and_execute(&executable);
+    verify(&ati_output, expected.inner());
+}
@@ -115,6 +115,7 @@ fn match_expr() {
         ExpectedSite::new(prefix_with_path_from_root(
             "match_expr/main.rs::untracked_primitive:::ENTER",
         ))
+        .register("x", 3)
         .register("a", 0)
         .register("b", 1)
         .register("c", 2),
@@ -123,6 +124,7 @@ fn match_expr() {
         ExpectedSite::new(prefix_with_path_from_root(
             "match_expr/main.rs::untracked_primitive:::EXIT",
         ))
+        .register("x", 3)
         .register("a", 0)
         .register("b", 1)
         .register("c", 2)
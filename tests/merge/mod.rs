@@ -0,0 +1,67 @@
+use std::path::Path;
+
+use crate::common::{ExpectedOutput, ExpectedSite, ati_to_report, delete, run_datir, verify};
+
+/// Merges hand-written `.ati` files, as written by three runs of a `--release` binary.
+#[test]
+fn merge() {
+    let test_dir = Path::new(file!()).parent().unwrap();
+    let runs = test_dir.join("runs");
+    let merged = test_dir.join("merged.ati");
+    delete(&merged);
+
+    let output = run_datir(&[
+        "merge",
+        runs.to_str().unwrap(),
+        "-o",
+        merged.to_str().unwrap(),
+    ]);
+    assert!(
+        output.status.success(),
+        "Unable to merge {runs:?}. Error output:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    // `factor ~ len` in the first run and `len ~ offset` in the second merge all three,
+    // while the third run, which keeps them apart, splits nothing.
+    let mut expected = ExpectedOutput::new();
+    expected.register_site(
+        ExpectedSite::new("main.rs::scale:::ENTER".to_string())
+            .register("factor", 0)
+            .register("len", 0)
+            .register("offset", 0),
+    );
+    expected.register_site(
+        ExpectedSite::new("main.rs::scale:::EXIT".to_string())
+            .register("factor", 0)
+            .register("len", 0)
+            .register("offset", 0)
+            .register("return", 0),
+    );
+    expected.register_site(
+        ExpectedSite::new("main.rs::scale:::EXIT17".to_string())
+            .register("factor", 0)
+            .register("return", 0),
+    );
+    expected.register_site(
+        ExpectedSite::new("main.rs::unused:::ENTER".to_string())
+            .register("x", 0)
+            .register("y", 1),
+    );
+
+    let merged_ati = std::fs::read_to_string(&merged).unwrap();
+    verify(&ati_to_report(&merged_ati), expected.inner());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    for count in [
+        "main.rs::scale:::ENTER: 3/3 runs",
+        "main.rs::scale:::EXIT: 2/3 runs",
+        "main.rs::scale:::EXIT17: 1/3 runs",
+        "main.rs::unused:::ENTER: 1/3 runs",
+    ] {
+        assert!(
+            stdout.contains(count),
+            "Missing `{count}` in output:\n{stdout}"
+        );
+    }
+}
@@ -0,0 +1,27 @@
+#![allow(unused)]
+// `Holder::pair` mixes a type parameter with a concrete type. Once instrumented, the `u32` half
+// always holds a tagged value, so the skipped `first` only untuples the `T` half of what `make`
+// returns.
+
+struct Holder<T> {
+    value: T,
+    pair: (T, u32),
+}
+
+#[ignore]
+fn main() {
+    first(3, 4);
+}
+
+fn make(value: u32, step: u32) -> Holder<u32> {
+    Holder {
+        value,
+        pair: (value, step),
+    }
+}
+
+#[datir::skip]
+fn first(x: u32, step: u32) -> u32 {
+    let holder = make(x, step);
+    holder.pair.0
+}
@@ -0,0 +1,44 @@
+use std::path::Path;
+
+use crate::common::{
+    ExpectedOutput, ExpectedSite, compile_and_execute, delete, prefix_with_path_from_root, verify,
+};
+
+#[test]
+fn mixed_generic_fields() {
+    let mut expected = ExpectedOutput::new();
+    expected.register_site(ExpectedSite::new(prefix_with_path_from_root(
+        "mixed_generic_fields/main.rs::main:::ENTER",
+    )));
+    expected.register_site(ExpectedSite::new(prefix_with_path_from_root(
+        "mixed_generic_fields/main.rs::main:::EXIT",
+    )));
+
+    // called from within the skipped `first`, with freshly tagged arguments.
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "mixed_generic_fields/main.rs::make:::ENTER",
+        ))
+        .register("value", 0)
+        .register("step", 1),
+    );
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "mixed_generic_fields/main.rs::make:::EXIT",
+        ))
+        .register("value", 0)
+        .register("step", 1)
+        .register("return.value", 0)
+        .register("return.pair.0", 0)
+        .register("return.pair.1", 1),
+    );
+
+    let executable = Path::new(file!())
+        .parent()
+        .unwrap()
+        .join("mixed_generic_fields.out");
+    delete(&executable);
+
+    let ati_output = compile_and_execute(&executable);
+    verify(&ati_output, expected.inner());
+}
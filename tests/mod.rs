@@ -22,20 +22,47 @@ mod array_with_slices;
 mod assign_compound;
 mod assign_tuples;
 mod binary_search;
+mod comparability;
+mod const_generics;
+mod contract;
+mod diff;
+mod exit_per_return;
+mod fn_headers;
 mod generic_struct;
+mod global_types;
+mod globals;
+mod instrument_diagnostics;
+mod instrumented_dependency;
 mod iterate_array;
+mod json_output;
 mod longest_increasing_subsequence;
+mod macros;
+mod merge;
+mod mixed_generic_fields;
 mod multi_file;
 mod multi_file_with_submodules;
+mod object_ppts;
 mod nested_returns;
+mod only_scope;
 mod op_through_trait;
+mod probes;
+mod public_abi;
 mod ranges;
 mod references;
 mod references_mut_reborrows;
+mod registry_dependency;
+mod runtime_name_collision;
+mod skip_scope;
+mod static_ati;
+mod std_enums;
+mod strings;
+mod suggest;
 mod simple;
 mod type_hints;
 mod unary_operators;
+mod units;
 mod untracked_fns;
+mod untracked_structured_returns;
 mod uses_enum;
 mod uses_methods;
 mod uses_struct;
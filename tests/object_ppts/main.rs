@@ -0,0 +1,80 @@
+#![allow(unused)]
+
+struct Counter {
+    val: u32,
+    step: u32,
+    unused: u32, // should stay in it's own AT
+}
+
+impl Counter {
+    fn bump(&mut self) {
+        self.val = self.val + self.step;
+    }
+}
+
+fn peek(c: &Counter) -> u32 {
+    c.unused
+}
+
+// Recursive: the nodes a list owns are folded into `Node:::OBJECT` as fields of its head.
+struct Node {
+    value: u32,
+    next: Option<Box<Node>>,
+}
+
+fn head(node: &Node) -> u32 {
+    node.value
+}
+
+// Nested within a `Line`, each `Point` is still folded into `Point:::OBJECT`, as it isn't nested
+// within another `Point`.
+struct Point {
+    x: u32,
+    y: u32,
+}
+
+struct Line {
+    from: Point,
+    to: Point,
+}
+
+fn width(line: &Line) -> u32 {
+    line.to.x - line.from.x
+}
+
+const LIMIT: u32 = 10; // never read, should stay in it's own AT
+static STEP: u32 = 2;
+
+fn advance(x: u32) -> u32 {
+    x + STEP
+}
+
+#[ignore]
+fn main() {
+    let mut c = Counter {
+        val: 1,
+        step: 2,
+        unused: 3,
+    };
+    c.bump();
+    peek(&c);
+
+    let list = Node {
+        value: 1,
+        next: Some(Box::new(Node {
+            value: 2,
+            next: Some(Box::new(Node {
+                value: 3,
+                next: None,
+            })),
+        })),
+    };
+    head(&list);
+
+    let line = Line {
+        from: Point { x: 1, y: 2 },
+        to: Point { x: 4, y: 6 },
+    };
+    width(&line);
+    advance(4);
+}
@@ -0,0 +1,160 @@
+use std::path::Path;
+
+use crate::common::{
+    ExpectedOutput, ExpectedSite, compile_and_execute_with_args, delete,
+    prefix_with_path_from_root, verify,
+};
+
+#[test]
+fn object_ppts() {
+    let mut expected = ExpectedOutput::new();
+
+    expected.register_site(ExpectedSite::new(prefix_with_path_from_root(
+        "object_ppts/main.rs::main:::ENTER",
+    )));
+    expected.register_site(ExpectedSite::new(prefix_with_path_from_root(
+        "object_ppts/main.rs::main:::EXIT",
+    )));
+
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "object_ppts/main.rs::Counter::bump:::ENTER",
+        ))
+        .register("self.val", 0)
+        .register("self.step", 1)
+        .register("self.unused", 2),
+    );
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "object_ppts/main.rs::Counter::bump:::EXIT",
+        ))
+        .register("self.val", 0)
+        .register("self.step", 0)
+        .register("self.unused", 1),
+    );
+
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "object_ppts/main.rs::peek:::ENTER",
+        ))
+        .register("c.val", 0)
+        .register("c.step", 0)
+        .register("c.unused", 1),
+    );
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "object_ppts/main.rs::peek:::EXIT",
+        ))
+        .register("c.val", 0)
+        .register("c.step", 0)
+        .register("c.unused", 1)
+        .register("return", 1),
+    );
+
+    // every observed `Counter`, whether as `self` or `c`, is folded into a single site.
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "object_ppts/main.rs::Counter:::OBJECT",
+        ))
+        .register("this.val", 0)
+        .register("this.step", 0)
+        .register("this.unused", 1),
+    );
+
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "object_ppts/main.rs::head:::ENTER",
+        ))
+        .register("node.value", 0)
+        .register("node.next::Some.0.value", 1)
+        .register("node.next::Some.0.next::Some.0.value", 2),
+    );
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "object_ppts/main.rs::head:::EXIT",
+        ))
+        .register("node.value", 0)
+        .register("node.next::Some.0.value", 1)
+        .register("node.next::Some.0.next::Some.0.value", 2)
+        .register("return", 0),
+    );
+
+    // the whole list is folded into a single observation of its head.
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "object_ppts/main.rs::Node:::OBJECT",
+        ))
+        .register("this.value", 0)
+        .register("this.next::Some.0.value", 1)
+        .register("this.next::Some.0.next::Some.0.value", 2),
+    );
+
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "object_ppts/main.rs::width:::ENTER",
+        ))
+        .register("line.from.x", 0)
+        .register("line.from.y", 1)
+        .register("line.to.x", 2)
+        .register("line.to.y", 3),
+    );
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "object_ppts/main.rs::width:::EXIT",
+        ))
+        .register("line.from.x", 0)
+        .register("line.from.y", 1)
+        .register("line.to.x", 0)
+        .register("line.to.y", 2)
+        .register("return", 0),
+    );
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "object_ppts/main.rs::Line:::OBJECT",
+        ))
+        .register("this.from.x", 0)
+        .register("this.from.y", 1)
+        .register("this.to.x", 0)
+        .register("this.to.y", 2),
+    );
+
+    // both points of the line are observed, as structs of another type than `Line`.
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "object_ppts/main.rs::Point:::OBJECT",
+        ))
+        .register("this.x", 0)
+        .register("this.y", 1),
+    );
+
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "object_ppts/main.rs::advance:::ENTER",
+        ))
+        .register("x", 0),
+    );
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "object_ppts/main.rs::advance:::EXIT",
+        ))
+        .register("x", 0)
+        .register("return", 0),
+    );
+
+    // every struct shares the globals of its module.
+    for name in ["Counter", "Node", "Point", "Line"] {
+        expected.register_site(
+            ExpectedSite::new(prefix_with_path_from_root(&format!(
+                "object_ppts/main.rs::{name}:::CLASS"
+            )))
+            .register("::LIMIT", 0)
+            .register("::STEP", 1),
+        );
+    }
+
+    let executable = Path::new(file!()).parent().unwrap().join("object.out");
+    delete(&executable);
+
+    let ati_output = compile_and_execute_with_args(&executable, &["--object-ppts"]);
+    verify(&ati_output, expected.inner());
+}
@@ -0,0 +1,33 @@
+#![allow(unused)]
+// Once an item is marked `#[datir::only]`, every function outside of the marked items is left
+// uninstrumented, apart from `main`. `shapes::scaled` lies within a marked module, but is skipped
+// itself, and a skip wins over an only.
+
+#[ignore]
+fn main() {
+    let a = area(3, 4);
+    let p = perimeter(3, 4);
+    shapes::square(a);
+    shapes::scaled(p, 2);
+}
+
+#[datir::only]
+fn area(w: u32, h: u32) -> u32 {
+    w * h
+}
+
+fn perimeter(w: u32, h: u32) -> u32 {
+    2 * (w + h)
+}
+
+#[datir::only]
+mod shapes {
+    pub fn square(s: u32) -> u32 {
+        s * s
+    }
+
+    #[datir::skip]
+    pub fn scaled(s: u32, k: u32) -> u32 {
+        s * k
+    }
+}
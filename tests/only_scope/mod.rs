@@ -0,0 +1,53 @@
+use std::path::Path;
+
+use crate::common::{
+    ExpectedOutput, ExpectedSite, compile_and_execute, delete, prefix_with_path_from_root, verify,
+};
+
+#[test]
+fn only_scope() {
+    let mut expected = ExpectedOutput::new();
+    expected.register_site(ExpectedSite::new(prefix_with_path_from_root(
+        "only_scope/main.rs::main:::ENTER",
+    )));
+    expected.register_site(ExpectedSite::new(prefix_with_path_from_root(
+        "only_scope/main.rs::main:::EXIT",
+    )));
+
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "only_scope/main.rs::area:::ENTER",
+        ))
+        .register("w", 0)
+        .register("h", 1),
+    );
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "only_scope/main.rs::area:::EXIT",
+        ))
+        .register("w", 0)
+        .register("h", 0)
+        .register("return", 0),
+    );
+
+    // marked through its module.
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "only_scope/main.rs::shapes::square:::ENTER",
+        ))
+        .register("s", 0),
+    );
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "only_scope/main.rs::shapes::square:::EXIT",
+        ))
+        .register("s", 0)
+        .register("return", 0),
+    );
+
+    let executable = Path::new(file!()).parent().unwrap().join("only_scope.out");
+    delete(&executable);
+
+    let ati_output = compile_and_execute(&executable);
+    verify(&ati_output, expected.inner());
+}
@@ -0,0 +1,23 @@
+#![allow(unused)]
+// Probes placed within a function body update their own sites, named after the enclosing
+// function. The macro binds the listed variables, the attribute those its `let` introduces.
+// The crate also builds without DATIR, through the no-op definitions of `datir-probe`.
+#![feature(register_tool)]
+#![register_tool(datir)]
+
+#[macro_use]
+extern crate datir_probe;
+
+#[ignore]
+fn main() {
+    total(3, 4, 10);
+}
+
+fn total(a: u32, b: u32, limit: u32) -> u32 {
+    let sum = a + b;
+    datir_probe!("summed", sum, a, limit);
+
+    #[datir::probe("capped")]
+    let capped = if sum > limit { limit } else { sum };
+    capped
+}
@@ -0,0 +1,71 @@
+use std::path::Path;
+
+use crate::common::{
+    ExpectedOutput, ExpectedSite, compile_and_execute, delete, prefix_with_path_from_root, verify,
+};
+
+#[test]
+fn probes() {
+    let mut expected = ExpectedOutput::new();
+    expected.register_site(ExpectedSite::new(prefix_with_path_from_root(
+        "probes/main.rs::main:::ENTER",
+    )));
+    expected.register_site(ExpectedSite::new(prefix_with_path_from_root(
+        "probes/main.rs::main:::EXIT",
+    )));
+
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root("probes/main.rs::total:::ENTER"))
+            .register("a", 0)
+            .register("b", 1)
+            .register("limit", 2),
+    );
+    // `limit` only interacts with `sum` after the probe.
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "probes/main.rs::total:::PROBE_summed",
+        ))
+        .register("sum", 0)
+        .register("a", 0)
+        .register("limit", 1),
+    );
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "probes/main.rs::total:::PROBE_capped",
+        ))
+        .register("capped", 0),
+    );
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root("probes/main.rs::total:::EXIT"))
+            .register("a", 0)
+            .register("b", 0)
+            .register("limit", 0)
+            .register("return", 0),
+    );
+
+    let executable = Path::new(file!()).parent().unwrap().join("probes.out");
+    delete(&executable);
+
+    let ati_output = compile_and_execute(&executable);
+    verify(&ati_output, expected.inner());
+
+    // decls-gen doesn't declare probe ppts, they are appended to the `.decls` file it wrote.
+    let decls = std::fs::read_to_string(executable.with_file_name("main.decls")).unwrap();
+    for (probe, vars) in [
+        ("summed", &["sum", "a", "limit"][..]),
+        ("capped", &["capped"]),
+    ] {
+        let ppt = prefix_with_path_from_root(&format!("probes/main.rs::total:::PROBE_{probe}"));
+        let header = format!("ppt {ppt}\nppt-type point\n");
+        let declaration = decls
+            .split("\n\n")
+            .map(str::trim_start)
+            .find(|block| block.starts_with(&header))
+            .unwrap_or_else(|| panic!("`{ppt}` is not declared:\n{decls}"));
+        for var in vars {
+            let var_decl =
+                format!("variable {var}\n  var-kind variable\n  dec-type u32\n  rep-type int\n");
+            assert!(declaration.contains(&var_decl), "{declaration}");
+        }
+    }
+}
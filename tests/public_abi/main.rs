@@ -0,0 +1,33 @@
+#![allow(unused)]
+// Functions on the public API boundary keep their untagged signature, so that uninstrumented code
+// can call them. Their shims tag the values they are passed afresh. `scaled_sum` is exported
+// (through `--preserve-public-abi`), `double` is marked `#[datir::keep_abi]`, and
+// `public_abi_square` is `#[no_mangle]`. `helper` is private, and instrumented as usual.
+
+#[ignore]
+fn main() {
+    let total = api::scaled_sum(2, 3, 4);
+    let diff = helper(total, 1);
+    let d = double(diff);
+    let sq = public_abi_square(d);
+}
+
+pub mod api {
+    pub fn scaled_sum(a: u32, b: u32, scale: u32) -> u32 {
+        (a + b) * scale
+    }
+}
+
+fn helper(x: u32, y: u32) -> u32 {
+    x - y
+}
+
+#[datir::keep_abi]
+fn double(n: u32) -> u32 {
+    n * 2
+}
+
+#[unsafe(no_mangle)]
+fn public_abi_square(n: u32) -> u32 {
+    n * n
+}
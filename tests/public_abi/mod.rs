@@ -0,0 +1,89 @@
+use std::path::Path;
+
+use crate::common::{
+    ExpectedOutput, ExpectedSite, compile_and_execute_with_args, delete,
+    prefix_with_path_from_root, verify,
+};
+
+#[test]
+fn public_abi() {
+    let mut expected = ExpectedOutput::new();
+    expected.register_site(ExpectedSite::new(prefix_with_path_from_root(
+        "public_abi/main.rs::main:::ENTER",
+    )));
+    expected.register_site(ExpectedSite::new(prefix_with_path_from_root(
+        "public_abi/main.rs::main:::EXIT",
+    )));
+
+    // exported, and called with untagged values.
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "public_abi/main.rs::api::scaled_sum:::ENTER",
+        ))
+        .register("a", 0)
+        .register("b", 1)
+        .register("scale", 2),
+    );
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "public_abi/main.rs::api::scaled_sum:::EXIT",
+        ))
+        .register("a", 0)
+        .register("b", 0)
+        .register("scale", 0)
+        .register("return", 0),
+    );
+
+    // private, and instrumented as usual.
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "public_abi/main.rs::helper:::ENTER",
+        ))
+        .register("x", 0)
+        .register("y", 1),
+    );
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "public_abi/main.rs::helper:::EXIT",
+        ))
+        .register("x", 0)
+        .register("y", 0)
+        .register("return", 0),
+    );
+
+    // `#[datir::keep_abi]`.
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "public_abi/main.rs::double:::ENTER",
+        ))
+        .register("n", 0),
+    );
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "public_abi/main.rs::double:::EXIT",
+        ))
+        .register("n", 0)
+        .register("return", 0),
+    );
+
+    // `#[no_mangle]`.
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "public_abi/main.rs::public_abi_square:::ENTER",
+        ))
+        .register("n", 0),
+    );
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "public_abi/main.rs::public_abi_square:::EXIT",
+        ))
+        .register("n", 0)
+        .register("return", 0),
+    );
+
+    let executable = Path::new(file!()).parent().unwrap().join("public_abi.out");
+    delete(&executable);
+
+    let ati_output = compile_and_execute_with_args(&executable, &["--preserve-public-abi"]);
+    verify(&ati_output, expected.inner());
+}
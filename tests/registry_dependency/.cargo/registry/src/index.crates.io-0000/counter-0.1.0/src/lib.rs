@@ -0,0 +1,3 @@
+pub fn bump(count: u32, step: u32) -> u32 {
+    count + step
+}
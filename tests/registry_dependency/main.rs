@@ -0,0 +1,14 @@
+#![allow(unused)]
+// `counter` lives under a `.cargo/registry` path, like a dependency downloaded from crates.io,
+// so it is only instrumented because it is allowlisted.
+extern crate counter;
+
+#[ignore]
+fn main() {
+    tally(1, 2, 3);
+}
+
+fn tally(x: u32, y: u32, z: u32) -> u32 {
+    // counter is instrumented, so x and y interact within counter::bump
+    counter::bump(x, y)
+}
@@ -0,0 +1,69 @@
+use std::path::Path;
+
+use crate::common::{
+    ExpectedOutput, ExpectedSite, compile_and_execute_with_args, delete,
+    prefix_with_path_from_root, verify,
+};
+
+/// Path of the `counter` crate root, relative to `tests/`.
+const COUNTER_ROOT: &str =
+    "registry_dependency/.cargo/registry/src/index.crates.io-0000/counter-0.1.0/src/lib.rs";
+
+#[test]
+fn registry_dependency() {
+    let mut expected = ExpectedOutput::new();
+    expected.register_site(ExpectedSite::new(prefix_with_path_from_root(
+        "registry_dependency/main.rs::main:::ENTER",
+    )));
+    expected.register_site(ExpectedSite::new(prefix_with_path_from_root(
+        "registry_dependency/main.rs::main:::EXIT",
+    )));
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "registry_dependency/main.rs::tally:::ENTER",
+        ))
+        .register("x", 0)
+        .register("y", 1)
+        .register("z", 2),
+    );
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "registry_dependency/main.rs::tally:::EXIT",
+        ))
+        .register("x", 0)
+        .register("y", 0)
+        .register("z", 2)
+        .register("return", 0),
+    );
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(&format!(
+            "{COUNTER_ROOT}::bump:::ENTER"
+        )))
+        .register("count", 0)
+        .register("step", 1),
+    );
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(&format!(
+            "{COUNTER_ROOT}::bump:::EXIT"
+        )))
+        .register("count", 0)
+        .register("step", 0)
+        .register("return", 0),
+    );
+
+    let counter_root = std::env::current_dir()
+        .unwrap()
+        .join("tests")
+        .join(COUNTER_ROOT);
+    let allowlist = format!("counter={}", counter_root.display());
+
+    let executable = Path::new(file!())
+        .parent()
+        .unwrap()
+        .join("registry_dependency.out");
+    delete(&executable);
+
+    let ati_output =
+        compile_and_execute_with_args(&executable, &["--instrument-crates", allowlist.as_str()]);
+    verify(&ati_output, expected.inner());
+}
@@ -0,0 +1,28 @@
+#![allow(unused)]
+// User items sharing their names with items of the runtime library. Generated code must
+// keep referring to the runtime's versions.
+
+struct Site {
+    x: u32,
+}
+
+struct Tagged(u32);
+
+enum ATI {
+    Off,
+    On(u32),
+}
+
+#[ignore]
+fn main() {
+    let site = Site { x: 1 };
+    let tagged = Tagged(2);
+    foo(site, tagged, ATI::On(3));
+}
+
+fn foo(s: Site, t: Tagged, a: ATI) -> u32 {
+    match a {
+        ATI::Off => s.x,
+        ATI::On(v) => s.x + t.0 + v,
+    }
+}
@@ -0,0 +1,40 @@
+use std::path::Path;
+
+use crate::common::{
+    ExpectedOutput, ExpectedSite, compile_and_execute, delete, prefix_with_path_from_root, verify,
+};
+
+#[test]
+fn runtime_name_collision() {
+    let mut expected = ExpectedOutput::new();
+    expected.register_site(ExpectedSite::new(prefix_with_path_from_root(
+        "runtime_name_collision/main.rs::main:::ENTER",
+    )));
+    expected.register_site(ExpectedSite::new(prefix_with_path_from_root(
+        "runtime_name_collision/main.rs::main:::EXIT",
+    )));
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "runtime_name_collision/main.rs::foo:::ENTER",
+        ))
+        .register("s.x", 0)
+        .register("t.0", 1)
+        .register("a::On.0", 2),
+    );
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "runtime_name_collision/main.rs::foo:::EXIT",
+        ))
+        // s, t, and a are captured by value
+        .register("return", 0),
+    );
+
+    let executable = Path::new(file!())
+        .parent()
+        .unwrap()
+        .join("runtime_name_collision.out");
+    delete(&executable);
+
+    let ati_output = compile_and_execute(&executable);
+    verify(&ati_output, expected.inner());
+}
@@ -0,0 +1,51 @@
+#![allow(unused)]
+// Skipped functions are compiled untagged, and get no program points. Values passed into them
+// lose their ids, and the values they return are tagged afresh. Calls out of skipped code into
+// instrumented functions tag the arguments afresh. `ignored` is skipped through `--skip`. The
+// skipped methods of `Counter` read and write its (tagged) field untupled.
+
+#[ignore]
+fn main() {
+    total(3, 4, 5);
+    let mut counter = Counter { count: 1 };
+    counter.bump(2);
+}
+
+fn total(a: u32, b: u32, c: u32) -> u32 {
+    let m = mix(a, b);
+    let n = generated::noise(b);
+    let k = ignored(c);
+    m + n + k
+}
+
+#[datir::skip]
+fn mix(x: u32, y: u32) -> u32 {
+    clamp(x * y, 10)
+}
+
+fn clamp(v: u32, hi: u32) -> u32 {
+    if v < hi { v } else { hi }
+}
+
+fn ignored(n: u32) -> u32 {
+    n + 1
+}
+
+struct Counter {
+    count: u32,
+}
+
+#[datir::skip]
+impl Counter {
+    fn bump(&mut self, by: u32) -> u32 {
+        self.count += by;
+        self.count
+    }
+}
+
+#[datir::skip]
+mod generated {
+    pub fn noise(n: u32) -> u32 {
+        n ^ 0x5a
+    }
+}
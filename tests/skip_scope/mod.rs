@@ -0,0 +1,101 @@
+use std::path::Path;
+
+use crate::common::{
+    ExpectedOutput, ExpectedSite, compile_and_execute_with_args, delete,
+    prefix_with_path_from_root, verify,
+};
+
+#[test]
+fn skip_scope() {
+    let mut expected = ExpectedOutput::new();
+    expected.register_site(ExpectedSite::new(prefix_with_path_from_root(
+        "skip_scope/main.rs::main:::ENTER",
+    )));
+    expected.register_site(ExpectedSite::new(prefix_with_path_from_root(
+        "skip_scope/main.rs::main:::EXIT",
+    )));
+
+    // `a`, `b` and `c` only flow into skipped functions, the returned values are fresh.
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "skip_scope/main.rs::total:::ENTER",
+        ))
+        .register("a", 0)
+        .register("b", 1)
+        .register("c", 2),
+    );
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "skip_scope/main.rs::total:::EXIT",
+        ))
+        .register("a", 0)
+        .register("b", 1)
+        .register("c", 2)
+        .register("return", 3),
+    );
+
+    // called from within the skipped `mix`, with freshly tagged arguments.
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "skip_scope/main.rs::clamp:::ENTER",
+        ))
+        .register("v", 0)
+        .register("hi", 1),
+    );
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "skip_scope/main.rs::clamp:::EXIT",
+        ))
+        .register("v", 0)
+        .register("hi", 0)
+        .register("return", 0),
+    );
+
+    let executable = Path::new(file!()).parent().unwrap().join("skip_scope.out");
+    delete(&executable);
+
+    let ati_output = compile_and_execute_with_args(&executable, &["--skip", "*::ignored"]);
+    verify(&ati_output, expected.inner());
+}
+
+#[test]
+fn skip_scope_only_filter() {
+    let mut expected = ExpectedOutput::new();
+    expected.register_site(ExpectedSite::new(prefix_with_path_from_root(
+        "skip_scope/main.rs::main:::ENTER",
+    )));
+    expected.register_site(ExpectedSite::new(prefix_with_path_from_root(
+        "skip_scope/main.rs::main:::EXIT",
+    )));
+
+    // `*` spans the `/` and `::` separators of the base ppt name, `?` matches a single
+    // character. `total` matches no pattern, and `ignored` is skipped, as a skip wins over an
+    // only.
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "skip_scope/main.rs::clamp:::ENTER",
+        ))
+        .register("v", 0)
+        .register("hi", 1),
+    );
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "skip_scope/main.rs::clamp:::EXIT",
+        ))
+        .register("v", 0)
+        .register("hi", 0)
+        .register("return", 0),
+    );
+
+    let executable = Path::new(file!())
+        .parent()
+        .unwrap()
+        .join("skip_scope_only_filter.out");
+    delete(&executable);
+
+    let ati_output = compile_and_execute_with_args(
+        &executable,
+        &["--only", "*::cl?mp,*::ignored", "--skip", "*::ignored"],
+    );
+    verify(&ati_output, expected.inner());
+}
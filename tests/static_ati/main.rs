@@ -0,0 +1,21 @@
+#![allow(unused)]
+// `clamp` only compares `value` to `limit` when `strict`, which it never is when run, so only
+// the static analysis unions them. `scale` multiplies its operands, which both analyses see.
+
+#[ignore]
+fn main() {
+    let clamped = clamp(5, 10, false);
+    let scaled = scale(clamped, 3);
+}
+
+fn clamp(value: usize, limit: usize, strict: bool) -> usize {
+    if strict && value > limit {
+        limit
+    } else {
+        value
+    }
+}
+
+fn scale(amount: usize, factor: usize) -> usize {
+    amount * factor
+}
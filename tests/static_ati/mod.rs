@@ -0,0 +1,73 @@
+use std::path::Path;
+
+use crate::common::{compile_and_execute, delete, prefix_with_path_from_root, run_datir};
+
+/// Infers abstract types statically, and compares them with those of a run.
+#[test]
+fn static_ati() {
+    let test_dir = Path::new(file!()).parent().unwrap();
+    let executable = test_dir.join("static_ati.out");
+    let observed = test_dir.join("observed.txt");
+    for path in [&executable, &observed] {
+        delete(path);
+    }
+
+    let report = compile_and_execute(&executable);
+    std::fs::write(&observed, format!("===ATI-ANALYSIS-START===\n{report}")).unwrap();
+
+    // Ppt names embed the source path the binary was instrumented from.
+    let source = std::env::current_dir()
+        .unwrap()
+        .join(test_dir)
+        .join("main.rs");
+    let output = run_datir(&[
+        "static",
+        source.to_str().unwrap(),
+        "--compare",
+        observed.to_str().unwrap(),
+    ]);
+    assert!(
+        output.status.success(),
+        "Unable to infer abstract types statically. Error output:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let ppt = |ppt: &str| prefix_with_path_from_root(&format!("static_ati/main.rs::{ppt}"));
+
+    // The comparison in `clamp` is never evaluated at runtime, but unions `value` and `limit`
+    // statically, and so does `return`, which is either of them.
+    for expected in [
+        format!(
+            "{}\nlimit -> 0\nstrict -> 1\nvalue -> 0\n---\n",
+            ppt("clamp:::ENTER")
+        ),
+        format!("{}\namount -> 0\nfactor -> 0\n---\n", ppt("scale:::ENTER")),
+    ] {
+        assert!(
+            stdout.contains(&expected),
+            "Missing {expected:?} from the static report:\n{stdout}"
+        );
+    }
+
+    let (_, comparison) = stdout
+        .split_once("===STATIC-DYNAMIC-COMPARISON===\n")
+        .unwrap_or_else(|| panic!("Missing comparison:\n{stdout}"));
+    for expected in [
+        format!(
+            "{}\n  limit: static {{limit, value}}, dynamic {{limit}}\n  \
+             value: static {{limit, value}}, dynamic {{value}}\n",
+            ppt("clamp:::ENTER")
+        ),
+        "  return: static {limit, return, value}, dynamic {return, value}\n".to_string(),
+    ] {
+        assert!(
+            comparison.contains(&expected),
+            "Missing {expected:?} from the comparison:\n{comparison}"
+        );
+    }
+    // Both analyses agree on `scale`.
+    assert!(
+        !comparison.contains(&ppt("scale:::")),
+        "Unexpected disagreement on `scale`:\n{comparison}"
+    );
+}
@@ -0,0 +1,25 @@
+#![allow(unused)]
+// `Option` and `Result` carry tagged payloads, which are bound at each site under the variant
+// they are held in. `?`, `map`, `unwrap`, `unwrap_or` and `ok_or` hand a payload over with its
+// id, and comparing two `Option`s compares (and interacts) their payloads.
+
+#[ignore]
+fn main() {
+    halve(Some(8), 3);
+    compare(Some(3), Some(5), 7);
+    lookup(Ok(8), 2);
+}
+
+fn halve(x: Option<u32>, fallback: u32) -> Option<u32> {
+    let half = x.map(|v| v / 2)?;
+    Some(Some(half).unwrap_or(fallback))
+}
+
+fn compare(a: Option<u32>, b: Option<u32>, c: u32) -> u32 {
+    if a == b { c } else { a.unwrap() }
+}
+
+fn lookup(r: Result<u32, u32>, code: u32) -> Result<u32, u32> {
+    let value = r?;
+    Some(value).ok_or(code)
+}
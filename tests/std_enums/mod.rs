@@ -0,0 +1,62 @@
+use std::path::Path;
+
+use crate::common::{
+    ExpectedOutput, ExpectedSite, compile_and_execute, delete, prefix_with_path_from_root, verify,
+};
+
+#[test]
+fn std_enums() {
+    let mut expected = ExpectedOutput::new();
+    expected.register_site(ExpectedSite::new(prefix_with_path_from_root(
+        "std_enums/main.rs::main:::ENTER",
+    )));
+    expected.register_site(ExpectedSite::new(prefix_with_path_from_root(
+        "std_enums/main.rs::main:::EXIT",
+    )));
+
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root("std_enums/main.rs::halve:::ENTER"))
+            .register("x::Some.0", 0)
+            .register("fallback", 1),
+    );
+    // the returned value is half of `x`'s payload, `fallback` was never used.
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root("std_enums/main.rs::halve:::EXIT"))
+            .register("x::Some.0", 0)
+            .register("fallback", 1)
+            .register("return::Some.0", 0),
+    );
+
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root("std_enums/main.rs::compare:::ENTER"))
+            .register("a::Some.0", 0)
+            .register("b::Some.0", 1)
+            .register("c", 2),
+    );
+    // `a == b` compared the payloads, and the payload of `a` was returned.
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root("std_enums/main.rs::compare:::EXIT"))
+            .register("a::Some.0", 0)
+            .register("b::Some.0", 0)
+            .register("c", 1)
+            .register("return", 0),
+    );
+
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root("std_enums/main.rs::lookup:::ENTER"))
+            .register("r::Ok.0", 0)
+            .register("code", 1),
+    );
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root("std_enums/main.rs::lookup:::EXIT"))
+            .register("r::Ok.0", 0)
+            .register("code", 1)
+            .register("return::Ok.0", 0),
+    );
+
+    let executable = Path::new(file!()).parent().unwrap().join("std_enums.out");
+    delete(&executable);
+
+    let ati_output = compile_and_execute(&executable);
+    verify(&ati_output, expected.inner());
+}
@@ -0,0 +1,36 @@
+#![allow(unused)]
+// Strings carry a single id, standing for their length. Lengths, byte offsets and slice bounds
+// drawn from a string share its id, and concatenating strings interacts them. Strings returned
+// by std methods (`trim`) and their contents (`char_indices`'s chars) are tagged afresh.
+
+#[ignore]
+fn main() {
+    let line = String::from("key=value");
+    split_at_eq(&line, 0);
+    greet("bob", String::from("hi"));
+    digit_offset("ab3c", '3', 9);
+}
+
+fn split_at_eq(s: &str, start: usize) -> usize {
+    let eq = s.find('=').unwrap();
+    let key = &s[start..eq];
+    key.len()
+}
+
+fn greet(name: &str, greeting: String) -> String {
+    if name.trim().is_empty() {
+        return greeting;
+    }
+    let mut out = greeting + " ";
+    out += name;
+    out
+}
+
+fn digit_offset(s: &str, digit: char, fallback: usize) -> usize {
+    for (i, c) in s.char_indices() {
+        if c == digit {
+            return i;
+        }
+    }
+    fallback
+}
@@ -0,0 +1,62 @@
+use std::path::Path;
+
+use crate::common::{
+    ExpectedOutput, ExpectedSite, compile_and_execute, delete, prefix_with_path_from_root, verify,
+};
+
+#[test]
+fn strings() {
+    let mut expected = ExpectedOutput::new();
+    expected.register_site(ExpectedSite::new(prefix_with_path_from_root(
+        "strings/main.rs::main:::ENTER",
+    )));
+    expected.register_site(ExpectedSite::new(prefix_with_path_from_root(
+        "strings/main.rs::main:::EXIT",
+    )));
+
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root("strings/main.rs::split_at_eq:::ENTER"))
+            .register("s", 0)
+            .register("start", 1),
+    );
+    // `eq` is an offset into `s`, and slicing `s` with `start..eq` interacts the range with it.
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root("strings/main.rs::split_at_eq:::EXIT"))
+            .register("s", 0)
+            .register("start", 0)
+            .register("return", 0),
+    );
+
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root("strings/main.rs::greet:::ENTER"))
+            .register("name", 0)
+            .register("greeting", 1),
+    );
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root("strings/main.rs::greet:::EXIT"))
+            .register("name", 0)
+            .register("greeting", 0)
+            .register("return", 0),
+    );
+
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root("strings/main.rs::digit_offset:::ENTER"))
+            .register("s", 0)
+            .register("digit", 1)
+            .register("fallback", 2),
+    );
+    // the returned offset comes from `s`, while `digit` was only compared with its chars.
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root("strings/main.rs::digit_offset:::EXIT"))
+            .register("s", 0)
+            .register("digit", 1)
+            .register("fallback", 2)
+            .register("return", 0),
+    );
+
+    let executable = Path::new(file!()).parent().unwrap().join("strings.out");
+    delete(&executable);
+
+    let ati_output = compile_and_execute(&executable);
+    verify(&ati_output, expected.inner());
+}
@@ -0,0 +1,23 @@
+#![allow(unused)]
+// Positions and counts never interact, so `datir suggest` proposes a newtype for each, covering
+// the parameters, return types and probed locals declaring them.
+
+#[ignore]
+fn main() {
+    let pos = back(advance(3, 4), 1);
+    let count = tally(2, 5);
+}
+
+fn advance(pos: usize, step: usize) -> usize {
+    let next = pos + step;
+    datir_probe!("moved", next);
+    next
+}
+
+fn back(pos: usize, step: usize) -> usize {
+    pos - step
+}
+
+fn tally(count: usize, extra: usize) -> usize {
+    count + extra
+}
@@ -0,0 +1,76 @@
+use std::path::Path;
+
+use crate::common::{compile_and_execute_with_args, delete, run_datir};
+
+/// Suggests newtypes from the program-wide abstract types of a `--global-types` run.
+#[test]
+fn suggest() {
+    let test_dir = Path::new(file!()).parent().unwrap();
+    let executable = test_dir.join("suggest.out");
+    let types = test_dir.join("types.txt");
+    let suggestions = test_dir.join("main.suggestions.json");
+    for path in [&executable, &types, &suggestions] {
+        delete(path);
+    }
+
+    let report = compile_and_execute_with_args(&executable, &["--global-types"]);
+    std::fs::write(&types, report).unwrap();
+
+    // Ppt names embed the source path the binary was instrumented from.
+    let source = std::env::current_dir()
+        .unwrap()
+        .join(test_dir)
+        .join("main.rs");
+    let output = run_datir(&[
+        "suggest",
+        source.to_str().unwrap(),
+        "-t",
+        types.to_str().unwrap(),
+        "-o",
+        suggestions.to_str().unwrap(),
+    ]);
+    assert!(
+        output.status.success(),
+        "Unable to suggest newtypes. Error output:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    // `pos` and `step` are each declared twice, `next` once, so the name is the smaller of `pos`
+    // and `step`.
+    let at =
+        |line: usize, col: usize, what: &str| format!("  {}:{line}:{col} {what}", source.display());
+    for expected in [
+        "newtype Pos(usize), for abstract type".to_string(),
+        at(11, 17, "parameter `pos` of `advance`"),
+        at(11, 30, "parameter `step` of `advance`"),
+        at(11, 40, "return type of `advance`"),
+        at(12, 13, "local `next` of `advance`"),
+        at(17, 14, "parameter `pos` of `back`"),
+        "newtype Count(usize), for abstract type".to_string(),
+        at(21, 17, "parameter `count` of `tally`"),
+        at(21, 31, "parameter `extra` of `tally`"),
+        at(21, 41, "return type of `tally`"),
+    ] {
+        assert!(
+            stdout.contains(&expected),
+            "Missing {expected:?} from suggestions:\n{stdout}"
+        );
+    }
+
+    // One diagnostic declaring both newtypes, then one per newtype.
+    let suggestions = std::fs::read_to_string(&suggestions).unwrap();
+    assert_eq!(suggestions.lines().count(), 3, "{suggestions}");
+    for expected in [
+        "pub struct Pos(pub usize);",
+        "pub struct Count(pub usize);",
+        r#""suggested_replacement":"Pos","suggestion_applicability":"MachineApplicable""#,
+        r#""suggested_replacement":": Pos","suggestion_applicability":"MaybeIncorrect""#,
+        r#""suggested_replacement":"Count","suggestion_applicability":"MachineApplicable""#,
+    ] {
+        assert!(
+            suggestions.contains(expected),
+            "Missing {expected:?} from rustfix suggestions:\n{suggestions}"
+        );
+    }
+}
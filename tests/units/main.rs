@@ -0,0 +1,24 @@
+#![allow(unused)]
+// `wait` adds a grace period in `s` to a timeout in `ms`, while `deadline` only subtracts an
+// unannotated value from a field in `ms`.
+
+struct Config {
+    #[datir::unit("ms")]
+    timeout: u64,
+    retries: u64,
+}
+
+#[ignore]
+fn main() {
+    let config = Config { timeout: 100, retries: 3 };
+    let total = wait(250, 2);
+    let left = deadline(&config, 50);
+}
+
+fn wait(#[datir::unit("ms")] timeout: u64, #[datir::unit("s")] grace: u64) -> u64 {
+    timeout + grace
+}
+
+fn deadline(config: &Config, elapsed: u64) -> u64 {
+    config.timeout - elapsed
+}
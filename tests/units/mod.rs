@@ -0,0 +1,65 @@
+use std::{path::Path, process::Command};
+
+use crate::common::{compile_and_execute, delete, prefix_with_path_from_root};
+
+/// Lists the units of each abstract type, and reports interactions between different units.
+#[test]
+fn units() {
+    let test_dir = Path::new(file!()).parent().unwrap();
+    let executable = test_dir.join("units.out");
+    delete(&executable);
+
+    let report = compile_and_execute(&executable);
+    let site = |ppt: &str| -> String {
+        let name = prefix_with_path_from_root(&format!("units/main.rs::{ppt}"));
+        let start = report
+            .find(&format!("{name}\n"))
+            .unwrap_or_else(|| panic!("Missing site {name} in report:\n{report}"));
+        let end = start + report[start..].find("---").unwrap();
+        report[start + name.len() + 1..end].to_string()
+    };
+    assert_eq!(site("wait:::ENTER"), "grace -> 0 [s]\ntimeout -> 1 [ms]\n");
+    assert_eq!(
+        site("wait:::EXIT"),
+        "grace -> 0 [ms, s]\nreturn -> 0 [ms, s]\ntimeout -> 0 [ms, s]\n"
+    );
+    // `elapsed` only takes on the unit of `config.timeout` by interacting with it.
+    let deadline_enter = site("deadline:::ENTER");
+    assert!(
+        deadline_enter.contains("config.timeout -> 1 [ms]\n"),
+        "{deadline_enter}"
+    );
+    assert!(
+        deadline_enter.contains("elapsed -> 2\n"),
+        "{deadline_enter}"
+    );
+    let deadline_exit = site("deadline:::EXIT");
+    assert!(
+        deadline_exit.contains("config.timeout -> 1 [ms]\n"),
+        "{deadline_exit}"
+    );
+    assert!(
+        deadline_exit.contains("elapsed -> 1 [ms]\n"),
+        "{deadline_exit}"
+    );
+
+    // Dimensional errors are printed to stderr, once.
+    let output = Command::new(std::env::current_dir().unwrap().join(&executable))
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let source = std::env::current_dir()
+        .unwrap()
+        .join(test_dir)
+        .join("main.rs");
+    let expected = format!(
+        "dimensional error: a value in `ms` ({source}:18:9) interacted with a value in `s` \
+         ({source}:18:44)",
+        source = source.display()
+    );
+    assert_eq!(stderr.lines().count(), 1, "{stderr}");
+    assert!(
+        stderr.contains(&expected),
+        "Expected {expected:?} in stderr:\n{stderr}"
+    );
+}
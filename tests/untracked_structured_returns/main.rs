@@ -0,0 +1,25 @@
+#[ignore]
+fn main() {
+    foo(10, 3, 4);
+}
+
+fn foo(a: u32, b: u32, c: u32) -> u32 {
+    // returns a (u32, bool), both of which get a fresh tag.
+    let (sum, _overflowed) = u32::overflowing_add(a, b);
+
+    // returns an Option<u32>, the inner value gets a fresh tag.
+    let diff = u32::checked_sub(a, b).unwrap();
+
+    // returns a Result<u32, TryFromIntError>, only the Ok side is tagged.
+    let parsed = u32::try_from(7u64).unwrap();
+
+    // sum and diff should interact with each other, but not with
+    // any of the parameters, as the untracked calls lose interactions.
+    let combined = sum + diff;
+
+    bar(combined, parsed, c)
+}
+
+fn bar(x: u32, y: u32, z: u32) -> u32 {
+    x + z
+}
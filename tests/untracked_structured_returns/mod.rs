@@ -0,0 +1,59 @@
+use std::path::Path;
+
+use crate::common::{
+    ExpectedOutput, ExpectedSite, compile_and_execute, delete, prefix_with_path_from_root, verify,
+};
+
+#[test]
+fn untracked_structured_returns() {
+    let mut expected = ExpectedOutput::new();
+    expected.register_site(ExpectedSite::new(prefix_with_path_from_root(
+        "untracked_structured_returns/main.rs::main:::ENTER",
+    )));
+    expected.register_site(ExpectedSite::new(prefix_with_path_from_root(
+        "untracked_structured_returns/main.rs::main:::EXIT",
+    )));
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "untracked_structured_returns/main.rs::foo:::ENTER",
+        ))
+        .register("a", 0)
+        .register("b", 1)
+        .register("c", 2),
+    );
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "untracked_structured_returns/main.rs::foo:::EXIT",
+        ))
+        .register("a", 0)
+        .register("b", 1)
+        .register("c", 2)
+        .register("return", 2),
+    );
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "untracked_structured_returns/main.rs::bar:::ENTER",
+        ))
+        .register("x", 0)
+        .register("y", 1)
+        .register("z", 2),
+    );
+    expected.register_site(
+        ExpectedSite::new(prefix_with_path_from_root(
+            "untracked_structured_returns/main.rs::bar:::EXIT",
+        ))
+        .register("x", 0)
+        .register("y", 1)
+        .register("z", 0)
+        .register("return", 0),
+    );
+
+    let executable = Path::new(file!())
+        .parent()
+        .unwrap()
+        .join("untracked_structured_returns.out");
+    delete(&executable);
+
+    let ati_output = compile_and_execute(&executable);
+    verify(&ati_output, expected.inner());
+}